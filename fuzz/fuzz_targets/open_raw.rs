@@ -0,0 +1,37 @@
+//! Opens arbitrary bytes, for inputs the structured generator can't reach.
+//! Run with `cargo fuzz run open_raw`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wavefile::{FrameRate,WaveFile};
+
+fuzz_target!(|data: &[u8]| {
+  for carved in WaveFile::carve(std::io::Cursor::new(data)).unwrap() {
+    let file = carved.file;
+
+    file.validate();
+    file.duration();
+    file.speakers();
+    file.cue_points();
+    file.markers();
+    file.regions();
+    file.labels();
+    file.playlist();
+    file.sampler();
+    file.instrument();
+    file.acid();
+    file.dbmd();
+    file.bext();
+    file.start_timecode(FrameRate::Fps25);
+    file.ixml();
+    file.axml();
+    file.chna();
+    file.cart();
+    file.peak_envelope();
+    file.tags();
+    file.disp();
+    file.title();
+    file.bit_usage();
+    assert_eq!(file.iter().count(), file.len());
+  }
+});
@@ -0,0 +1,69 @@
+use std::io::Cursor;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use error::WaveError;
+
+const ACID_ONE_SHOT    : u32 = 0x01;
+const ACID_ROOT_NOTE   : u32 = 0x02;
+const ACID_STRETCH     : u32 = 0x04;
+const ACID_DISK_BASED  : u32 = 0x08;
+
+/// Contents of the `acid` chunk written by ACID and other loop tools,
+/// describing the musical timing of the audio.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct Acid {
+  pub flags:             u32,
+  /// The MIDI note of the loop's key, if `has_root_note()`.
+  pub root_note:         u16,
+  /// Length of the loop, in beats.
+  pub beats:             u32,
+  pub meter_denominator: u16,
+  pub meter_numerator:   u16,
+  /// Tempo of the loop, in beats per minute.
+  pub tempo:             f32
+}
+
+impl Acid {
+  /// Returns `true` if the file is a one-shot sample rather than a loop.
+  pub fn is_one_shot(&self) -> bool {
+    self.flags & ACID_ONE_SHOT != 0
+  }
+
+  /// Returns `true` if `root_note` is set and the loop should follow the
+  /// project's key.
+  pub fn has_root_note(&self) -> bool {
+    self.flags & ACID_ROOT_NOTE != 0
+  }
+
+  /// Returns `true` if the loop should be time-stretched to the project's
+  /// tempo.
+  pub fn stretches(&self) -> bool {
+    self.flags & ACID_STRETCH != 0
+  }
+
+  /// Returns `true` if the file should be streamed from disk rather than
+  /// loaded into memory.
+  pub fn is_disk_based(&self) -> bool {
+    self.flags & ACID_DISK_BASED != 0
+  }
+
+  pub(crate) fn parse_chunk(data: &[u8]) -> Result<Acid, WaveError> {
+    if data.len() < 24 {
+      let msg = format!("Acid chunk is only {} bytes long", data.len());
+      return Err(WaveError::ParseError(msg));
+    }
+
+    let mut cursor = Cursor::new(data);
+    let flags             = cursor.read_u32::<LittleEndian>()?;
+    let root_note         = cursor.read_u16::<LittleEndian>()?;
+    // two fields of unknown purpose follow the root note.
+    cursor.set_position(12);
+    let beats             = cursor.read_u32::<LittleEndian>()?;
+    let meter_denominator = cursor.read_u16::<LittleEndian>()?;
+    let meter_numerator   = cursor.read_u16::<LittleEndian>()?;
+    let tempo             = cursor.read_f32::<LittleEndian>()?;
+
+    Ok(Acid { flags, root_note, beats, meter_denominator, meter_numerator, tempo })
+  }
+}
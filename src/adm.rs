@@ -0,0 +1,64 @@
+use std::io::Cursor;
+
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
+
+use error::WaveError;
+use read_zstring;
+
+/// Size of a single entry of the `chna` chunk.
+const CHNA_ENTRY_SIZE : usize = 40;
+
+/// An entry of the `chna` chunk, assigning an audio track of the file to an
+/// element of the Audio Definition Model.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ChnaEntry {
+  /// One-based index of the track (channel) in the file.
+  pub track_index:      u16,
+  /// The `audioTrackUID` this track carries, e.g. `ATU_00000001`.
+  pub track_uid:        String,
+  /// The `audioTrackFormat` or `audioChannelFormat` reference, e.g.
+  /// `AT_00031001_01`.
+  pub track_format_ref: String,
+  /// The `audioPackFormat` reference, e.g. `AP_00031001`.
+  pub pack_format_ref:  String
+}
+
+/// Contents of the `chna` chunk of an ADM file.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Chna {
+  /// Number of tracks used in the file.
+  pub num_tracks: u16,
+  /// Number of track UIDs used in the file.
+  pub num_uids:   u16,
+  pub entries:    Vec<ChnaEntry>
+}
+
+impl Chna {
+  /// Returns the entries describing the given one-based track index.
+  pub fn track(&self, index: u16) -> Vec<&ChnaEntry> {
+    self.entries.iter().filter(|e| e.track_index == index).collect()
+  }
+
+  pub(crate) fn parse_chunk(data: &[u8]) -> Result<Chna, WaveError> {
+    let mut cursor = Cursor::new(data);
+    let num_tracks = cursor.read_u16::<LittleEndian>()?;
+    let num_uids   = cursor.read_u16::<LittleEndian>()?;
+
+    // writers commonly reserve more entries than they use, leaving the rest
+    // zeroed out.
+    let entries = data[4..].chunks(CHNA_ENTRY_SIZE)
+      .filter(|entry| entry.len() == CHNA_ENTRY_SIZE)
+      .map(|entry| {
+        ChnaEntry {
+          track_index:      LittleEndian::read_u16(&entry[0..2]),
+          track_uid:        read_zstring(&entry[2..14]),
+          track_format_ref: read_zstring(&entry[14..28]),
+          pack_format_ref:  read_zstring(&entry[28..39])
+        }
+      })
+      .filter(|entry| entry.track_index != 0)
+      .collect();
+
+    Ok(Chna { num_tracks, num_uids, entries })
+  }
+}
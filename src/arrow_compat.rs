@@ -0,0 +1,116 @@
+use std::sync::Arc;
+#[cfg(feature = "parquet")]
+use std::io::Write;
+
+use arrow_array::{ArrayRef,Float32Array,Float64Array,RecordBatch,UInt64Array};
+use arrow_schema::{DataType,Field,Schema,SchemaRef};
+#[cfg(feature = "parquet")]
+use parquet::arrow::ArrowWriter;
+
+#[cfg(feature = "parquet")]
+use error::WaveError;
+use WaveFile;
+
+/// Number of frames in each record batch written by `write_parquet()`.
+#[cfg(feature = "parquet")]
+const PARQUET_BATCH_ROWS : usize = 1 << 16;
+
+/// The frames of a file as Arrow record batches of up to a given number of
+/// rows, one row per frame.
+///
+/// Returned by `WaveFile::record_batches`.
+pub struct RecordBatches<'a> {
+  file:   &'a WaveFile,
+  schema: SchemaRef,
+  rows:   usize,
+  /// The index of the first frame of the next batch.
+  next:   usize
+}
+
+impl<'a> Iterator for RecordBatches<'a> {
+  type Item = RecordBatch;
+
+  fn next(&mut self) -> Option<RecordBatch> {
+    let channels = self.file.channels();
+    let frames   = self.rows.min(self.file.len() - self.next);
+
+    if frames == 0 {
+      return None;
+    }
+
+    let mut samples = vec![0.0; frames * channels];
+    let rate        = self.file.sample_rate() as f64;
+    let first       = self.next;
+
+    self.file.read_frames_f32_into(first, &mut samples);
+    self.next += frames;
+
+    let mut columns : Vec<ArrayRef> = vec![
+      Arc::new(UInt64Array::from_iter_values((first..first + frames).map(|frame| frame as u64))),
+      Arc::new(Float64Array::from_iter_values((first..first + frames).map(|frame| frame as f64 / rate)))
+    ];
+
+    for channel in 0..channels {
+      let values = samples.iter().skip(channel).step_by(channels).cloned();
+
+      columns.push(Arc::new(Float32Array::from_iter_values(values)));
+    }
+
+    Some(RecordBatch::try_new(self.schema.clone(), columns).expect("the columns match the schema"))
+  }
+}
+
+impl WaveFile {
+  /// The Arrow schema of the batches from `record_batches()`: the index of
+  /// each frame as `frame`, its start in seconds as `time`, then each
+  /// channel's sample as floats like `read_frames_f32_into()`, as
+  /// `channel_0`, `channel_1` and so on.
+  pub fn arrow_schema(&self) -> SchemaRef {
+    let mut fields = vec![
+      Field::new("frame", DataType::UInt64, false),
+      Field::new("time", DataType::Float64, false)
+    ];
+
+    for channel in 0..self.channels() {
+      fields.push(Field::new(format!("channel_{}", channel), DataType::Float32, false));
+    }
+
+    Arc::new(Schema::new(fields))
+  }
+
+  /// Decodes the file into Arrow record batches of up to `rows` frames
+  /// each, as described by `arrow_schema()`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::WaveFile;
+  ///
+  /// let wav  = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  /// let rows = wav.record_batches(4096).map(|batch| batch.num_rows()).sum::<usize>();
+  ///
+  /// assert_eq!(rows, wav.len());
+  /// ```
+  pub fn record_batches(&self, rows: usize) -> RecordBatches<'_> {
+    RecordBatches {
+      file:   self,
+      schema: self.arrow_schema(),
+      rows:   rows.max(1),
+      next:   0
+    }
+  }
+
+  /// Writes every frame of the file to `writer` as a Parquet file, with the
+  /// columns of `arrow_schema()`.
+  #[cfg(feature = "parquet")]
+  pub fn write_parquet<W: Write + Send>(&self, writer: W) -> Result<(), WaveError> {
+    let to_error   = |e: parquet::errors::ParquetError| WaveError::WriteError(e.to_string());
+    let mut writer = ArrowWriter::try_new(writer, self.arrow_schema(), None).map_err(to_error)?;
+
+    for batch in self.record_batches(PARQUET_BATCH_ROWS) {
+      writer.write(&batch).map_err(to_error)?;
+    }
+    writer.close().map_err(to_error)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,562 @@
+use std::future::Future;
+use std::io::{self,SeekFrom};
+use std::mem;
+#[cfg(feature = "async")]
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{ready,Context,Poll};
+
+use futures_core::Stream;
+#[cfg(feature = "async")]
+use tokio::fs::File;
+#[cfg(feature = "async")]
+use tokio::io::{AsyncRead,AsyncSeek,ReadBuf};
+
+use error::WaveError;
+use options::WaveFileOptions;
+use sampler::{Instrument,Sampler};
+use {check_riff,stream,Format,Frame,WaveFile,WaveInfo};
+
+/// The reads made by `AsyncWaveFile`.
+pub trait AsyncSource: Unpin {
+  fn poll_read_into(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>>;
+}
+
+/// The seeks made by `AsyncWaveFile` to find the length of a file, which
+/// streams such as network connections can't make.
+pub trait AsyncSeekSource: AsyncSource {
+  /// Seeks to `pos`, noting in `started` whether a seek is in progress
+  /// between calls.
+  fn poll_seek_to(&mut self, cx: &mut Context, pos: SeekFrom, started: &mut bool) -> Poll<io::Result<u64>>;
+}
+
+#[cfg(feature = "async")]
+impl<R: AsyncRead + Unpin> AsyncSource for R {
+  fn poll_read_into(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+    let mut buf = ReadBuf::new(buf);
+
+    ready!(Pin::new(self).poll_read(cx, &mut buf))?;
+    Poll::Ready(Ok(buf.filled().len()))
+  }
+}
+
+#[cfg(feature = "async")]
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncSeekSource for R {
+  fn poll_seek_to(&mut self, cx: &mut Context, pos: SeekFrom, started: &mut bool) -> Poll<io::Result<u64>> {
+    poll_seek(self, cx, pos, started)
+  }
+}
+
+/// Seeks `seeker` to `pos`, noting in `started` whether the seek is in
+/// progress between calls.
+#[cfg(feature = "async")]
+pub(crate) fn poll_seek<S: AsyncSeek + Unpin>(seeker: &mut S, cx: &mut Context, pos: SeekFrom, started: &mut bool)
+                                              -> Poll<io::Result<u64>> {
+  if !*started {
+    Pin::new(&mut *seeker).start_seek(pos)?;
+    *started = true;
+  }
+
+  let position = ready!(Pin::new(seeker).poll_complete(cx));
+
+  *started = false;
+  Poll::Ready(position)
+}
+
+/// Adapts a reader implementing the `futures-io` traits, such as a file
+/// opened through smol or async-std, to be read by `AsyncWaveFile`
+/// without depending on a particular runtime.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate futures_io;
+/// extern crate wavefile;
+///
+/// use futures_io::{AsyncRead,AsyncSeek};
+/// use wavefile::{AsyncWaveFile,FuturesReader,WaveFileOptions};
+///
+/// fn open<R: AsyncRead + AsyncSeek + Unpin>(file: R) {
+///   let opening = AsyncWaveFile::from_reader(FuturesReader(file), WaveFileOptions::new());
+///   // await `opening` on any executor.
+/// }
+/// ```
+#[cfg(feature = "futures")]
+pub struct FuturesReader<R>(pub R);
+
+#[cfg(feature = "futures")]
+impl<R: futures_io::AsyncRead + Unpin> AsyncSource for FuturesReader<R> {
+  fn poll_read_into(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.0).poll_read(cx, buf)
+  }
+}
+
+#[cfg(feature = "futures")]
+impl<R: futures_io::AsyncRead + futures_io::AsyncSeek + Unpin> AsyncSeekSource for FuturesReader<R> {
+  fn poll_seek_to(&mut self, cx: &mut Context, pos: SeekFrom, _: &mut bool) -> Poll<io::Result<u64>> {
+    Pin::new(&mut self.0).poll_seek(cx, pos)
+  }
+}
+
+/// Progress through filling a buffer from a source.
+#[derive(Default)]
+struct Fill {
+  /// The number of bytes of the buffer already read.
+  filled: usize
+}
+
+impl Fill {
+  fn poll<R: AsyncSource>(&mut self, reader: &mut R, cx: &mut Context, buf: &mut [u8]) -> Poll<Result<(), WaveError>> {
+    while self.filled < buf.len() {
+      match ready!(reader.poll_read_into(cx, &mut buf[self.filled..]))? {
+        0     => return Poll::Ready(Err(WaveError::ParseError("Unexpected EOF".into()))),
+        count => self.filled += count
+      }
+    }
+
+    Poll::Ready(Ok(()))
+  }
+}
+
+/// A wavefile read asynchronously, through tokio or, with `FuturesReader`,
+/// any runtime, so services can decode without blocking their runtime.
+///
+/// Only the chunks before the audio are loaded, and parsed by the same
+/// scanner as `WaveFile::open`, so chunks following the audio are ignored.
+/// Frames are read as they are requested, so the source is only read as
+/// fast as they are consumed.
+pub struct AsyncWaveFile<R> {
+  reader: R,
+  /// The file up to the start of the audio.
+  header: WaveFile
+}
+
+#[cfg(feature = "async")]
+impl AsyncWaveFile<File> {
+  /// Opens the file at `path` through `tokio::fs`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// extern crate futures_core;
+  /// extern crate tokio;
+  /// extern crate wavefile;
+  ///
+  /// use std::future::poll_fn;
+  /// use std::pin::Pin;
+  /// use futures_core::Stream;
+  /// use wavefile::AsyncWaveFile;
+  ///
+  /// let runtime    = tokio::runtime::Builder::new_current_thread().build().unwrap();
+  /// let wav        = runtime.block_on(AsyncWaveFile::open("./fixtures/test-s24le.wav")).unwrap();
+  /// let mut frames = wav.frames();
+  /// let frame      = runtime.block_on(poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)));
+  ///
+  /// assert_eq!(frame.unwrap().unwrap().len(), 2);
+  /// ```
+  pub fn open<P: AsRef<Path>>(path: P) -> impl Future<Output = Result<AsyncWaveFile<File>, WaveError>> + Send {
+    AsyncWaveFile::open_with(path, WaveFileOptions::default())
+  }
+
+  /// Opens the file at `path` through `tokio::fs`, with `options`.
+  pub fn open_with<P: AsRef<Path>>(path: P, options: WaveFileOptions)
+      -> impl Future<Output = Result<AsyncWaveFile<File>, WaveError>> + Send {
+    let path = path.as_ref().to_owned();
+
+    Open::new(OpenState::Opening(Box::pin(File::open(path))), Some(File::poll_seek_to), options)
+  }
+}
+
+impl<R: AsyncSeekSource> AsyncWaveFile<R> {
+  /// Parses the header of the wavefile in `reader`.  `max_allocation` and
+  /// `max_scan_bytes` also bound the memory used to hold the header.
+  pub fn from_reader(reader: R, options: WaveFileOptions) -> impl Future<Output = Result<AsyncWaveFile<R>, WaveError>> {
+    Open::new(OpenState::Length(reader), Some(R::poll_seek_to), options)
+  }
+}
+
+impl<R: AsyncSource> AsyncWaveFile<R> {
+  /// Parses the header of the wavefile being received from `reader`, which
+  /// is read from the start without seeking, such as a network connection.
+  ///
+  /// As the length of the stream isn't known, a truncated stream is only
+  /// noticed once its audio runs out.  When the header leaves the size of
+  /// the audio open, as live streams do, `len()` is only an upper bound and
+  /// the frames run until the stream ends.
+  pub fn from_stream(reader: R, options: WaveFileOptions) -> impl Future<Output = Result<AsyncWaveFile<R>, WaveError>> {
+    let mut open = Open::new(OpenState::Header(reader, usize::MAX), None, options);
+
+    open.bytes = vec![0; stream::RIFF_HEADER];
+    open
+  }
+
+  /// Consumes the file, returning a stream of its frames, decoded a block
+  /// at a time.
+  pub fn frames(self) -> AsyncFrames<R> {
+    let block = (self.header.options.block_samples / self.channels()).max(1);
+
+    self.frames_buffered(block)
+  }
+
+  /// Consumes the file, returning a stream of its frames that reads ahead
+  /// of the consumer by up to `capacity` frames.
+  ///
+  /// Reading ahead hides the latency of a slow source, such as a network
+  /// connection, while the consumer is busy with earlier frames.  Once
+  /// `capacity` frames are waiting, the stream stops reading until the
+  /// consumer catches up, so a producer that outpaces it is held back by
+  /// its flow control rather than growing the buffer.  Reads are only made
+  /// while the stream is polled.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// extern crate futures_core;
+  /// extern crate tokio;
+  /// extern crate wavefile;
+  ///
+  /// use std::future::poll_fn;
+  /// use std::pin::Pin;
+  /// use futures_core::Stream;
+  /// use wavefile::{AsyncWaveFile,WaveFileOptions};
+  ///
+  /// let runtime    = tokio::runtime::Builder::new_current_thread().build().unwrap();
+  /// let bytes      = std::fs::read("./fixtures/test-s24le.wav").unwrap();
+  /// // a slice is read like a socket, from the start and without seeking.
+  /// let opening    = AsyncWaveFile::from_stream(&bytes[..], WaveFileOptions::new());
+  /// let wav        = runtime.block_on(opening).unwrap();
+  /// let mut frames = wav.frames_buffered(4096);
+  /// let frame      = runtime.block_on(poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)));
+  ///
+  /// assert_eq!(frame.unwrap().unwrap().len(), 2);
+  /// assert!(frames.buffered() <= 4096);
+  /// ```
+  pub fn frames_buffered(self, capacity: usize) -> AsyncFrames<R> {
+    let stride = self.header.stride;
+    let block  = (self.header.options.block_samples / self.channels()).max(1);
+
+    AsyncFrames {
+      unread: self.header.len() * stride,
+      file:   self,
+      block:  Vec::with_capacity(block * stride),
+      offset: 0,
+      frames: block,
+      buffer: vec![0; capacity.max(1) * stride],
+      start:  0,
+      end:    0,
+      eof:    false,
+      failed: false
+    }
+  }
+
+  /// Returns the reader the file was opened from.
+  pub fn into_inner(self) -> R {
+    self.reader
+  }
+}
+
+impl<R> AsyncWaveFile<R> {
+  /// See `WaveFile::info()`.
+  pub fn info(&self) -> WaveInfo {
+    self.header.info()
+  }
+
+  pub fn channels(&self) -> usize {
+    self.header.channels()
+  }
+
+  pub fn sample_rate(&self) -> usize {
+    self.header.sample_rate()
+  }
+
+  /// The total number of frames present in the file.
+  pub fn len(&self) -> usize {
+    self.header.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.header.is_empty()
+  }
+
+  pub fn data_format(&self) -> Format {
+    self.header.data_format()
+  }
+
+  /// The title of the file, if its tags precede the audio.
+  pub fn title(&self) -> Option<String> {
+    self.header.title()
+  }
+
+  /// The file's `smpl` chunk, if it precedes the audio.
+  pub fn sampler(&self) -> Option<&Sampler> {
+    self.header.sampler()
+  }
+
+  /// The file's `inst` chunk, if it precedes the audio.
+  pub fn instrument(&self) -> Option<Instrument> {
+    self.header.instrument()
+  }
+}
+
+#[cfg(feature = "async")]
+pub(crate) type Opening<R> = Pin<Box<dyn Future<Output = io::Result<R>> + Send>>;
+
+/// Seeks a source to a position, noting whether a seek is in progress.
+type SeekFn<R> = fn(&mut R, &mut Context, SeekFrom, &mut bool) -> Poll<io::Result<u64>>;
+
+enum OpenState<R> {
+  #[cfg(feature = "async")]
+  Opening(Opening<R>),
+  /// Finding the length of the stream.
+  Length(R),
+  /// Returning to the start of the stream, given its length.
+  Rewind(R, usize),
+  /// Reading the RIFF header, or the header or body of a chunk.
+  Header(R, usize),
+  Done
+}
+
+/// Reads the chunks of a file up to and including the header of its data
+/// chunk, then parses them.
+struct Open<R> {
+  state:    OpenState<R>,
+  /// How to seek the source, unless it is read as a stream.
+  seek:     Option<SeekFn<R>>,
+  seeking:  bool,
+  options:  WaveFileOptions,
+  bytes:    Vec<u8>,
+  fill:     Fill,
+  /// Whether `bytes` ends with the header of a chunk, rather than a body.
+  at_chunk: bool
+}
+
+impl<R: AsyncSource> Open<R> {
+  fn new(state: OpenState<R>, seek: Option<SeekFn<R>>, options: WaveFileOptions) -> Open<R> {
+    Open { state, seek, seeking: false, options, bytes: Vec::new(), fill: Fill::default(), at_chunk: false }
+  }
+}
+
+impl<R: AsyncSource> Future for Open<R> {
+  type Output = Result<AsyncWaveFile<R>, WaveError>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    let open = self.get_mut();
+
+    loop {
+      open.state = match mem::replace(&mut open.state, OpenState::Done) {
+        #[cfg(feature = "async")]
+        OpenState::Opening(mut opening) => match opening.as_mut().poll(cx) {
+          Poll::Ready(reader) => OpenState::Length(reader?),
+          Poll::Pending       => {
+            open.state = OpenState::Opening(opening);
+            return Poll::Pending;
+          }
+        },
+        OpenState::Length(mut reader) => {
+          let seek = open.seek.expect("only seekable sources are measured");
+
+          match seek(&mut reader, cx, SeekFrom::End(0), &mut open.seeking) {
+            Poll::Ready(len) => OpenState::Rewind(reader, len? as usize),
+            Poll::Pending    => {
+              open.state = OpenState::Length(reader);
+              return Poll::Pending;
+            }
+          }
+        },
+        OpenState::Rewind(mut reader, len) => {
+          let seek = open.seek.expect("only seekable sources are measured");
+
+          match seek(&mut reader, cx, SeekFrom::Start(0), &mut open.seeking) {
+            Poll::Ready(position) => {
+              position?;
+              open.bytes = vec![0; stream::RIFF_HEADER];
+              OpenState::Header(reader, len)
+            },
+            Poll::Pending         => {
+              open.state = OpenState::Rewind(reader, len);
+              return Poll::Pending;
+            }
+          }
+        },
+        OpenState::Header(mut reader, len) => {
+          match open.fill.poll(&mut reader, cx, &mut open.bytes) {
+            Poll::Ready(result) => result?,
+            Poll::Pending       => {
+              open.state = OpenState::Header(reader, len);
+              return Poll::Pending;
+            }
+          }
+
+          let scan_end = len.min(open.options.max_scan_bytes.unwrap_or(usize::MAX));
+          let body     = if open.at_chunk {
+            stream::chunk_body(&open.bytes, scan_end, &open.options)?
+          } else {
+            if open.bytes.len() == stream::RIFF_HEADER {
+              check_riff(&open.bytes)?;
+            }
+            Some(0)
+          };
+
+          match body {
+            Some(body) if open.at_chunk => {
+              open.bytes.resize(open.bytes.len() + body, 0);
+              open.at_chunk = false;
+            },
+            // the parser reports the missing data chunk once the header
+            // runs out.
+            Some(_) if open.bytes.len() + 8 <= scan_end => {
+              open.bytes.resize(open.bytes.len() + 8, 0);
+              open.at_chunk = true;
+            },
+            _ => {
+              let header = stream::open_header(&open.bytes, len, open.options.clone())?;
+
+              return Poll::Ready(Ok(AsyncWaveFile { reader, header }));
+            }
+          }
+
+          OpenState::Header(reader, len)
+        },
+        OpenState::Done => panic!("polled after completion")
+      };
+    }
+  }
+}
+
+/// A stream of the frames of an `AsyncWaveFile`, decoded a block at a
+/// time from a bounded buffer read ahead of the consumer.  The stream ends
+/// after the first error.
+///
+/// Returned by `AsyncWaveFile::frames` and `AsyncWaveFile::frames_buffered`.
+pub struct AsyncFrames<R> {
+  file:   AsyncWaveFile<R>,
+  /// The number of bytes of audio not yet read from the source.
+  unread: usize,
+  block:  Vec<i32>,
+  /// Position of the next frame to yield within `block`.
+  offset: usize,
+  /// The most frames decoded into `block` at once.
+  frames: usize,
+  /// The encoded frames read ahead, at `start..end`.
+  buffer: Vec<u8>,
+  start:  usize,
+  end:    usize,
+  eof:    bool,
+  failed: bool
+}
+
+impl<R> AsyncFrames<R> {
+  /// The number of frames read from the source but not yet yielded.
+  pub fn buffered(&self) -> usize {
+    let file = &self.file.header;
+
+    (self.end - self.start) / file.stride + (self.block.len() - self.offset) / file.channels()
+  }
+
+  /// The most frames read ahead of the consumer.
+  pub fn capacity(&self) -> usize {
+    self.buffer.len() / self.file.header.stride
+  }
+
+  /// Returns the reader the file was opened from.  Any frames read ahead
+  /// are lost.
+  pub fn into_inner(self) -> R {
+    self.file.reader
+  }
+}
+
+impl<R: AsyncSource> AsyncFrames<R> {
+  /// Reads from the source until the buffer is full, the audio is all read
+  /// or the source has nothing ready.
+  fn poll_read_ahead(&mut self, cx: &mut Context) -> Result<(), WaveError> {
+    let stride = self.file.header.stride;
+
+    // frames decoded but not yet yielded count towards the capacity.
+    while !self.eof && self.unread > 0 && self.buffered() < self.capacity() {
+      if self.end == self.buffer.len() {
+        if self.start == 0 {
+          break;
+        }
+        self.buffer.copy_within(self.start..self.end, 0);
+        self.end  -= self.start;
+        self.start = 0;
+      }
+
+      let room  = (self.capacity() - self.buffered()) * stride - (self.end - self.start) % stride;
+      let count = (self.buffer.len() - self.end).min(room).min(self.unread);
+
+      match self.file.reader.poll_read_into(cx, &mut self.buffer[self.end..self.end + count]) {
+        Poll::Ready(Ok(0))     => self.eof = true,
+        Poll::Ready(Ok(count)) => {
+          self.end    += count;
+          self.unread -= count;
+        },
+        Poll::Ready(Err(e))    => return Err(e.into()),
+        Poll::Pending          => break
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl<R: AsyncSource> Stream for AsyncFrames<R> {
+  type Item = Result<Frame, WaveError>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+    let frames = self.get_mut();
+
+    if frames.failed {
+      return Poll::Ready(None);
+    }
+
+    if let Err(e) = frames.poll_read_ahead(cx) {
+      frames.failed = true;
+      return Poll::Ready(Some(Err(e)));
+    }
+
+    let header   = &frames.file.header;
+    let channels = header.channels();
+
+    if frames.offset == frames.block.len() {
+      let count = ((frames.end - frames.start) / header.stride).min(frames.frames);
+
+      if count == 0 {
+        // with room in the buffer, the source had nothing ready.
+        if !frames.eof && frames.unread > 0 {
+          return Poll::Pending;
+        }
+
+        if frames.unread > 0 && !header.info.data_size_untrusted {
+          frames.failed = true;
+          return Poll::Ready(Some(Err(WaveError::ParseError("Unexpected EOF".into()))));
+        }
+
+        return Poll::Ready(None);
+      }
+
+      let length = count * header.stride;
+
+      trace_event!(TRACE, frames = count, buffered = frames.end - frames.start, "decode block");
+      frames.block.resize(count * channels, 0);
+      stream::decode_block(header, header.decoder, &frames.buffer[frames.start..frames.start + length], &mut frames.block);
+      frames.start += length;
+      frames.offset = 0;
+    }
+
+    let frame = frames.block[frames.offset..frames.offset + channels].to_vec();
+
+    frames.offset += channels;
+    Poll::Ready(Some(Ok(frame)))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let remaining = self.buffered() + self.unread / self.file.header.stride;
+
+    if self.file.header.info.data_size_untrusted {
+      (self.buffered(), Some(remaining))
+    } else {
+      (remaining, Some(remaining))
+    }
+  }
+}
@@ -0,0 +1,278 @@
+use std::future::Future;
+use std::io::{self,Seek,SeekFrom,Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{ready,Context,Poll};
+
+use tokio::fs::File;
+use tokio::io::{AsyncSeek,AsyncWrite};
+
+use async_io::{self,Opening};
+use error::WaveError;
+use writer::{WaveSpec,WaveWriter};
+use FourCC;
+
+/// The bytes a `WaveWriter` has produced but that are not yet written out,
+/// along with its later changes to bytes already written.
+#[derive(Default)]
+struct Staging {
+  /// Bytes following those written out.
+  buffer:   Vec<u8>,
+  /// Changes to bytes already written out, with their offsets.
+  patches:  Vec<(u64, Vec<u8>)>,
+  position: u64,
+  /// The length of the file.
+  end:      u64
+}
+
+impl Write for Staging {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let flushed = self.end - self.buffer.len() as u64;
+
+    if self.position > self.end {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, "write past the end of the file"));
+    }
+
+    if self.position < flushed {
+      let count = buf.len().min((flushed - self.position) as usize);
+
+      self.patches.push((self.position, buf[..count].to_vec()));
+      self.position += count as u64;
+      return Ok(count);
+    }
+
+    let start   = (self.position - flushed) as usize;
+    let overlap = buf.len().min(self.buffer.len() - start);
+
+    self.buffer[start..start + overlap].copy_from_slice(&buf[..overlap]);
+    self.buffer.extend_from_slice(&buf[overlap..]);
+    self.position += buf.len() as u64;
+    self.end       = self.end.max(self.position);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+impl Seek for Staging {
+  fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+    let position = match pos {
+      SeekFrom::Start(offset)   => Some(offset),
+      SeekFrom::End(offset)     => self.end.checked_add_signed(offset),
+      SeekFrom::Current(offset) => self.position.checked_add_signed(offset)
+    };
+
+    match position {
+      Some(position) => {
+        self.position = position;
+        Ok(position)
+      },
+      None           => Err(io::Error::new(io::ErrorKind::InvalidInput, "seek before the start of the file"))
+    }
+  }
+}
+
+/// Writes frames of audio to a new wavefile asynchronously, such as through
+/// tokio, so services can produce files inside async handlers.
+///
+/// This is a `WaveWriter` whose output is held in memory until it is
+/// written out by `write_frames()` or `finalize()`.  A writer dropped
+/// without being finalized leaves the file incomplete.
+///
+/// # Example
+///
+/// ```
+/// extern crate tokio;
+/// extern crate wavefile;
+///
+/// use std::io::Cursor;
+/// use wavefile::{AsyncWaveWriter,Format,WaveSpec};
+///
+/// let runtime    = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// let spec       = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+/// let mut out    = Vec::new();
+/// let mut writer = AsyncWaveWriter::new(Cursor::new(&mut out), spec).unwrap();
+///
+/// runtime.block_on(writer.write_frames(&[0, 1000, 2000, 1000])).unwrap();
+/// runtime.block_on(writer.finalize()).unwrap();
+/// assert_eq!(out.len(), 44 + 4 * 2);
+/// ```
+pub struct AsyncWaveWriter<W> {
+  inner:   W,
+  writer:  WaveWriter<Staging>,
+  /// The number of bytes of the buffer or patch being written out.
+  written: usize,
+  seeking: bool,
+  /// Whether the stream is positioned at the patch being written out.
+  sought:  bool,
+  /// Whether the stream must return to the end after writing patches.
+  patched: bool
+}
+
+impl AsyncWaveWriter<File> {
+  /// Creates a new file at `path` through `tokio::fs`, replacing any
+  /// existing file.
+  pub fn create<P: AsRef<Path>>(path: P, spec: WaveSpec)
+      -> impl Future<Output = Result<AsyncWaveWriter<File>, WaveError>> + Send {
+    let path = path.as_ref().to_owned();
+
+    Create { creating: Box::pin(File::create(path)), spec }
+  }
+}
+
+impl<W: AsyncWrite + AsyncSeek + Unpin> AsyncWaveWriter<W> {
+  /// Creates a writer producing a wavefile in `inner`, which is expected to
+  /// be empty.
+  pub fn new(inner: W, spec: WaveSpec) -> Result<AsyncWaveWriter<W>, WaveError> {
+    Ok(AsyncWaveWriter {
+      inner,
+      writer:  WaveWriter::new(Staging::default(), spec)?,
+      written: 0,
+      seeking: false,
+      sought:  false,
+      patched: false
+    })
+  }
+
+  /// The format of the audio being written.
+  pub fn spec(&self) -> WaveSpec {
+    self.writer.spec()
+  }
+
+  /// The number of frames written so far.
+  pub fn len(&self) -> usize {
+    self.writer.len()
+  }
+
+  /// Returns `true` if no frames have been written yet.
+  pub fn is_empty(&self) -> bool {
+    self.writer.is_empty()
+  }
+
+  /// Adds a chunk to the file, like `WaveWriter::add_chunk`.  It is
+  /// written out along with the next frames.
+  pub fn add_chunk(&mut self, id: FourCC, body: &[u8]) -> Result<(), WaveError> {
+    self.writer.add_chunk(id, body)
+  }
+
+  /// Writes interleaved frames, on the same scale as
+  /// `WaveWriter::write_frame`.  `samples` must hold a whole number of
+  /// frames.
+  pub fn write_frames<'a>(&'a mut self, samples: &[i32]) -> impl Future<Output = Result<(), WaveError>> + 'a {
+    let channels = self.spec().channels as usize;
+    let staged   = if !samples.len().is_multiple_of(channels) {
+      let msg = format!("{} samples don't make whole frames of {} channel(s)", samples.len(), channels);
+
+      Err(WaveError::WriteError(msg))
+    } else {
+      samples.chunks(channels).try_for_each(|frame| self.writer.write_frame(frame))
+    };
+
+    Flush { writer: self, staged: Some(staged) }
+  }
+
+  /// Writes any remaining chunks, fills in the sizes in the file header and
+  /// flushes the stream.
+  pub fn finalize(mut self) -> impl Future<Output = Result<(), WaveError>> {
+    let staged = self.writer.finish();
+
+    Finalize { writer: self, staged: Some(staged) }
+  }
+
+  /// Writes out the staged bytes, then the patches to bytes already
+  /// written, and flushes the stream.
+  fn poll_write_out(&mut self, cx: &mut Context) -> Poll<Result<(), WaveError>> {
+    let staging = self.writer.get_mut();
+
+    while self.written < staging.buffer.len() {
+      match ready!(Pin::new(&mut self.inner).poll_write(cx, &staging.buffer[self.written..]))? {
+        0     => return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero).into())),
+        count => self.written += count
+      }
+    }
+    staging.buffer.clear();
+    self.written = 0;
+
+    while !staging.patches.is_empty() {
+      let (offset, ref bytes) = staging.patches[0];
+
+      if !self.sought {
+        ready!(async_io::poll_seek(&mut self.inner, cx, SeekFrom::Start(offset), &mut self.seeking))?;
+        self.sought  = true;
+        self.patched = true;
+      }
+
+      while self.written < bytes.len() {
+        match ready!(Pin::new(&mut self.inner).poll_write(cx, &bytes[self.written..]))? {
+          0     => return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero).into())),
+          count => self.written += count
+        }
+      }
+
+      staging.patches.remove(0);
+      self.written = 0;
+      self.sought  = false;
+    }
+
+    if self.patched {
+      ready!(async_io::poll_seek(&mut self.inner, cx, SeekFrom::Start(staging.end), &mut self.seeking))?;
+      self.patched = false;
+    }
+
+    ready!(Pin::new(&mut self.inner).poll_flush(cx))?;
+    Poll::Ready(Ok(()))
+  }
+}
+
+struct Create {
+  creating: Opening<File>,
+  spec:     WaveSpec
+}
+
+impl Future for Create {
+  type Output = Result<AsyncWaveWriter<File>, WaveError>;
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    let file = ready!(self.creating.as_mut().poll(cx))?;
+
+    Poll::Ready(AsyncWaveWriter::new(file, self.spec))
+  }
+}
+
+/// Writes out the output of the frames just staged.
+struct Flush<'a, W> {
+  writer: &'a mut AsyncWaveWriter<W>,
+  /// The result of staging, until it has been reported.
+  staged: Option<Result<(), WaveError>>
+}
+
+impl<'a, W: AsyncWrite + AsyncSeek + Unpin> Future for Flush<'a, W> {
+  type Output = Result<(), WaveError>;
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    if let Some(staged) = self.staged.take() {
+      staged?;
+    }
+
+    self.writer.poll_write_out(cx)
+  }
+}
+
+struct Finalize<W> {
+  writer: AsyncWaveWriter<W>,
+  staged: Option<Result<(), WaveError>>
+}
+
+impl<W: AsyncWrite + AsyncSeek + Unpin> Future for Finalize<W> {
+  type Output = Result<(), WaveError>;
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    if let Some(staged) = self.staged.take() {
+      staged?;
+    }
+
+    self.writer.poll_write_out(cx)
+  }
+}
@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use error::WaveError;
+use formats::Format;
+use progress::{CancelToken,Progress};
+use writer::{WaveWriter,WaveSpec};
+use WaveFile;
+
+/// Converts each file in `inputs`, given as pairs of source and destination
+/// paths, to the sample format of `spec`, converting up to `parallelism`
+/// files at once.
+///
+/// Returns the number of frames written or the error met for each file, in
+/// the order of `inputs`.  A file that fails may leave a partial copy at its
+/// destination, which must differ from its source.  The channel count and
+/// sample rate of `spec` must match those of every source.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::{WaveSpec,Format};
+/// use wavefile::batch;
+///
+/// let spec   = WaveSpec { channels: 2, sample_rate: 44100, bits_per_sample: 16, format: Format::PCM };
+/// let inputs = [("./a.wav", "./a-16.wav"), ("./b.wav", "./b-16.wav")];
+///
+/// for ((source, _), result) in inputs.iter().zip(batch::convert(&inputs, spec, 4)) {
+///   if let Err(e) = result {
+///     println!("{}: {}", source, e);
+///   }
+/// }
+/// ```
+pub fn convert<S, T>(inputs: &[(S, T)], spec: WaveSpec, parallelism: usize) -> Vec<Result<u64, WaveError>>
+  where S: AsRef<str> + Sync, T: AsRef<str> + Sync {
+  convert_all(inputs, spec, parallelism, None)
+}
+
+/// Converts each file in `inputs` like `convert`, reporting the number of
+/// files finished out of all of them to `progress`.
+///
+/// Once the token of `progress` is cancelled, files being converted stop
+/// at their next block and those not yet started are skipped, failing with
+/// `WaveError::Cancelled`.
+pub fn convert_with_progress<S, T>(inputs: &[(S, T)], spec: WaveSpec, parallelism: usize, progress: &mut Progress)
+                                   -> Vec<Result<u64, WaveError>>
+  where S: AsRef<str> + Sync, T: AsRef<str> + Sync {
+  convert_all(inputs, spec, parallelism, Some(progress))
+}
+
+fn convert_all<S, T>(inputs: &[(S, T)], spec: WaveSpec, parallelism: usize, progress: Option<&mut Progress>)
+                     -> Vec<Result<u64, WaveError>>
+  where S: AsRef<str> + Sync, T: AsRef<str> + Sync {
+  let next     = AtomicUsize::new(0);
+  let results  = Mutex::new(Vec::with_capacity(inputs.len()));
+  let cancel   = progress.as_ref().map(|progress| progress.token().clone()).unwrap_or_default();
+  let progress = Mutex::new(progress);
+
+  thread::scope(|scope| {
+    for _ in 0..parallelism.max(1).min(inputs.len()) {
+      scope.spawn(|| loop {
+        let i = next.fetch_add(1, Ordering::Relaxed);
+
+        let (source, destination) = match inputs.get(i) {
+          Some((source, destination)) => (source.as_ref(), destination.as_ref()),
+          None                        => break
+        };
+        let result      = convert_file(source, destination, spec, &cancel);
+        let mut results = results.lock().unwrap();
+
+        results.push((i, result));
+
+        // workers notice cancellation through their token.
+        if let Some(ref mut progress) = *progress.lock().unwrap() {
+          let _ = progress.update(results.len(), inputs.len());
+        }
+      });
+    }
+  });
+
+  // workers finish files in any order.
+  let mut results = results.into_inner().unwrap();
+
+  results.sort_by_key(|&(i, _)| i);
+  results.into_iter().map(|(_, result)| result).collect()
+}
+
+fn convert_file(source: &str, destination: &str, spec: WaveSpec, cancel: &CancelToken) -> Result<u64, WaveError> {
+  cancel.check()?;
+
+  let file     = WaveFile::open(source)?;
+  let channels = file.channels();
+
+  if channels != spec.channels as usize || file.sample_rate() != spec.sample_rate as usize {
+    return Err(WaveError::Unsupported(format!("Can't convert {} channel(s) at {} Hz to {} channel(s) at {} Hz",
+                                              channels, file.sample_rate(), spec.channels, spec.sample_rate)));
+  }
+
+  // samples are moved to full scale and back down to the target depth.
+  let from       = headroom(file.data_format(), file.bits_per_sample());
+  let to         = headroom(spec.format, spec.bits_per_sample as usize);
+  let mut writer = WaveWriter::create(destination, spec)?;
+  let mut buffer = vec![0; (4096 / channels).max(1) * channels];
+  let mut first  = 0;
+
+  loop {
+    let frames = file.read_frames(first, &mut buffer);
+
+    if frames == 0 {
+      break;
+    }
+    cancel.check()?;
+
+    for frame in buffer[..frames * channels].chunks_mut(channels) {
+      for sample in frame.iter_mut() {
+        *sample = (*sample << from) >> to;
+      }
+      writer.write_frame(frame)?;
+    }
+    first += frames;
+  }
+
+  writer.finalize()?;
+  Ok(first as u64)
+}
+
+/// Number of bits samples of the format given fall short of full scale,
+/// as decoded by `WaveFile::read_frames`.
+fn headroom(format: Format, bits_per_sample: usize) -> u32 {
+  match format {
+    Format::IEEEFloat => 0,
+    _                 => 32 - bits_per_sample.min(32) as u32
+  }
+}
@@ -0,0 +1,146 @@
+use std::io::{Cursor,Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use error::WaveError;
+use text::TextEncoding;
+use umid::Umid;
+use write_fixed;
+
+/// Size of the fixed fields of the `bext` chunk, before the coding history.
+const BEXT_FIXED_SIZE : usize = 602;
+
+/// Value of a loudness field which has not been measured.
+const LOUDNESS_UNSET : i16 = 0x7fff;
+
+/// EBU R128 loudness measurements, present in version 2 `bext` chunks.
+/// Fields that were not measured are `None`.
+#[derive(Debug,Copy,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct Loudness {
+  /// Integrated loudness in LUFS.
+  pub integrated:     Option<f32>,
+  /// Loudness range in LU.
+  pub range:          Option<f32>,
+  /// Maximum true peak level in dBTP.
+  pub max_true_peak:  Option<f32>,
+  /// Highest momentary loudness in LUFS.
+  pub max_momentary:  Option<f32>,
+  /// Highest short term loudness in LUFS.
+  pub max_short_term: Option<f32>
+}
+
+/// Contents of the Broadcast Wave `bext` chunk.
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct Bext {
+  pub description:          String,
+  /// Name of the organisation or device which created the file.
+  pub originator:           String,
+  pub originator_reference: String,
+  /// Date of creation, formatted as `yyyy:mm:dd`.
+  pub origination_date:     String,
+  /// Time of creation, formatted as `hh:mm:ss`.
+  pub origination_time:     String,
+  /// Number of samples since midnight at which the first frame of the file
+  /// should be played.
+  pub time_reference:       u64,
+  pub version:              u16,
+  /// SMPTE unique material identifier, all zeroes if unset.
+  pub umid:                 Umid,
+  /// Loudness measurements, only present for version 2 or later.
+  pub loudness:             Option<Loudness>,
+  /// Describes the history of coding processes applied to the audio.
+  pub coding_history:       String
+}
+
+impl Bext {
+  pub(crate) fn parse_chunk(data: &[u8], encoding: TextEncoding) -> Result<Bext, WaveError> {
+    if data.len() < BEXT_FIXED_SIZE {
+      let msg = format!("Broadcast extension chunk is only {} bytes long", data.len());
+      return Err(WaveError::ParseError(msg));
+    }
+
+    let mut cursor = Cursor::new(&data[338..]);
+    let time_low   = cursor.read_u32::<LittleEndian>()? as u64;
+    let time_high  = cursor.read_u32::<LittleEndian>()? as u64;
+    let version    = cursor.read_u16::<LittleEndian>()?;
+    let mut umid   = [0u8; 64];
+
+    umid.copy_from_slice(&data[348..412]);
+
+    let umid = Umid::from_bytes(umid);
+    cursor.set_position(74);
+
+    let loudness = if version >= 2 {
+      Some(Loudness {
+        integrated:     read_loudness(&mut cursor)?,
+        range:          read_loudness(&mut cursor)?,
+        max_true_peak:  read_loudness(&mut cursor)?,
+        max_momentary:  read_loudness(&mut cursor)?,
+        max_short_term: read_loudness(&mut cursor)?
+      })
+    } else {
+      None
+    };
+
+    Ok(Bext {
+      description:          encoding.decode_zstring(&data[0..256]),
+      originator:           encoding.decode_zstring(&data[256..288]),
+      originator_reference: encoding.decode_zstring(&data[288..320]),
+      origination_date:     encoding.decode_zstring(&data[320..330]),
+      origination_time:     encoding.decode_zstring(&data[330..338]),
+      time_reference:       (time_high << 32) | time_low,
+      version,
+      umid,
+      loudness,
+      coding_history:       encoding.decode_zstring(&data[BEXT_FIXED_SIZE..])
+    })
+  }
+
+  /// Writes the chunk body, without the chunk header.  Text fields longer
+  /// than their fixed size in the chunk are truncated.
+  pub fn write_to<W: Write>(&self, out: &mut W) -> Result<(), WaveError> {
+    write_fixed(out, &self.description, 256)?;
+    write_fixed(out, &self.originator, 32)?;
+    write_fixed(out, &self.originator_reference, 32)?;
+    write_fixed(out, &self.origination_date, 10)?;
+    write_fixed(out, &self.origination_time, 8)?;
+    out.write_u32::<LittleEndian>(self.time_reference as u32)?;
+    out.write_u32::<LittleEndian>((self.time_reference >> 32) as u32)?;
+    out.write_u16::<LittleEndian>(self.version)?;
+    out.write_all(self.umid.as_bytes())?;
+
+    match self.loudness {
+      Some(ref loudness) if self.version >= 2 => {
+        write_loudness(out, loudness.integrated)?;
+        write_loudness(out, loudness.range)?;
+        write_loudness(out, loudness.max_true_peak)?;
+        write_loudness(out, loudness.max_momentary)?;
+        write_loudness(out, loudness.max_short_term)?;
+      },
+      _ => out.write_all(&[0u8; 10])?
+    }
+
+    out.write_all(&[0u8; 180])?;
+    out.write_all(self.coding_history.as_bytes())?;
+    Ok(())
+  }
+}
+
+fn read_loudness(cursor: &mut Cursor<&[u8]>) -> Result<Option<f32>, WaveError> {
+  match cursor.read_i16::<LittleEndian>()? {
+    LOUDNESS_UNSET => Ok(None),
+    value          => Ok(Some(value as f32 / 100.0))
+  }
+}
+
+fn write_loudness<W: Write>(out: &mut W, value: Option<f32>) -> Result<(), WaveError> {
+  let raw = match value {
+    Some(value) => (value * 100.0).round().clamp(-32768.0, 32766.0) as i16,
+    None        => LOUDNESS_UNSET
+  };
+
+  out.write_i16::<LittleEndian>(raw)?;
+  Ok(())
+}
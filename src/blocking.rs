@@ -0,0 +1,172 @@
+//! Runs the synchronous reader on tokio's blocking pool, for async
+//! applications that must not block their executor but don't need the
+//! streaming `AsyncWaveFile`.
+//!
+//! Opening a file and touching its mapped pages can wait on the disk, so
+//! each call here is made through `tokio::task::spawn_blocking`.  Frames
+//! are decoded a block of `WaveFileOptions::block_samples` samples per
+//! call, which keeps each call short and the pool free for other work.
+//!
+//! # Example
+//!
+//! ```
+//! extern crate futures_core;
+//! extern crate tokio;
+//! extern crate wavefile;
+//!
+//! use std::future::poll_fn;
+//! use std::pin::Pin;
+//! use std::sync::Arc;
+//! use futures_core::Stream;
+//! use wavefile::blocking;
+//!
+//! let runtime    = tokio::runtime::Builder::new_current_thread().build().unwrap();
+//! let wav        = runtime.block_on(blocking::open("./fixtures/test-s24le.wav")).unwrap();
+//! let wav        = Arc::new(wav);
+//! let mut blocks = blocking::blocks(wav.clone());
+//! let mut count  = 0;
+//!
+//! while let Some(block) = runtime.block_on(poll_fn(|cx| Pin::new(&mut blocks).poll_next(cx))) {
+//!   count += block.unwrap().len() / wav.channels();
+//! }
+//!
+//! assert_eq!(count, wav.len());
+//! ```
+
+use std::future::Future;
+use std::io;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready,Context,Poll};
+
+use futures_core::Stream;
+use tokio::task::{self,JoinHandle};
+
+use error::WaveError;
+use options::WaveFileOptions;
+use WaveFile;
+
+/// A call made on the blocking pool once first polled, as spawning it needs
+/// the runtime.
+struct Blocking<T, F> {
+  call:   Option<F>,
+  handle: Option<JoinHandle<Result<T, WaveError>>>
+}
+
+impl<T, F> Future for Blocking<T, F>
+  where T: Send + 'static, F: FnOnce() -> Result<T, WaveError> + Send + Unpin + 'static {
+  type Output = Result<T, WaveError>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    let blocking = self.get_mut();
+
+    if let Some(call) = blocking.call.take() {
+      blocking.handle = Some(task::spawn_blocking(call));
+    }
+
+    poll_join(blocking.handle.as_mut().expect("the call was spawned"), cx)
+  }
+}
+
+fn spawn<T, F>(call: F) -> Blocking<T, F> {
+  Blocking { call: Some(call), handle: None }
+}
+
+fn poll_join<T>(handle: &mut JoinHandle<Result<T, WaveError>>, cx: &mut Context) -> Poll<Result<T, WaveError>> {
+  match ready!(Pin::new(handle).poll(cx)) {
+    Ok(result) => Poll::Ready(result),
+    // the call panicked, or the runtime is shutting down.
+    Err(e)     => Poll::Ready(Err(io::Error::from(e).into()))
+  }
+}
+
+/// Opens the file at `path` like `WaveFile::open`, on the blocking pool.
+pub fn open<S: Into<String>>(path: S) -> impl Future<Output = Result<WaveFile, WaveError>> {
+  open_with(path, WaveFileOptions::default())
+}
+
+/// Opens the file at `path` like `WaveFile::open_with`, on the blocking
+/// pool.
+pub fn open_with<S: Into<String>>(path: S, options: WaveFileOptions) -> impl Future<Output = Result<WaveFile, WaveError>> {
+  let path = path.into();
+
+  spawn(move || WaveFile::open_with(path, options))
+}
+
+/// Decodes the frames of `file` in `frames` on the blocking pool,
+/// interleaved like `WaveFile::read_frames`.  The range is clamped to the
+/// frames present.
+pub fn read_frames(file: Arc<WaveFile>, frames: Range<usize>) -> impl Future<Output = Result<Vec<i32>, WaveError>> {
+  spawn(decode(file, frames))
+}
+
+/// Returns a stream of the frames of `file`, interleaved, decoded a block
+/// at a time on the blocking pool.
+pub fn blocks(file: Arc<WaveFile>) -> Blocks {
+  let frames = (file.options.block_samples / file.channels()).max(1);
+
+  Blocks { file, next: 0, frames, block: None }
+}
+
+/// A stream of blocks of interleaved samples, each decoded by one call on
+/// the blocking pool.  Only one block is decoded at a time, and only while
+/// the stream is polled.
+///
+/// Returned by `blocking::blocks`.
+pub struct Blocks {
+  file:   Arc<WaveFile>,
+  /// The index of the first frame not yet asked for.
+  next:   usize,
+  /// The number of frames in each block.
+  frames: usize,
+  /// The block being decoded.
+  block:  Option<JoinHandle<Result<Vec<i32>, WaveError>>>
+}
+
+impl Stream for Blocks {
+  type Item = Result<Vec<i32>, WaveError>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+    let blocks = self.get_mut();
+
+    if blocks.block.is_none() {
+      if blocks.next >= blocks.file.len() {
+        return Poll::Ready(None);
+      }
+
+      let end = blocks.next + blocks.frames;
+
+      blocks.block = Some(task::spawn_blocking(decode(blocks.file.clone(), blocks.next..end)));
+      blocks.next  = end;
+    }
+
+    let block = ready!(poll_join(blocks.block.as_mut().expect("a block is being decoded"), cx));
+
+    blocks.block = None;
+    if block.is_err() {
+      // the stream ends after the first error.
+      blocks.next = blocks.file.len();
+    }
+    Poll::Ready(Some(block))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let frames    = self.file.len().saturating_sub(self.next);
+    let remaining = frames.div_ceil(self.frames) + self.block.is_some() as usize;
+
+    (remaining, Some(remaining))
+  }
+}
+
+/// The blocking call decoding `frames` from `file`.
+fn decode(file: Arc<WaveFile>, frames: Range<usize>) -> impl FnOnce() -> Result<Vec<i32>, WaveError> {
+  move || {
+    let first       = frames.start.min(file.len());
+    let count       = frames.end.clamp(first, file.len()) - first;
+    let mut samples = vec![0; count * file.channels()];
+
+    file.read_frames(first, &mut samples);
+    Ok(samples)
+  }
+}
@@ -0,0 +1,166 @@
+use std::io::Write;
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+
+use error::WaveError;
+use text::TextEncoding;
+use {write_fixed,FourCC};
+
+/// Size of the fixed fields of the `cart` chunk, before the tag text.
+const CART_FIXED_SIZE : usize = 2048;
+
+/// Number of post timer slots in the `cart` chunk.
+const CART_TIMERS     : usize = 8;
+
+/// A timer marker of the `cart` chunk, such as the start of an intro or the
+/// point at which the next cut should be started.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct CartTimer {
+  /// What the timer marks, e.g. `SEG1` or `INT1`.
+  pub usage: FourCC,
+  /// Position of the timer, in frames.
+  pub value: u32
+}
+
+/// Contents of the AES46 `cart` chunk used by radio automation systems.
+/// Dates are formatted as `yyyy/mm/dd` and times as `hh:mm:ss`.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Cart {
+  /// Version of the chunk, e.g. `0101`.
+  pub version:              String,
+  pub title:                String,
+  pub artist:               String,
+  /// Identifier of the cut in the automation system.
+  pub cut_id:               String,
+  pub client_id:            String,
+  pub category:             String,
+  pub classification:       String,
+  /// Text describing the end of the audio, e.g. `fade out`.
+  pub out_cue:              String,
+  /// Date and time from which the cut may be aired.
+  pub start_date:           String,
+  pub start_time:           String,
+  /// Date and time after which the cut may no longer be aired.
+  pub end_date:             String,
+  pub end_time:             String,
+  pub producer_app_id:      String,
+  pub producer_app_version: String,
+  pub user_def:             String,
+  /// Sample value corresponding to 0 dB reference level.
+  pub level_reference:      i32,
+  /// The timers in use, unused slots are omitted.
+  pub post_timers:          Vec<CartTimer>,
+  pub url:                  String,
+  /// Free form text, lines are terminated with CR LF.
+  pub tag_text:             String
+}
+
+impl Default for Cart {
+  fn default() -> Cart {
+    Cart {
+      version:              "0101".into(),
+      title:                String::new(),
+      artist:               String::new(),
+      cut_id:               String::new(),
+      client_id:            String::new(),
+      category:             String::new(),
+      classification:       String::new(),
+      out_cue:              String::new(),
+      start_date:           String::new(),
+      start_time:           String::new(),
+      end_date:             String::new(),
+      end_time:             String::new(),
+      producer_app_id:      String::new(),
+      producer_app_version: String::new(),
+      user_def:             String::new(),
+      level_reference:      0,
+      post_timers:          Vec::new(),
+      url:                  String::new(),
+      tag_text:             String::new()
+    }
+  }
+}
+
+impl Cart {
+  pub(crate) fn parse_chunk(data: &[u8], encoding: TextEncoding) -> Result<Cart, WaveError> {
+    if data.len() < CART_FIXED_SIZE {
+      let msg = format!("Cart chunk is only {} bytes long", data.len());
+      return Err(WaveError::ParseError(msg));
+    }
+
+    let level_reference = LittleEndian::read_i32(&data[680..684]);
+    let mut post_timers = Vec::with_capacity(CART_TIMERS);
+
+    for i in 0..CART_TIMERS {
+      let offset    = 684 + i * 8;
+      let mut usage = [0u8; 4];
+
+      usage.copy_from_slice(&data[offset..offset + 4]);
+
+      if usage != [0u8; 4] {
+        let value = LittleEndian::read_u32(&data[offset + 4..offset + 8]);
+        post_timers.push(CartTimer { usage, value });
+      }
+    }
+
+    Ok(Cart {
+      version:              encoding.decode_zstring(&data[0..4]),
+      title:                encoding.decode_zstring(&data[4..68]),
+      artist:               encoding.decode_zstring(&data[68..132]),
+      cut_id:               encoding.decode_zstring(&data[132..196]),
+      client_id:            encoding.decode_zstring(&data[196..260]),
+      category:             encoding.decode_zstring(&data[260..324]),
+      classification:       encoding.decode_zstring(&data[324..388]),
+      out_cue:              encoding.decode_zstring(&data[388..452]),
+      start_date:           encoding.decode_zstring(&data[452..462]),
+      start_time:           encoding.decode_zstring(&data[462..470]),
+      end_date:             encoding.decode_zstring(&data[470..480]),
+      end_time:             encoding.decode_zstring(&data[480..488]),
+      producer_app_id:      encoding.decode_zstring(&data[488..552]),
+      producer_app_version: encoding.decode_zstring(&data[552..616]),
+      user_def:             encoding.decode_zstring(&data[616..680]),
+      level_reference,
+      post_timers,
+      url:                  encoding.decode_zstring(&data[1024..2048]),
+      tag_text:             encoding.decode_zstring(&data[CART_FIXED_SIZE..])
+    })
+  }
+
+  /// Writes the chunk body, without the chunk header.  Fields longer than
+  /// their fixed size in the chunk are truncated, and at most 8 post timers
+  /// are written.
+  pub fn write_to<W: Write>(&self, out: &mut W) -> Result<(), WaveError> {
+    write_fixed(out, &self.version, 4)?;
+    write_fixed(out, &self.title, 64)?;
+    write_fixed(out, &self.artist, 64)?;
+    write_fixed(out, &self.cut_id, 64)?;
+    write_fixed(out, &self.client_id, 64)?;
+    write_fixed(out, &self.category, 64)?;
+    write_fixed(out, &self.classification, 64)?;
+    write_fixed(out, &self.out_cue, 64)?;
+    write_fixed(out, &self.start_date, 10)?;
+    write_fixed(out, &self.start_time, 8)?;
+    write_fixed(out, &self.end_date, 10)?;
+    write_fixed(out, &self.end_time, 8)?;
+    write_fixed(out, &self.producer_app_id, 64)?;
+    write_fixed(out, &self.producer_app_version, 64)?;
+    write_fixed(out, &self.user_def, 64)?;
+    out.write_i32::<LittleEndian>(self.level_reference)?;
+
+    for i in 0..CART_TIMERS {
+      match self.post_timers.get(i) {
+        Some(timer) => {
+          out.write_all(&timer.usage)?;
+          out.write_u32::<LittleEndian>(timer.value)?;
+        },
+        None => out.write_all(&[0u8; 8])?
+      }
+    }
+
+    out.write_all(&[0u8; 276])?;
+    write_fixed(out, &self.url, 1024)?;
+    out.write_all(self.tag_text.as_bytes())?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,114 @@
+use std::io::{ErrorKind,Read,Seek,SeekFrom};
+use std::ops::Range;
+
+use byteorder::{ByteOrder, LittleEndian};
+use memmap::{Mmap,Protection};
+
+use error::WaveError;
+use options::WaveFileOptions;
+use WaveFile;
+
+/// Size of the blocks the stream is scanned in.
+const SCAN_BLOCK : usize = 1 << 16;
+
+/// A wavefile recovered by `WaveFile::carve`.
+pub struct CarvedWave {
+  /// Offset of the file's RIFF header within the stream.
+  pub offset: usize,
+  /// Offset just past the last byte of the file within the stream.
+  pub end:    usize,
+  /// The part of the stream holding the recovered frames.
+  pub audio:  Range<usize>,
+  /// The recovered file.  If its end was lost, `info().truncated` is set
+  /// and only the complete frames present can be read.
+  pub file:   WaveFile
+}
+
+pub(crate) fn carve<R: Read + Seek>(mut reader: R, options: WaveFileOptions) -> Result<Vec<CarvedWave>, WaveError> {
+  let origin         = reader.stream_position()?;
+  let (starts, size) = signatures(&mut reader)?;
+  let mut carved     = Vec::new();
+  let mut resume     = 0;
+
+  for (index, &(offset, declared)) in starts.iter().enumerate() {
+    // a signature inside the audio of a recovered file isn't a file.
+    if offset < resume {
+      continue;
+    }
+
+    let next = starts.get(index + 1).map(|&(next, _)| next).unwrap_or(size);
+
+    // the next file most likely overwrote the end of this one, and a size
+    // of zero means this one was never finalized.
+    let end = if declared == 0 { next } else { (offset + 8).saturating_add(declared as usize).min(next) };
+
+    // beyond the limit the file is recovered as if its end was lost.
+    let end = match options.max_allocation {
+      Some(max) => end.min(offset.saturating_add(max)),
+      None      => end
+    };
+
+    if end - offset < 12 {
+      continue;
+    }
+
+    let mut mmap = Mmap::anonymous(end - offset, Protection::ReadWrite)?;
+
+    reader.seek(SeekFrom::Start(origin + offset as u64))?;
+    reader.read_exact(unsafe { mmap.as_mut_slice() })?;
+
+    let file = match WaveFile::from_mmap(mmap, options.clone()) {
+      Ok(file) => file,
+      Err(_)   => continue
+    };
+
+    if file.is_empty() {
+      continue;
+    }
+
+    let start = offset + file.data_offset;
+    let audio = start..start + file.data_size;
+
+    resume = end;
+    carved.push(CarvedWave { offset, end, audio, file });
+  }
+
+  Ok(carved)
+}
+
+/// Returns the offset and declared size of every RIFF header of a wavefile
+/// in `reader`, and the length of the stream, scanning it a block at a
+/// time.
+fn signatures<R: Read>(reader: &mut R) -> Result<(Vec<(usize, u32)>, usize), WaveError> {
+  let mut starts = Vec::new();
+  let mut window = vec![0; SCAN_BLOCK + 11];
+  // the bytes kept from the previous block, and the offset of the first.
+  let mut kept   = 0;
+  let mut base   = 0;
+
+  loop {
+    let read = match reader.read(&mut window[kept..]) {
+      Ok(0)                                            => return Ok((starts, base + kept)),
+      Ok(read)                                         => read,
+      Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+      Err(e)                                           => return Err(e.into())
+    };
+    let filled = kept + read;
+
+    if filled < 12 {
+      kept = filled;
+      continue;
+    }
+
+    for offset in 0..filled - 11 {
+      if &window[offset..offset + 4] == b"RIFF" && &window[offset + 8..offset + 12] == b"WAVE" {
+        starts.push((base + offset, LittleEndian::read_u32(&window[offset + 4..offset + 8])));
+      }
+    }
+
+    // the last eleven bytes may start a header completed by the next block.
+    window.copy_within(filled - 11..filled, 0);
+    base += filled - 11;
+    kept  = 11;
+  }
+}
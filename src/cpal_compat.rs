@@ -0,0 +1,99 @@
+use cpal::{BufferSize,FromSample,OutputCallbackInfo,Sample,SizedSample,StreamConfig};
+
+use {WaveFile,WaveInfo};
+
+impl WaveInfo {
+  /// The configuration of a cpal output stream playing the file as is, at
+  /// its own sample rate and channel count.
+  pub fn stream_config(&self) -> StreamConfig {
+    StreamConfig {
+      channels:    self.channels,
+      sample_rate: self.sample_rate,
+      buffer_size: BufferSize::Default
+    }
+  }
+}
+
+/// Feeds the frames of a file to a cpal output stream, converted to the
+/// stream's sample type, and silence once the file has been played.
+///
+/// Returned by `WaveFile::playback`.  The stream is expected to have been
+/// built with `WaveInfo::stream_config()`.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate cpal;
+/// extern crate wavefile;
+///
+/// use cpal::traits::{DeviceTrait,HostTrait,StreamTrait};
+/// use wavefile::WaveFile;
+///
+/// let wav    = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+/// let config = wav.info().stream_config();
+/// let length = std::time::Duration::from_millis(wav.duration() as u64);
+/// let device = cpal::default_host().default_output_device().unwrap();
+/// let stream = device.build_output_stream::<f32, _, _>(config, wav.playback().into_callback(),
+///                                                      |e| eprintln!("{}", e), None).unwrap();
+///
+/// stream.play().unwrap();
+/// std::thread::sleep(length);
+/// ```
+pub struct Playback {
+  file:   WaveFile,
+  /// The index of the next frame to play.
+  next:   usize,
+  /// Samples decoded for the callback, reused from call to call.
+  buffer: Vec<f32>
+}
+
+impl Playback {
+  pub(crate) fn new(file: WaveFile) -> Playback {
+    Playback { file, next: 0, buffer: Vec::new() }
+  }
+
+  /// Fills `out` with the next frames of the file, interleaved, followed
+  /// by silence if the file ends first.
+  pub fn fill<T: Sample + FromSample<f32>>(&mut self, out: &mut [T]) {
+    let channels = self.file.channels();
+
+    if self.buffer.len() < out.len() {
+      self.buffer.resize(out.len(), 0.0);
+    }
+
+    let frames  = self.file.read_frames_f32_into(self.next, &mut self.buffer[..out.len()]);
+    let samples = frames * channels;
+
+    for (out, &sample) in out.iter_mut().zip(&self.buffer[..samples]) {
+      *out = T::from_sample(sample);
+    }
+    for out in &mut out[samples..] {
+      *out = T::EQUILIBRIUM;
+    }
+    self.next += frames;
+  }
+
+  /// The index of the next frame to be played.
+  pub fn position(&self) -> usize {
+    self.next
+  }
+
+  /// Returns `true` once every frame of the file has been played.
+  pub fn is_finished(&self) -> bool {
+    self.next >= self.file.len()
+  }
+
+  /// Turns the playback into a data callback for
+  /// `cpal::traits::DeviceTrait::build_output_stream`.
+  pub fn into_callback<T>(mut self) -> impl FnMut(&mut [T], &OutputCallbackInfo) + Send + 'static
+    where T: SizedSample + FromSample<f32> {
+    move |out, _| self.fill(out)
+  }
+}
+
+impl WaveFile {
+  /// Plays the file through a cpal output stream.  See `Playback`.
+  pub fn playback(self) -> Playback {
+    Playback::new(self)
+  }
+}
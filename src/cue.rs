@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::io::{Cursor,Read,Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use error::WaveError;
+use text::TextEncoding;
+use FourCC;
+
+const LABL : u32 = 0x6c62616c;
+const NOTE : u32 = 0x65746f6e;
+const LTXT : u32 = 0x7478746c;
+
+/// A single entry of the `cue ` chunk, marking a position in the audio data.
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct CuePoint {
+  /// Identifier of the cue point, referenced by the associated data list.
+  pub id:            u32,
+  /// Position of the cue point in play order, in frames.
+  pub position:      u32,
+  /// ID of the chunk containing the cue point, usually `data`.
+  pub data_chunk_id: FourCC,
+  pub chunk_start:   u32,
+  pub block_start:   u32,
+  /// Offset of the cue point within the data chunk, in frames.
+  pub sample_offset: u32
+}
+
+/// A labeled text (`ltxt`) entry of the associated data list, describing a
+/// region of audio starting at the cue point with the same ID.
+#[derive(Debug,Clone,PartialEq)]
+pub struct LabeledText {
+  pub cue_id:        u32,
+  /// Length of the region, in frames.
+  pub sample_length: u32,
+  /// What the region is used for, e.g. `rgn `.
+  pub purpose:       FourCC,
+  pub country:       u16,
+  pub language:      u16,
+  pub dialect:       u16,
+  pub code_page:     u16,
+  pub text:          String
+}
+
+/// The contents of a `LIST` chunk of type `adtl`, which attaches labels,
+/// notes and regions to cue points.
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct AssociatedData {
+  /// `labl` entries, keyed by cue point ID.
+  pub labels: HashMap<u32, String>,
+  /// `note` entries, keyed by cue point ID.
+  pub notes:  HashMap<u32, String>,
+  /// `ltxt` entries, in file order.
+  pub texts:  Vec<LabeledText>
+}
+
+/// An entry of the `plst` chunk, describing a segment of audio to play back.
+/// Segments are played in the order they appear in the playlist.
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct PlaylistSegment {
+  /// ID of the cue point at which the segment starts.
+  pub cue_id: u32,
+  /// Length of the segment, in frames.
+  pub length: u32,
+  /// Number of times the segment is played.
+  pub repeat: u32
+}
+
+/// A named position in the audio, built from a cue point and its label.
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct Marker {
+  pub id:       u32,
+  /// Frame offset of the marker.
+  pub position: u32,
+  pub label:    Option<String>,
+  pub note:     Option<String>
+}
+
+/// A labeled span of audio, built from a cue point and its `ltxt` entry.
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct Region {
+  pub id:      u32,
+  /// Frame offset at which the region starts.
+  pub start:   u32,
+  /// Duration of the region, in frames.
+  pub length:  u32,
+  pub purpose: FourCC,
+  pub label:   Option<String>,
+  pub text:    Option<String>
+}
+
+impl CuePoint {
+  pub(crate) fn parse_chunk(data: &[u8]) -> Result<Vec<CuePoint>, WaveError> {
+    let mut cursor = Cursor::new(data);
+    let count      = cursor.read_u32::<LittleEndian>()? as usize;
+
+    if count > data.len() / 24 {
+      let msg = format!("Cue chunk declares {} points but only holds {} bytes", count, data.len());
+      return Err(WaveError::ParseError(msg));
+    }
+
+    let mut points = Vec::with_capacity(count);
+
+    for _ in 0..count {
+      let id            = cursor.read_u32::<LittleEndian>()?;
+      let position      = cursor.read_u32::<LittleEndian>()?;
+      let mut data_chunk_id = [0u8; 4];
+      cursor.read_exact(&mut data_chunk_id)?;
+      let chunk_start   = cursor.read_u32::<LittleEndian>()?;
+      let block_start   = cursor.read_u32::<LittleEndian>()?;
+      let sample_offset = cursor.read_u32::<LittleEndian>()?;
+
+      points.push(CuePoint {
+        id, position, data_chunk_id, chunk_start, block_start, sample_offset
+      });
+    }
+
+    Ok(points)
+  }
+
+  /// Writes the body of a `cue ` chunk holding `points`.
+  pub(crate) fn write_chunk<W: Write>(points: &[CuePoint], out: &mut W) -> Result<(), WaveError> {
+    out.write_u32::<LittleEndian>(points.len() as u32)?;
+
+    for point in points {
+      out.write_u32::<LittleEndian>(point.id)?;
+      out.write_u32::<LittleEndian>(point.position)?;
+      out.write_all(&point.data_chunk_id)?;
+      out.write_u32::<LittleEndian>(point.chunk_start)?;
+      out.write_u32::<LittleEndian>(point.block_start)?;
+      out.write_u32::<LittleEndian>(point.sample_offset)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl PlaylistSegment {
+  pub(crate) fn parse_chunk(data: &[u8]) -> Result<Vec<PlaylistSegment>, WaveError> {
+    let mut cursor = Cursor::new(data);
+    let count      = cursor.read_u32::<LittleEndian>()? as usize;
+
+    if count > data.len() / 12 {
+      let msg = format!("Playlist chunk declares {} segments but only holds {} bytes", count, data.len());
+      return Err(WaveError::ParseError(msg));
+    }
+
+    let mut segments = Vec::with_capacity(count);
+
+    for _ in 0..count {
+      let cue_id = cursor.read_u32::<LittleEndian>()?;
+      let length = cursor.read_u32::<LittleEndian>()?;
+      let repeat = cursor.read_u32::<LittleEndian>()?;
+
+      segments.push(PlaylistSegment { cue_id, length, repeat });
+    }
+
+    Ok(segments)
+  }
+}
+
+impl AssociatedData {
+  /// Parses the body of an `adtl` list, not including the list type.
+  pub(crate) fn parse_list(data: &[u8], encoding: TextEncoding) -> Result<AssociatedData, WaveError> {
+    let mut adtl   = AssociatedData::default();
+    let mut cursor = Cursor::new(data);
+
+    while (cursor.position() as usize) + 8 <= data.len() {
+      let id    = cursor.read_u32::<LittleEndian>()?;
+      let size  = cursor.read_u32::<LittleEndian>()? as usize;
+      let start = cursor.position() as usize;
+
+      if size > data.len() - start {
+        let msg = format!("Associated data entry {0:x} overruns its list", id);
+        return Err(WaveError::ParseError(msg));
+      }
+
+      let body = &data[start..start + size];
+
+      match id {
+        LABL => {
+          let (cue_id, text) = read_cue_text(body, encoding)?;
+          adtl.labels.insert(cue_id, text);
+        },
+        NOTE => {
+          let (cue_id, text) = read_cue_text(body, encoding)?;
+          adtl.notes.insert(cue_id, text);
+        },
+        LTXT => adtl.texts.push(LabeledText::parse(body, encoding)?),
+        _    => { }
+      }
+
+      // entries are word aligned.
+      cursor.set_position((start + size + (size & 1)) as u64);
+    }
+
+    Ok(adtl)
+  }
+
+  /// Writes the body of an `adtl` list, including the list type.  Labels
+  /// and notes are written in cue point order.
+  pub(crate) fn write_to<W: Write>(&self, out: &mut W) -> Result<(), WaveError> {
+    let mut labels = self.labels.iter().collect::<Vec<_>>();
+    let mut notes  = self.notes.iter().collect::<Vec<_>>();
+
+    labels.sort();
+    notes.sort();
+    out.write_all(b"adtl")?;
+
+    for (&cue_id, text) in labels {
+      write_cue_text(out, LABL, cue_id, text)?;
+    }
+    for (&cue_id, text) in notes {
+      write_cue_text(out, NOTE, cue_id, text)?;
+    }
+
+    for ltxt in &self.texts {
+      let size = 20 + ltxt.text.len() + 1;
+
+      out.write_u32::<LittleEndian>(LTXT)?;
+      out.write_u32::<LittleEndian>(size as u32)?;
+      out.write_u32::<LittleEndian>(ltxt.cue_id)?;
+      out.write_u32::<LittleEndian>(ltxt.sample_length)?;
+      out.write_all(&ltxt.purpose)?;
+      out.write_u16::<LittleEndian>(ltxt.country)?;
+      out.write_u16::<LittleEndian>(ltxt.language)?;
+      out.write_u16::<LittleEndian>(ltxt.dialect)?;
+      out.write_u16::<LittleEndian>(ltxt.code_page)?;
+      out.write_all(ltxt.text.as_bytes())?;
+      out.write_all(if size % 2 == 1 { b"\0\0" } else { b"\0" })?;
+    }
+
+    Ok(())
+  }
+}
+
+impl LabeledText {
+  fn parse(data: &[u8], encoding: TextEncoding) -> Result<LabeledText, WaveError> {
+    let mut cursor = Cursor::new(data);
+    let cue_id        = cursor.read_u32::<LittleEndian>()?;
+    let sample_length = cursor.read_u32::<LittleEndian>()?;
+    let mut purpose   = [0u8; 4];
+    cursor.read_exact(&mut purpose)?;
+    let country       = cursor.read_u16::<LittleEndian>()?;
+    let language      = cursor.read_u16::<LittleEndian>()?;
+    let dialect       = cursor.read_u16::<LittleEndian>()?;
+    let code_page     = cursor.read_u16::<LittleEndian>()?;
+    let text          = encoding.decode_zstring(&data[cursor.position() as usize..]);
+
+    Ok(LabeledText {
+      cue_id, sample_length, purpose, country, language, dialect, code_page, text
+    })
+  }
+}
+
+/// Joins cue points with their labels and notes.
+pub fn markers(cues: &[CuePoint], adtl: &AssociatedData) -> Vec<Marker> {
+  cues.iter().map(|cue| {
+    Marker {
+      id:       cue.id,
+      position: cue.sample_offset,
+      label:    adtl.labels.get(&cue.id).cloned(),
+      note:     adtl.notes.get(&cue.id).cloned()
+    }
+  }).collect()
+}
+
+/// Joins `ltxt` entries with the cue points they start at.  Entries whose cue
+/// point is missing are ignored.
+pub fn regions(cues: &[CuePoint], adtl: &AssociatedData) -> Vec<Region> {
+  adtl.texts.iter().filter_map(|ltxt| {
+    cues.iter().find(|cue| cue.id == ltxt.cue_id).map(|cue| {
+      Region {
+        id:      cue.id,
+        start:   cue.sample_offset,
+        length:  ltxt.sample_length,
+        purpose: ltxt.purpose,
+        label:   adtl.labels.get(&cue.id).cloned(),
+        text:    if ltxt.text.is_empty() { None } else { Some(ltxt.text.clone()) }
+      }
+    })
+  }).collect()
+}
+
+fn read_cue_text(data: &[u8], encoding: TextEncoding) -> Result<(u32, String), WaveError> {
+  let mut cursor = Cursor::new(data);
+  let cue_id     = cursor.read_u32::<LittleEndian>()?;
+
+  Ok((cue_id, encoding.decode_zstring(&data[4..])))
+}
+
+fn write_cue_text<W: Write>(out: &mut W, id: u32, cue_id: u32, text: &str) -> Result<(), WaveError> {
+  let size = 4 + text.len() + 1;
+
+  out.write_u32::<LittleEndian>(id)?;
+  out.write_u32::<LittleEndian>(size as u32)?;
+  out.write_u32::<LittleEndian>(cue_id)?;
+  out.write_all(text.as_bytes())?;
+  out.write_all(if size % 2 == 1 { b"\0\0" } else { b"\0" })?;
+  Ok(())
+}
@@ -0,0 +1,374 @@
+use std::io::Cursor;
+
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
+
+use error::WaveError;
+
+/// Data rates of Dolby Digital streams in kbit/s, indexed by their code.
+const DATA_RATES : [u16; 19] = [32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640];
+
+/// Value of a loudness field of the audio info segment which has not been
+/// measured.
+const LOUDNESS_UNSET : i16 = 0x7fff;
+
+/// The kind of metadata held by a segment of the `dbmd` chunk.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum DbmdSegmentKind {
+  DolbyE,
+  DolbyDigital,
+  DolbyDigitalPlus,
+  AudioInfo,
+  DolbyAtmos,
+  DolbyAtmosSupplemental,
+  Other(u8)
+}
+
+impl DbmdSegmentKind {
+  pub fn decode(id: u8) -> DbmdSegmentKind {
+    match id {
+      1  => DbmdSegmentKind::DolbyE,
+      3  => DbmdSegmentKind::DolbyDigital,
+      7  => DbmdSegmentKind::DolbyDigitalPlus,
+      8  => DbmdSegmentKind::AudioInfo,
+      9  => DbmdSegmentKind::DolbyAtmos,
+      10 => DbmdSegmentKind::DolbyAtmosSupplemental,
+      n  => DbmdSegmentKind::Other(n)
+    }
+  }
+}
+
+/// A single metadata segment of the `dbmd` chunk.
+#[derive(Debug,Clone,PartialEq)]
+pub struct DbmdSegment {
+  pub id:       u8,
+  /// The segment's payload, laid out as described by Dolby's metadata chunk
+  /// specification for its kind.
+  pub data:     Vec<u8>,
+  pub checksum: u8
+}
+
+impl DbmdSegment {
+  pub fn kind(&self) -> DbmdSegmentKind {
+    DbmdSegmentKind::decode(self.id)
+  }
+
+  /// Returns `true` if `checksum` matches the segment, the low byte of the
+  /// sum of the two size bytes, the payload and the checksum being zero.
+  pub fn is_valid(&self) -> bool {
+    let size = self.data.len() as u16;
+    let sum  = self.data.iter().fold((size as u8).wrapping_add((size >> 8) as u8), |sum, &byte| sum.wrapping_add(byte));
+
+    sum.wrapping_add(self.checksum) == 0
+  }
+}
+
+/// The extended downmix parameters of a Dolby Digital program, stated in
+/// its first extra bit stream information, `xbsi1`.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct DolbyDownmix {
+  /// The preferred stereo downmix, `dmixmod`: 1 for Lt/Rt, 2 for Lo/Ro,
+  /// and 0 if not indicated.
+  pub preferred_mode:      u8,
+  pub lt_rt_center_code:   u8,
+  pub lt_rt_surround_code: u8,
+  pub lo_ro_center_code:   u8,
+  pub lo_ro_surround_code: u8
+}
+
+impl DolbyDownmix {
+  /// The level of the center channel in an Lt/Rt downmix, in dB.
+  pub fn lt_rt_center_level(&self) -> f32 {
+    center_level(self.lt_rt_center_code)
+  }
+
+  /// The level of the surround channels in an Lt/Rt downmix, in dB, or
+  /// `None` for a reserved code.
+  pub fn lt_rt_surround_level(&self) -> Option<f32> {
+    surround_level(self.lt_rt_surround_code)
+  }
+
+  /// The level of the center channel in an Lo/Ro downmix, in dB.
+  pub fn lo_ro_center_level(&self) -> f32 {
+    center_level(self.lo_ro_center_code)
+  }
+
+  /// The level of the surround channels in an Lo/Ro downmix, in dB, or
+  /// `None` for a reserved code.
+  pub fn lo_ro_surround_level(&self) -> Option<f32> {
+    surround_level(self.lo_ro_surround_code)
+  }
+}
+
+/// The encoder parameters of a Dolby Digital or Dolby Digital Plus
+/// program, as stated in its segment of the `dbmd` chunk.  Levels are
+/// kept as the codes of the bit stream, and decoded to decibels by the
+/// methods.
+///
+/// Both segments start with the same fields, packed into bytes as follows,
+/// bits counted from the most significant:
+///
+/// | Byte | Bits | Field                              |
+/// |------|------|------------------------------------|
+/// | 0    | 3-7  | data rate code (Dolby Digital)     |
+/// | 1    | 0-2  | `bsmod`                            |
+/// | 1    | 5-7  | `acmod`                            |
+/// | 2    | 0-1  | `cmixlev`                          |
+/// | 2    | 2-3  | `surmixlev`                        |
+/// | 2    | 4-5  | `dsurmod`                          |
+/// | 2    | 7    | `lfeon`                            |
+/// | 3    | 3-7  | `dialnorm`                         |
+/// | 6    | 6    | `copyrightb`                       |
+/// | 6    | 7    | `origbs`                           |
+/// | 7    | 0    | `xbsi1e`                           |
+/// | 7    | 6-7  | `dmixmod`                          |
+/// | 8    | 2-4  | `ltrtcmixlev`                      |
+/// | 8    | 5-7  | `ltrtsurmixlev`                    |
+/// | 9    | 2-4  | `lorocmixlev`                      |
+/// | 9    | 5-7  | `lorosurmixlev`                    |
+///
+/// A Dolby Digital Plus segment follows them with a flag for the LFE
+/// downmix level in bit 0 and the level, `lfemixlevcod`, in bits 3-7 of
+/// byte 10, and its data rate in kbit/s as a little endian word in bytes
+/// 11 and 12.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct DolbyDigital {
+  /// Data rate of the encoded stream, in kbit/s.
+  pub data_rate:          u16,
+  /// The kind of service, `bsmod`, like 0 for a complete main program or
+  /// 5 for commentary.
+  pub bitstream_mode:     u8,
+  /// The channels coded, `acmod`, like 2 for stereo or 7 for three front
+  /// and two surround channels.
+  pub audio_coding_mode:  u8,
+  /// Whether the program has a low frequency effects channel.
+  pub lfe:                bool,
+  pub dialnorm_code:      u8,
+  pub center_mix_code:    u8,
+  pub surround_mix_code:  u8,
+  /// Whether a stereo program is Dolby Surround encoded, `dsurmod`: 2 if
+  /// it is, 1 if not and 0 if not indicated.
+  pub surround_mode:      u8,
+  pub copyright:          bool,
+  /// Whether the program is an original rather than a copy.
+  pub original:           bool,
+  pub extended_downmix:   Option<DolbyDownmix>,
+  /// The LFE downmix level code, only stated by Dolby Digital Plus.
+  pub lfe_mix_code:       Option<u8>
+}
+
+impl DolbyDigital {
+  /// The dialogue level of the program, `dialnorm`, in dBFS, from -1 to
+  /// -31.  The reserved code 0 reads as -31, as decoders treat it.
+  pub fn dialnorm(&self) -> i8 {
+    match self.dialnorm_code {
+      0    => -31,
+      code => -(code as i8)
+    }
+  }
+
+  /// The level of the center channel in a stereo downmix, in dB, or `None`
+  /// for a reserved code.
+  pub fn center_mix_level(&self) -> Option<f32> {
+    match self.center_mix_code {
+      0 => Some(-3.0),
+      1 => Some(-4.5),
+      2 => Some(-6.0),
+      _ => None
+    }
+  }
+
+  /// The level of the surround channels in a stereo downmix, in dB, or
+  /// `None` for a reserved code.
+  pub fn surround_mix_level(&self) -> Option<f32> {
+    match self.surround_mix_code {
+      0 => Some(-3.0),
+      1 => Some(-6.0),
+      2 => Some(f32::NEG_INFINITY),
+      _ => None
+    }
+  }
+
+  /// The level of the LFE channel in a downmix, in dB, from 10 to -21, if
+  /// stated.
+  pub fn lfe_mix_level(&self) -> Option<f32> {
+    self.lfe_mix_code.map(|code| 10.0 - code as f32)
+  }
+
+  fn parse(data: &[u8], plus: bool) -> Result<DolbyDigital, WaveError> {
+    let size = if plus { 13 } else { 10 };
+
+    if data.len() < size {
+      let msg = format!("Dolby Digital metadata segment is only {} bytes long", data.len());
+      return Err(WaveError::ParseError(msg));
+    }
+
+    let data_rate = if plus {
+      LittleEndian::read_u16(&data[11..13])
+    } else {
+      match DATA_RATES.get((data[0] & 0x1f) as usize) {
+        Some(&rate) => rate,
+        None        => {
+          let msg = format!("Invalid Dolby Digital data rate code {}", data[0] & 0x1f);
+          return Err(WaveError::ParseError(msg));
+        }
+      }
+    };
+
+    let extended_downmix = if data[7] & 0x80 != 0 {
+      Some(DolbyDownmix {
+        preferred_mode:      data[7] & 0x03,
+        lt_rt_center_code:   data[8] >> 3 & 0x07,
+        lt_rt_surround_code: data[8] & 0x07,
+        lo_ro_center_code:   data[9] >> 3 & 0x07,
+        lo_ro_surround_code: data[9] & 0x07
+      })
+    } else {
+      None
+    };
+
+    Ok(DolbyDigital {
+      data_rate,
+      bitstream_mode:    data[1] >> 5,
+      audio_coding_mode: data[1] & 0x07,
+      lfe:               data[2] & 0x01 != 0,
+      dialnorm_code:     data[3] & 0x1f,
+      center_mix_code:   data[2] >> 6,
+      surround_mix_code: data[2] >> 4 & 0x03,
+      surround_mode:     data[2] >> 2 & 0x03,
+      copyright:         data[6] & 0x02 != 0,
+      original:          data[6] & 0x01 != 0,
+      extended_downmix,
+      lfe_mix_code:      if plus && data[10] & 0x80 != 0 { Some(data[10] & 0x1f) } else { None }
+    })
+  }
+}
+
+/// The level of the center channel in an extended downmix, in dB.
+fn center_level(code: u8) -> f32 {
+  match code {
+    0 => 3.0,
+    1 => 1.5,
+    2 => 0.0,
+    3 => -1.5,
+    4 => -3.0,
+    5 => -4.5,
+    6 => -6.0,
+    _ => f32::NEG_INFINITY
+  }
+}
+
+/// The level of the surround channels in an extended downmix, in dB, with
+/// the codes below 3 reserved.
+fn surround_level(code: u8) -> Option<f32> {
+  match code {
+    0..=2 => None,
+    7     => Some(f32::NEG_INFINITY),
+    code  => Some(center_level(code))
+  }
+}
+
+/// The program information of the audio info segment of the `dbmd` chunk:
+/// the program's number in a byte, then its integrated loudness in LUFS
+/// and its true peak level in dBTP, each in hundredths as a little endian
+/// word of 0x7fff if not measured.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct AudioInfo {
+  pub program_id: u8,
+  /// Integrated loudness in LUFS.
+  pub loudness:   Option<f32>,
+  /// Maximum true peak level in dBTP.
+  pub true_peak:  Option<f32>
+}
+
+impl AudioInfo {
+  fn parse(data: &[u8]) -> Result<AudioInfo, WaveError> {
+    if data.len() < 5 {
+      let msg = format!("Dolby audio info segment is only {} bytes long", data.len());
+      return Err(WaveError::ParseError(msg));
+    }
+
+    let level = |bytes: &[u8]| match LittleEndian::read_i16(bytes) {
+      LOUDNESS_UNSET => None,
+      value          => Some(value as f32 / 100.0)
+    };
+
+    Ok(AudioInfo { program_id: data[0], loudness: level(&data[1..3]), true_peak: level(&data[3..5]) })
+  }
+}
+
+/// Contents of the `dbmd` chunk, holding the Dolby metadata of a broadcast
+/// master as a series of segments, each tagged with the codec or purpose
+/// it applies to.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Dbmd {
+  /// Version of the chunk, with the major version in the highest byte.
+  pub version:  u32,
+  /// The metadata segments, in file order.
+  pub segments: Vec<DbmdSegment>
+}
+
+impl Dbmd {
+  /// Returns the first segment of the given kind.
+  pub fn segment(&self, kind: DbmdSegmentKind) -> Option<&DbmdSegment> {
+    self.segments.iter().find(|segment| segment.kind() == kind)
+  }
+
+  /// Returns the parameters of the first Dolby Digital segment.
+  pub fn dolby_digital(&self) -> Option<DolbyDigital> {
+    self.segment(DbmdSegmentKind::DolbyDigital).and_then(|segment| DolbyDigital::parse(&segment.data, false).ok())
+  }
+
+  /// Returns the parameters of the first Dolby Digital Plus segment.
+  pub fn dolby_digital_plus(&self) -> Option<DolbyDigital> {
+    self.segment(DbmdSegmentKind::DolbyDigitalPlus).and_then(|segment| DolbyDigital::parse(&segment.data, true).ok())
+  }
+
+  /// Returns the program information of the first audio info segment.
+  pub fn audio_info(&self) -> Option<AudioInfo> {
+    self.segment(DbmdSegmentKind::AudioInfo).and_then(|segment| AudioInfo::parse(&segment.data).ok())
+  }
+
+  pub(crate) fn parse_chunk(data: &[u8]) -> Result<Dbmd, WaveError> {
+    let mut cursor   = Cursor::new(data);
+    let version      = cursor.read_u32::<LittleEndian>()?;
+    let mut segments = Vec::new();
+
+    // the segment list is terminated by an ID of zero, or the end of the chunk.
+    while (cursor.position() as usize) < data.len() {
+      let id = cursor.read_u8()?;
+
+      if id == 0 {
+        break;
+      }
+
+      let size  = cursor.read_u16::<LittleEndian>()? as usize;
+      let start = cursor.position() as usize;
+
+      if size + 1 > data.len() - start {
+        let msg = format!("Dolby metadata segment {} overruns its chunk", id);
+        return Err(WaveError::ParseError(msg));
+      }
+
+      let segment = DbmdSegment { id, data: data[start..start + size].to_vec(), checksum: data[start + size] };
+
+      if !segment.is_valid() {
+        let msg = format!("Dolby metadata segment {} fails its checksum", id);
+        return Err(WaveError::ParseError(msg));
+      }
+
+      // the segments decoded are checked as they are read, so their
+      // accessors only need to find them.
+      match segment.kind() {
+        DbmdSegmentKind::DolbyDigital     => { DolbyDigital::parse(&segment.data, false)?; },
+        DbmdSegmentKind::DolbyDigitalPlus => { DolbyDigital::parse(&segment.data, true)?; },
+        DbmdSegmentKind::AudioInfo        => { AudioInfo::parse(&segment.data)?; },
+        _                                 => { }
+      }
+
+      segments.push(segment);
+      cursor.set_position((start + size + 1) as u64);
+    }
+
+    Ok(Dbmd { version, segments })
+  }
+}
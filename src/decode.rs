@@ -0,0 +1,303 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use formats::Format;
+
+/// Decodes whole samples from the bytes given into `out`: as integers on the
+/// scale of `WaveFileIterator`, or as floats with full scale at 1.0.
+pub(crate) type Decoder<T> = fn(&[u8], &mut [T]);
+
+/// A sample encoding, decoded the same way every time.
+trait Sample {
+  const SIZE : usize;
+
+  fn decode(bytes: &[u8]) -> i32;
+
+  fn decode_f32(bytes: &[u8]) -> f32;
+}
+
+struct Int8;
+struct Int16;
+struct Int24;
+struct Int32;
+struct Float32;
+struct Float64;
+
+impl Sample for Int8 {
+  const SIZE : usize = 1;
+
+  // 8 bit samples are unsigned, centred on 128.
+  fn decode(bytes: &[u8]) -> i32 {
+    bytes[0] as i32 - 128
+  }
+
+  fn decode_f32(bytes: &[u8]) -> f32 {
+    Int8::decode(bytes) as f32 * (1.0 / 128.0)
+  }
+}
+
+impl Sample for Int16 {
+  const SIZE : usize = 2;
+
+  fn decode(bytes: &[u8]) -> i32 {
+    LittleEndian::read_i16(bytes) as i32
+  }
+
+  fn decode_f32(bytes: &[u8]) -> f32 {
+    Int16::decode(bytes) as f32 * (1.0 / 32768.0)
+  }
+}
+
+impl Sample for Int24 {
+  const SIZE : usize = 3;
+
+  fn decode(bytes: &[u8]) -> i32 {
+    // place the sample in the top bytes, so the shift extends its sign.
+    i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8
+  }
+
+  fn decode_f32(bytes: &[u8]) -> f32 {
+    Int24::decode(bytes) as f32 * (1.0 / 8388608.0)
+  }
+}
+
+impl Sample for Int32 {
+  const SIZE : usize = 4;
+
+  fn decode(bytes: &[u8]) -> i32 {
+    LittleEndian::read_i32(bytes)
+  }
+
+  fn decode_f32(bytes: &[u8]) -> f32 {
+    Int32::decode(bytes) as f32 * (1.0 / 2147483648.0)
+  }
+}
+
+impl Sample for Float32 {
+  const SIZE : usize = 4;
+
+  fn decode(bytes: &[u8]) -> i32 {
+    (Float32::decode_f32(bytes) * 2147483647.0) as i32
+  }
+
+  fn decode_f32(bytes: &[u8]) -> f32 {
+    LittleEndian::read_f32(bytes)
+  }
+}
+
+impl Sample for Float64 {
+  const SIZE : usize = 8;
+
+  fn decode(bytes: &[u8]) -> i32 {
+    (LittleEndian::read_f64(bytes) * 2147483647.0) as i32
+  }
+
+  fn decode_f32(bytes: &[u8]) -> f32 {
+    LittleEndian::read_f64(bytes) as f32
+  }
+}
+
+fn decode<S: Sample>(data: &[u8], out: &mut [i32]) {
+  for (sample, out) in data.chunks_exact(S::SIZE).zip(out.iter_mut()) {
+    *out = S::decode(sample);
+  }
+}
+
+fn decode_f32<S: Sample>(data: &[u8], out: &mut [f32]) {
+  for (sample, out) in data.chunks_exact(S::SIZE).zip(out.iter_mut()) {
+    *out = S::decode_f32(sample);
+  }
+}
+
+fn undecodable<T>(_: &[u8], _: &mut [T]) { }
+
+#[cfg(target_arch = "x86_64")]
+fn decode_int24_ssse3(data: &[u8], out: &mut [i32]) {
+  // only picked by `decoder()` once SSSE3 is known to be available.
+  unsafe { x86::unpack_int24(data, out) }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn decode_int24_f32_ssse3(data: &[u8], out: &mut [f32]) {
+  // only picked by `float_decoder()` once SSSE3 is known to be available.
+  unsafe { x86::unpack_int24_f32(data, out) }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn decode_int8_f32_sse2(data: &[u8], out: &mut [f32]) {
+  // SSE2 is part of every x86_64 processor.
+  unsafe { x86::int8_to_f32(data, out) }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn decode_int16_f32_sse2(data: &[u8], out: &mut [f32]) {
+  unsafe { x86::int16_to_f32(data, out) }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn decode_int32_f32_sse2(data: &[u8], out: &mut [f32]) {
+  unsafe { x86::int32_to_f32(data, out) }
+}
+
+/// Picks the decoder for samples of the format and size given, once, so that
+/// decoding never branches on either per sample.
+pub(crate) fn decoder(format: Format, bytes_per_sample: usize) -> Decoder<i32> {
+  match (format, bytes_per_sample) {
+    (Format::PCM, 1)       => decode::<Int8>,
+    (Format::PCM, 2)       => decode::<Int16>,
+    #[cfg(target_arch = "x86_64")]
+    (Format::PCM, 3) if is_x86_feature_detected!("ssse3") => decode_int24_ssse3,
+    (Format::PCM, 3)       => decode::<Int24>,
+    (Format::PCM, 4)       => decode::<Int32>,
+    (Format::IEEEFloat, 4) => decode::<Float32>,
+    (Format::IEEEFloat, 8) => decode::<Float64>,
+    // `WaveFile::open` rejects everything else.
+    _                      => undecodable
+  }
+}
+
+/// Picks the decoder for samples of the format and size given as floats,
+/// which converts them as it reads them.
+pub(crate) fn float_decoder(format: Format, bytes_per_sample: usize) -> Decoder<f32> {
+  match (format, bytes_per_sample) {
+    #[cfg(target_arch = "x86_64")]
+    (Format::PCM, 1)       => decode_int8_f32_sse2,
+    #[cfg(not(target_arch = "x86_64"))]
+    (Format::PCM, 1)       => decode_f32::<Int8>,
+    #[cfg(target_arch = "x86_64")]
+    (Format::PCM, 2)       => decode_int16_f32_sse2,
+    #[cfg(not(target_arch = "x86_64"))]
+    (Format::PCM, 2)       => decode_f32::<Int16>,
+    #[cfg(target_arch = "x86_64")]
+    (Format::PCM, 3) if is_x86_feature_detected!("ssse3") => decode_int24_f32_ssse3,
+    (Format::PCM, 3)       => decode_f32::<Int24>,
+    #[cfg(target_arch = "x86_64")]
+    (Format::PCM, 4)       => decode_int32_f32_sse2,
+    #[cfg(not(target_arch = "x86_64"))]
+    (Format::PCM, 4)       => decode_f32::<Int32>,
+    (Format::IEEEFloat, 4) => decode_f32::<Float32>,
+    (Format::IEEEFloat, 8) => decode_f32::<Float64>,
+    _                      => undecodable
+  }
+}
+
+/// Decodes the samples of consecutive frames, `stride` bytes apart, from
+/// `data` into `out`.  `data` must hold `out.len() / channels` frames.
+pub(crate) fn decode_frames<T>(decoder: Decoder<T>, channels: usize, frame_size: usize, stride: usize,
+                               data: &[u8], out: &mut [T]) {
+  if stride == frame_size {
+    // without padding between frames, the samples are one run.
+    return decoder(data, out);
+  }
+
+  for (frame, samples) in data.chunks(stride).zip(out.chunks_exact_mut(channels)) {
+    decoder(&frame[..frame_size], samples);
+  }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+  use std::arch::x86_64::*;
+
+  /// Spreads four packed 24 bit samples into the top three bytes of each
+  /// lane and shifts them down again, extending their sign.
+  #[target_feature(enable = "ssse3")]
+  unsafe fn unpack(data: &[u8], i: usize) -> __m128i {
+    let shuffle = _mm_setr_epi8(-1, 0, 1,  2, -1, 3,  4,  5,
+                                -1, 6, 7,  8, -1, 9, 10, 11);
+    let packed  = _mm_loadu_si128(data.as_ptr().add(i * 3) as *const __m128i);
+
+    _mm_srai_epi32(_mm_shuffle_epi8(packed, shuffle), 8)
+  }
+
+  /// Number of samples that can be unpacked four at a time, as each load
+  /// reads 16 bytes to use 12 of them.
+  fn vectors(data: &[u8], count: usize) -> usize {
+    if data.len() < 16 {
+      return 0;
+    }
+    count.min((data.len() - 16) / 3 + 1) / 4 * 4
+  }
+
+  #[target_feature(enable = "ssse3")]
+  pub(super) unsafe fn unpack_int24(data: &[u8], out: &mut [i32]) {
+    let count = (data.len() / 3).min(out.len());
+    let done  = vectors(data, count);
+
+    for i in (0..done).step_by(4) {
+      _mm_storeu_si128(out.as_mut_ptr().add(i) as *mut __m128i, unpack(data, i));
+    }
+
+    super::decode::<super::Int24>(&data[done * 3..], &mut out[done..count]);
+  }
+
+  #[target_feature(enable = "ssse3")]
+  pub(super) unsafe fn unpack_int24_f32(data: &[u8], out: &mut [f32]) {
+    let count = (data.len() / 3).min(out.len());
+    let done  = vectors(data, count);
+    let scale = _mm_set1_ps(1.0 / 8388608.0);
+
+    for i in (0..done).step_by(4) {
+      _mm_storeu_ps(out.as_mut_ptr().add(i), _mm_mul_ps(_mm_cvtepi32_ps(unpack(data, i)), scale));
+    }
+
+    super::decode_f32::<super::Int24>(&data[done * 3..], &mut out[done..count]);
+  }
+
+  /// Converts unsigned 8 bit samples sixteen at a time, flipping their top
+  /// bit to make them signed and widening each to a lane by repeating it
+  /// and shifting it down again.
+  pub(super) unsafe fn int8_to_f32(data: &[u8], out: &mut [f32]) {
+    let count = data.len().min(out.len());
+    let done  = count / 16 * 16;
+    let scale = _mm_set1_ps(1.0 / 128.0);
+    let flip  = _mm_set1_epi8(-128);
+
+    for i in (0..done).step_by(16) {
+      let bytes = _mm_xor_si128(_mm_loadu_si128(data.as_ptr().add(i) as *const __m128i), flip);
+
+      for (j, &half) in [_mm_unpacklo_epi8(bytes, bytes), _mm_unpackhi_epi8(bytes, bytes)].iter().enumerate() {
+        let low  = _mm_srai_epi32(_mm_unpacklo_epi16(half, half), 24);
+        let high = _mm_srai_epi32(_mm_unpackhi_epi16(half, half), 24);
+
+        _mm_storeu_ps(out.as_mut_ptr().add(i + j * 8), _mm_mul_ps(_mm_cvtepi32_ps(low), scale));
+        _mm_storeu_ps(out.as_mut_ptr().add(i + j * 8 + 4), _mm_mul_ps(_mm_cvtepi32_ps(high), scale));
+      }
+    }
+
+    super::decode_f32::<super::Int8>(&data[done..], &mut out[done..count]);
+  }
+
+  /// Converts 16 bit samples eight at a time, widening each to a lane by
+  /// repeating it and shifting it down again.
+  pub(super) unsafe fn int16_to_f32(data: &[u8], out: &mut [f32]) {
+    let count = (data.len() / 2).min(out.len());
+    let done  = count / 8 * 8;
+    let scale = _mm_set1_ps(1.0 / 32768.0);
+
+    for i in (0..done).step_by(8) {
+      let packed = _mm_loadu_si128(data.as_ptr().add(i * 2) as *const __m128i);
+      let low    = _mm_srai_epi32(_mm_unpacklo_epi16(packed, packed), 16);
+      let high   = _mm_srai_epi32(_mm_unpackhi_epi16(packed, packed), 16);
+
+      _mm_storeu_ps(out.as_mut_ptr().add(i), _mm_mul_ps(_mm_cvtepi32_ps(low), scale));
+      _mm_storeu_ps(out.as_mut_ptr().add(i + 4), _mm_mul_ps(_mm_cvtepi32_ps(high), scale));
+    }
+
+    super::decode_f32::<super::Int16>(&data[done * 2..], &mut out[done..count]);
+  }
+
+  /// Converts 32 bit samples four at a time.
+  pub(super) unsafe fn int32_to_f32(data: &[u8], out: &mut [f32]) {
+    let count = (data.len() / 4).min(out.len());
+    let done  = count / 4 * 4;
+    let scale = _mm_set1_ps(1.0 / 2147483648.0);
+
+    for i in (0..done).step_by(4) {
+      let ints = _mm_loadu_si128(data.as_ptr().add(i * 4) as *const __m128i);
+
+      _mm_storeu_ps(out.as_mut_ptr().add(i), _mm_mul_ps(_mm_cvtepi32_ps(ints), scale));
+    }
+
+    super::decode_f32::<super::Int32>(&data[done * 4..], &mut out[done..count]);
+  }
+}
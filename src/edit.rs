@@ -0,0 +1,190 @@
+use std::fs::{File,OpenOptions};
+use std::io::{BufWriter,Seek,SeekFrom,Write};
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+
+use error::WaveError;
+use bext::Bext;
+use tags::InfoTags;
+use {WaveFile,FourCC,fourcc,RIFF,WAVE,LIST,INFO,BEXT};
+
+/// Chunks describing the audio itself, which are never stripped.
+const STRUCTURAL_CHUNKS : [FourCC; 3] = [*b"fmt ", *b"fact", *b"data"];
+
+/// Copies the wavefile at `src` to `dst`, dropping every chunk except those
+/// describing the audio (`fmt `, `fact` and `data`) and those listed in
+/// `keep`.  Useful for scrubbing recordings of identifying metadata before
+/// publishing them.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::strip_metadata;
+///
+/// // keep the Broadcast Wave chunk, drop LIST, iXML and everything else.
+/// strip_metadata("./in.wav", "./out.wav", &[*b"bext"]).unwrap();
+/// ```
+pub fn strip_metadata<S: Into<String>, T: Into<String>>(src: S, dst: T, keep: &[FourCC]) -> Result<(), WaveError> {
+  let file   = WaveFile::open(src)?;
+  let bytes  = file.bytes();
+  let chunks = file.chunks()
+    .filter(|chunk| STRUCTURAL_CHUNKS.contains(&chunk.0) || keep.contains(&chunk.0))
+    .map(|(id, offset, size)| {
+      // a truncated data chunk is copied as far as it goes.
+      let end = (offset + size).min(bytes.len());
+      (id, &bytes[offset..end])
+    })
+    .collect::<Vec<_>>();
+
+  let riff_size = chunks.iter().fold(4, |total, chunk| {
+    total + 8 + chunk.1.len() as u64 + (chunk.1.len() & 1) as u64
+  });
+
+  if riff_size > u32::MAX as u64 {
+    return Err(WaveError::WriteError("File exceeds 4 GiB".into()));
+  }
+
+  let mut out = BufWriter::new(File::create(dst.into())?);
+
+  out.write_u32::<LittleEndian>(RIFF)?;
+  out.write_u32::<LittleEndian>(riff_size as u32)?;
+  out.write_u32::<LittleEndian>(WAVE)?;
+
+  for (id, body) in chunks {
+    out.write_all(&id)?;
+    out.write_u32::<LittleEndian>(body.len() as u32)?;
+    out.write_all(body)?;
+
+    if body.len() % 2 == 1 {
+      out.write_u8(0)?;
+    }
+  }
+
+  out.flush()?;
+  Ok(())
+}
+
+/// Replaces the `bext` chunk of the wavefile at `path`, or adds one if it
+/// has none.  See `write_info_tags` for how the file is modified.
+pub fn write_bext<S: Into<String>>(path: S, bext: &Bext) -> Result<(), WaveError> {
+  let mut body = Vec::new();
+
+  bext.write_to(&mut body)?;
+  replace_chunk(path.into(), BEXT, None, body)
+}
+
+/// Replaces the `INFO` list of the wavefile at `path`, or adds one if it has
+/// none.
+///
+/// The audio data is never rewritten: if the new tags fit in the space of
+/// the old ones they are updated in place, with any space left over turned
+/// into a `JUNK` chunk.  Otherwise the old list is blanked out into a `JUNK`
+/// chunk and the new one is appended to the end of the file.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::{WaveFile,write_info_tags};
+///
+/// let mut tags = WaveFile::open("./in.wav").unwrap().tags().clone();
+/// tags.set(*b"INAM", "Concert, second half");
+/// write_info_tags("./in.wav", &tags).unwrap();
+/// ```
+pub fn write_info_tags<S: Into<String>>(path: S, tags: &InfoTags) -> Result<(), WaveError> {
+  let mut body = Vec::new();
+
+  tags.write_to(&mut body)?;
+  replace_chunk(path.into(), LIST, Some(INFO), body)
+}
+
+/// Writes `body` over the first chunk with the given ID (and list type, for
+/// `LIST` chunks), or appends it if there is no such chunk or it is too small.
+fn replace_chunk(path: String, id: u32, list_type: Option<u32>, mut body: Vec<u8>) -> Result<(), WaveError> {
+  // the mapping has to be gone before the file is modified.
+  let existing = {
+    let file  = WaveFile::open(path.clone())?;
+    let bytes = file.bytes();
+
+    let found = file.chunks().find(|&(chunk_id, offset, size)| {
+      chunk_id == fourcc(id) && match list_type {
+        Some(list_type) => {
+          size >= 4 && offset + 4 <= bytes.len() &&
+            LittleEndian::read_u32(&bytes[offset..offset + 4]) == list_type
+        },
+        None => true
+      }
+    });
+    found
+  };
+
+  if body.len() > u32::MAX as usize - 1 {
+    return Err(WaveError::WriteError("Chunk exceeds 4 GiB".into()));
+  }
+
+  let mut file = OpenOptions::new().read(true).write(true).open(&path)?;
+
+  if let Some((_, offset, size)) = existing {
+    let space  = size + (size & 1);
+    let needed = body.len() + (body.len() & 1);
+
+    if needed <= space {
+      let junk = space - needed;
+
+      // too little space is left for a JUNK chunk header, so pad the body.
+      if junk > 0 && junk < 8 {
+        body.resize(space, 0);
+      }
+
+      file.seek(SeekFrom::Start(offset as u64 - 4))?;
+      write_chunk_body(&mut file, &body)?;
+
+      if junk >= 8 {
+        file.write_all(b"JUNK")?;
+        file.write_u32::<LittleEndian>((junk - 8) as u32)?;
+        file.write_all(&vec![0u8; junk - 8])?;
+      }
+
+      return Ok(());
+    }
+
+    // blank out the old chunk, so it no longer holds any metadata.
+    file.seek(SeekFrom::Start(offset as u64 - 8))?;
+    file.write_all(b"JUNK")?;
+    file.seek(SeekFrom::Current(4))?;
+    file.write_all(&vec![0u8; space])?;
+  }
+
+  let mut end = file.seek(SeekFrom::End(0))?;
+
+  if end % 2 == 1 {
+    file.write_u8(0)?;
+    end += 1;
+  }
+
+  file.write_u32::<LittleEndian>(id)?;
+  write_chunk_body(&mut file, &body)?;
+
+  // the new chunk's header makes up for the 8 bytes of the RIFF header.
+  let riff_size = end + (body.len() + (body.len() & 1)) as u64;
+
+  if riff_size > u32::MAX as u64 {
+    return Err(WaveError::WriteError("File exceeds 4 GiB".into()));
+  }
+
+  file.seek(SeekFrom::Start(4))?;
+  file.write_u32::<LittleEndian>(riff_size as u32)?;
+  file.flush()?;
+  Ok(())
+}
+
+/// Writes the size of a chunk followed by its body and pad byte.
+fn write_chunk_body<W: Write>(out: &mut W, body: &[u8]) -> Result<(), WaveError> {
+  out.write_u32::<LittleEndian>(body.len() as u32)?;
+  out.write_all(body)?;
+
+  if body.len() % 2 == 1 {
+    out.write_u8(0)?;
+  }
+
+  Ok(())
+}
@@ -0,0 +1,201 @@
+use std::io;
+
+use embedded_io::{Read,ReadExactError,Seek,SeekFrom};
+
+use error::WaveError;
+use options::WaveFileOptions;
+use sampler::{Instrument,Sampler};
+use {check_riff,decode,stream,Format,WaveFile,WaveInfo};
+
+/// A wavefile read through the `embedded-io` traits, such as from a file on
+/// an SD card, for firmware without the memory to hold the whole file.
+///
+/// Only the chunks before the audio are loaded, and parsed by the same
+/// scanner as `WaveFile::open`, so chunks following the audio are ignored.
+/// Frames are read from the stream as they are requested.
+///
+/// # Example
+///
+/// ```
+/// extern crate embedded_io;
+/// extern crate wavefile;
+///
+/// use embedded_io::{ErrorType,Read,Seek,SeekFrom};
+/// use wavefile::EmbeddedWaveFile;
+///
+/// // stands in for a driver's file handle.
+/// struct Card { bytes: Vec<u8>, position: usize }
+///
+/// impl ErrorType for Card {
+///   type Error = embedded_io::ErrorKind;
+/// }
+///
+/// impl Read for Card {
+///   fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+///     let count = buf.len().min(self.bytes.len() - self.position);
+///
+///     buf[..count].copy_from_slice(&self.bytes[self.position..self.position + count]);
+///     self.position += count;
+///     Ok(count)
+///   }
+/// }
+///
+/// impl Seek for Card {
+///   fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+///     self.position = match pos {
+///       SeekFrom::Start(offset)   => offset as usize,
+///       SeekFrom::End(offset)     => (self.bytes.len() as i64 + offset) as usize,
+///       SeekFrom::Current(offset) => (self.position as i64 + offset) as usize
+///     }.min(self.bytes.len());
+///     Ok(self.position as u64)
+///   }
+/// }
+///
+/// let bytes      = std::fs::read("./fixtures/test-s24le.wav").unwrap();
+/// let mut wav    = EmbeddedWaveFile::open(Card { bytes, position: 0 }).unwrap();
+/// let mut buffer = vec![0; 256 * wav.channels()];
+///
+/// assert_eq!(wav.read_frames(0, &mut buffer).unwrap(), 256);
+/// ```
+pub struct EmbeddedWaveFile<R> {
+  reader: R,
+  /// The file up to the start of the audio.
+  header: WaveFile,
+  /// The encoded frames most recently read.
+  block:  Vec<u8>
+}
+
+impl<R: Read + Seek> EmbeddedWaveFile<R> {
+  /// Parses the header of the wavefile in `reader`, with the default
+  /// options.
+  pub fn open(reader: R) -> Result<EmbeddedWaveFile<R>, WaveError> {
+    EmbeddedWaveFile::open_with(reader, WaveFileOptions::default())
+  }
+
+  /// Parses the header of the wavefile in `reader`.  `max_allocation` and
+  /// `max_scan_bytes` also bound the memory used to hold the header.
+  pub fn open_with(mut reader: R, options: WaveFileOptions) -> Result<EmbeddedWaveFile<R>, WaveError> {
+    let len    = reader.seek(SeekFrom::End(0)).map_err(io_error)? as usize;
+    let bytes  = read_header(&mut reader, len, &options)?;
+    let header = stream::open_header(&bytes, len, options)?;
+
+    Ok(EmbeddedWaveFile { reader, header, block: Vec::new() })
+  }
+
+  /// Decodes frames starting at frame `first` into `buffer`, like
+  /// `WaveFile::read_frames()`, returning the number of frames read.
+  pub fn read_frames(&mut self, first: usize, buffer: &mut [i32]) -> Result<usize, WaveError> {
+    let decoder = self.header.decoder;
+
+    self.decode_frames(decoder, first, buffer)
+  }
+
+  /// Decodes frames starting at frame `first` into `buffer` as floats, like
+  /// `WaveFile::read_frames_f32_into()`, returning the number of frames
+  /// read.
+  pub fn read_frames_f32_into(&mut self, first: usize, buffer: &mut [f32]) -> Result<usize, WaveError> {
+    let decoder = self.header.decoder_f32;
+
+    self.decode_frames(decoder, first, buffer)
+  }
+
+  fn decode_frames<T>(&mut self, decoder: decode::Decoder<T>, first: usize, buffer: &mut [T]) -> Result<usize, WaveError> {
+    let channels                = self.header.channels();
+    let (start, length, frames) = stream::frame_range(&self.header, first, buffer.len() / channels);
+
+    trace_event!(TRACE, first, frames, "read block");
+    self.block.resize(length, 0);
+    self.reader.seek(SeekFrom::Start(start)).map_err(io_error)?;
+    self.reader.read_exact(&mut self.block).map_err(read_error)?;
+    stream::decode_block(&self.header, decoder, &self.block, buffer);
+    Ok(frames)
+  }
+
+  /// Returns the reader the file was opened from.
+  pub fn into_inner(self) -> R {
+    self.reader
+  }
+}
+
+impl<R> EmbeddedWaveFile<R> {
+  /// See `WaveFile::info()`.
+  pub fn info(&self) -> WaveInfo {
+    self.header.info()
+  }
+
+  pub fn channels(&self) -> usize {
+    self.header.channels()
+  }
+
+  pub fn sample_rate(&self) -> usize {
+    self.header.sample_rate()
+  }
+
+  /// The total number of frames present in the file.
+  pub fn len(&self) -> usize {
+    self.header.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.header.is_empty()
+  }
+
+  pub fn data_format(&self) -> Format {
+    self.header.data_format()
+  }
+
+  /// The title of the file, if its tags precede the audio.
+  pub fn title(&self) -> Option<String> {
+    self.header.title()
+  }
+
+  /// The file's `smpl` chunk, if it precedes the audio.
+  pub fn sampler(&self) -> Option<&Sampler> {
+    self.header.sampler()
+  }
+
+  /// The file's `inst` chunk, if it precedes the audio.
+  pub fn instrument(&self) -> Option<Instrument> {
+    self.header.instrument()
+  }
+}
+
+/// Reads the chunks of the file in `reader` up to and including the header
+/// of its data chunk.
+fn read_header<R: Read + Seek>(reader: &mut R, len: usize, options: &WaveFileOptions) -> Result<Vec<u8>, WaveError> {
+  let scan_end  = len.min(options.max_scan_bytes.unwrap_or(usize::MAX));
+  let mut bytes = vec![0; stream::RIFF_HEADER];
+
+  reader.seek(SeekFrom::Start(0)).map_err(io_error)?;
+  reader.read_exact(&mut bytes).map_err(read_error)?;
+  check_riff(&bytes)?;
+
+  // the parser reports the missing data chunk once the header runs out.
+  while bytes.len() + 8 <= scan_end {
+    let start = bytes.len();
+
+    bytes.resize(start + 8, 0);
+    reader.read_exact(&mut bytes[start..]).map_err(read_error)?;
+
+    let body = match stream::chunk_body(&bytes, scan_end, options)? {
+      Some(body) => body,
+      None       => break
+    };
+
+    bytes.resize(start + 8 + body, 0);
+    reader.read_exact(&mut bytes[start + 8..]).map_err(read_error)?;
+  }
+
+  Ok(bytes)
+}
+
+fn io_error<E: embedded_io::Error>(e: E) -> WaveError {
+  WaveError::from(io::Error::new(e.kind().into(), format!("{:?}", e)))
+}
+
+fn read_error<E: embedded_io::Error>(e: ReadExactError<E>) -> WaveError {
+  match e {
+    ReadExactError::UnexpectedEof => WaveError::ParseError("Unexpected EOF".into()),
+    ReadExactError::Other(e)      => io_error(e)
+  }
+}
@@ -2,43 +2,100 @@ use std::io;
 use std::fmt::{self,Display};
 use std::error::Error;
 
-use byteorder;
+use FourCC;
 
+/// Errors raised while reading or writing wave files.  New variants may be
+/// added as the parser learns to report problems more precisely, so matches
+/// need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum WaveError {
   IoError(io::Error),
   Unsupported(String),
-  ParseError(String)
-}
-
-
-impl From<io::Error> for WaveError {
-  fn from(e: io::Error) -> Self {
-    WaveError::IoError(e)
+  ParseError(String),
+  WriteError(String),
+  /// The body of a chunk is malformed.
+  InvalidChunk {
+    id:      FourCC,
+    /// File offset of the chunk's body.
+    offset:  usize,
+    message: String
+  },
+  /// The file doesn't start with a `RIFF` header of form type `WAVE`.
+  NotWave {
+    /// The ID found in place of `RIFF` or `WAVE`.
+    id:     FourCC,
+    offset: usize
+  },
+  /// A chunk of a kind `UnknownChunks::Reject` rules out.
+  UnexpectedChunk {
+    id:     FourCC,
+    /// File offset of the chunk's body.
+    offset: usize
+  },
+  /// A chunk every file needs is missing.
+  MissingChunk {
+    id:     FourCC,
+    /// File offset at which the search for the chunk stopped.
+    offset: usize
+  },
+  /// A chunk's declared size runs past the end of the file.
+  ChunkOverrun {
+    id:        FourCC,
+    offset:    usize,
+    size:      u32,
+    /// Number of bytes from the start of the body to the end of the file.
+    available: usize
+  },
+  /// A field of a chunk contradicts the values it is derived from.
+  Mismatch {
+    id:       FourCC,
+    offset:   usize,
+    field:    &'static str,
+    expected: u64,
+    found:    u64
+  },
+  /// The file exceeds one of the limits set through `WaveFileOptions`.
+  LimitExceeded {
+    /// The name of the option setting the limit.
+    limit: &'static str,
+    max:   usize,
+    found: usize
+  },
+  /// The operation was stopped through its `CancelToken`.
+  Cancelled,
+  /// A transform drove a sample past full scale, with `Clipping::Error`.
+  Clipped {
+    /// The index of the frame, counted from the start of the stage's input.
+    frame: u64
   }
 }
 
-impl From<byteorder::Error> for WaveError {
-  fn from(e: byteorder::Error) -> Self {
-    match e {
-      byteorder::Error::UnexpectedEOF => WaveError::ParseError("Unexpected EOF".into()),
-      byteorder::Error::Io(e) => WaveError::IoError(e)
+impl WaveError {
+  /// Attributes a parse error to the chunk whose body starts at `offset`.
+  pub(crate) fn in_chunk(self, id: FourCC, offset: usize) -> WaveError {
+    match self {
+      WaveError::ParseError(message) => WaveError::InvalidChunk { id, offset, message },
+      other                          => other
     }
   }
 }
 
-impl Error for WaveError {
-  fn description(&self) -> &str {
-    match self {
-      &WaveError::ParseError(ref s)  => &s,
-      &WaveError::Unsupported(ref s) => &s,
-      &WaveError::IoError(ref e)     => e.description()
+
+impl From<io::Error> for WaveError {
+  fn from(e: io::Error) -> Self {
+    match e.kind() {
+      // running out of data while decoding means the file is malformed.
+      io::ErrorKind::UnexpectedEof => WaveError::ParseError("Unexpected EOF".into()),
+      _                            => WaveError::IoError(e)
     }
   }
+}
 
-  fn cause(&self) -> Option<&Error> {
-    match self {
-      &WaveError::IoError(ref e) => Some(e),
+impl Error for WaveError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      WaveError::IoError(ref e) => Some(e),
       _ => None
     }
   }
@@ -46,10 +103,36 @@ impl Error for WaveError {
 
 impl Display for WaveError {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    match self {
-      &WaveError::IoError(ref e)     => write!(f, "IO Error: {}", e),
-      &WaveError::ParseError(ref s)  => write!(f, "Parse Error: {}", s),
-      &WaveError::Unsupported(ref s) => write!(f, "Unsupported Format Error: {}", s)
+    match *self {
+      WaveError::IoError(ref e)     => write!(f, "IO Error: {}", e),
+      WaveError::ParseError(ref s)  => write!(f, "Parse Error: {}", s),
+      WaveError::Unsupported(ref s) => write!(f, "Unsupported Format Error: {}", s),
+      WaveError::WriteError(ref s)  => write!(f, "Write Error: {}", s),
+      WaveError::InvalidChunk { ref id, offset, ref message } => {
+        write!(f, "Parse Error: {} (in {:?} chunk at offset {})", message, String::from_utf8_lossy(id), offset)
+      },
+      WaveError::NotWave { ref id, offset } => {
+        write!(f, "Parse Error: Not a Wavefile (found {:?} at offset {})", String::from_utf8_lossy(id), offset)
+      },
+      WaveError::UnexpectedChunk { ref id, offset } => {
+        write!(f, "Parse Error: Unexpected {:?} chunk at offset {}", String::from_utf8_lossy(id), offset)
+      },
+      WaveError::MissingChunk { ref id, offset } => {
+        write!(f, "Parse Error: {:?} chunk not found before offset {}", String::from_utf8_lossy(id), offset)
+      },
+      WaveError::ChunkOverrun { ref id, offset, size, available } => {
+        write!(f, "Parse Error: {:?} chunk of {} bytes at offset {} runs past the end of the file, which has {} bytes left",
+               String::from_utf8_lossy(id), size, offset, available)
+      },
+      WaveError::Mismatch { ref id, offset, field, expected, found } => {
+        write!(f, "Parse Error: {} of {:?} chunk at offset {} is {}, expected {}",
+               field, String::from_utf8_lossy(id), offset, found, expected)
+      },
+      WaveError::LimitExceeded { limit, max, found } => {
+        write!(f, "Limit Error: {} is {}, but found {}", limit, max, found)
+      },
+      WaveError::Cancelled => write!(f, "Cancelled"),
+      WaveError::Clipped { frame } => write!(f, "Clipping Error: frame {} exceeds full scale", frame)
     }
   }
 }
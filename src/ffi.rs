@@ -0,0 +1,172 @@
+//! Bindings for Swift and Kotlin, generated with `uniffi-bindgen` from a
+//! build of this crate as a `cdylib` with the `uniffi` feature enabled.
+//!
+//! The types mirror the Rust API in the shapes uniffi can express:
+//! frame counts are `u64` and samples are floats.
+
+use std::fmt::{self,Display};
+use std::io::Cursor;
+use std::sync::Arc;
+
+use error::WaveError;
+use options::WaveFileOptions;
+use writer::{WaveSpec,WaveWriter};
+use WaveFile;
+
+/// Raised when a file can't be opened or written.
+#[derive(Debug,uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum FfiError {
+  Io(String),
+  Invalid(String)
+}
+
+impl Display for FfiError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      FfiError::Io(ref s)      => write!(f, "IO Error: {}", s),
+      FfiError::Invalid(ref s) => write!(f, "{}", s)
+    }
+  }
+}
+
+impl From<WaveError> for FfiError {
+  fn from(e: WaveError) -> FfiError {
+    match e {
+      WaveError::IoError(e) => FfiError::Io(e.to_string()),
+      other                 => FfiError::Invalid(other.to_string())
+    }
+  }
+}
+
+/// The format of a file.
+#[derive(Debug,Clone,PartialEq,uniffi::Record)]
+pub struct FfiWaveInfo {
+  pub channels:        u16,
+  pub sample_rate:     u32,
+  pub bits_per_sample: u16,
+  /// `"PCM"` or `"IEEEFloat"`.
+  pub format:          String,
+  pub frames:          u64,
+  pub seconds:         f64
+}
+
+/// A named position in the audio.  See `Marker`.
+#[derive(Debug,Clone,PartialEq,uniffi::Record)]
+pub struct FfiMarker {
+  pub id:       u32,
+  pub position: u64,
+  pub label:    Option<String>
+}
+
+/// A labeled span of audio.  See `Region`.
+#[derive(Debug,Clone,PartialEq,uniffi::Record)]
+pub struct FfiRegion {
+  pub id:     u32,
+  pub start:  u64,
+  pub length: u64,
+  pub label:  Option<String>,
+  pub text:   Option<String>
+}
+
+/// A wavefile opened for reading.
+#[derive(uniffi::Object)]
+pub struct FfiWaveFile {
+  file: WaveFile
+}
+
+#[uniffi::export]
+impl FfiWaveFile {
+  #[uniffi::constructor]
+  pub fn open(path: String) -> Result<Arc<FfiWaveFile>, FfiError> {
+    Ok(Arc::new(FfiWaveFile { file: WaveFile::open(path)? }))
+  }
+
+  /// Parses a copy of `bytes`.
+  #[uniffi::constructor]
+  pub fn from_bytes(bytes: Vec<u8>) -> Result<Arc<FfiWaveFile>, FfiError> {
+    Ok(Arc::new(FfiWaveFile { file: WaveFile::from_bytes(&bytes, WaveFileOptions::default())? }))
+  }
+
+  pub fn info(&self) -> FfiWaveInfo {
+    let file = &self.file;
+
+    FfiWaveInfo {
+      channels:        file.info.channels,
+      sample_rate:     file.info.sample_rate,
+      bits_per_sample: file.info.bits_per_sample,
+      format:          format!("{:?}", file.data_format()),
+      frames:          file.len() as u64,
+      seconds:         file.len() as f64 / file.sample_rate().max(1) as f64
+    }
+  }
+
+  pub fn title(&self) -> Option<String> {
+    self.file.title()
+  }
+
+  pub fn markers(&self) -> Vec<FfiMarker> {
+    self.file.markers().into_iter().map(|marker| FfiMarker {
+      id:       marker.id,
+      position: marker.position as u64,
+      label:    marker.label
+    }).collect()
+  }
+
+  pub fn regions(&self) -> Vec<FfiRegion> {
+    self.file.regions().into_iter().map(|region| FfiRegion {
+      id:     region.id,
+      start:  region.start as u64,
+      length: region.length as u64,
+      label:  region.label,
+      text:   region.text
+    }).collect()
+  }
+
+  /// Decodes up to `count` frames starting at frame `first`, interleaved,
+  /// as floats like `read_frames_f32_into()`.
+  pub fn read_frames(&self, first: u64, count: u64) -> Vec<f32> {
+    let (first, count) = self.clamp(first, count);
+    let mut samples    = vec![0.0; count * self.file.channels()];
+
+    self.file.read_frames_f32_into(first, &mut samples);
+    samples
+  }
+
+  /// A new wavefile, in the same format, holding up to `count` frames
+  /// starting at frame `first`, such as those of a region.
+  pub fn extract(&self, first: u64, count: u64) -> Result<Vec<u8>, FfiError> {
+    let (first, count) = self.clamp(first, count);
+    let file           = &self.file;
+    let spec           = WaveSpec {
+      channels:        file.info.channels,
+      sample_rate:     file.info.sample_rate,
+      bits_per_sample: file.info.bits_per_sample,
+      format:          file.data_format()
+    };
+    let mut out        = Cursor::new(Vec::new());
+
+    {
+      let mut writer = WaveWriter::new(&mut out, spec)?;
+      let mut frame  = vec![0; file.channels()];
+
+      for index in first..first + count {
+        file.read_frames(index, &mut frame);
+        writer.write_frame(&frame)?;
+      }
+      writer.finalize()?;
+    }
+
+    Ok(out.into_inner())
+  }
+}
+
+impl FfiWaveFile {
+  /// Limits a range of frames to those present.
+  fn clamp(&self, first: u64, count: u64) -> (usize, usize) {
+    let len   = self.file.len() as u64;
+    let first = first.min(len);
+
+    (first as usize, count.min(len - first) as usize)
+  }
+}
@@ -0,0 +1,76 @@
+use error::WaveError;
+use WaveFile;
+
+/// The sample rate of the audio fed by `WaveFile::feed_fingerprint`, that
+/// at which chromaprint analyses audio.
+pub const FINGERPRINT_SAMPLE_RATE : u32 = 11025;
+
+impl WaveFile {
+  /// Feeds every frame of the file to `feed` as mono 16 bit samples at
+  /// `FINGERPRINT_SAMPLE_RATE`, the layout chromaprint expects, so the
+  /// blocks can be passed straight to `chromaprint_feed` after
+  /// `chromaprint_start(ctx, 11025, 1)`.
+  ///
+  /// Channels are averaged, and the rate converted by averaging the
+  /// samples falling within each output sample, or repeating them if the
+  /// file's rate is lower.  Fails if the file's sample rate is 0.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::{WaveFile,FINGERPRINT_SAMPLE_RATE};
+  ///
+  /// let wav       = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  /// let mut count = 0;
+  ///
+  /// wav.feed_fingerprint(|samples| count += samples.len()).unwrap();
+  /// assert_eq!(count, wav.len() * FINGERPRINT_SAMPLE_RATE as usize / wav.sample_rate());
+  /// ```
+  pub fn feed_fingerprint<F: FnMut(&[i16])>(&self, mut feed: F) -> Result<(), WaveError> {
+    if self.info.sample_rate == 0 {
+      return Err(WaveError::Unsupported("a sample rate of 0".into()));
+    }
+
+    let channels    = self.channels();
+    let ratio       = self.info.sample_rate as f64 / FINGERPRINT_SAMPLE_RATE as f64;
+    let mut samples = vec![0.0; (self.options.block_samples / channels).max(1) * channels];
+    let mut output  = Vec::with_capacity(samples.len() / channels + 1);
+    let mut next    = 0;
+    // the input frame at which the current output sample ends.
+    let mut edge    = ratio;
+    let mut sum     = 0.0;
+    let mut count   = 0;
+    let mut last    = 0;
+
+    loop {
+      let frames = self.read_frames_f32_into(next, &mut samples);
+
+      if frames == 0 {
+        break;
+      }
+
+      for (index, frame) in samples[..frames * channels].chunks(channels).enumerate() {
+        sum   += frame.iter().sum::<f32>() as f64 / channels as f64;
+        count += 1;
+
+        while (next + index + 1) as f64 >= edge {
+          if count > 0 {
+            last  = (sum / count as f64 * 32768.0).round().clamp(-32768.0, 32767.0) as i16;
+            sum   = 0.0;
+            count = 0;
+          }
+          output.push(last);
+          edge += ratio;
+        }
+      }
+
+      next += frames;
+      if !output.is_empty() {
+        feed(&output);
+        output.clear();
+      }
+    }
+
+    Ok(())
+  }
+}
@@ -2,7 +2,9 @@ const FORMAT_PCM  : u16 = 1;
 const FORMAT_IEEE : u16 = 3;
 const FORMAT_EXT  : u16 = 0xfffe;
 
-#[derive(Debug,Copy,Clone,PartialEq)]
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 pub enum Format {
   PCM       = FORMAT_PCM  as isize,
   IEEEFloat = FORMAT_IEEE as isize,
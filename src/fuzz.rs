@@ -0,0 +1,173 @@
+//! Generators for fuzzing code that consumes wavefiles, enabled by the
+//! `arbitrary` feature.
+
+use arbitrary::{Arbitrary,Result,Unstructured};
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use formats::Format;
+use {WaveInfo,FourCC};
+
+/// IDs of the chunks this crate parses, so generated files exercise their
+/// parsers rather than only being skipped.
+const KNOWN_CHUNKS : [&FourCC; 16] = [
+  b"LIST", b"cue ", b"plst", b"smpl", b"inst", b"bext", b"iXML", b"axml",
+  b"chna", b"cart", b"levl", b"DISP", b"acid", b"dbmd", b"fact", b"JUNK"
+];
+
+const SAMPLE_RATES : [u32; 6] = [8000, 22050, 44100, 48000, 96000, 192000];
+
+/// Caps the audio generated, so fuzzers spend their time on the headers.
+const MAX_FRAMES : u64 = 64;
+
+/// Generates mostly plausible headers, with the occasional extreme value.
+/// Sizes and rates are derived from the other fields, as in a well-formed
+/// file.
+impl<'a> Arbitrary<'a> for WaveInfo {
+  fn arbitrary(u: &mut Unstructured<'a>) -> Result<WaveInfo> {
+    let audio_format    = Format::arbitrary(u)?;
+    let channels        = if rarely(u, 16)? { u16::arbitrary(u)? } else { u.int_in_range(1..=8)? };
+    let bits_per_sample = if rarely(u, 16)? { u16::arbitrary(u)? } else { *u.choose(&[8, 16, 24, 32])? };
+    let sample_rate     = if rarely(u, 16)? { u32::arbitrary(u)? } else { *u.choose(&SAMPLE_RATES)? };
+    let block_align     = channels.saturating_mul(bits_per_sample / 8);
+    let extended        = audio_format == Format::Extended;
+
+    Ok(WaveInfo {
+      audio_format,
+      channels,
+      sample_rate,
+      byte_rate:           sample_rate.wrapping_mul(block_align as u32),
+      block_align,
+      bits_per_sample,
+      total_frames:        u.int_in_range(0..=MAX_FRAMES)?,
+      valid_bps:           if extended { Some(bits_per_sample) } else { None },
+      channel_mask:        if extended { Some(u32::arbitrary(u)?) } else { None },
+      subformat:           if extended { Some(*u.choose(&[Format::PCM, Format::IEEEFloat])?) } else { None },
+      data_size_untrusted: rarely(u, 8)?,
+      truncated:           rarely(u, 8)?
+    })
+  }
+}
+
+/// The bytes of a wavefile built from an arbitrary `WaveInfo`, with random
+/// metadata chunks and the kinds of damage found in the wild: wrong RIFF and
+/// format fields, unset data sizes, missing pad bytes, chunks out of order
+/// and files cut short.
+///
+/// # Example
+///
+/// ```
+/// extern crate arbitrary;
+/// extern crate wavefile;
+///
+/// use arbitrary::{Arbitrary,Unstructured};
+/// use wavefile::ArbitraryWave;
+///
+/// # fn main() {
+/// let mut u = Unstructured::new(b"raw input from the fuzzer");
+/// let wave  = ArbitraryWave::arbitrary(&mut u).unwrap();
+///
+/// assert_eq!(&wave.bytes[..4], b"RIFF");
+/// # }
+/// ```
+#[derive(Debug,Clone)]
+pub struct ArbitraryWave {
+  /// The header the file was built from.  `data_size_untrusted` and
+  /// `truncated` tell whether the data size was left unset and whether the
+  /// file was cut short.
+  pub info:  WaveInfo,
+  pub bytes: Vec<u8>
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryWave {
+  fn arbitrary(u: &mut Unstructured<'a>) -> Result<ArbitraryWave> {
+    let info       = WaveInfo::arbitrary(u)?;
+    let mut chunks = vec![(*b"fmt ", format_chunk(u, &info)?)];
+    let mut data   = vec![0u8; info.block_align as usize * info.total_frames as usize];
+
+    u.fill_buffer(&mut data)?;
+
+    for _ in 0..u.int_in_range(0..=4)? {
+      let id   = if u.ratio(3, 4)? { **u.choose(&KNOWN_CHUNKS)? } else { <FourCC>::arbitrary(u)? };
+      let body = (0..u.int_in_range(0..=256)?).map(|_| u8::arbitrary(u)).collect::<Result<Vec<_>>>()?;
+
+      chunks.push((id, body));
+    }
+
+    // an unset data size only works for the last chunk.
+    let position = if info.data_size_untrusted {
+      chunks.len()
+    } else if rarely(u, 8)? {
+      0
+    } else {
+      u.int_in_range(1..=chunks.len())?
+    };
+
+    chunks.insert(position, (*b"data", data));
+
+    let mut bytes = Vec::new();
+
+    for (id, body) in chunks {
+      let size = if &id == b"data" && info.data_size_untrusted {
+        *u.choose(&[0, u32::MAX])?
+      } else {
+        body.len() as u32
+      };
+
+      bytes.extend_from_slice(&id);
+      bytes.write_u32::<LittleEndian>(size).unwrap();
+      bytes.extend_from_slice(&body);
+
+      if body.len() % 2 == 1 && !rarely(u, 16)? {
+        bytes.push(0);
+      }
+    }
+
+    let riff_size = if rarely(u, 8)? { u32::arbitrary(u)? } else { bytes.len() as u32 + 4 };
+    let mut file  = Vec::with_capacity(bytes.len() + 12);
+
+    file.extend_from_slice(b"RIFF");
+    file.write_u32::<LittleEndian>(riff_size).unwrap();
+    file.extend_from_slice(b"WAVE");
+    file.extend_from_slice(&bytes);
+
+    if info.truncated {
+      let len = u.int_in_range(12..=file.len())?;
+      file.truncate(len);
+    }
+
+    Ok(ArbitraryWave { info, bytes: file })
+  }
+}
+
+/// Returns `true` with a chance of one in `odds`, and `false` once the
+/// input runs out, so that short inputs yield well-formed files.
+fn rarely(u: &mut Unstructured, odds: u8) -> Result<bool> {
+  Ok(!u.ratio(odds - 1, odds)?)
+}
+
+/// Encodes the `fmt ` chunk of `info`, occasionally with inconsistent
+/// fields.
+fn format_chunk(u: &mut Unstructured, info: &WaveInfo) -> Result<Vec<u8>> {
+  let mut body = Vec::new();
+
+  let byte_rate   = if rarely(u, 8)? { u32::arbitrary(u)? } else { info.byte_rate };
+  let block_align = if rarely(u, 8)? { u16::arbitrary(u)? } else { info.block_align };
+
+  body.write_u16::<LittleEndian>(info.audio_format as u16).unwrap();
+  body.write_u16::<LittleEndian>(info.channels).unwrap();
+  body.write_u32::<LittleEndian>(info.sample_rate).unwrap();
+  body.write_u32::<LittleEndian>(byte_rate).unwrap();
+  body.write_u16::<LittleEndian>(block_align).unwrap();
+  body.write_u16::<LittleEndian>(info.bits_per_sample).unwrap();
+
+  if let (Some(valid_bps), Some(channel_mask), Some(subformat)) = (info.valid_bps, info.channel_mask, info.subformat) {
+    body.write_u16::<LittleEndian>(22).unwrap();
+    body.write_u16::<LittleEndian>(valid_bps).unwrap();
+    body.write_u32::<LittleEndian>(channel_mask).unwrap();
+    body.write_u16::<LittleEndian>(subformat as u16).unwrap();
+    // the rest of the subformat GUID.
+    body.extend_from_slice(b"\x00\x00\x00\x00\x10\x00\x80\x00\x00\xaa\x00\x38\x9b\x71");
+  }
+
+  Ok(body)
+}
@@ -0,0 +1,32 @@
+//! A stand-in for `memmap::Mmap` on targets that can't map files, such as
+//! WebAssembly, which holds the file on the heap instead.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub enum Protection {
+  Read,
+  ReadWrite
+}
+
+pub struct Mmap(Vec<u8>);
+
+impl Mmap {
+  pub fn open_path<P: AsRef<Path>>(path: P, _: Protection) -> io::Result<Mmap> {
+    fs::read(path).map(Mmap)
+  }
+
+  pub fn anonymous(len: usize, _: Protection) -> io::Result<Mmap> {
+    Ok(Mmap(vec![0; len]))
+  }
+
+  /// Unsafe only to match `memmap`, whose mappings may change underneath.
+  pub unsafe fn as_slice(&self) -> &[u8] {
+    &self.0
+  }
+
+  pub unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+    &mut self.0
+  }
+}
@@ -0,0 +1,117 @@
+use std::io::{Seek,Write};
+
+use hound;
+
+use error::WaveError;
+use formats::Format;
+use writer::WaveSpec;
+use {WaveFile,WaveInfo};
+
+impl From<hound::SampleFormat> for Format {
+  fn from(format: hound::SampleFormat) -> Format {
+    match format {
+      hound::SampleFormat::Int   => Format::PCM,
+      hound::SampleFormat::Float => Format::IEEEFloat
+    }
+  }
+}
+
+/// The subformat is used for extensible files.
+impl From<WaveInfo> for hound::WavSpec {
+  fn from(info: WaveInfo) -> hound::WavSpec {
+    hound::WavSpec {
+      channels:        info.channels,
+      sample_rate:     info.sample_rate,
+      bits_per_sample: info.bits_per_sample,
+      sample_format:   sample_format(info.subformat.unwrap_or(info.audio_format))
+    }
+  }
+}
+
+impl From<WaveSpec> for hound::WavSpec {
+  fn from(spec: WaveSpec) -> hound::WavSpec {
+    hound::WavSpec {
+      channels:        spec.channels,
+      sample_rate:     spec.sample_rate,
+      bits_per_sample: spec.bits_per_sample,
+      sample_format:   sample_format(spec.format)
+    }
+  }
+}
+
+impl From<hound::WavSpec> for WaveSpec {
+  fn from(spec: hound::WavSpec) -> WaveSpec {
+    WaveSpec {
+      channels:        spec.channels,
+      sample_rate:     spec.sample_rate,
+      bits_per_sample: spec.bits_per_sample,
+      format:          spec.sample_format.into()
+    }
+  }
+}
+
+impl From<hound::Error> for WaveError {
+  fn from(e: hound::Error) -> WaveError {
+    match e {
+      hound::Error::IoError(e) => e.into(),
+      other                    => WaveError::WriteError(other.to_string())
+    }
+  }
+}
+
+fn sample_format(format: Format) -> hound::SampleFormat {
+  match format {
+    Format::IEEEFloat => hound::SampleFormat::Float,
+    _                 => hound::SampleFormat::Int
+  }
+}
+
+impl WaveFile {
+  /// Writes every frame of the file to `writer`, which is expected to have
+  /// been created with the spec of this file, as given by
+  /// `hound::WavSpec::from(file.info())`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// extern crate hound;
+  /// extern crate wavefile;
+  ///
+  /// use wavefile::WaveFile;
+  ///
+  /// let wav        = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  /// let path       = std::env::temp_dir().join("wavefile-doc-hound.wav");
+  /// let mut writer = hound::WavWriter::create(&path, wav.info().into()).unwrap();
+  ///
+  /// wav.write_to_hound(&mut writer).unwrap();
+  /// writer.finalize().unwrap();
+  /// # std::fs::remove_file(path).unwrap();
+  /// ```
+  pub fn write_to_hound<W: Write + Seek>(&self, writer: &mut hound::WavWriter<W>) -> Result<(), WaveError> {
+    if self.data_format() == Format::IEEEFloat {
+      self.copy_to_hound(writer, WaveFile::read_frames_f32_into)
+    } else {
+      self.copy_to_hound(writer, WaveFile::read_frames)
+    }
+  }
+
+  fn copy_to_hound<W, T>(&self, writer: &mut hound::WavWriter<W>, read: fn(&WaveFile, usize, &mut [T]) -> usize)
+                         -> Result<(), WaveError>
+    where W: Write + Seek, T: hound::Sample + Copy + Default {
+    let channels   = self.channels();
+    let mut buffer = vec![T::default(); (4096 / channels).max(1) * channels];
+    let mut first  = 0;
+
+    loop {
+      let frames = read(self, first, &mut buffer);
+
+      if frames == 0 {
+        return Ok(());
+      }
+      for &sample in &buffer[..frames * channels] {
+        writer.write_sample(sample)?;
+      }
+      first += frames;
+    }
+  }
+}
@@ -0,0 +1,74 @@
+use roxmltree::{Document,Node};
+
+use error::WaveError;
+
+/// A track entry of an iXML `TRACK_LIST`, naming one channel of the file.
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct IXmlTrack {
+  /// One-based index of the channel as recorded.
+  pub channel_index:    Option<u16>,
+  /// One-based index of the channel within the interleaved audio data.
+  pub interleave_index: Option<u16>,
+  pub name:             Option<String>,
+  pub function:         Option<String>
+}
+
+/// The production fields of an iXML chunk most commonly used by film sound
+/// workflows.
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct IXml {
+  pub project: Option<String>,
+  pub scene:   Option<String>,
+  pub take:    Option<String>,
+  pub tape:    Option<String>,
+  pub note:    Option<String>,
+  pub tracks:  Vec<IXmlTrack>
+}
+
+impl IXml {
+  /// Parses an iXML document.
+  pub fn parse(xml: &str) -> Result<IXml, WaveError> {
+    let doc = match Document::parse(xml) {
+      Ok(doc) => doc,
+      Err(e)  => return Err(WaveError::ParseError(format!("Invalid iXML: {}", e)))
+    };
+    let root = doc.root_element();
+
+    if !root.has_tag_name("BWFXML") {
+      let msg = format!("Unexpected iXML root element {}", root.tag_name().name());
+      return Err(WaveError::ParseError(msg));
+    }
+
+    let tracks = child(root, "TRACK_LIST").map(|list| {
+      list.children().filter(|n| n.has_tag_name("TRACK")).map(|track| {
+        IXmlTrack {
+          channel_index:    text(track, "CHANNEL_INDEX").and_then(|s| s.parse().ok()),
+          interleave_index: text(track, "INTERLEAVE_INDEX").and_then(|s| s.parse().ok()),
+          name:             text(track, "NAME"),
+          function:         text(track, "FUNCTION")
+        }
+      }).collect()
+    }).unwrap_or_default();
+
+    Ok(IXml {
+      project: text(root, "PROJECT"),
+      scene:   text(root, "SCENE"),
+      take:    text(root, "TAKE"),
+      tape:    text(root, "TAPE"),
+      note:    text(root, "NOTE"),
+      tracks
+    })
+  }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+  node.children().find(|n| n.has_tag_name(name))
+}
+
+fn text(node: Node, name: &str) -> Option<String> {
+  child(node, name)
+    .and_then(|n| n.text())
+    .map(|s| s.trim())
+    .filter(|s| !s.is_empty())
+    .map(|s| s.to_owned())
+}
@@ -0,0 +1,148 @@
+use std::io::{BufRead,Write};
+
+use error::WaveError;
+use cue::{CuePoint,AssociatedData,LabeledText,Marker,Region};
+use fourcc;
+use DATA;
+
+/// A label of an Audacity label track, as exported with "Export Labels".
+/// Point labels start and end at the same time.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Label {
+  /// Start of the label, in seconds.
+  pub start: f64,
+  /// End of the label, in seconds.
+  pub end:   f64,
+  pub text:  String
+}
+
+impl Label {
+  /// Returns `true` if the label marks a single position rather than a span.
+  pub fn is_point(&self) -> bool {
+    self.end <= self.start
+  }
+}
+
+/// Reads an Audacity label track: one label per line, holding the start and
+/// end times in seconds and the label text, separated by tabs.  The spectral
+/// selection lines Audacity writes after some labels are skipped.
+///
+/// # Example
+///
+/// ```
+/// use wavefile::read_label_track;
+///
+/// let labels = read_label_track("1.5\t1.5\tClap\n2.000000\t4.250000\tVerse\n".as_bytes()).unwrap();
+///
+/// assert_eq!(labels.len(), 2);
+/// assert!(labels[0].is_point());
+/// assert_eq!(labels[1].text, "Verse");
+/// ```
+pub fn read_label_track<R: BufRead>(input: R) -> Result<Vec<Label>, WaveError> {
+  let mut labels = Vec::new();
+
+  for (number, line) in input.lines().enumerate() {
+    let line = line?;
+    let line = line.trim_end_matches('\r');
+
+    if line.trim().is_empty() || line.starts_with('\\') {
+      continue;
+    }
+
+    let mut fields = line.splitn(3, '\t');
+    let start      = parse_time(fields.next(), number)?;
+    let end        = parse_time(fields.next(), number)?;
+    let text       = fields.next().unwrap_or("").to_owned();
+
+    labels.push(Label { start, end: end.max(start), text });
+  }
+
+  Ok(labels)
+}
+
+/// Writes `labels` as an Audacity label track.
+pub fn write_label_track<W: Write>(out: &mut W, labels: &[Label]) -> Result<(), WaveError> {
+  for label in labels {
+    writeln!(out, "{:.6}\t{:.6}\t{}", label.start, label.end, label.text)?;
+  }
+
+  Ok(())
+}
+
+fn parse_time(field: Option<&str>, number: usize) -> Result<f64, WaveError> {
+  match field.and_then(|field| field.trim().parse::<f64>().ok()) {
+    Some(time) if time >= 0.0 => Ok(time),
+    _ => {
+      let msg = format!("Invalid time on line {} of label track", number + 1);
+      Err(WaveError::ParseError(msg))
+    }
+  }
+}
+
+/// Converts markers and regions into labels.  Regions become span labels,
+/// named by their label or else their text; other markers become point
+/// labels named by their label or else their note.
+pub(crate) fn from_cues(markers: &[Marker], regions: &[Region], sample_rate: u32) -> Vec<Label> {
+  let rate = sample_rate.max(1) as f64;
+
+  markers.iter().map(|marker| {
+    let start = marker.position as f64 / rate;
+
+    match regions.iter().find(|region| region.id == marker.id) {
+      Some(region) => Label {
+        start:  region.start as f64 / rate,
+        end:    (region.start as f64 + region.length as f64) / rate,
+        text:   region.label.clone().or_else(|| region.text.clone()).unwrap_or_default()
+      },
+      None => Label {
+        start,
+        end:    start,
+        text:   marker.label.clone().or_else(|| marker.note.clone()).unwrap_or_default()
+      }
+    }
+  }).collect()
+}
+
+/// Converts labels into cue points and the associated data naming them.
+/// Span labels get an `ltxt` entry of purpose `rgn `.
+pub(crate) fn to_cues(labels: &[Label], sample_rate: u32) -> (Vec<CuePoint>, AssociatedData) {
+  let mut cues = Vec::with_capacity(labels.len());
+  let mut adtl = AssociatedData::default();
+
+  for (index, label) in labels.iter().enumerate() {
+    let id     = index as u32 + 1;
+    let offset = to_frames(label.start, sample_rate);
+
+    cues.push(CuePoint {
+      id,
+      position:      offset,
+      data_chunk_id: fourcc(DATA),
+      chunk_start:   0,
+      block_start:   0,
+      sample_offset: offset
+    });
+
+    if !label.text.is_empty() {
+      adtl.labels.insert(id, label.text.clone());
+    }
+
+    if !label.is_point() {
+      adtl.texts.push(LabeledText {
+        cue_id:        id,
+        sample_length: to_frames(label.end, sample_rate) - offset,
+        purpose:       *b"rgn ",
+        country:       0,
+        language:      0,
+        dialect:       0,
+        code_page:     0,
+        text:          String::new()
+      });
+    }
+  }
+
+  (cues, adtl)
+}
+
+fn to_frames(seconds: f64, sample_rate: u32) -> u32 {
+  (seconds * sample_rate as f64).round().min(u32::MAX as f64) as u32
+}
@@ -0,0 +1,226 @@
+use std::io::{Cursor,Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use error::WaveError;
+use {read_zstring,write_fixed};
+
+/// Size of the `levl` chunk header, including the chunk ID and size.
+const LEVL_HEADER_SIZE : u32 = 128;
+
+const PEAK_FORMAT_8  : u32 = 1;
+const PEAK_FORMAT_16 : u32 = 2;
+
+/// Value of `peak_of_peaks` when its position is unknown.
+const PEAK_POSITION_UNKNOWN : u32 = 0xffffffff;
+
+/// Contents of the Broadcast Wave `levl` chunk, a precomputed envelope of the
+/// peak levels of the audio used for drawing waveforms.
+#[derive(Debug,Clone,PartialEq)]
+pub struct PeakEnvelope {
+  pub version:          u32,
+  /// Size of each peak value, either 8 or 16 bits.
+  pub bits_per_value:   u16,
+  /// `1` if only the absolute peak is stored for each block, `2` if the
+  /// positive and negative peaks are stored separately.
+  pub points_per_value: u16,
+  /// Number of audio frames summarized by each peak frame.
+  pub block_size:       u32,
+  pub channels:         u16,
+  /// Frame offset of the loudest sample in the file, if known.
+  pub peak_of_peaks:    Option<u32>,
+  /// Creation time, formatted as `yyyy:mm:dd:hh:mm:ss:uuu`.
+  pub timestamp:        String,
+  /// Peak magnitudes, interleaved by channel and then by point, one peak
+  /// frame per block of audio frames.
+  pub peaks:            Vec<u16>
+}
+
+impl PeakEnvelope {
+  /// Number of peak frames in the envelope.
+  pub fn len(&self) -> usize {
+    self.peaks.len() / self.values_per_frame().max(1)
+  }
+
+  /// Returns `true` if the envelope contains no peak frames.
+  pub fn is_empty(&self) -> bool {
+    self.peaks.is_empty()
+  }
+
+  /// Returns the peak values of one channel for the given peak frame, or
+  /// `None` if either is out of range.
+  pub fn peak(&self, frame: usize, channel: usize) -> Option<&[u16]> {
+    let points = self.points_per_value as usize;
+    let start  = frame * self.values_per_frame() + channel * points;
+
+    if channel >= self.channels as usize || start + points > self.peaks.len() {
+      return None;
+    }
+
+    Some(&self.peaks[start..start + points])
+  }
+
+  fn values_per_frame(&self) -> usize {
+    self.channels as usize * self.points_per_value as usize
+  }
+
+  pub(crate) fn parse_chunk(data: &[u8]) -> Result<PeakEnvelope, WaveError> {
+    if data.len() < 120 {
+      let msg = format!("Peak envelope chunk is only {} bytes long", data.len());
+      return Err(WaveError::ParseError(msg));
+    }
+
+    let mut cursor = Cursor::new(data);
+    let version          = cursor.read_u32::<LittleEndian>()?;
+    let format           = cursor.read_u32::<LittleEndian>()?;
+    let points_per_value = cursor.read_u32::<LittleEndian>()?;
+    let block_size       = cursor.read_u32::<LittleEndian>()?;
+    let channels         = cursor.read_u32::<LittleEndian>()?;
+    let frames           = cursor.read_u32::<LittleEndian>()? as usize;
+    let peak_of_peaks    = cursor.read_u32::<LittleEndian>()?;
+    let offset           = cursor.read_u32::<LittleEndian>()?;
+    let timestamp        = read_zstring(&data[32..60]);
+
+    let bytes_per_value = match format {
+      PEAK_FORMAT_8  => 1,
+      PEAK_FORMAT_16 => 2,
+      other          => {
+        let msg = format!("Unexpected peak envelope format {}", other);
+        return Err(WaveError::ParseError(msg));
+      }
+    };
+
+    if points_per_value == 0 || points_per_value > 2 || channels == 0 || channels > 0xffff {
+      let msg = format!("Invalid peak envelope layout: {} channel(s) of {} point(s)",
+                        channels, points_per_value);
+      return Err(WaveError::ParseError(msg));
+    }
+
+    // the offset is measured from the start of the chunk header.
+    let start = offset.saturating_sub(8) as usize;
+    let count = frames * channels as usize * points_per_value as usize;
+
+    if start > data.len() || count > (data.len() - start) / bytes_per_value {
+      let msg = format!("Peak envelope declares {} peak frames but only holds {} bytes", frames, data.len());
+      return Err(WaveError::ParseError(msg));
+    }
+
+    let mut cursor = Cursor::new(&data[start..]);
+    let mut peaks  = Vec::with_capacity(count);
+
+    for _ in 0..count {
+      peaks.push(match bytes_per_value {
+        1 => cursor.read_u8()? as u16,
+        _ => cursor.read_u16::<LittleEndian>()?
+      });
+    }
+
+    Ok(PeakEnvelope {
+      version,
+      bits_per_value:   bytes_per_value as u16 * 8,
+      points_per_value: points_per_value as u16,
+      block_size,
+      channels:         channels as u16,
+      peak_of_peaks:    if peak_of_peaks == PEAK_POSITION_UNKNOWN { None } else { Some(peak_of_peaks) },
+      timestamp,
+      peaks
+    })
+  }
+
+  /// Writes the chunk body, without the chunk header.
+  pub fn write_to<W: Write>(&self, out: &mut W) -> Result<(), WaveError> {
+    let format = if self.bits_per_value == 8 { PEAK_FORMAT_8 } else { PEAK_FORMAT_16 };
+
+    out.write_u32::<LittleEndian>(self.version)?;
+    out.write_u32::<LittleEndian>(format)?;
+    out.write_u32::<LittleEndian>(self.points_per_value as u32)?;
+    out.write_u32::<LittleEndian>(self.block_size)?;
+    out.write_u32::<LittleEndian>(self.channels as u32)?;
+    out.write_u32::<LittleEndian>(self.len() as u32)?;
+    out.write_u32::<LittleEndian>(self.peak_of_peaks.unwrap_or(PEAK_POSITION_UNKNOWN))?;
+    out.write_u32::<LittleEndian>(LEVL_HEADER_SIZE)?;
+    write_fixed(out, &self.timestamp, 28)?;
+    out.write_all(&[0u8; 60])?;
+
+    for &peak in &self.peaks {
+      if format == PEAK_FORMAT_8 {
+        out.write_u8(peak.min(0xff) as u8)?;
+      } else {
+        out.write_u16::<LittleEndian>(peak)?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Accumulates a 16 bit, single point `PeakEnvelope` from frames as they are
+/// written.
+pub(crate) struct PeakBuilder {
+  envelope: PeakEnvelope,
+  /// Full scale sample value of the audio being written.
+  scale:    f64,
+  current:  Vec<u16>,
+  in_block: u32,
+  frame:    u32,
+  loudest:  u16
+}
+
+impl PeakBuilder {
+  pub(crate) fn new(channels: u16, block_size: u32, scale: f64) -> PeakBuilder {
+    PeakBuilder {
+      envelope: PeakEnvelope {
+        version:          1,
+        bits_per_value:   16,
+        points_per_value: 1,
+        block_size,
+        channels,
+        peak_of_peaks:    None,
+        timestamp:        String::new(),
+        peaks:            Vec::new()
+      },
+      scale,
+      current:  vec![0; channels as usize],
+      in_block: 0,
+      frame:    0,
+      loudest:  0
+    }
+  }
+
+  pub(crate) fn add_frame(&mut self, frame: &[i32]) {
+    for (peak, &sample) in self.current.iter_mut().zip(frame) {
+      let level = ((sample as f64).abs() / self.scale * 32767.0).min(32767.0) as u16;
+
+      if level > *peak {
+        *peak = level;
+      }
+      if level > self.loudest || self.envelope.peak_of_peaks.is_none() {
+        self.loudest = level;
+        self.envelope.peak_of_peaks = Some(self.frame);
+      }
+    }
+
+    self.frame    += 1;
+    self.in_block += 1;
+
+    if self.in_block == self.envelope.block_size {
+      self.flush_block();
+    }
+  }
+
+  pub(crate) fn finish(mut self) -> PeakEnvelope {
+    if self.in_block > 0 {
+      self.flush_block();
+    }
+    self.envelope
+  }
+
+  fn flush_block(&mut self) {
+    self.envelope.peaks.extend_from_slice(&self.current);
+
+    for peak in &mut self.current {
+      *peak = 0;
+    }
+    self.in_block = 0;
+  }
+}
@@ -1,18 +1,192 @@
+#[cfg(not(target_arch = "wasm32"))]
 extern crate memmap;
 extern crate byteorder;
+#[cfg(feature = "xml")]
+extern crate roxmltree;
+#[cfg(feature = "id3")]
+extern crate id3;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "arbitrary")]
+#[macro_use]
+extern crate arbitrary;
+#[cfg(feature = "bytemuck")]
+extern crate bytemuck;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+extern crate io_uring;
+#[cfg(feature = "hound")]
+extern crate hound;
+#[cfg(feature = "symphonia")]
+extern crate symphonia_core;
+#[cfg(feature = "cpal")]
+extern crate cpal;
+#[cfg(feature = "rodio")]
+extern crate rodio;
+#[cfg(feature = "ndarray")]
+extern crate ndarray;
+#[cfg(feature = "arrow")]
+extern crate arrow_array;
+#[cfg(feature = "arrow")]
+extern crate arrow_schema;
+#[cfg(feature = "parquet")]
+extern crate parquet;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(feature = "uniffi")]
+extern crate uniffi;
+#[cfg(feature = "embedded-io")]
+extern crate embedded_io;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(any(feature = "async", feature = "futures"))]
+extern crate futures_core;
+#[cfg(feature = "futures")]
+extern crate futures_io;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
+#[macro_use]
+mod trace;
 
 pub mod error;
 pub mod speakers;
 pub mod formats;
+pub mod cue;
+pub mod sampler;
+pub mod bext;
+#[cfg(feature = "xml")]
+pub mod ixml;
+pub mod adm;
+pub mod cart;
+pub mod writer;
+pub mod levl;
+pub mod tags;
+pub mod edit;
+pub mod text;
+pub mod labels;
+pub mod timecode;
+pub mod acid;
+pub mod umid;
+pub mod dbmd;
+pub mod options;
+pub mod repair;
+pub mod validate;
+pub mod carve;
+pub mod prefetch;
+pub mod batch;
+pub mod progress;
+pub mod transcode;
+pub mod ops;
+pub mod loudness;
+mod decode;
+#[cfg(target_arch = "wasm32")]
+mod heap;
+mod raw;
+mod fingerprint;
+mod realtime;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
+#[cfg(feature = "hound")]
+mod hound_compat;
+#[cfg(feature = "symphonia")]
+mod symphonia_compat;
+#[cfg(feature = "cpal")]
+mod cpal_compat;
+#[cfg(feature = "rodio")]
+mod rodio_compat;
+#[cfg(feature = "arrow")]
+mod arrow_compat;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "uniffi")]
+pub mod ffi;
+#[cfg(feature = "embedded-io")]
+mod embedded;
+#[cfg(any(feature = "embedded-io", feature = "async", feature = "futures"))]
+mod stream;
+#[cfg(any(feature = "async", feature = "futures"))]
+mod async_io;
+#[cfg(feature = "async")]
+mod async_writer;
+#[cfg(feature = "async")]
+pub mod blocking;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
 
 pub use self::error::WaveError;
 pub use self::speakers::SpeakerPosition;
 pub use self::formats::Format;
+pub use self::cue::{CuePoint,Marker,Region,PlaylistSegment};
+pub use self::sampler::{Sampler,SampleLoop,LoopType,Instrument};
+pub use self::bext::{Bext,Loudness};
+#[cfg(feature = "xml")]
+pub use self::ixml::{IXml,IXmlTrack};
+pub use self::adm::{Chna,ChnaEntry};
+pub use self::cart::{Cart,CartTimer};
+pub use self::writer::{WaveWriter,WaveSpec};
+pub use self::levl::PeakEnvelope;
+pub use self::tags::{InfoTags,Disp};
+pub use self::edit::{strip_metadata,write_bext,write_info_tags};
+pub use self::ops::{reverse,extract,extract_time,extract_channel,remap_channels,concat,concat_with,ConcatOptions,
+                      split_by_duration,split_at_cues,split_at_cues_with,split_on_silence,split_on_silence_with,Segment,
+                      normalize_peak,normalize_loudness};
+pub use self::text::TextEncoding;
+pub use self::labels::{Label,read_label_track,write_label_track};
+pub use self::timecode::{Timecode,FrameRate};
+pub use self::acid::Acid;
+pub use self::umid::Umid;
+pub use self::dbmd::{Dbmd,DbmdSegment,DbmdSegmentKind,DolbyDigital,DolbyDownmix,AudioInfo};
+pub use self::options::{WaveFileOptions,UnknownChunks,DuplicateChunks};
+pub use self::repair::{repair,Repair,RepairReport};
+pub use self::validate::{Issue,Severity,BitUsage};
+pub use self::carve::CarvedWave;
+pub use self::prefetch::Prefetching;
+pub use self::progress::{Progress,CancelToken};
+pub use self::fingerprint::FINGERPRINT_SAMPLE_RATE;
+pub use self::realtime::RealtimeReader;
+#[cfg(feature = "symphonia")]
+pub use self::symphonia_compat::{SymphoniaReader,SymphoniaDecoder};
+#[cfg(feature = "cpal")]
+pub use self::cpal_compat::Playback;
+#[cfg(feature = "rodio")]
+pub use self::rodio_compat::RodioSource;
+#[cfg(feature = "arrow")]
+pub use self::arrow_compat::RecordBatches;
+#[cfg(feature = "wasm")]
+pub use self::wasm::WasmWaveFile;
+#[cfg(feature = "embedded-io")]
+pub use self::embedded::EmbeddedWaveFile;
+#[cfg(any(feature = "async", feature = "futures"))]
+pub use self::async_io::{AsyncWaveFile,AsyncFrames,AsyncSource,AsyncSeekSource};
+#[cfg(feature = "futures")]
+pub use self::async_io::FuturesReader;
+#[cfg(feature = "async")]
+pub use self::async_writer::AsyncWaveWriter;
+#[cfg(feature = "arbitrary")]
+pub use self::fuzz::ArbitraryWave;
+
+use self::cue::AssociatedData;
 
-use std::io::{Seek,SeekFrom,Cursor};
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!("wavefile");
+
+use std::io::{Read,Seek,SeekFrom,Cursor,Write};
+use std::ops::Range;
+use std::sync::OnceLock;
+#[cfg(not(target_arch = "wasm32"))]
 use memmap::{Mmap,Protection};
+#[cfg(target_arch = "wasm32")]
+use heap::{Mmap,Protection};
+
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+/// A four character code identifying a RIFF chunk, such as `*b"fmt "`.
+pub type FourCC = [u8; 4];
 
 const RIFF : u32 = 0x46464952;
 const WAVE : u32 = 0x45564157;
@@ -20,49 +194,152 @@ const FMT_ : u32 = 0x20746d66;
 const DATA : u32 = 0x61746164;
 const LIST : u32 = 0x5453494c;
 const FACT : u32 = 0x74636166;
+const CUE_ : u32 = 0x20657563;
+const PLST : u32 = 0x74736c70;
+const SMPL : u32 = 0x6c706d73;
+const INST : u32 = 0x74736e69;
+const BEXT : u32 = 0x74786562;
+const IXML : u32 = 0x4c4d5869;
+const AXML : u32 = 0x6c6d7861;
+const CHNA : u32 = 0x616e6863;
+const CART : u32 = 0x74726163;
+const ID3_ : u32 = 0x20336469;
+const ID3U : u32 = 0x20334449;
+const LEVL : u32 = 0x6c76656c;
+const DISP : u32 = 0x50534944;
+const JUNK : u32 = 0x4b4e554a;
+const ACID : u32 = 0x64696361;
+const DBMD : u32 = 0x646d6264;
+
+const ADTL : u32 = 0x6c746461;
+const INFO : u32 = 0x4f464e49;
+
+/// Number of samples `read_all_parallel()` hands each task.
+#[cfg(feature = "rayon")]
+const PARALLEL_BLOCK_SAMPLES : usize = 1 << 16;
+
+/// Chunks read by this crate that a file should only hold one of.
+const SINGLE_CHUNKS : [u32; 18] = [
+  FMT_, DATA, FACT, CUE_, PLST, SMPL, INST, BEXT, IXML, AXML, CHNA, CART,
+  ID3_, ID3U, LEVL, DISP, ACID, DBMD
+];
 
 /// Contains information included in the wavefile's header section,
 /// describing the format, sample size, and number of audio channels
 /// present.
-#[derive(Debug,Copy,Clone)]
+///
+/// Being plain `Copy` data, it can be handed to other threads freely, and
+/// compared to check that files share a format.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub struct WaveInfo {
   /// Which encoding format this file uses.
   /// If the format is `Format::Extended`, then the actual audio format is
   /// instead determined by the `subformat` field.
-  pub audio_format:    Format,
+  pub audio_format:        Format,
   /// Number of distinct audio channels.
-  pub channels:        u16,
+  pub channels:            u16,
   /// Number of audio samples per second.
-  pub sample_rate:     u32,
-  pub byte_rate:       u32,
-  pub block_align:     u16,
+  pub sample_rate:         u32,
+  pub byte_rate:           u32,
+  pub block_align:         u16,
   /// Number of bits used to represent each sample.
-  pub bits_per_sample: u16,
+  pub bits_per_sample:     u16,
   /// Number of frames present in the file.  Each frame contains one sample per
   /// channel.
-  pub total_frames:    u32,
-  pub valid_bps:       Option<u16>,
-  pub channel_mask:    Option<u32>,
+  pub total_frames:        u64,
+  pub valid_bps:           Option<u16>,
+  pub channel_mask:        Option<u32>,
   /// For `Format::Extended` files, this field contains the actual audo encoding
   /// of the file, either `Format::PCM` or `Format::IEEEFloat`.
-  pub subformat:       Option<Format>
+  pub subformat:           Option<Format>,
+  /// `true` if the data chunk's size was left unset, as streaming encoders
+  /// do, and the audio was instead assumed to run to the end of the file.
+  pub data_size_untrusted: bool,
+  /// `true` if the file ends before the end of its data chunk, in which case
+  /// `total_frames` only counts the complete frames present.
+  pub truncated:           bool
 }
 
+/// A wavefile opened for reading.
+///
+/// The file is memory mapped rather than read through a buffer: frames are
+/// decoded straight from the mapping, with the operating system paging the
+/// audio in, and its readahead, as it is touched.  Reading a frame never
+/// makes a system call.
+///
+/// On WebAssembly, which can't map files, the file is read onto the heap.
+///
+/// # Threads
+///
+/// A `WaveFile` is `Send` and `Sync`, and is never changed by reading, so
+/// one file can be decoded from many threads at once through a shared
+/// reference or an `Arc`.  Each thread decodes its own range with the
+/// positional `read_frames()`, into a buffer of its own, without locking;
+/// `frame_ranges()` splits the file evenly for this.  Iterators, such as
+/// `iter()`, keep their position to themselves, so every thread should
+/// make its own.
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use wavefile::WaveFile;
+///
+/// let wav     = Arc::new(WaveFile::open("./fixtures/test-s24le.wav").unwrap());
+/// let workers = wav.frame_ranges(4).into_iter().map(|range| {
+///   let wav = wav.clone();
+///
+///   thread::spawn(move || {
+///     let mut buffer = vec![0; range.len() * wav.channels()];
+///
+///     wav.read_frames(range.start, &mut buffer);
+///     buffer.iter().map(|sample| sample.abs()).max().unwrap_or(0)
+///   })
+/// }).collect::<Vec<_>>();
+/// let peak = workers.into_iter().map(|worker| worker.join().unwrap()).max();
+///
+/// assert!(peak.unwrap() > 0);
+/// ```
 pub struct WaveFile {
   mmap:        Mmap,
   data_offset: usize,
   data_size:   usize,
-  info:        WaveInfo
+  /// Distance between the starts of successive frames, in bytes.
+  stride:      usize,
+  decoder:     decode::Decoder<i32>,
+  decoder_f32: decode::Decoder<f32>,
+  info:        WaveInfo,
+  cue_points:  OnceLock<Vec<CuePoint>>,
+  adtl:        OnceLock<AssociatedData>,
+  playlist:    Vec<PlaylistSegment>,
+  sampler:     Option<Sampler>,
+  instrument:  Option<Instrument>,
+  bext:        OnceLock<Option<Bext>>,
+  ixml:        Option<ChunkText>,
+  axml:        Option<ChunkText>,
+  chna:        Option<Chna>,
+  cart:        Option<Cart>,
+  id3:         Option<Range<usize>>,
+  levl:        Option<PeakEnvelope>,
+  tags:        OnceLock<InfoTags>,
+  disp:        Option<Disp>,
+  acid:        Option<Acid>,
+  dbmd:        Option<Dbmd>,
+  fact_frames: Option<u32>,
+  chunks:      Vec<(FourCC, usize, usize)>,
+  options:     WaveFileOptions
 }
 
 /// An iterator which yields successive `Frames` of audio from the associated
 /// wavefile.
 pub struct WaveFileIterator<'a> {
-  file:             &'a WaveFile,
-  pos:              usize,
-  base:             usize,
-  end:              usize,
-  bytes_per_sample: usize,
+  file:   &'a WaveFile,
+  /// The index of the first frame not yet decoded into `block`.
+  next:   usize,
+  /// Samples of frames decoded ahead, reused from block to block.
+  block:  Vec<i32>,
+  /// Position of the next frame to yield within `block`.
+  offset: usize
 }
 
 /// Represents a single frame of audio, containing one sample per audio channel.
@@ -84,23 +361,150 @@ impl WaveFile {
   /// };
   /// ```
   pub fn open<S: Into<String>>(path: S) -> Result<WaveFile, WaveError> {
-    let filename = path.into();
-    let mmap = try!(Mmap::open_path(filename, Protection::Read));
+    WaveFile::open_with(path, WaveFileOptions::default())
+  }
+
+  /// Constructs a new `WaveFile`, failing on any chunk this crate doesn't
+  /// recognize or any inconsistency in its format instead of working
+  /// around it.
+  pub fn open_strict<S: Into<String>>(path: S) -> Result<WaveFile, WaveError> {
+    let options = WaveFileOptions::new()
+      .strict(true)
+      .unknown_chunks(UnknownChunks::Reject)
+      .duplicate_chunks(DuplicateChunks::Reject);
+
+    WaveFile::open_with(path, options)
+  }
+
+  /// Constructs a new `WaveFile`, decoding the text of its `INFO`, `bext`,
+  /// `cart`, `DISP` and associated data chunks with the given encoding
+  /// rather than UTF-8.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::{WaveFile,TextEncoding};
+  ///
+  /// let wav = WaveFile::open_with_encoding("./fixtures/test-latin1.wav", TextEncoding::Latin1).unwrap();
+  ///
+  /// assert_eq!(wav.title(), Some("Café Müller".to_owned()));
+  /// ```
+  pub fn open_with_encoding<S: Into<String>>(path: S, encoding: TextEncoding) -> Result<WaveFile, WaveError> {
+    WaveFile::open_with(path, WaveFileOptions::new().text_encoding(encoding))
+  }
+
+  /// Constructs a new `WaveFile`, parsing it as configured by `options`.
+  ///
+  /// Opening maps the file rather than reading it, so takes the same few
+  /// system calls however many chunks the header holds and wherever they
+  /// lie: the chunks are paged in as they are parsed.
+  pub fn open_with<S: Into<String>>(path: S, options: WaveFileOptions) -> Result<WaveFile, WaveError> {
+    let mmap = Mmap::open_path(path.into(), Protection::Read)?;
+
+    WaveFile::from_mmap(mmap, options)
+  }
+
+  /// Constructs a new `WaveFile` like `open_with`, but reads the whole file
+  /// into memory up front with io_uring, keeping many block reads in flight
+  /// rather than faulting the mapping in page by page.
+  ///
+  /// This suits servers working through many files at once on fast storage.
+  /// Where io_uring is unavailable the file is read the ordinary way.
+  #[cfg(all(feature = "io-uring", target_os = "linux"))]
+  pub fn open_io_uring<S: Into<String>>(path: S, options: WaveFileOptions) -> Result<WaveFile, WaveError> {
+    let mmap = uring::read(&path.into(), options.read_block_size)?;
+
+    WaveFile::from_mmap(mmap, options)
+  }
+
+  /// Scans an arbitrary byte stream, such as a disk image or a corrupted
+  /// download, for wavefiles and recovers as many complete frames of each
+  /// as it can.  The stream is scanned a block at a time, and then only the
+  /// part of it holding each file is read back into memory.
+  ///
+  /// # Example
+  ///
+  /// ```no_run
+  /// use std::fs::File;
+  /// use wavefile::WaveFile;
+  ///
+  /// let image = File::open("./sdcard.img").unwrap();
+  ///
+  /// for carved in WaveFile::carve(image).unwrap() {
+  ///   println!("{} frames at {:?}", carved.file.len(), carved.audio);
+  /// }
+  /// ```
+  pub fn carve<R: Read + Seek>(reader: R) -> Result<Vec<CarvedWave>, WaveError> {
+    carve::carve(reader, WaveFileOptions::new())
+  }
+
+  /// Recovers wavefiles from a byte stream like `carve`, parsing them with
+  /// `options`.  No more than `max_allocation` bytes are read for any one
+  /// file, so a file longer than that is recovered as if its end was lost.
+  pub fn carve_with<R: Read + Seek>(reader: R, options: WaveFileOptions) -> Result<Vec<CarvedWave>, WaveError> {
+    carve::carve(reader, options)
+  }
+
+  /// Parses a copy of `bytes` held in anonymous memory.
+  #[cfg(any(test, feature = "symphonia", feature = "uniffi"))]
+  fn from_bytes(bytes: &[u8], options: WaveFileOptions) -> Result<WaveFile, WaveError> {
+    let mut mmap = Mmap::anonymous(bytes.len(), Protection::ReadWrite)?;
+
+    unsafe { mmap.as_mut_slice() }.copy_from_slice(bytes);
+    WaveFile::from_mmap(mmap, options)
+  }
+
+  fn from_mmap(mmap: Mmap, options: WaveFileOptions) -> Result<WaveFile, WaveError> {
     let info = WaveInfo {
-      audio_format:    Format::PCM,
-      channels:        0,
-      sample_rate:     0,
-      byte_rate:       0,
-      block_align:     0,
-      bits_per_sample: 0,
-      total_frames:    0,
-      valid_bps:       None,
-      channel_mask:    None,
-      subformat:       None
+      audio_format:        Format::PCM,
+      channels:            0,
+      sample_rate:         0,
+      byte_rate:           0,
+      block_align:         0,
+      bits_per_sample:     0,
+      total_frames:        0,
+      valid_bps:           None,
+      channel_mask:        None,
+      subformat:           None,
+      data_size_untrusted: false,
+      truncated:           false
+    };
+    let mut file = WaveFile {
+      mmap,
+      data_offset: 0,
+      data_size:   0,
+      stride:      0,
+      decoder:     decode::decoder(Format::Extended, 0),
+      decoder_f32: decode::float_decoder(Format::Extended, 0),
+      info,
+      cue_points:  OnceLock::new(),
+      adtl:        OnceLock::new(),
+      playlist:    Vec::new(),
+      sampler:     None,
+      instrument:  None,
+      bext:        OnceLock::new(),
+      ixml:        None,
+      axml:        None,
+      chna:        None,
+      cart:        None,
+      id3:         None,
+      levl:        None,
+      tags:        OnceLock::new(),
+      disp:        None,
+      acid:        None,
+      dbmd:        None,
+      fact_frames: None,
+      chunks:      Vec::new(),
+      options
     };
-    let mut file = WaveFile { mmap: mmap, data_offset: 0, data_size: 0, info: info };
 
-    try!(file.read_header_chunks());
+    let _span = trace_span!(DEBUG, "open", bytes = file.bytes().len());
+
+    if let Err(e) = file.read_header_chunks() {
+      trace_event!(WARN, error = %e, "rejected file");
+      return Err(e);
+    }
+    trace_event!(DEBUG, channels = file.channels(), sample_rate = file.sample_rate(), frames = file.len(), "opened file");
 
     Ok(file)
   }
@@ -121,6 +525,11 @@ impl WaveFile {
     self.info.total_frames as usize
   }
 
+  /// Returns `true` if the file contains no audio frames.
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
   /// The duration in milliseconds of the file.
   pub fn duration(&self) -> usize {
     self.len() * 1000 / self.sample_rate()
@@ -132,25 +541,291 @@ impl WaveFile {
 
   pub fn data_format(&self) -> Format {
     if self.info.audio_format == Format::Extended {
-      self.info.subformat.unwrap()
+      self.info.subformat.unwrap_or(Format::Extended)
     } else {
       self.info.audio_format
     }
   }
 
   pub fn speakers(&self) -> Option<Vec<SpeakerPosition>> {
-    match self.info.channel_mask {
-      None       => None,
-      Some(mask) => Some(SpeakerPosition::decode(mask as isize))
+    self.info.channel_mask.map(|mask| SpeakerPosition::decode(mask as isize))
+  }
+
+  /// The cue points stored in the file's `cue ` chunk, if any.
+  pub fn cue_points(&self) -> &[CuePoint] {
+    self.cue_points.get_or_init(|| {
+      self.parse_deferred((CUE_, 0), CuePoint::parse_chunk).and_then(Result::ok).unwrap_or_default()
+    })
+  }
+
+  /// Returns one `Marker` per cue point, named by the matching `labl` and
+  /// `note` entries of the file's associated data list.
+  pub fn markers(&self) -> Vec<Marker> {
+    cue::markers(self.cue_points(), self.adtl())
+  }
+
+  /// Returns the labeled regions described by the `ltxt` entries of the
+  /// file's associated data list.
+  pub fn regions(&self) -> Vec<Region> {
+    cue::regions(self.cue_points(), self.adtl())
+  }
+
+  /// Returns the file's markers and regions as Audacity labels, in cue point
+  /// order.  See `write_label_track` for saving them as a label track.
+  pub fn labels(&self) -> Vec<Label> {
+    labels::from_cues(&self.markers(), &self.regions(), self.info.sample_rate)
+  }
+
+  /// The playback segments stored in the file's `plst` chunk, in play order.
+  pub fn playlist(&self) -> &[PlaylistSegment] {
+    &self.playlist
+  }
+
+  /// The sampler information stored in the file's `smpl` chunk, including
+  /// its loops and MIDI unity note.
+  pub fn sampler(&self) -> Option<&Sampler> {
+    self.sampler.as_ref()
+  }
+
+  /// The instrument zone stored in the file's `inst` chunk, if any.
+  pub fn instrument(&self) -> Option<Instrument> {
+    self.instrument
+  }
+
+  /// An SFZ `<region>` definition for the file, stored at `sample`
+  /// relative to the SFZ file, so sample packs can be mapped into software
+  /// samplers.
+  ///
+  /// The root key and tuning come from the `smpl` chunk, or else the
+  /// `inst` chunk, which also gives the key and velocity range and the
+  /// volume.  The first loop of the `smpl` chunk becomes the region's loop.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::WaveFile;
+  ///
+  /// let wav = WaveFile::open("./fixtures/test-sampler.wav").unwrap();
+  ///
+  /// assert!(wav.sfz_region("test-sampler.wav").contains("loop_start=8\n"));
+  /// ```
+  pub fn sfz_region(&self, sample: &str) -> String {
+    sampler::sfz_region(sample, self.sampler.as_ref(), self.instrument)
+  }
+
+  /// The loop tempo, key and length stored in the file's `acid` chunk, if
+  /// any.
+  pub fn acid(&self) -> Option<Acid> {
+    self.acid
+  }
+
+  /// The Dolby metadata stored in the file's `dbmd` chunk, if any.
+  pub fn dbmd(&self) -> Option<&Dbmd> {
+    self.dbmd.as_ref()
+  }
+
+  /// The number of frames stated by the file's `fact` chunk, if any.
+  pub fn fact_frames(&self) -> Option<u32> {
+    self.fact_frames
+  }
+
+  /// The Broadcast Wave metadata stored in the file's `bext` chunk, if any.
+  pub fn bext(&self) -> Option<&Bext> {
+    self.bext.get_or_init(|| {
+      self.parse_deferred((BEXT, 0), |body| Bext::parse_chunk(body, self.options.encoding)).and_then(Result::ok)
+    }).as_ref()
+  }
+
+  /// The timecode at which the file starts, computed from the time reference
+  /// of its `bext` chunk.  Returns `None` if the file has no `bext` chunk.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::{WaveFile,FrameRate};
+  ///
+  /// let wav = WaveFile::open("./fixtures/test-bext.wav").unwrap();
+  /// let tc  = wav.start_timecode(FrameRate::Fps25).unwrap();
+  ///
+  /// assert_eq!(tc.to_string(), "10:00:00:00");
+  /// ```
+  pub fn start_timecode(&self, rate: FrameRate) -> Option<Timecode> {
+    self.bext().map(|bext| {
+      Timecode::from_samples(bext.time_reference, self.info.sample_rate, rate)
+    })
+  }
+
+  /// The raw XML document stored in the file's `iXML` chunk, if any.
+  pub fn ixml(&self) -> Option<&str> {
+    self.ixml.as_ref().map(|text| self.text(text))
+  }
+
+  /// Parses the file's `iXML` chunk into its production fields.
+  /// Returns `Ok(None)` if the file has no `iXML` chunk.
+  #[cfg(feature = "xml")]
+  pub fn ixml_metadata(&self) -> Result<Option<IXml>, WaveError> {
+    match self.ixml() {
+      Some(xml) => IXml::parse(xml).map(Some),
+      None      => Ok(None)
     }
   }
 
+  /// The Audio Definition Model XML document stored in the file's `axml`
+  /// chunk, if any.
+  pub fn axml(&self) -> Option<&str> {
+    self.axml.as_ref().map(|text| self.text(text))
+  }
+
+  /// The track to ADM element assignments stored in the file's `chna`
+  /// chunk, if any.
+  pub fn chna(&self) -> Option<&Chna> {
+    self.chna.as_ref()
+  }
+
+  /// The radio automation metadata stored in the file's AES46 `cart` chunk,
+  /// if any.
+  pub fn cart(&self) -> Option<&Cart> {
+    self.cart.as_ref()
+  }
+
+  /// The raw ID3v2 tag stored in the file's `id3 ` chunk, if any.
+  pub fn id3(&self) -> Option<&[u8]> {
+    self.id3.clone().map(|range| &self.bytes()[range])
+  }
+
+  /// Parses the ID3v2 tag stored in the file's `id3 ` chunk.
+  /// Returns `Ok(None)` if the file has no `id3 ` chunk.
+  #[cfg(feature = "id3")]
+  pub fn id3_tag(&self) -> Result<Option<id3::Tag>, WaveError> {
+    match self.id3() {
+      Some(bytes) => match id3::Tag::read_from2(Cursor::new(bytes)) {
+        Ok(tag) => Ok(Some(tag)),
+        Err(e)  => Err(WaveError::ParseError(format!("Invalid ID3 tag: {}", e)))
+      },
+      None => Ok(None)
+    }
+  }
+
+  /// The precomputed peak envelope stored in the file's `levl` chunk, if any.
+  pub fn peak_envelope(&self) -> Option<&PeakEnvelope> {
+    self.levl.as_ref()
+  }
+
+  /// The text tags stored in the file's `INFO` list.
+  pub fn tags(&self) -> &InfoTags {
+    self.tags.get_or_init(|| {
+      self.parse_deferred((LIST, INFO), |body| InfoTags::parse_list(&body[4..], self.options.encoding))
+        .and_then(Result::ok)
+        .unwrap_or_default()
+    })
+  }
+
+  /// The contents of the file's `DISP` chunk, if any.
+  pub fn disp(&self) -> Option<&Disp> {
+    self.disp.as_ref()
+  }
+
+  /// The title of the file, taken from the `INAM` tag or, failing that, the
+  /// text of the `DISP` chunk written by older Windows tools.
+  pub fn title(&self) -> Option<String> {
+    match self.tags().title() {
+      Some(title) => Some(title.to_owned()),
+      None        => self.disp.as_ref().and_then(|disp| disp.decode_text(self.options.encoding))
+    }
+  }
+
+  /// The encoding used to decode the file's metadata text.
+  pub fn text_encoding(&self) -> TextEncoding {
+    self.options.encoding
+  }
+
+  /// Returns the ID, offset and size of every top-level chunk found in the
+  /// file, in file order.  The offset is that of the chunk's body, following
+  /// its 8 byte header.
+  pub fn chunks(&self) -> impl Iterator<Item = (FourCC, usize, usize)> + '_ {
+    self.chunks.iter().cloned()
+  }
+
+  /// Returns the body of the first chunk with the given ID, including
+  /// chunks this crate doesn't otherwise interpret.  The body is borrowed
+  /// from the file rather than copied, as are the documents returned by
+  /// `ixml()`, `axml()` and `id3()`.
+  pub fn chunk_data(&self, id: FourCC) -> Option<&[u8]> {
+    let bytes = self.bytes();
+
+    self.chunks.iter()
+      .find(|chunk| chunk.0 == id)
+      .map(|&(_, offset, size)| {
+        let end = (offset + size).min(bytes.len());
+        &bytes[offset.min(end)..end]
+      })
+  }
+
+  /// Returns a copy of the body of the first chunk with the given ID,
+  /// including chunks this crate doesn't otherwise interpret.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::WaveFile;
+  ///
+  /// let wav = WaveFile::open("./fixtures/test-markers.wav").unwrap();
+  /// let cue = wav.read_chunk(*b"cue ").unwrap();
+  ///
+  /// assert_eq!(cue.len(), 52);
+  /// ```
+  pub fn read_chunk(&self, id: FourCC) -> Option<Vec<u8>> {
+    self.chunk_data(id).map(|body| body.to_vec())
+  }
+
   /// Returns a copy of the `WaveInfo` for this file,
   /// parsed from the file header.
   pub fn info(&self) -> WaveInfo {
     self.info
   }
 
+  /// Checks the file for departures from the specification that were
+  /// worked around while opening it, such as wrong sizes or missing pad
+  /// bytes, as well as for warning signs like an implausible duration.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::{WaveFile,Severity};
+  ///
+  /// let wav = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  ///
+  /// assert!(wav.validate().iter().all(|issue| issue.severity() < Severity::Violation));
+  /// ```
+  pub fn validate(&self) -> Vec<Issue> {
+    validate::check(self)
+  }
+
+  /// Finds how many bits of each sample the audio actually uses, to check
+  /// claims such as 24 bit delivery of what was recorded in 16 bits.  Unlike
+  /// `validate` this reads all of the audio.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::WaveFile;
+  ///
+  /// let wav   = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  /// let usage = wav.bit_usage();
+  ///
+  /// assert_eq!(usage.declared, 24);
+  /// assert!(!usage.is_padded());
+  /// ```
+  pub fn bit_usage(&self) -> BitUsage {
+    validate::bit_usage(self, None).expect("only cancellation fails")
+  }
+
+  /// Finds how many bits of each sample the audio uses like `bit_usage`,
+  /// reporting the frames checked to `progress`.
+  pub fn bit_usage_with_progress(&self, progress: &mut Progress) -> Result<BitUsage, WaveError> {
+    validate::bit_usage(self, Some(progress))
+  }
+
   /// Returns an iterator which yields each individual `Frame` successively
   /// until it reaches the end of the file.
   ///
@@ -165,198 +840,831 @@ impl WaveFile {
   ///   println!("{:?}", frame);
   /// }
   /// ```
-  pub fn iter(&self) -> WaveFileIterator {
-    let bytes_per_sample = self.info.bits_per_sample as usize / 8;
+  pub fn iter(&self) -> WaveFileIterator<'_> {
     WaveFileIterator {
-      file:             &self,
-      pos:              0,
-      base:             self.data_offset,
-      end:              self.data_offset + self.data_size,
-      bytes_per_sample: bytes_per_sample
+      file:   self,
+      next:   0,
+      block:  Vec::new(),
+      offset: 0
     }
   }
 
-  fn read_header_chunks(&mut self) -> Result<(), WaveError> {
-    let mut cursor   = Cursor::new(unsafe { self.mmap.as_slice() } );
-    let mut have_fmt = false;
-    let mut chunk_id = try!(cursor.read_u32::<LittleEndian>());
+  /// Consumes the file, returning an iterator over its frames that are
+  /// decoded ahead on a worker thread, up to `depth` blocks at a time.
+  ///
+  /// This hides the latency of slow storage, such as spinning disks or
+  /// network filesystems, from consumers that must not stall, like
+  /// playback.
+  pub fn prefetching(self, depth: usize) -> Prefetching {
+    Prefetching::new(self, depth)
+  }
 
-    let mut chunk_size : u32;
+  /// Decodes frames starting at frame `first` into `buffer`, interleaved,
+  /// on the same scale as `iter()`.  Decodes as many whole frames as fit,
+  /// and returns their number, which is zero once `first` is past the end.
+  ///
+  /// Decoding into a buffer that is reused from call to call avoids the
+  /// allocation `iter()` makes for every frame.  Every supported format
+  /// stores frames a fixed number of bytes apart, so `first` is found
+  /// directly however far into the file it lies.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::WaveFile;
+  ///
+  /// let wav        = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  /// let mut buffer = vec![0; 4096 * wav.channels()];
+  /// let mut first  = 0;
+  /// let mut peak   = 0;
+  ///
+  /// loop {
+  ///   let frames = wav.read_frames(first, &mut buffer);
+  ///
+  ///   if frames == 0 {
+  ///     break;
+  ///   }
+  ///
+  ///   peak   = buffer[..frames * wav.channels()].iter().fold(peak, |peak, s| peak.max(s.abs()));
+  ///   first += frames;
+  /// }
+  ///
+  /// assert_eq!(first, wav.len());
+  /// ```
+  pub fn read_frames(&self, first: usize, buffer: &mut [i32]) -> usize {
+    self.decode_frames(self.decoder, first, buffer)
+  }
 
-    try!(cursor.read_u32::<LittleEndian>());
+  /// Splits the frames of the file into at most `parts` contiguous ranges
+  /// of nearly equal length, in order, for decoding on separate threads.
+  /// An empty file has no ranges.
+  pub fn frame_ranges(&self, parts: usize) -> Vec<Range<usize>> {
+    let parts = parts.max(1).min(self.len());
 
-    let riff_type = try!(cursor.read_u32::<LittleEndian>());
+    (0..parts).map(|part| part * self.len() / parts..(part + 1) * self.len() / parts).collect()
+  }
 
-    if chunk_id != RIFF || riff_type != WAVE {
-      return Err(WaveError::ParseError("Not a Wavefile".into()));
-    }
+  /// Decodes frames starting at frame `first` into `buffer` like
+  /// `read_frames()`, as floats between -1.0 and 1.0.  Float audio is
+  /// returned as stored, so may exceed that range.
+  pub fn read_frames_f32(&self, first: usize, buffer: &mut [f32]) -> usize {
+    self.read_frames_f32_into(first, buffer)
+  }
 
+  /// Decodes frames starting at frame `first` into `buffer` like
+  /// `read_frames_f32()`.
+  ///
+  /// Samples are converted as they are read, without decoding them to
+  /// integers first.
+  pub fn read_frames_f32_into(&self, first: usize, buffer: &mut [f32]) -> usize {
+    self.decode_frames(self.decoder_f32, first, buffer)
+  }
 
-    loop {
-      chunk_id   = try!(cursor.read_u32::<LittleEndian>());
-      chunk_size = try!(cursor.read_u32::<LittleEndian>());
+  fn decode_frames<T>(&self, decoder: decode::Decoder<T>, first: usize, buffer: &mut [T]) -> usize {
+    let channels   = self.channels();
+    let frames     = (buffer.len() / channels).min(self.len().saturating_sub(first));
+    let start      = self.data_offset + first.min(self.len()) * self.stride;
+    let data       = &self.bytes()[start..start + frames * self.stride];
+    let frame_size = channels * (self.info.bits_per_sample as usize / 8);
 
-      match chunk_id {
-        FMT_ => {
-          have_fmt = true;
-          let fmt = try!(cursor.read_u16::<LittleEndian>());
-          self.info.audio_format = match Format::decode(fmt) {
-            Some(f) => f,
-            None    => {
-              let msg = format!("Unexpected format {0:x}", fmt);
-              return Err(WaveError::ParseError(msg));
-            }
-          };
-          self.info.channels        = try!(cursor.read_u16::<LittleEndian>());
-          self.info.sample_rate     = try!(cursor.read_u32::<LittleEndian>());
-          self.info.byte_rate       = try!(cursor.read_u32::<LittleEndian>());
-          self.info.block_align     = try!(cursor.read_u16::<LittleEndian>());
-          self.info.bits_per_sample = try!(cursor.read_u16::<LittleEndian>());
-
-          if self.info.audio_format == Format::Extended {
-            match try!(cursor.read_u16::<LittleEndian>()) {
-              0 => { },
-              22 => {
-                self.info.valid_bps    = Some(try!(cursor.read_u16::<LittleEndian>()));
-                self.info.channel_mask = Some(try!(cursor.read_u32::<LittleEndian>()));
-                let subformat          = try!(cursor.read_u16::<LittleEndian>());
-                self.info.subformat    = match Format::decode(subformat) {
-                  Some(f) => Some(f),
-                  None    => {
-                    let msg = format!("Unexpected subformat {0:x}", subformat);
-                    return Err(WaveError::ParseError(msg));
-                  }
-                };
-                try!(cursor.seek(SeekFrom::Current(14)));
-              },
-              x => {
-                let msg = format!("Unexpected extension size: {}", x);
-                return Err(WaveError::ParseError(msg));
-              }
-            }
+    trace_event!(TRACE, first, frames, "read block");
+    decode::decode_frames(decoder, channels, frame_size, self.stride, data,
+                          &mut buffer[..frames * channels]);
+    frames
+  }
 
-          }
-        },
-        DATA  => {
-          self.data_size = chunk_size as usize;
-          break;
-        },
-        LIST  => { try!(cursor.seek(SeekFrom::Current(chunk_size as i64))); },
-        FACT  => { try!(cursor.seek(SeekFrom::Current(chunk_size as i64))); },
-        other => {
-          let msg = format!("Unexpected Chunk ID {0:x}", other);
-          return Err(WaveError::ParseError(msg));
-        }
-      }
-    }
+  /// Writes the frames in `frames` to `out` as CSV: a header row, then one
+  /// row per frame holding its start in seconds and one sample per channel,
+  /// as floats like `read_frames_f32_into()`.  The range is clamped to the
+  /// frames present.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::WaveFile;
+  ///
+  /// let wav     = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  /// let mut csv = Vec::new();
+  ///
+  /// wav.export_csv(&mut csv, 0..10).unwrap();
+  ///
+  /// let csv = String::from_utf8(csv).unwrap();
+  ///
+  /// assert!(csv.starts_with("time,channel_0,channel_1\n0.000000,"));
+  /// assert_eq!(csv.lines().count(), 11);
+  /// ```
+  pub fn export_csv<W: Write>(&self, out: &mut W, frames: Range<usize>) -> Result<(), WaveError> {
+    let channels   = self.channels();
+    let rate       = self.sample_rate().max(1) as f64;
+    let end        = frames.end.min(self.len());
+    let mut buffer = vec![0.0; (self.options.block_samples / channels).max(1) * channels];
+    let mut first  = frames.start.min(end);
 
-    if !have_fmt {
-      return Err(WaveError::ParseError("Format Chunk not found".into()));
+    write!(out, "time")?;
+    for channel in 0..channels {
+      write!(out, ",channel_{}", channel)?;
     }
+    writeln!(out)?;
 
-    if self.info.channels == 0 || self.info.bits_per_sample < 8 {
-      let msg = format!("Invalid channel count {} or bits per sample {} value",
-                        self.info.channels, self.info.bits_per_sample);
+    while first < end {
+      let wanted = (end - first).min(buffer.len() / channels);
+      let read   = self.read_frames_f32_into(first, &mut buffer[..wanted * channels]);
 
-      return Err(WaveError::ParseError(msg));
+      for (i, frame) in buffer[..read * channels].chunks(channels).enumerate() {
+        write!(out, "{:.6}", (first + i) as f64 / rate)?;
+        for sample in frame {
+          write!(out, ",{}", sample)?;
+        }
+        writeln!(out)?;
+      }
+      first += read;
     }
 
-    self.info.total_frames = self.data_size as u32 / (self.info.channels as u32 * self.info.bits_per_sample as u32 / 8 );
-
-    self.data_offset = cursor.position() as usize;
     Ok(())
   }
-}
-
-impl<'a> Iterator for WaveFileIterator<'a> {
-  type Item = Frame;
 
-  fn next(&mut self) -> Option<Self::Item> {
-    let mut cursor = Cursor::new(unsafe { self.file.mmap.as_slice() });
+  /// Decodes every frame of the file, interleaved, a block at a time,
+  /// reporting the frames decoded to `progress`.  See `Progress`.
+  ///
+  /// Fails with `WaveError::LimitExceeded` if the samples would take more
+  /// than `max_allocation` bytes.
+  pub fn read_all_with_progress(&self, progress: &mut Progress) -> Result<Vec<i32>, WaveError> {
+    let channels    = self.channels();
+    let block       = (self.options.block_samples / channels).max(1) * channels;
+    let mut samples = vec![0; self.decoded_samples::<i32>()?];
 
-    if let Err(_) = cursor.seek(SeekFrom::Start((self.base + self.pos) as u64)) {
-      return None;
-    };
+    for (i, chunk) in samples.chunks_mut(block).enumerate() {
+      let first = i * block / channels;
 
-    if cursor.position() as usize == self.end {
-      return None;
+      self.read_frames(first, chunk);
+      progress.update(first + chunk.len() / channels, self.len())?;
     }
 
-    let (frame, new_pos) = match self.file.data_format() {
-      Format::PCM => WaveFileIterator::next_pcm(&mut cursor,
-                                                self.file.channels(),
-                                                self.bytes_per_sample),
-      Format::IEEEFloat => WaveFileIterator::next_float(&mut cursor,
-                                                        self.file.channels(),
-                                                        self.bytes_per_sample),
-      _ => unreachable!()
-    };
+    Ok(samples)
+  }
 
-    self.pos = new_pos - self.base;
+  /// Decodes every frame of the file, interleaved, splitting the work
+  /// across the rayon thread pool.
+  ///
+  /// The whole file is decoded into one buffer, so this suits analysing
+  /// files that fit in memory as quickly as possible.  Fails with
+  /// `WaveError::LimitExceeded` if that buffer would take more than
+  /// `max_allocation` bytes.
+  #[cfg(feature = "rayon")]
+  pub fn read_all_parallel(&self) -> Result<Vec<i32>, WaveError> {
+    use rayon::prelude::*;
 
+    let channels    = self.channels();
+    let block       = (PARALLEL_BLOCK_SAMPLES / channels).max(1) * channels;
+    let mut samples = vec![0; self.decoded_samples::<i32>()?];
 
-    Some(frame)
+    samples.par_chunks_mut(block).enumerate().for_each(|(i, chunk)| {
+      self.read_frames(i * block / channels, chunk);
+    });
+
+    Ok(samples)
   }
-}
 
-impl<'a> WaveFileIterator<'a> {
-  fn next_pcm(cursor: &mut Cursor<&[u8]>, channels: usize, bps: usize) -> (Frame, usize) {
-    let mut samples : Vec<i32> = Vec::with_capacity(channels);
+  /// Decodes every frame of the file into a matrix with one row per frame
+  /// and one column per channel, as floats like `read_frames_f32_into()`.
+  /// Fails with `WaveError::LimitExceeded` if the matrix would take more
+  /// than `max_allocation` bytes.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::WaveFile;
+  ///
+  /// let wav    = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  /// let matrix = wav.to_ndarray().unwrap();
+  ///
+  /// assert_eq!(matrix.dim(), (wav.len(), wav.channels()));
+  /// ```
+  #[cfg(feature = "ndarray")]
+  pub fn to_ndarray(&self) -> Result<ndarray::Array2<f32>, WaveError> {
+    let mut samples = vec![0.0; self.decoded_samples::<f32>()?];
 
-    for _ in 0..channels {
-      match cursor.read_int::<LittleEndian>(bps) {
-        Ok(sample) => samples.push(sample as i32),
-        Err(e)     => { panic!("{:?}", e); }
+    self.read_frames_f32_into(0, &mut samples);
+    Ok(ndarray::Array2::from_shape_vec((self.len(), self.channels()), samples)
+      .expect("one sample was read per channel of every frame"))
+  }
+
+  /// The number of samples in the file, checking that decoding them all
+  /// as `T` stays within `max_allocation`.
+  fn decoded_samples<T>(&self) -> Result<usize, WaveError> {
+    let samples = self.len() * self.channels();
+    let bytes   = samples.saturating_mul(std::mem::size_of::<T>());
+
+    if let Some(max) = self.options.max_allocation {
+      if bytes > max {
+        return Err(WaveError::LimitExceeded { limit: "max_allocation", max, found: bytes });
       }
     }
 
-    (samples, cursor.position() as usize)
+    Ok(samples)
   }
 
-  fn next_float(cursor: &mut Cursor<&[u8]>, channels: usize, bps: usize) -> (Frame, usize) {
-    match bps {
-      4 => WaveFileIterator::next_float32(cursor, channels),
-      8 => WaveFileIterator::next_float64(cursor, channels),
-      _ => panic!("Can't handle the specified float bytes per sample")
-    }
+  /// The samples of a 16 bit PCM file, interleaved, borrowed straight from
+  /// the mapping without decoding.
+  ///
+  /// Returns `None` unless the frames are packed without padding and the
+  /// audio is aligned for `i16` within the file, or on big endian targets.
+  #[cfg(feature = "bytemuck")]
+  pub fn samples_i16(&self) -> Option<&[i16]> {
+    self.samples(16)
   }
 
-  fn next_float32(cursor: &mut Cursor<&[u8]>, channels: usize) -> (Frame, usize) {
-    let mut samples : Vec<i32> = Vec::with_capacity(channels);
+  /// The samples of a 32 bit PCM file, interleaved, borrowed straight from
+  /// the mapping without decoding.  Samples are not rescaled, so match
+  /// `iter()`.
+  ///
+  /// Returns `None` under the same conditions as `samples_i16()`.
+  #[cfg(feature = "bytemuck")]
+  pub fn samples_i32(&self) -> Option<&[i32]> {
+    self.samples(32)
+  }
 
-    for _ in 0..channels {
-      match cursor.read_f32::<LittleEndian>() {
-        Ok(sample) => {
-          let scaled = (sample * 2147483647.0) as i32;
-          samples.push(scaled);
-        },
-        Err(e)     => { panic!("{:?}", e); }
-      }
+  #[cfg(feature = "bytemuck")]
+  fn samples<T: bytemuck::Pod>(&self, bits: u16) -> Option<&[T]> {
+    let frame_size = self.channels() * bits as usize / 8;
+
+    if cfg!(target_endian = "big") || self.data_format() != Format::PCM
+       || self.info.bits_per_sample != bits || self.stride != frame_size {
+      return None;
     }
 
-    (samples, cursor.position() as usize)
+    let data = &self.bytes()[self.data_offset..self.data_offset + self.data_size];
+
+    bytemuck::try_cast_slice(data).ok()
   }
 
-  fn next_float64(cursor: &mut Cursor<&[u8]>, channels: usize) -> (Frame, usize) {
-    let mut samples : Vec<i32> = Vec::with_capacity(channels);
+  /// The associated data list, which names cue points and regions.
+  fn adtl(&self) -> &AssociatedData {
+    self.adtl.get_or_init(|| {
+      self.parse_deferred((LIST, ADTL), |body| AssociatedData::parse_list(&body[4..], self.options.encoding))
+        .and_then(Result::ok)
+        .unwrap_or_default()
+    })
+  }
 
-    for _ in 0..channels {
-      match cursor.read_f64::<LittleEndian>() {
-        Ok(sample) => {
-          let scaled = (sample * 2147483647.0) as i32;
-          samples.push(scaled);
-        },
-        Err(e)     => { panic!("{:?}", e); }
+  /// Parses the metadata chunk identified by `key` with `parse` when its
+  /// parsing was put off at open, picked according to the duplicate chunk
+  /// policy.  Returns `None` if there is no such chunk.
+  fn parse_deferred<T, F>(&self, key: (u32, u32), parse: F) -> Option<Result<T, WaveError>>
+    where F: FnOnce(&[u8]) -> Result<T, WaveError> {
+    if !self.options.lazy_metadata || !self.options.load_metadata {
+      return None;
+    }
+
+    let bytes     = self.bytes();
+    let mut found = self.chunks.iter().filter(|&&(id, offset, size)| {
+      single_chunk_key(bytes, LittleEndian::read_u32(&id), offset, size) == Some(key)
+    });
+    let chunk     = match self.options.duplicates {
+      DuplicateChunks::Last => found.next_back(),
+      _                     => found.next()
+    };
+    let (id, offset, size) = *chunk?;
+
+    if let Some(max) = self.options.max_allocation {
+      if size > max {
+        return Some(Err(WaveError::LimitExceeded { limit: "max_allocation", max, found: size }));
       }
     }
 
-    (samples, cursor.position() as usize)
+    let body = chunk_body(bytes, LittleEndian::read_u32(&id), offset, size as u32);
+
+    Some(body.and_then(parse).map_err(|e| e.in_chunk(id, offset)))
   }
-}
 
-#[test]
-fn test_info() {
-  let file = match WaveFile::open("./fixtures/test-s24le.wav") {
+  /// The errors met parsing the metadata chunks put off at open, which
+  /// their accessors treat as missing.
+  pub(crate) fn deferred_errors(&self) -> Vec<WaveError> {
+    let encoding = self.options.encoding;
+    let results  = vec![
+      self.parse_deferred((CUE_, 0), |body| CuePoint::parse_chunk(body).map(drop)),
+      self.parse_deferred((BEXT, 0), |body| Bext::parse_chunk(body, encoding).map(drop)),
+      self.parse_deferred((LIST, INFO), |body| InfoTags::parse_list(&body[4..], encoding).map(drop)),
+      self.parse_deferred((LIST, ADTL), |body| AssociatedData::parse_list(&body[4..], encoding).map(drop))
+    ];
+
+    results.into_iter().flatten().filter_map(Result::err).collect()
+  }
+
+  fn text<'a>(&'a self, text: &'a ChunkText) -> &'a str {
+    match *text {
+      // checked to be UTF-8 by `ChunkText::read`.
+      ChunkText::Mapped(ref range) => std::str::from_utf8(&self.bytes()[range.clone()]).unwrap_or(""),
+      ChunkText::Decoded(ref text) => text
+    }
+  }
+
+  /// The raw contents of the file.
+  fn bytes(&self) -> &[u8] {
+    unsafe { self.mmap.as_slice() }
+  }
+
+  fn read_header_chunks(&mut self) -> Result<(), WaveError> {
+    let bytes        = unsafe { self.mmap.as_slice() };
+    let mut cursor   = Cursor::new(bytes);
+    let mut have_fmt = false;
+
+    let mut chunk_id   : u32;
+    let mut chunk_size : u32;
+
+    // the RIFF header's ID, size and form type.
+    for _ in 0..3 {
+      cursor.read_u32::<LittleEndian>()?;
+    }
+    check_riff(bytes)?;
+
+    let mut have_data  = false;
+    let mut fmt_offset = 0;
+    let max_allocation = self.options.max_allocation;
+    let mut seen       = Vec::new();
+    let scan_end       = bytes.len().min(self.options.max_scan_bytes.unwrap_or(usize::MAX));
+    let _span          = trace_span!(DEBUG, "scan_chunks", scan_end);
+
+    // metadata chunks frequently follow the audio data, so scan to the end.
+    while (cursor.position() as usize) + 8 <= scan_end {
+      chunk_id   = cursor.read_u32::<LittleEndian>()?;
+      chunk_size = cursor.read_u32::<LittleEndian>()?;
+
+      let body_start = cursor.position();
+      let next_chunk = body_start + chunk_size as u64 + (chunk_size & 1) as u64;
+
+      if let Some(max) = self.options.max_chunks {
+        if self.chunks.len() >= max {
+          return Err(WaveError::LimitExceeded { limit: "max_chunks", max, found: max + 1 });
+        }
+      }
+
+      self.chunks.push((fourcc(chunk_id), body_start as usize, chunk_size as usize));
+      trace_event!(TRACE, id = %String::from_utf8_lossy(&fourcc(chunk_id)), offset = body_start, size = chunk_size, "chunk");
+
+      let context   = |e: WaveError| e.in_chunk(fourcc(chunk_id), body_start as usize);
+      let read_body = || {
+        // parsers allocate in proportion to the size of the chunks they read.
+        if let Some(max) = max_allocation {
+          if chunk_size as usize > max {
+            return Err(WaveError::LimitExceeded { limit: "max_allocation", max, found: chunk_size as usize });
+          }
+        }
+        chunk_body(bytes, chunk_id, body_start as usize, chunk_size)
+      };
+
+      if let Some(key) = single_chunk_key(bytes, chunk_id, body_start as usize, chunk_size as usize) {
+        if !seen.contains(&key) {
+          seen.push(key);
+        } else {
+          match self.options.duplicates {
+            DuplicateChunks::Reject => {
+              let msg = format!("Duplicate {:?} chunk", String::from_utf8_lossy(&fourcc(chunk_id)));
+              return Err(context(WaveError::ParseError(msg)));
+            },
+            DuplicateChunks::Last if chunk_id != DATA => { },
+            _ => {
+              trace_event!(DEBUG, id = %String::from_utf8_lossy(&fourcc(chunk_id)), offset = body_start,
+                           "skipped duplicate chunk");
+              cursor.set_position(next_chunk);
+              continue;
+            }
+          }
+        }
+      }
+
+      match chunk_id {
+        FMT_ => {
+          let body = read_body()?;
+
+          have_fmt   = true;
+          fmt_offset = body_start as usize;
+          read_format(&mut self.info, body).map_err(context)?;
+        },
+        DATA if !have_data => {
+          have_data        = true;
+          self.data_offset = body_start as usize;
+          self.data_size   = chunk_size as usize;
+
+          // streaming encoders can't know the size when writing the header,
+          // though a zero size followed by more chunks is simply no audio.
+          let unset = chunk_size == u32::MAX || (chunk_size == 0 && !chunks_follow(bytes, body_start as usize));
+
+          if unset {
+            self.data_size = bytes.len() - self.data_offset;
+            self.info.data_size_untrusted = true;
+            trace_event!(WARN, declared = chunk_size, "data chunk size unset, assuming audio runs to the end");
+            break;
+          }
+
+          if self.data_size > bytes.len() - self.data_offset {
+            trace_event!(WARN, declared = chunk_size, available = bytes.len() - self.data_offset,
+                         "data chunk runs past the end of the file");
+            self.data_size      = bytes.len() - self.data_offset;
+            self.info.truncated = true;
+          }
+        },
+        FACT  => {
+          let body = read_body()?;
+
+          if body.len() < 4 {
+            let msg = format!("Fact chunk is only {} bytes long", body.len());
+            return Err(context(WaveError::ParseError(msg)));
+          }
+
+          self.fact_frames = Some(LittleEndian::read_u32(body));
+        },
+        _ if !self.options.load_metadata => { },
+        // their location is kept in `chunks`, for parsing on first use.
+        LIST | CUE_ | BEXT if self.options.lazy_metadata => { },
+        LIST  => {
+          let body = read_body()?;
+
+          if body.len() >= 4 {
+            match LittleEndian::read_u32(body) {
+              ADTL => self.adtl = OnceLock::from(AssociatedData::parse_list(&body[4..], self.options.encoding).map_err(context)?),
+              INFO => self.tags = OnceLock::from(InfoTags::parse_list(&body[4..], self.options.encoding).map_err(context)?),
+              _    => { }
+            }
+          }
+        },
+        CUE_  => {
+          let body = read_body()?;
+
+          self.cue_points = OnceLock::from(CuePoint::parse_chunk(body).map_err(context)?);
+        },
+        PLST  => {
+          let body = read_body()?;
+
+          self.playlist = PlaylistSegment::parse_chunk(body).map_err(context)?;
+        },
+        SMPL  => {
+          let body = read_body()?;
+
+          self.sampler = Some(Sampler::parse_chunk(body).map_err(context)?);
+        },
+        INST  => {
+          let body = read_body()?;
+
+          self.instrument = Some(Instrument::parse_chunk(body).map_err(context)?);
+        },
+        BEXT  => {
+          let body = read_body()?;
+
+          self.bext = OnceLock::from(Some(Bext::parse_chunk(body, self.options.encoding).map_err(context)?));
+        },
+        IXML  => {
+          let body = read_body()?;
+
+          self.ixml = Some(ChunkText::read(body, body_start as usize));
+        },
+        AXML  => {
+          let body = read_body()?;
+
+          self.axml = Some(ChunkText::read(body, body_start as usize));
+        },
+        CHNA  => {
+          let body = read_body()?;
+
+          self.chna = Some(Chna::parse_chunk(body).map_err(context)?);
+        },
+        CART  => {
+          let body = read_body()?;
+
+          self.cart = Some(Cart::parse_chunk(body, self.options.encoding).map_err(context)?);
+        },
+        ID3_ | ID3U => {
+          let body = read_body()?;
+
+          self.id3 = Some(body_start as usize..body_start as usize + body.len());
+        },
+        LEVL  => {
+          let body = read_body()?;
+
+          self.levl = Some(PeakEnvelope::parse_chunk(body).map_err(context)?);
+        },
+        DISP  => {
+          let body = read_body()?;
+
+          self.disp = Some(Disp::parse_chunk(body).map_err(context)?);
+        },
+        ACID  => {
+          let body = read_body()?;
+
+          self.acid = Some(Acid::parse_chunk(body).map_err(context)?);
+        },
+        DBMD  => {
+          let body = read_body()?;
+
+          self.dbmd = Some(Dbmd::parse_chunk(body).map_err(context)?);
+        },
+        // only the first data chunk is played.
+        DATA | JUNK => { },
+        other if self.options.unknown_chunks == UnknownChunks::Reject => {
+          return Err(WaveError::UnexpectedChunk { id: fourcc(other), offset: body_start as usize });
+        },
+        // unknown chunks are still listed by `chunks()`.
+        _     => { }
+      }
+
+      // odd sized chunks are followed by a pad byte.
+      cursor.set_position(next_chunk);
+    }
+
+    if !have_fmt {
+      return Err(WaveError::MissingChunk { id: fourcc(FMT_), offset: cursor.position() as usize });
+    }
+
+    if !have_data {
+      return Err(WaveError::MissingChunk { id: fourcc(DATA), offset: cursor.position() as usize });
+    }
+
+    if let Some(ref formats) = self.options.allowed_formats {
+      let format = self.info.subformat.unwrap_or(self.info.audio_format);
+
+      if !formats.contains(&format) {
+        return Err(WaveError::Unsupported(format!("{:?} audio is not allowed", format)));
+      }
+    }
+
+    if self.info.channels == 0 || self.info.bits_per_sample < 8 {
+      let msg = format!("Invalid channel count {} or bits per sample {} value",
+                        self.info.channels, self.info.bits_per_sample);
+
+      return Err(WaveError::ParseError(msg));
+    }
+
+    // durations and timecodes divide by the rate.
+    if self.info.sample_rate == 0 {
+      return Err(WaveError::ParseError("Invalid sample rate 0".into()));
+    }
+
+    // the iterator can't decode anything else.
+    let bits      = self.info.bits_per_sample;
+    let supported = match self.data_format() {
+      Format::PCM       => bits <= 32,
+      Format::IEEEFloat => bits == 32 || bits == 64,
+      Format::Extended  => false
+    };
+
+    if !supported {
+      let msg = format!("Can't decode {} bit {:?} audio", bits, self.data_format());
+      return Err(WaveError::Unsupported(msg));
+    }
+
+    if let Some(max) = self.options.max_channels {
+      if self.info.channels as usize > max {
+        return Err(WaveError::LimitExceeded { limit: "max_channels", max, found: self.info.channels as usize });
+      }
+    }
+
+    let frame_size  = self.info.channels as usize * (self.info.bits_per_sample as usize / 8);
+    let block_align = self.info.block_align as usize;
+    let byte_rate   = self.info.sample_rate as u64 * block_align as u64;
+
+    if self.options.strict && block_align != frame_size {
+      return Err(WaveError::Mismatch {
+        id:       fourcc(FMT_),
+        offset:   fmt_offset,
+        field:    "block_align",
+        expected: frame_size as u64,
+        found:    block_align as u64
+      });
+    }
+
+    if self.options.strict && self.info.byte_rate as u64 != byte_rate {
+      return Err(WaveError::Mismatch {
+        id:       fourcc(FMT_),
+        offset:   fmt_offset,
+        field:    "byte_rate",
+        expected: byte_rate,
+        found:    self.info.byte_rate as u64
+      });
+    }
+
+    // writers padding their frames state the padded size in block_align,
+    // while a block_align too small to hold a frame must be wrong.
+    self.stride = block_align.max(frame_size);
+
+    let format = self.info.subformat.unwrap_or(self.info.audio_format);
+
+    self.decoder     = decode::decoder(format, self.info.bits_per_sample as usize / 8);
+    self.decoder_f32 = decode::float_decoder(format, self.info.bits_per_sample as usize / 8);
+    self.count_frames();
+
+    Ok(())
+  }
+
+  /// Derives the number of frames from the size of the audio data.
+  fn count_frames(&mut self) {
+    if self.info.data_size_untrusted || self.info.truncated {
+      self.data_size -= self.data_size % self.stride;
+    }
+
+    self.info.total_frames = (self.data_size / self.stride) as u64;
+
+    // padding after the last frame of compressed or float audio would
+    // otherwise be read as frames.
+    let format = self.info.subformat.unwrap_or(self.info.audio_format);
+
+    if let Some(frames) = self.fact_frames {
+      if self.options.trust_fact && format != Format::PCM && (frames as u64) < self.info.total_frames {
+        self.info.total_frames = frames as u64;
+        self.data_size         = frames as usize * self.stride;
+      }
+    }
+  }
+}
+
+/// Reads the body of a `fmt ` chunk into `info`.
+fn read_format(info: &mut WaveInfo, data: &[u8]) -> Result<(), WaveError> {
+  let mut cursor = Cursor::new(data);
+  let fmt = cursor.read_u16::<LittleEndian>()?;
+  info.audio_format = match Format::decode(fmt) {
+    Some(f) => f,
+    None    => {
+      let msg = format!("Unexpected format {0:x}", fmt);
+      return Err(WaveError::ParseError(msg));
+    }
+  };
+  info.channels        = cursor.read_u16::<LittleEndian>()?;
+  info.sample_rate     = cursor.read_u32::<LittleEndian>()?;
+  info.byte_rate       = cursor.read_u32::<LittleEndian>()?;
+  info.block_align     = cursor.read_u16::<LittleEndian>()?;
+  info.bits_per_sample = cursor.read_u16::<LittleEndian>()?;
+
+  if info.audio_format == Format::Extended {
+    match cursor.read_u16::<LittleEndian>()? {
+      0 => { },
+      22 => {
+        info.valid_bps    = Some(cursor.read_u16::<LittleEndian>()?);
+        info.channel_mask = Some(cursor.read_u32::<LittleEndian>()?);
+        let subformat     = cursor.read_u16::<LittleEndian>()?;
+        info.subformat    = match Format::decode(subformat) {
+          Some(f) => Some(f),
+          None    => {
+            let msg = format!("Unexpected subformat {0:x}", subformat);
+            return Err(WaveError::ParseError(msg));
+          }
+        };
+        cursor.seek(SeekFrom::Current(14))?;
+      },
+      x => {
+        let msg = format!("Unexpected extension size: {}", x);
+        return Err(WaveError::ParseError(msg));
+      }
+    }
+
+  }
+
+  Ok(())
+}
+
+/// Identifies chunks of which only one is read: those in `SINGLE_CHUNKS`,
+/// with both kinds of ID3 chunk counting as one, and `LIST` chunks by their
+/// list type.
+pub(crate) fn single_chunk_key(bytes: &[u8], id: u32, offset: usize, size: usize) -> Option<(u32, u32)> {
+  match id {
+    ID3U => Some((ID3_, 0)),
+    LIST if size >= 4 && offset + 4 <= bytes.len() => {
+      match LittleEndian::read_u32(&bytes[offset..offset + 4]) {
+        list_type @ (ADTL | INFO) => Some((LIST, list_type)),
+        _                         => None
+      }
+    },
+    id if SINGLE_CHUNKS.contains(&id) => Some((id, 0)),
+    _ => None
+  }
+}
+
+/// Converts a chunk ID as read from the file into its four characters.
+fn fourcc(id: u32) -> FourCC {
+  let mut code = [0u8; 4];
+
+  LittleEndian::write_u32(&mut code, id);
+  code
+}
+
+/// Checks whether the bytes from `offset` to the end of the file are a run of
+/// well-formed chunks rather than audio.
+fn chunks_follow(bytes: &[u8], mut offset: usize) -> bool {
+  if offset >= bytes.len() {
+    return false;
+  }
+
+  while offset < bytes.len() {
+    if offset + 8 > bytes.len() {
+      return false;
+    }
+
+    let id   = &bytes[offset..offset + 4];
+    let size = LittleEndian::read_u32(&bytes[offset + 4..offset + 8]) as usize;
+    let end  = offset + 8 + size;
+
+    if !id.iter().all(|&b| b == b' ' || b.is_ascii_graphic()) || end > bytes.len() {
+      return false;
+    }
+
+    offset = end + (size & 1);
+  }
+
+  true
+}
+
+/// Checks the RIFF header at the start of `bytes`, which must hold at least
+/// its 12 bytes.
+pub(crate) fn check_riff(bytes: &[u8]) -> Result<(), WaveError> {
+  if LittleEndian::read_u32(&bytes[0..4]) != RIFF {
+    return Err(WaveError::NotWave { id: fourcc(LittleEndian::read_u32(&bytes[0..4])), offset: 0 });
+  }
+
+  if LittleEndian::read_u32(&bytes[8..12]) != WAVE {
+    return Err(WaveError::NotWave { id: fourcc(LittleEndian::read_u32(&bytes[8..12])), offset: 8 });
+  }
+
+  Ok(())
+}
+
+/// Returns the body of the chunk starting at `offset`, failing if the declared
+/// size runs past the end of the file.
+fn chunk_body(bytes: &[u8], id: u32, offset: usize, size: u32) -> Result<&[u8], WaveError> {
+  let end = offset + size as usize;
+
+  if end > bytes.len() {
+    let available = bytes.len().saturating_sub(offset);
+    return Err(WaveError::ChunkOverrun { id: fourcc(id), offset, size, available });
+  }
+
+  Ok(&bytes[offset..end])
+}
+
+/// Reads a UTF-8 string terminated by a NUL byte or the end of the data.
+fn read_zstring(data: &[u8]) -> String {
+  TextEncoding::Utf8.decode_zstring(data)
+}
+
+/// The NUL terminated text of a chunk, such as an XML document.
+enum ChunkText {
+  /// Valid UTF-8 at this range of the file.
+  Mapped(Range<usize>),
+  /// Text that had to be repaired to be UTF-8.
+  Decoded(String)
+}
+
+impl ChunkText {
+  /// Reads the text of `body`, found at `offset` within the file.
+  fn read(body: &[u8], offset: usize) -> ChunkText {
+    let end = body.iter().position(|&b| b == 0).unwrap_or(body.len());
+
+    match std::str::from_utf8(&body[..end]) {
+      Ok(_)  => ChunkText::Mapped(offset..offset + end),
+      Err(_) => ChunkText::Decoded(TextEncoding::Utf8.decode_zstring(body))
+    }
+  }
+}
+
+/// Writes `s` NUL padded or truncated to exactly `len` bytes.
+fn write_fixed<W: Write>(out: &mut W, s: &str, len: usize) -> Result<(), WaveError> {
+  let bytes = s.as_bytes();
+  let used  = bytes.len().min(len);
+
+  out.write_all(&bytes[..used])?;
+  out.write_all(&vec![0u8; len - used])?;
+  Ok(())
+}
+
+impl<'a> Iterator for WaveFileIterator<'a> {
+  type Item = Frame;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let channels = self.file.channels();
+
+    if self.offset == self.block.len() {
+      let frames = (self.file.options.block_samples / channels).max(1);
+
+      self.block.resize(frames * channels, 0);
+
+      let read = self.file.read_frames(self.next, &mut self.block);
+
+      self.block.truncate(read * channels);
+      self.next  += read;
+      self.offset = 0;
+
+      if read == 0 {
+        return None;
+      }
+    }
+
+    let frame = self.block[self.offset..self.offset + channels].to_vec();
+
+    self.offset += channels;
+    Some(frame)
+  }
+}
+
+#[test]
+fn test_info() {
+  let file = match WaveFile::open("./fixtures/test-s24le.wav") {
     Ok(f) => f,
     Err(e) => panic!("Error: {:?}", e)
   };
@@ -393,7 +1701,7 @@ fn test_iter() {
   };
 
   let frames = file.iter().take(2).collect::<Vec<_>>();
-  let expected = vec![
+  let expected = [
     [19581, 19581],
     [24337, 24337]
   ];
@@ -421,7 +1729,7 @@ fn test_float_extended() {
   let frames = file.iter().take(2).collect::<Vec<_>>();
   // these are the same values as the 24-bit samples,
   // however we've scaled to 32-bit.
-  let expected = vec![
+  let expected = [
     [5012736, 5012736],
     [6230272, 6230272]
   ];
@@ -439,3 +1747,3489 @@ fn test_duration() {
   let file = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
   assert_eq!(file.duration(), 10456);
 }
+
+#[test]
+fn test_markers() {
+  let file = WaveFile::open("./fixtures/test-markers.wav").unwrap();
+
+  assert_eq!(file.cue_points().len(), 2);
+  assert_eq!(file.markers(), [
+    Marker { id: 1, position: 10, label: Some("Intro".into()), note: Some("count in".into()) },
+    Marker { id: 2, position: 40, label: Some("Verse".into()), note: None }
+  ]);
+  assert_eq!(file.regions(), [
+    Region {
+      id:      2,
+      start:   40,
+      length:  30,
+      purpose: *b"rgn ",
+      label:   Some("Verse".into()),
+      text:    Some("first verse".into())
+    }
+  ]);
+  assert_eq!(file.len(), 100);
+}
+
+#[test]
+fn test_label_track() {
+  let file   = WaveFile::open("./fixtures/test-markers.wav").unwrap();
+  let labels = file.labels();
+  let mut track = Vec::new();
+
+  write_label_track(&mut track, &labels).unwrap();
+  assert_eq!(String::from_utf8(track.clone()).unwrap(),
+             "0.001250\t0.001250\tIntro\n0.005000\t0.008750\tVerse\n");
+  assert_eq!(read_label_track(&track[..]).unwrap(), labels);
+
+  let path = std::env::temp_dir().join("wavefile-test-labels.wav");
+  let spec = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+  let mut writer = WaveWriter::create(path.to_str().unwrap(), spec).unwrap();
+
+  writer.set_labels(&labels).unwrap();
+  for _ in 0..100 {
+    writer.write_frame(&[0]).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  let copy = WaveFile::open(path.to_str().unwrap()).unwrap();
+
+  assert_eq!(copy.labels(), labels);
+  assert_eq!(copy.regions()[0].start,  40);
+  assert_eq!(copy.regions()[0].length, 30);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_playlist() {
+  let file = WaveFile::open("./fixtures/test-markers.wav").unwrap();
+
+  assert_eq!(file.playlist(), [
+    PlaylistSegment { cue_id: 2, length: 30, repeat: 2 },
+    PlaylistSegment { cue_id: 1, length: 10, repeat: 1 }
+  ]);
+}
+
+#[test]
+fn test_sampler() {
+  let file    = WaveFile::open("./fixtures/test-sampler.wav").unwrap();
+  let sampler = file.sampler().unwrap();
+
+  assert_eq!(sampler.midi_unity_note, 60);
+  assert_eq!(sampler.fine_tune(),     50.0);
+  assert_eq!(sampler.loops, [
+    SampleLoop { cue_id: 1, loop_type: LoopType::Forward, start: 8, end: 63, fraction: 0, play_count: 0 }
+  ]);
+}
+
+#[test]
+fn test_instrument() {
+  let file = WaveFile::open("./fixtures/test-sampler.wav").unwrap();
+
+  assert_eq!(file.instrument().unwrap(), Instrument {
+    base_note:     60,
+    detune:        -12,
+    gain:          -3,
+    low_note:      48,
+    high_note:     72,
+    low_velocity:  1,
+    high_velocity: 127
+  });
+}
+
+#[test]
+fn test_acid() {
+  let file = WaveFile::open("./fixtures/test-acid.wav").unwrap();
+  let acid = file.acid().unwrap();
+
+  assert_eq!(acid.root_note, 57);
+  assert_eq!(acid.beats,     8);
+  assert_eq!(acid.tempo,     120.0);
+  assert_eq!((acid.meter_numerator, acid.meter_denominator), (4, 4));
+  assert!(acid.has_root_note() && acid.stretches());
+  assert!(!acid.is_one_shot() && !acid.is_disk_based());
+  assert!(WaveFile::open("./fixtures/test-sampler.wav").unwrap().acid().is_none());
+}
+
+#[test]
+fn test_dbmd() {
+  let file = WaveFile::open("./fixtures/test-dbmd.wav").unwrap();
+  let dbmd = file.dbmd().unwrap();
+
+  assert_eq!(dbmd.version, 0x01000006);
+  assert_eq!(dbmd.segments.len(), 3);
+  assert_eq!(dbmd.segments[0].kind(), DbmdSegmentKind::DolbyDigital);
+  assert_eq!(dbmd.segments[0].data.len(), 96);
+  assert!(dbmd.segments.iter().all(|segment| segment.is_valid()));
+  assert!(dbmd.segment(DbmdSegmentKind::DolbyE).is_none());
+
+  // 5.1 at 448 kbit/s, preferring an Lo/Ro downmix.
+  let digital  = dbmd.dolby_digital().unwrap();
+  let downmix  = digital.extended_downmix.unwrap();
+
+  assert_eq!(digital.data_rate, 448);
+  assert_eq!((digital.bitstream_mode, digital.audio_coding_mode, digital.lfe), (0, 7, true));
+  assert_eq!(digital.dialnorm(), -24);
+  assert_eq!(digital.center_mix_level(), Some(-3.0));
+  assert_eq!(digital.surround_mix_level(), Some(-6.0));
+  assert!(digital.copyright && digital.original);
+  assert_eq!(digital.lfe_mix_level(), None);
+  assert_eq!(downmix.preferred_mode, 2);
+  assert_eq!(downmix.lt_rt_center_level(), -1.5);
+  assert_eq!(downmix.lt_rt_surround_level(), Some(-3.0));
+  assert_eq!(downmix.lo_ro_center_level(), -3.0);
+  assert_eq!(downmix.lo_ro_surround_level(), Some(-6.0));
+
+  // a Dolby Surround encoded stereo commentary at 192 kbit/s.
+  let plus = dbmd.dolby_digital_plus().unwrap();
+
+  assert_eq!(plus.data_rate, 192);
+  assert_eq!((plus.bitstream_mode, plus.audio_coding_mode, plus.lfe, plus.surround_mode), (5, 2, false, 2));
+  assert_eq!(plus.dialnorm(), -27);
+  assert_eq!(plus.center_mix_level(), Some(-4.5));
+  assert_eq!(plus.surround_mix_level(), Some(f32::NEG_INFINITY));
+  assert_eq!(plus.lfe_mix_level(), Some(0.0));
+  assert!(plus.extended_downmix.is_none());
+
+  assert_eq!(dbmd.audio_info(), Some(AudioInfo { program_id: 3, loudness: Some(-23.0), true_peak: Some(-1.5) }));
+
+  // a segment failing its checksum, or too short for its kind, is rejected.
+  let mut bytes = std::fs::read("./fixtures/test-dbmd.wav").unwrap();
+  let parse     = |bytes: &[u8]| match WaveFile::from_bytes(bytes, WaveFileOptions::new()) {
+    Err(WaveError::InvalidChunk { message, .. }) => message,
+    other                                        => panic!("unexpected result {:?}", other.map(|f| f.info()))
+  };
+
+  bytes[0x33 + 3] ^= 0x01;
+  assert_eq!(parse(&bytes), "Dolby metadata segment 3 fails its checksum");
+  bytes[0x33 + 3] ^= 0x01;
+
+  // the audio info segment cut to four bytes, with its checksum following.
+  let audio_info = 0x30 + 100 + 17;
+  let checksum   = bytes[audio_info + 3..audio_info + 7].iter().fold(4u8, |sum, &byte| sum.wrapping_add(byte));
+
+  bytes[audio_info + 1] = 4;
+  bytes[audio_info + 7] = 0u8.wrapping_sub(checksum);
+  bytes[audio_info + 8] = 0;
+  assert_eq!(parse(&bytes), "Dolby audio info segment is only 4 bytes long");
+}
+
+#[test]
+fn test_bext() {
+  let file = WaveFile::open("./fixtures/test-bext.wav").unwrap();
+  let bext = file.bext().unwrap();
+
+  assert_eq!(bext.description,          "Interview, take 3");
+  assert_eq!(bext.originator,           "wavefile");
+  assert_eq!(bext.originator_reference, "USID0001");
+  assert_eq!(bext.origination_date,     "2016:03:30");
+  assert_eq!(bext.origination_time,     "12:34:56");
+  assert_eq!(bext.time_reference,       48000 * 3600 * 10 + 5);
+  assert_eq!(bext.version,              2);
+  assert_eq!(bext.umid.as_bytes()[63],  63);
+  assert_eq!(bext.coding_history,       "A=PCM,F=48000,W=16,M=mono\r\n");
+
+  let loudness = bext.loudness.unwrap();
+  assert_eq!(loudness.integrated,    Some(-23.0));
+  assert_eq!(loudness.range,         Some(5.4));
+  assert_eq!(loudness.max_true_peak, Some(-1.5));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+  let file = WaveFile::open("./fixtures/test-bext.wav").unwrap();
+  let json = serde_json::to_string(file.bext().unwrap()).unwrap();
+  let bext = serde_json::from_str::<Bext>(&json).unwrap();
+
+  assert_eq!(&bext, file.bext().unwrap());
+
+  let info = serde_json::to_value(file.info()).unwrap();
+
+  assert_eq!(info["audio_format"], "PCM");
+  assert_eq!(info["sample_rate"],  48000);
+
+  let file    = WaveFile::open("./fixtures/test-markers.wav").unwrap();
+  let json    = serde_json::to_string(&file.markers()).unwrap();
+  let markers = serde_json::from_str::<Vec<Marker>>(&json).unwrap();
+
+  assert_eq!(markers, file.markers());
+}
+
+#[test]
+fn test_timecode() {
+  let file = WaveFile::open("./fixtures/test-bext.wav").unwrap();
+  let tc   = file.start_timecode(FrameRate::Fps2997Drop).unwrap();
+
+  // drop frame timecode keeps in step with the clock every ten minutes.
+  assert_eq!(tc.to_string(), "10:00:00;01");
+
+  let start = tc.to_samples(48000, FrameRate::Fps2997Drop);
+
+  assert_eq!(Timecode::from_samples(start,     48000, FrameRate::Fps2997Drop), tc);
+  assert_eq!(Timecode::from_samples(start - 1, 48000, FrameRate::Fps2997Drop).frames, 0);
+
+  let cases = [
+    (1799,  FrameRate::Fps2997Drop, "00:00:59;29"),
+    (1800,  FrameRate::Fps2997Drop, "00:01:00;02"),
+    (17982, FrameRate::Fps2997Drop, "00:10:00;00"),
+    (17981, FrameRate::Fps2997Drop, "00:09:59;29"),
+    (1800,  FrameRate::Fps2997,     "00:01:00:00"),
+    (86400, FrameRate::Fps24,       "01:00:00:00"),
+    (2500,  FrameRate::Fps25,       "00:01:40:00")
+  ];
+
+  for &(frames, rate, text) in &cases {
+    let tc = Timecode::from_frames(frames, rate);
+
+    assert_eq!(tc.to_string(), text);
+    assert_eq!(tc.to_frames(rate), frames);
+    assert_eq!(text.parse::<Timecode>().unwrap(), tc);
+  }
+
+  assert!("10:61:00:00".parse::<Timecode>().is_err());
+  assert!("10:00:00".parse::<Timecode>().is_err());
+
+  // hours past midnight, frames past the rate and skipped frames.
+  assert!("24:00:00:00".parse::<Timecode>().is_err());
+  assert!("99:00:00:99".parse::<Timecode>().is_err());
+  assert!("00:00:00;250".parse::<Timecode>().is_err());
+  assert!("00:00:00:30".parse::<Timecode>().is_err());
+  assert!("00:01:00;00".parse::<Timecode>().is_err());
+  assert!("00:01:00;01".parse::<Timecode>().is_err());
+  assert!("00:10:00;00".parse::<Timecode>().is_ok());
+  assert!("00:01:00:00".parse::<Timecode>().is_ok());
+
+  assert!(Timecode::parse("00:00:00:25", FrameRate::Fps25).is_err());
+  assert!(Timecode::parse("00:00:00:24", FrameRate::Fps25).is_ok());
+  assert!(Timecode::parse("00:00:00:24", FrameRate::Fps23976).is_err());
+  assert!(Timecode::parse("00:01:00:01", FrameRate::Fps2997Drop).is_err());
+  assert!(Timecode::new(23, 59, 59, 23, FrameRate::Fps24).is_ok());
+  assert!(Timecode::new(23, 59, 60, 0, FrameRate::Fps24).is_err());
+
+  // every valid timecode round trips through its frame count.
+  for &rate in &[FrameRate::Fps25, FrameRate::Fps2997Drop] {
+    let tc = Timecode::parse("23:59:59;24", rate).unwrap();
+
+    assert_eq!(Timecode::from_frames(tc.to_frames(rate), rate), tc);
+  }
+}
+
+#[test]
+fn test_umid() {
+  let file = WaveFile::open("./fixtures/test-bext.wav").unwrap();
+  let mut bext = file.bext().unwrap().clone();
+
+  assert!(!bext.umid.is_nil());
+  assert_eq!(bext.umid.instance_number(), 0x0d0e0f);
+
+  bext.umid = Umid::generate();
+
+  let path = std::env::temp_dir().join("wavefile-test-umid.wav");
+  let spec = WaveSpec { channels: 1, sample_rate: 48000, bits_per_sample: 16, format: Format::PCM };
+  let mut writer = WaveWriter::create(path.to_str().unwrap(), spec).unwrap();
+
+  writer.set_bext(&bext).unwrap();
+  writer.write_frame(&[0]).unwrap();
+  writer.finalize().unwrap();
+
+  let copy = WaveFile::open(path.to_str().unwrap()).unwrap();
+  let umid = copy.bext().unwrap().umid;
+
+  assert_eq!(umid, bext.umid);
+  assert_eq!(umid.universal_label()[..4], [0x06, 0x0a, 0x2b, 0x34]);
+  assert_eq!(umid.material_number()[6] >> 4, 4);
+  assert_eq!(umid.source_pack(), None);
+  assert_eq!(umid.to_string().len(), 64);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_ixml() {
+  let file = WaveFile::open("./fixtures/test-ixml.wav").unwrap();
+  let xml  = file.ixml().unwrap();
+
+  assert!(xml.starts_with("<?xml"));
+  assert!(xml.contains("<SCENE>12A</SCENE>"));
+}
+
+#[cfg(feature = "xml")]
+#[test]
+fn test_ixml_metadata() {
+  let file = WaveFile::open("./fixtures/test-ixml.wav").unwrap();
+  let ixml = file.ixml_metadata().unwrap().unwrap();
+
+  assert_eq!(ixml.project, Some("Feature".into()));
+  assert_eq!(ixml.scene,   Some("12A".into()));
+  assert_eq!(ixml.take,    Some("3".into()));
+  assert_eq!(ixml.tape,    Some("DAY04".into()));
+  assert_eq!(ixml.tracks.len(), 2);
+  assert_eq!(ixml.tracks[1].name,             Some("Lav".into()));
+  assert_eq!(ixml.tracks[1].interleave_index, Some(2));
+}
+
+#[test]
+fn test_adm() {
+  let file = WaveFile::open("./fixtures/test-adm.wav").unwrap();
+  let chna = file.chna().unwrap();
+
+  assert_eq!(chna.num_tracks, 2);
+  assert_eq!(chna.entries.len(), 2);
+  assert_eq!(chna.track(2), [&ChnaEntry {
+    track_index:      2,
+    track_uid:        "ATU_00000002".into(),
+    track_format_ref: "AT_00031002_01".into(),
+    pack_format_ref:  "AP_00031002".into()
+  }]);
+  assert!(file.axml().unwrap().contains("<audioFormatExtended/>"));
+}
+
+#[test]
+fn test_write_cart() {
+  let path = std::env::temp_dir().join("wavefile-test-cart.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 2, sample_rate: 44100, bits_per_sample: 24, format: Format::PCM };
+  let cart = Cart {
+    title:       "Station ID".into(),
+    artist:      "Jingles Inc".into(),
+    cut_id:      "ID0042".into(),
+    start_date:  "2016/01/01".into(),
+    start_time:  "00:00:00".into(),
+    end_date:    "2016/12/31".into(),
+    end_time:    "23:59:59".into(),
+    post_timers: vec![CartTimer { usage: *b"INT1", value: 22050 }],
+    tag_text:    "jingle\r\n".into(),
+    ..Cart::default()
+  };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  writer.set_cart(&cart).unwrap();
+  writer.write_frame(&[1000, -1000]).unwrap();
+  writer.write_frame(&[8388607, -8388608]).unwrap();
+  writer.finalize().unwrap();
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert_eq!(file.cart(), Some(&cart));
+  assert_eq!(file.len(), 2);
+  assert_eq!(file.iter().collect::<Vec<_>>(), [[1000, -1000], [8388607, -8388608]]);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_id3() {
+  let file = WaveFile::open("./fixtures/test-id3.wav").unwrap();
+
+  assert_eq!(&file.id3().unwrap()[0..3], b"ID3");
+}
+
+#[cfg(feature = "id3")]
+#[test]
+fn test_id3_tag() {
+  use id3::TagLike;
+
+  let file = WaveFile::open("./fixtures/test-id3.wav").unwrap();
+  let tag  = file.id3_tag().unwrap().unwrap();
+
+  assert_eq!(tag.title(),  Some("Field Recording"));
+  assert_eq!(tag.artist(), Some("Someone"));
+}
+
+#[test]
+fn test_write_peak_envelope() {
+  let path = std::env::temp_dir().join("wavefile-test-levl.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  writer.generate_peak_envelope(4).unwrap();
+  for &sample in &[0, 16384, -32768, 100, 1000, -1000] {
+    writer.write_frame(&[sample]).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  // the envelope is written after the audio data.
+  let file = WaveFile::open(path).unwrap();
+  let levl = file.peak_envelope().unwrap();
+
+  assert_eq!(file.chunks().last().unwrap().0, *b"levl");
+
+  assert_eq!(levl.block_size,    4);
+  assert_eq!(levl.len(),         2);
+  assert_eq!(levl.peaks,         [32767, 999]);
+  assert_eq!(levl.peak_of_peaks, Some(2));
+  assert_eq!(levl.peak(1, 0),    Some(&[999][..]));
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_info_tags() {
+  let file = WaveFile::open("./fixtures/test-info.wav").unwrap();
+  let tags = file.tags();
+
+  assert_eq!(tags.title(),    Some("Morning Birds"));
+  assert_eq!(tags.artist(),   Some("Field Team"));
+  assert_eq!(tags.software(), Some("wavefile"));
+  assert_eq!(tags.get(b"ICMT"), None);
+  assert_eq!(file.title(), Some("Morning Birds".into()));
+  assert_eq!(file.disp().unwrap().text(), Some("Birds (DISP)".into()));
+}
+
+#[test]
+fn test_disp_title() {
+  let file = WaveFile::open("./fixtures/test-disp.wav").unwrap();
+
+  assert!(file.tags().is_empty());
+  assert_eq!(file.title(), Some("Birds (DISP)".into()));
+}
+
+#[test]
+fn test_text_encoding() {
+  let utf8 = WaveFile::open("./fixtures/test-latin1.wav").unwrap();
+
+  assert_eq!(utf8.text_encoding(), TextEncoding::Utf8);
+  assert_eq!(utf8.title(), Some("Caf\u{fffd} M\u{fffd}ller".into()));
+
+  let latin1 = WaveFile::open_with_encoding("./fixtures/test-latin1.wav", TextEncoding::Latin1).unwrap();
+
+  assert_eq!(latin1.title(), Some("Café Müller".into()));
+  assert_eq!(latin1.tags().artist(), Some("Ensemble \u{93}Ré\u{94}"));
+
+  let windows = WaveFile::open_with_encoding("./fixtures/test-latin1.wav", TextEncoding::Windows1252).unwrap();
+
+  assert_eq!(windows.title(), Some("Café Müller".into()));
+  assert_eq!(windows.tags().artist(), Some("Ensemble “Ré”"));
+}
+
+#[test]
+fn test_chunks() {
+  let file   = WaveFile::open("./fixtures/test-markers.wav").unwrap();
+  let chunks = file.chunks().collect::<Vec<_>>();
+
+  assert_eq!(chunks, [
+    (*b"fmt ", 20,  16),
+    (*b"cue ", 44,  52),
+    (*b"LIST", 104, 102),
+    (*b"plst", 214, 28),
+    (*b"data", 250, 200)
+  ]);
+  assert_eq!(file.read_chunk(*b"plst").unwrap().len(), 28);
+  assert_eq!(file.read_chunk(*b"JUNK"), None);
+}
+
+#[test]
+fn test_write_custom_chunks() {
+  let path = std::env::temp_dir().join("wavefile-test-custom.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  writer.add_chunk(*b"sess", b"session-1234").unwrap();
+  assert!(writer.add_chunk(*b"data", b"").is_err());
+  writer.write_frame(&[42]).unwrap();
+  writer.add_chunk(*b"fpnt", b"abc").unwrap();
+  writer.finalize().unwrap();
+
+  // chunks added before the audio precede it, later ones follow it padded
+  // to an even length.
+  let bytes = std::fs::read(path).unwrap();
+  assert_eq!(&bytes[36..56], b"sess\x0c\0\0\0session-1234");
+  assert_eq!(&bytes[56..60], b"data");
+  assert!(bytes.ends_with(b"fpnt\x03\0\0\0abc\0"));
+  assert_eq!(LittleEndian::read_u32(&bytes[4..8]) as usize, bytes.len() - 8);
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert_eq!(file.read_chunk(*b"fpnt"), Some(b"abc".to_vec()));
+  assert_eq!(file.iter().collect::<Vec<_>>(), [vec![42]]);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_odd_chunk_padding() {
+  let path = std::env::temp_dir().join("wavefile-test-padding.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  writer.add_chunk(*b"odd ", b"xyz").unwrap();
+  writer.add_chunk(*b"inst", &[60, 0, 0, 0, 127, 0, 127]).unwrap();
+  writer.write_frame(&[7]).unwrap();
+  writer.finalize().unwrap();
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert_eq!(file.read_chunk(*b"odd "), Some(b"xyz".to_vec()));
+  assert_eq!(file.instrument().unwrap().high_note, 127);
+  assert_eq!(file.iter().collect::<Vec<_>>(), [vec![7]]);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_streamed_data_size() {
+  let path = std::env::temp_dir().join("wavefile-test-streamed.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 2, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  for i in 0..10 {
+    writer.write_frame(&[i, -i]).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  let mut bytes = std::fs::read(path).unwrap();
+
+  assert!(!WaveFile::open(path).unwrap().info().data_size_untrusted);
+
+  // a trailing partial frame is dropped.
+  bytes.push(1);
+
+  for &size in &[0u32, 0xffffffff] {
+    LittleEndian::write_u32(&mut bytes[40..44], size);
+    std::fs::write(path, &bytes).unwrap();
+
+    let file = WaveFile::open(path).unwrap();
+
+    assert!(file.info().data_size_untrusted);
+    assert_eq!(file.len(), 10);
+    assert_eq!(file.iter().last(), Some(vec![9, -9]));
+  }
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_empty_data_followed_by_list() {
+  let path = std::env::temp_dir().join("wavefile-test-empty-data.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 2, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let list = b"LIST\x12\0\0\0INFOINAM\x06\0\0\0Quiet\0";
+
+  WaveWriter::create(path, spec).unwrap().finalize().unwrap();
+
+  let mut bytes = std::fs::read(path).unwrap();
+
+  assert_eq!(&bytes[36..44], b"data\0\0\0\0");
+
+  // a data chunk last in the file and declaring no audio is still taken as streamed.
+  assert!(WaveFile::open(path).unwrap().info().data_size_untrusted);
+
+  bytes.extend_from_slice(list);
+  LittleEndian::write_u32(&mut bytes[4..8], 36 + 8 + list.len() as u32);
+  std::fs::write(path, &bytes).unwrap();
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert!(!file.info().data_size_untrusted);
+  assert_eq!(file.len(), 0);
+  assert_eq!(file.iter().next(), None);
+  assert!(file.chunks().any(|(id, _, _)| id == *b"LIST"));
+  assert_eq!(file.tags().title(), Some("Quiet"));
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_truncated_data() {
+  let path  = std::env::temp_dir().join("wavefile-test-truncated.wav");
+  let path  = path.to_str().unwrap();
+  let bytes = std::fs::read("./fixtures/test-s24le.wav").unwrap();
+  let full  = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+
+  assert!(!full.info().truncated);
+
+  // cut the file off in the middle of a frame.
+  let frame = full.channels() * 3;
+  std::fs::write(path, &bytes[..bytes.len() - 10 * frame - 1]).unwrap();
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert!(file.info().truncated);
+  assert_eq!(file.len(), full.len() - 11);
+  assert_eq!(file.iter().count(), full.len() - 11);
+  assert_eq!(file.iter().last(), full.iter().nth(full.len() - 12));
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_block_align() {
+  let path = std::env::temp_dir().join("wavefile-test-block-align.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 2, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  for i in 0..5 {
+    writer.write_frame(&[i, 100]).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  assert!(WaveFile::open_strict(path).is_ok());
+
+  // declare mono audio, leaving every frame padded to four bytes.
+  let mut bytes = std::fs::read(path).unwrap();
+  LittleEndian::write_u16(&mut bytes[22..24], 1);
+  std::fs::write(path, &bytes).unwrap();
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert_eq!(file.len(), 5);
+  assert_eq!(file.iter().collect::<Vec<_>>(), [[0], [1], [2], [3], [4]]);
+  assert!(WaveFile::open_strict(path).is_err());
+
+  // a block align too small for a frame is ignored.
+  LittleEndian::write_u16(&mut bytes[22..24], 2);
+  LittleEndian::write_u16(&mut bytes[32..34], 1);
+  std::fs::write(path, &bytes).unwrap();
+
+  assert_eq!(WaveFile::open(path).unwrap().iter().last(), Some(vec![4, 100]));
+  assert!(WaveFile::open_strict(path).is_err());
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_open_with_options() {
+  let path    = "./fixtures/test-markers.wav";
+  let options = WaveFileOptions::new().load_metadata(false);
+  let file    = WaveFile::open_with(path, options).unwrap();
+
+  assert!(file.cue_points().is_empty());
+  assert_eq!(file.chunks().count(), WaveFile::open(path).unwrap().chunks().count());
+  assert_eq!(file.len(), 100);
+
+  // the data chunk follows the cue and adtl chunks.
+  assert!(WaveFile::open_with(path, WaveFileOptions::new().max_scan_bytes(64)).is_err());
+  assert!(WaveFile::open_with(path, WaveFileOptions::new().max_scan_bytes(1024)).is_ok());
+
+  let float = WaveFileOptions::new().allowed_formats(&[Format::IEEEFloat]);
+
+  assert!(WaveFile::open_with(path, float.clone()).is_err());
+  assert!(WaveFile::open_with("./fixtures/test-f32le.wav", float).is_ok());
+}
+
+#[test]
+fn test_skip_unknown_chunks() {
+  let path = std::env::temp_dir().join("wavefile-test-unknown.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  writer.add_chunk(*b"PAD ", &[0; 6]).unwrap();
+  writer.add_chunk(*b"sess", b"session-1234").unwrap();
+  writer.write_frame(&[42]).unwrap();
+  writer.finalize().unwrap();
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert_eq!(file.read_chunk(*b"sess"), Some(b"session-1234".to_vec()));
+  assert_eq!(file.iter().collect::<Vec<_>>(), [vec![42]]);
+  assert!(WaveFile::open_strict(path).is_err());
+  assert!(WaveFile::open_strict("./fixtures/test-markers.wav").is_ok());
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_error_context() {
+  let path = std::env::temp_dir().join("wavefile-test-errors.wav");
+  let path = path.to_str().unwrap();
+
+  // a cue chunk claiming far more points than it holds.
+  let mut bytes = std::fs::read("./fixtures/test-markers.wav").unwrap();
+  let cue       = bytes.windows(4).position(|w| w == b"cue ").unwrap();
+  bytes[cue + 8..cue + 12].copy_from_slice(&1000u32.to_le_bytes());
+  std::fs::write(path, &bytes).unwrap();
+
+  match WaveFile::open(path) {
+    Err(WaveError::InvalidChunk { id, offset, .. }) => {
+      assert_eq!(id, *b"cue ");
+      assert_eq!(offset, cue + 8);
+    },
+    other => panic!("expected an invalid cue chunk, got {:?}", other.err())
+  }
+
+  // a bext chunk running past the end of the file.
+  let spec       = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  writer.write_frame(&[42]).unwrap();
+  writer.finalize().unwrap();
+
+  let mut bytes = std::fs::read(path).unwrap();
+  let end       = bytes.len();
+  bytes.extend_from_slice(b"bext");
+  bytes.extend_from_slice(&602u32.to_le_bytes());
+  bytes.extend_from_slice(&[0; 10]);
+  std::fs::write(path, &bytes).unwrap();
+
+  match WaveFile::open(path) {
+    Err(WaveError::ChunkOverrun { id, offset, size, available }) => {
+      assert_eq!((id, offset, size, available), (*b"bext", end + 8, 602, 10));
+    },
+    other => panic!("expected an overrun, got {:?}", other.err())
+  }
+
+  // a block align that doesn't match the sample size.
+  bytes.truncate(end);
+  bytes[32] = 4;
+  std::fs::write(path, &bytes).unwrap();
+
+  assert!(WaveFile::open(path).is_ok());
+  match WaveFile::open_strict(path) {
+    Err(WaveError::Mismatch { id, field, expected, found, .. }) => {
+      assert_eq!((id, field, expected, found), (*b"fmt ", "block_align", 2, 4));
+    },
+    other => panic!("expected a mismatch, got {:?}", other.err())
+  }
+
+  // damage to the structure of the file.
+  let markers = std::fs::read("./fixtures/test-markers.wav").unwrap();
+  let open    = |bytes: &[u8], options: WaveFileOptions| WaveFile::from_bytes(bytes, options).err();
+
+  let mut bytes = markers.clone();
+  bytes[8..12].copy_from_slice(b"AVI ");
+
+  match open(&bytes, WaveFileOptions::new()) {
+    Some(WaveError::NotWave { id, offset }) => assert_eq!((id, offset), (*b"AVI ", 8)),
+    other                                   => panic!("expected a non-wave file, got {:?}", other)
+  }
+
+  match open(&markers[..242], WaveFileOptions::new()) {
+    Some(WaveError::MissingChunk { id, offset }) => assert_eq!((id, offset), (*b"data", 242)),
+    other                                        => panic!("expected a missing data chunk, got {:?}", other)
+  }
+
+  let mut bytes = markers.clone();
+  bytes.extend_from_slice(b"sess\x02\0\0\0id");
+
+  match open(&bytes, WaveFileOptions::new().unknown_chunks(UnknownChunks::Reject)) {
+    Some(WaveError::UnexpectedChunk { id, offset }) => assert_eq!((id, offset), (*b"sess", markers.len() + 8)),
+    other                                           => panic!("expected an unexpected chunk, got {:?}", other)
+  }
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_error_source() {
+  use std::error::Error;
+
+  let err: Box<dyn Error> = match WaveFile::open("./fixtures/missing.wav") {
+    Err(e) => Box::new(e),
+    Ok(_)  => panic!("opened a missing file")
+  };
+  let io  = err.source().and_then(|e| e.downcast_ref::<std::io::Error>()).unwrap();
+
+  assert_eq!(io.kind(), std::io::ErrorKind::NotFound);
+  assert!(err.to_string().starts_with("IO Error: "));
+
+  let err = WaveError::ParseError("Not a Wavefile".into());
+
+  assert!(err.source().is_none());
+  assert_eq!(err.to_string(), "Parse Error: Not a Wavefile");
+}
+
+#[test]
+fn test_repair() {
+  let src  = std::env::temp_dir().join("wavefile-test-crashed.wav");
+  let src  = src.to_str().unwrap();
+  let dst  = std::env::temp_dir().join("wavefile-test-repaired.wav");
+  let dst  = dst.to_str().unwrap();
+  let spec = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(src, spec).unwrap();
+  for sample in 1..4 {
+    writer.write_frame(&[sample]).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  assert!(repair(src, dst, true).unwrap().is_clean());
+
+  // what a recorder leaves behind when it loses power mid-frame.
+  let mut bytes = std::fs::read(src).unwrap();
+  bytes[4..8].copy_from_slice(&[0; 4]);
+  bytes[40..44].copy_from_slice(&[0; 4]);
+  bytes[32] = 4;
+  bytes.push(4);
+  std::fs::write(src, &bytes).unwrap();
+
+  let report = repair(src, dst, true).unwrap();
+
+  assert_eq!(report.repairs, [
+    Repair::RiffSize { declared: 0, actual: 42 },
+    Repair::BlockAlign { declared: 4, actual: 2 },
+    Repair::DataSize { declared: 0, actual: 6 }
+  ]);
+
+  let file = WaveFile::open_strict(dst).unwrap();
+
+  assert_eq!(file.iter().collect::<Vec<_>>(), [vec![1], vec![2], vec![3]]);
+  assert!(!file.info().data_size_untrusted);
+  std::fs::remove_file(src).unwrap();
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_validate() {
+  let path = std::env::temp_dir().join("wavefile-test-validate.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  writer.write_frame(&[42]).unwrap();
+  writer.finalize().unwrap();
+
+  // an odd-sized chunk appended without its pad byte or updating the header.
+  let original  = std::fs::read(path).unwrap();
+  let mut bytes = original.clone();
+  bytes.extend_from_slice(b"note\x03\x00\x00\x00abc");
+  std::fs::write(path, &bytes).unwrap();
+
+  assert_eq!(WaveFile::open(path).unwrap().validate(), [
+    Issue::RiffSize { declared: 38, actual: 49 },
+    Issue::MissingPadByte { id: *b"note", offset: 54 }
+  ]);
+
+  // a streamed file with a block align too large for its samples.
+  let mut bytes = original;
+  bytes[40..44].copy_from_slice(&[0; 4]);
+  bytes[32] = 4;
+  std::fs::write(path, &bytes).unwrap();
+
+  let issues = WaveFile::open(path).unwrap().validate();
+
+  assert_eq!(issues, [
+    Issue::DataSizeUnset,
+    Issue::BlockAlign { declared: 4, expected: 2 },
+    Issue::ByteRate { declared: 16000, expected: 32000 },
+    Issue::SuspiciousDuration { seconds: 0.0 }
+  ]);
+  assert_eq!(issues.iter().map(Issue::severity).max(), Some(Severity::Violation));
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_limits() {
+  let limited = |options: WaveFileOptions| match WaveFile::open_with("./fixtures/test-markers.wav", options) {
+    Err(WaveError::LimitExceeded { limit, .. }) => Some(limit),
+    Err(e)                                      => panic!("unexpected error {}", e),
+    Ok(_)                                       => None
+  };
+
+  assert_eq!(limited(WaveFileOptions::new().max_allocation(16)), Some("max_allocation"));
+  assert_eq!(limited(WaveFileOptions::new().max_chunks(2)), Some("max_chunks"));
+  assert_eq!(limited(WaveFileOptions::new().max_channels(0)), Some("max_channels"));
+
+  let options = WaveFileOptions::new().max_allocation(1 << 16).max_chunks(16).max_channels(8);
+
+  assert_eq!(limited(options), None);
+
+  // decoding the whole file counts too.
+  let file = WaveFile::open_with("./fixtures/test-s24le.wav", WaveFileOptions::new().max_allocation(1 << 16)).unwrap();
+
+  match file.read_all_with_progress(&mut Progress::new(|_, _| { })) {
+    Err(WaveError::LimitExceeded { limit, found, .. }) => assert_eq!((limit, found), ("max_allocation", file.len() * 2 * 4)),
+    other                                              => panic!("unexpected result {:?}", other.map(|s| s.len()))
+  }
+}
+
+#[test]
+fn test_format_after_data() {
+  let path = std::env::temp_dir().join("wavefile-test-fmt-last.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 2, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  writer.write_frame(&[1, -1]).unwrap();
+  writer.write_frame(&[2, -2]).unwrap();
+  writer.finalize().unwrap();
+
+  // move the format chunk behind the audio.
+  let bytes     = std::fs::read(path).unwrap();
+  let mut moved = bytes[..12].to_vec();
+  moved.extend_from_slice(&bytes[36..]);
+  moved.extend_from_slice(&bytes[12..36]);
+  std::fs::write(path, &moved).unwrap();
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert_eq!(file.info().channels, 2);
+  assert_eq!(file.iter().collect::<Vec<_>>(), [vec![1, -1], vec![2, -2]]);
+  assert_eq!(file.validate(), [Issue::FormatAfterData]);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_carve() {
+  let path = std::env::temp_dir().join("wavefile-test-carve.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  for sample in 1..4 {
+    writer.write_frame(&[sample]).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  let complete = std::fs::read(path).unwrap();
+
+  // a recording cut off mid-frame before its header was finalized.
+  let mut crashed = complete.clone();
+  crashed[4..8].copy_from_slice(&[0; 4]);
+  crashed[40..44].copy_from_slice(&[0; 4]);
+  crashed.pop();
+
+  let mut image = vec![0xff; 13];
+  image.extend_from_slice(&complete);
+  image.extend_from_slice(b"garbage");
+  image.extend_from_slice(&crashed);
+
+  let carved = WaveFile::carve(Cursor::new(&image)).unwrap();
+  let second = 13 + complete.len() + 7;
+
+  assert_eq!(carved.len(), 2);
+  assert_eq!((carved[0].offset, carved[0].end), (13, 13 + complete.len()));
+  assert_eq!(carved[0].audio, 13 + 44..13 + 50);
+  assert_eq!(carved[0].file.iter().collect::<Vec<_>>(), [vec![1], vec![2], vec![3]]);
+  assert_eq!((carved[1].offset, carved[1].end), (second, image.len()));
+  assert_eq!(carved[1].audio, second + 44..second + 48);
+  assert_eq!(carved[1].file.iter().collect::<Vec<_>>(), [vec![1], vec![2]]);
+
+  // a header split between the blocks the stream is scanned in.
+  let mut image = vec![0xff; (1 << 16) + 5];
+  image.extend_from_slice(&complete);
+
+  let carved = WaveFile::carve(Cursor::new(&image)).unwrap();
+
+  assert_eq!(carved.len(), 1);
+  assert_eq!(carved[0].offset, (1 << 16) + 5);
+  assert_eq!(carved[0].file.len(), 3);
+
+  // no more than max_allocation bytes are read for a file.
+  let carved = WaveFile::carve_with(Cursor::new(&image), WaveFileOptions::new().max_allocation(48)).unwrap();
+
+  assert_eq!(carved[0].end, (1 << 16) + 5 + 48);
+  assert!(carved[0].file.info().truncated);
+  assert_eq!(carved[0].file.iter().collect::<Vec<_>>(), [vec![1], vec![2]]);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn test_arbitrary_wave() {
+  use arbitrary::{Arbitrary,Unstructured};
+
+  // without input, the generator falls back to a well-formed empty file.
+  let wave = ArbitraryWave::arbitrary(&mut Unstructured::new(&[])).unwrap();
+  let file = WaveFile::from_bytes(&wave.bytes, WaveFileOptions::new().strict(true)).unwrap();
+
+  assert_eq!(file.info().channels, wave.info.channels);
+  assert!(file.validate().iter().all(|issue| issue.severity() == Severity::Warning));
+
+  let mut state = 0x2545f4914f6cdd1du64;
+  let mut input = vec![0u8; 4096];
+
+  for _ in 0..256 {
+    for byte in input.iter_mut() {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      *byte = state as u8;
+    }
+
+    let wave = ArbitraryWave::arbitrary(&mut Unstructured::new(&input)).unwrap();
+
+    if let Ok(file) = WaveFile::from_bytes(&wave.bytes, WaveFileOptions::new()) {
+      file.validate();
+      file.duration();
+      file.speakers();
+      file.cue_points();
+      file.markers();
+      file.regions();
+      file.labels();
+      file.playlist();
+      file.sampler();
+      file.instrument();
+      file.acid();
+      file.dbmd();
+      file.bext();
+      file.start_timecode(FrameRate::Fps25);
+      file.ixml();
+      file.axml();
+      file.chna();
+      file.cart();
+      file.peak_envelope();
+      file.tags();
+      file.disp();
+      file.title();
+      file.bit_usage();
+      assert_eq!(file.iter().count(), file.len());
+    }
+  }
+}
+
+#[test]
+fn test_zero_sample_rate() {
+  let mut bytes = std::fs::read("./fixtures/test-markers.wav").unwrap();
+
+  bytes[24..28].copy_from_slice(&[0; 4]);
+
+  match WaveFile::from_bytes(&bytes, WaveFileOptions::new()) {
+    Err(WaveError::ParseError(msg)) => assert_eq!(msg, "Invalid sample rate 0"),
+    other                           => panic!("unexpected result {:?}", other.map(|f| f.info()))
+  }
+}
+
+#[test]
+fn test_fact_frames() {
+  let path = std::env::temp_dir().join("wavefile-test-fact.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 32, format: Format::IEEEFloat };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  for sample in 1..4 {
+    writer.write_frame(&[sample]).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  assert_eq!(WaveFile::open(path).unwrap().fact_frames(), Some(3));
+
+  // the last frame is only padding.
+  let mut bytes = std::fs::read(path).unwrap();
+  let fact      = bytes.windows(4).position(|w| w == b"fact").unwrap();
+  bytes[fact + 8..fact + 12].copy_from_slice(&2u32.to_le_bytes());
+  std::fs::write(path, &bytes).unwrap();
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert_eq!(file.len(), 2);
+  assert_eq!(file.iter().count(), 2);
+  assert_eq!(WaveFile::open_with(path, WaveFileOptions::new().trust_fact(false)).unwrap().len(), 3);
+
+  // a fact chunk can't add frames missing from the data.
+  bytes[fact + 8..fact + 12].copy_from_slice(&100u32.to_le_bytes());
+  std::fs::write(path, &bytes).unwrap();
+
+  assert_eq!(WaveFile::open(path).unwrap().len(), 3);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_undecodable_formats() {
+  use byteorder::WriteBytesExt;
+
+  let path = std::env::temp_dir().join("wavefile-test-undecodable.wav");
+  let path = path.to_str().unwrap();
+
+  let open = |format: u16, bits: u16, extension: &[u8]| {
+    let mut fmt = Vec::new();
+    fmt.write_u16::<LittleEndian>(format).unwrap();
+    fmt.write_u16::<LittleEndian>(1).unwrap();
+    fmt.write_u32::<LittleEndian>(8000).unwrap();
+    fmt.write_u32::<LittleEndian>(8000 * (bits as u32 / 8)).unwrap();
+    fmt.write_u16::<LittleEndian>(bits / 8).unwrap();
+    fmt.write_u16::<LittleEndian>(bits).unwrap();
+    fmt.extend_from_slice(extension);
+
+    let mut bytes = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+    bytes.write_u32::<LittleEndian>(fmt.len() as u32).unwrap();
+    bytes.extend_from_slice(&fmt);
+    bytes.extend_from_slice(b"data\x10\0\0\0");
+    bytes.extend_from_slice(&[0x7f; 16]);
+    std::fs::write(path, &bytes).unwrap();
+
+    WaveFile::open(path)
+  };
+
+  for &(format, bits) in &[(1, 40), (3, 16), (3, 24), (0xfffe, 16)] {
+    let extension : &[u8] = if format == 0xfffe { &[0, 0] } else { &[] };
+
+    match open(format, bits, extension) {
+      Err(WaveError::Unsupported(_)) => { },
+      other                          => panic!("{} bit format {:x} opened: {:?}", bits, format, other.err())
+    }
+  }
+
+  // channel mask bits past the defined speaker positions are ignored.
+  let mut extension = vec![22, 0, 16, 0, 0x04, 0, 0x1c, 0x40, 1, 0];
+  extension.extend_from_slice(b"\x00\x00\x00\x00\x10\x00\x80\x00\x00\xaa\x00\x38\x9b\x71");
+
+  let file = open(0xfffe, 16, &extension).unwrap();
+
+  assert_eq!(file.speakers(), Some(vec![SpeakerPosition::FrontCenter]));
+  assert_eq!(file.iter().count(), 8);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_frames_past_4gib() {
+  let path = std::env::temp_dir().join("wavefile-test-huge.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 8, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  writer.write_frame(&[1]).unwrap();
+  writer.finalize().unwrap();
+
+  // a streamed recording running past what a 32 bit data size can hold,
+  // kept sparse so it takes no space on disk.
+  let mut bytes = std::fs::read(path).unwrap();
+  bytes[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+  std::fs::write(path, &bytes).unwrap();
+
+  let frames = 5 << 30;
+  std::fs::OpenOptions::new().write(true).open(path).unwrap().set_len(44 + frames).unwrap();
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert_eq!(file.info().total_frames, frames);
+  assert_eq!(file.len() as u64, frames);
+  assert_eq!(file.iter().next(), Some(vec![1]));
+
+  drop(file);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_duplicate_chunks() {
+  let path = std::env::temp_dir().join("wavefile-test-duplicates.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+
+  let acid = |tempo: f32| {
+    let mut body = vec![0u8; 24];
+    body[20..24].copy_from_slice(&tempo.to_le_bytes());
+    body
+  };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  writer.add_chunk(*b"acid", &acid(120.0)).unwrap();
+  writer.add_chunk(*b"acid", &acid(120.0)).unwrap();
+  writer.add_chunk(*b"acid", &acid(90.0)).unwrap();
+  writer.write_frame(&[42]).unwrap();
+  writer.finalize().unwrap();
+
+  let tempo = |policy: DuplicateChunks| {
+    WaveFile::open_with(path, WaveFileOptions::new().duplicate_chunks(policy)).map(|file| file.acid().unwrap().tempo)
+  };
+
+  assert_eq!(tempo(DuplicateChunks::First).unwrap(), 120.0);
+  assert_eq!(tempo(DuplicateChunks::Last).unwrap(), 90.0);
+
+  match tempo(DuplicateChunks::Reject) {
+    Err(WaveError::InvalidChunk { id, offset, .. }) => assert_eq!((id, offset), (*b"acid", 76)),
+    other                                           => panic!("duplicate accepted: {:?}", other)
+  }
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert!(WaveFile::open_strict(path).is_err());
+  assert_eq!(file.validate(), [
+    Issue::DuplicateChunk { id: *b"acid", offset: 76, conflicting: false },
+    Issue::DuplicateChunk { id: *b"acid", offset: 108, conflicting: true }
+  ]);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_bit_usage() {
+  let path = std::env::temp_dir().join("wavefile-test-bits.wav");
+  let path = path.to_str().unwrap();
+
+  let write = |format: Format, bits: u16, samples: &[i32]| {
+    let spec       = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: bits, format };
+    let mut writer = WaveWriter::create(path, spec).unwrap();
+
+    for &sample in samples {
+      writer.write_frame(&[sample]).unwrap();
+    }
+    writer.finalize().unwrap();
+  };
+
+  // 16 bit audio padded to 24 bits.
+  write(Format::PCM, 24, &[256, -512, 1 << 16]);
+  assert_eq!(WaveFile::open(path).unwrap().bit_usage(), BitUsage { declared: 24, used: 16 });
+
+  write(Format::PCM, 24, &[256, -512, 1]);
+  assert!(!WaveFile::open(path).unwrap().bit_usage().is_padded());
+
+  write(Format::PCM, 16, &[0, 0]);
+  assert_eq!(WaveFile::open(path).unwrap().bit_usage().used, 0);
+
+  // 16 bit audio stored as floats.
+  let store = |values: &[f32]| {
+    write(Format::IEEEFloat, 32, &vec![0; values.len()]);
+
+    let mut bytes = std::fs::read(path).unwrap();
+    let data      = bytes.windows(4).position(|w| w == b"data").unwrap() + 8;
+
+    for (i, value) in values.iter().enumerate() {
+      bytes[data + i * 4..data + i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+    }
+    std::fs::write(path, &bytes).unwrap();
+
+    WaveFile::open(path).unwrap().bit_usage()
+  };
+
+  assert_eq!(store(&[0.5, -0.25, 3.0 / 32768.0]), BitUsage { declared: 32, used: 16 });
+  assert_eq!(store(&[0.5, 0.1]), BitUsage { declared: 32, used: 32 });
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_read_frames() {
+  for path in &["./fixtures/test-s24le.wav", "./fixtures/test-f32le.wav"] {
+    let wav      = WaveFile::open(*path).unwrap();
+    let frames   = wav.iter().collect::<Vec<_>>();
+    let channels = wav.channels();
+
+    // an odd buffer size leaves a partial block at the end
+    let mut buffer = vec![0; 7 * channels + 1];
+    let mut first  = 0;
+
+    loop {
+      let read = wav.read_frames(first, &mut buffer);
+
+      if read == 0 {
+        break;
+      }
+      for (i, frame) in buffer[..read * channels].chunks(channels).enumerate() {
+        assert_eq!(frame, &frames[first + i][..]);
+      }
+      first += read;
+    }
+    assert_eq!(first, wav.len());
+    assert_eq!(wav.read_frames(wav.len() + 1, &mut buffer), 0);
+  }
+}
+
+#[test]
+fn test_sample_depths() {
+  for &bits in &[8u16, 16, 24, 32] {
+    let path = std::env::temp_dir().join(format!("wavefile-test-depth-{}.wav", bits));
+    let spec = WaveSpec { channels: 2, sample_rate: 8000, bits_per_sample: bits, format: Format::PCM };
+    let max  = ((1i64 << (bits - 1)) - 1) as i32;
+    let mut writer = WaveWriter::create(path.to_str().unwrap(), spec).unwrap();
+
+    writer.write_frame(&[max, -max - 1]).unwrap();
+    writer.write_frame(&[-1, 1]).unwrap();
+    writer.finalize().unwrap();
+
+    let file = WaveFile::open(path.to_str().unwrap()).unwrap();
+
+    assert_eq!(file.iter().collect::<Vec<_>>(), vec![vec![max, -max - 1], vec![-1, 1]]);
+    std::fs::remove_file(path).unwrap();
+  }
+}
+
+#[test]
+fn test_unsigned_8_bit() {
+  // the fixture opens with silence, stored as 128.
+  let file   = WaveFile::open("./fixtures/test-u8.wav").unwrap();
+  let frames = file.iter().take(434).collect::<Vec<_>>();
+
+  assert_eq!(frames[0], [0, 0]);
+  assert_eq!(frames[431], [-1, -1]);
+  assert_eq!(frames[432], [-1, -1]);
+  assert_eq!(frames[433], [0, 0]);
+
+  let mut frame = [0.0; 2];
+
+  file.read_frames_f32_into(431, &mut frame);
+  assert_eq!(frame, [-1.0 / 128.0, -1.0 / 128.0]);
+
+  let path = std::env::temp_dir().join("wavefile-test-u8.wav");
+  let path = path.to_str().unwrap();
+  let spec = WaveSpec { channels: 2, sample_rate: 8000, bits_per_sample: 8, format: Format::PCM };
+
+  let mut writer = WaveWriter::create(path, spec).unwrap();
+  writer.write_frame(&[0, -1]).unwrap();
+  writer.write_frame(&[127, -128]).unwrap();
+  writer.write_frame_f64(&[0.0, -1.0]).unwrap();
+  writer.finalize().unwrap();
+
+  let file = WaveFile::open(path).unwrap();
+
+  assert_eq!(&file.bytes()[file.data_offset..file.data_offset + 6], [128, 127, 255, 0, 128, 0]);
+  assert_eq!(file.iter().collect::<Vec<_>>(), [[0, -1], [127, -128], [0, -128]]);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_int24_unpacking() {
+  // enough samples for whole vectors and a remainder, of both signs.
+  let data     = (0..37 * 3).map(|i| (i * 77 + 5) as u8).collect::<Vec<_>>();
+  let mut out  = vec![0; 37];
+
+  decode::decoder(Format::PCM, 3)(&data, &mut out);
+
+  for (sample, bytes) in out.iter().zip(data.chunks(3)) {
+    assert_eq!(*sample, ((bytes[0] as i32) | (bytes[1] as i32) << 8 | (bytes[2] as i32) << 16) << 8 >> 8);
+  }
+
+  let mut floats = vec![0.0; 37];
+
+  decode::float_decoder(Format::PCM, 3)(&data, &mut floats);
+  assert_eq!(floats, out.iter().map(|&s| s as f32 / 8388608.0).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_int_to_float() {
+  // enough samples for whole vectors and a remainder, of both signs.
+  let data = (0..37 * 4).map(|i| (i * 77 + 5) as u8).collect::<Vec<_>>();
+
+  for &(bytes, scale) in &[(1, 128.0), (2, 32768.0), (4, 2147483648.0)] {
+    let count      = data.len() / bytes;
+    let mut ints   = vec![0; count];
+    let mut floats = vec![0.0; count];
+
+    decode::decoder(Format::PCM, bytes)(&data, &mut ints);
+    decode::float_decoder(Format::PCM, bytes)(&data, &mut floats);
+    assert_eq!(floats, ints.iter().map(|&s| s as f32 * (1.0 / scale as f32)).collect::<Vec<_>>());
+  }
+}
+
+#[test]
+fn test_read_frames_f32() {
+  let wav        = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let mut buffer = vec![0.0; 3001 * wav.channels()];
+  let frames     = wav.read_frames_f32(1000, &mut buffer);
+
+  assert_eq!(frames, 3001);
+  for (i, frame) in wav.iter().skip(1000).take(frames).enumerate() {
+    for (c, sample) in frame.iter().enumerate() {
+      assert_eq!(buffer[i * wav.channels() + c], *sample as f32 / 8388608.0);
+    }
+  }
+  assert_eq!(wav.read_frames_f32(wav.len() - 1, &mut buffer), 1);
+
+  let wav    = WaveFile::open("./fixtures/test-f32le.wav").unwrap();
+  let frames = wav.read_frames_f32(0, &mut buffer);
+
+  for (i, frame) in wav.iter().take(frames).enumerate() {
+    for (c, sample) in frame.iter().enumerate() {
+      assert!((buffer[i * wav.channels() + c] - *sample as f32 / 2147483647.0).abs() < 1e-6);
+    }
+  }
+}
+
+#[cfg(feature = "bytemuck")]
+#[test]
+fn test_typed_samples() {
+  for &bits in &[16u16, 32] {
+    let path = std::env::temp_dir().join(format!("wavefile-test-typed-{}.wav", bits));
+    let spec = WaveSpec { channels: 2, sample_rate: 8000, bits_per_sample: bits, format: Format::PCM };
+    let mut writer = WaveWriter::create(path.to_str().unwrap(), spec).unwrap();
+
+    for i in 0..100 {
+      writer.write_frame(&[i, -i]).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let file    = WaveFile::open(path.to_str().unwrap()).unwrap();
+    let decoded = file.iter().flatten().collect::<Vec<_>>();
+
+    if bits == 16 {
+      let samples = file.samples_i16().unwrap().iter().map(|&s| s as i32).collect::<Vec<_>>();
+
+      assert_eq!(samples, decoded);
+      assert!(file.samples_i32().is_none());
+    } else {
+      assert_eq!(file.samples_i32().unwrap(), &decoded[..]);
+      assert!(file.samples_i16().is_none());
+    }
+    std::fs::remove_file(path).unwrap();
+  }
+
+  let file = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+
+  assert!(file.samples_i16().is_none());
+  assert!(file.samples_i32().is_none());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_read_all_parallel() {
+  for path in &["./fixtures/test-s24le.wav", "./fixtures/test-u8.wav", "./fixtures/test-f32le.wav"] {
+    let file = WaveFile::open(*path).unwrap();
+
+    assert_eq!(file.read_all_parallel().unwrap(), file.iter().flatten().collect::<Vec<_>>());
+  }
+}
+
+#[test]
+fn test_prefetching() {
+  let file   = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let frames = file.iter().collect::<Vec<_>>();
+
+  assert!(file.prefetching(2).eq(frames.into_iter()));
+
+  // dropped early, the worker must not block forever.
+  let file = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+
+  assert_eq!(file.prefetching(1).take(10).count(), 10);
+}
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+#[test]
+fn test_open_io_uring() {
+  let mapped = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let read   = WaveFile::open_io_uring("./fixtures/test-s24le.wav", WaveFileOptions::new()).unwrap();
+
+  assert_eq!(read.len(), mapped.len());
+  assert!(read.iter().eq(mapped.iter()));
+}
+
+#[test]
+fn test_lazy_metadata() {
+  let lazy = WaveFileOptions::new().lazy_metadata(true);
+
+  for path in &["./fixtures/test-info.wav", "./fixtures/test-markers.wav", "./fixtures/test-bext.wav"] {
+    let eager = WaveFile::open(*path).unwrap();
+    let file  = WaveFile::open_with(*path, lazy.clone()).unwrap();
+
+    assert_eq!(file.tags(),       eager.tags());
+    assert_eq!(file.cue_points(), eager.cue_points());
+    assert_eq!(file.markers(),    eager.markers());
+    assert_eq!(file.regions(),    eager.regions());
+    assert_eq!(file.bext(),       eager.bext());
+    assert_eq!(file.validate(),   eager.validate());
+  }
+
+  // a malformed cue chunk only shows once it's asked for.
+  let path      = std::env::temp_dir().join("wavefile-test-lazy.wav");
+  let mut bytes = std::fs::read("./fixtures/test-markers.wav").unwrap();
+  let cue       = bytes.windows(4).position(|w| w == b"cue ").unwrap();
+
+  bytes[cue + 8..cue + 12].copy_from_slice(&1000u32.to_le_bytes());
+  std::fs::write(&path, &bytes).unwrap();
+
+  let file = WaveFile::open_with(path.to_str().unwrap(), lazy).unwrap();
+
+  assert!(file.cue_points().is_empty());
+  assert!(file.markers().is_empty());
+
+  // but is still reported.
+  let malformed = file.validate().into_iter().filter(|issue| match *issue {
+    Issue::MalformedMetadata { ref error } => error.contains("\"cue \" chunk"),
+    _                                      => false
+  });
+
+  assert_eq!(malformed.count(), 1);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_buffer_sizes() {
+  let path   = "./fixtures/test-s24le.wav";
+  let frames = WaveFile::open(path).unwrap().iter().collect::<Vec<_>>();
+
+  for &samples in &[0, 1, 7, 1 << 20] {
+    let options = WaveFileOptions::new().block_samples(samples).read_block_size(4097);
+    let file    = WaveFile::open_with(path, options.clone()).unwrap();
+
+    assert!(file.iter().eq(frames.iter().cloned()));
+    assert!(file.prefetching(2).take(10000).eq(frames.iter().take(10000).cloned()));
+
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    assert!(WaveFile::open_io_uring(path, options).unwrap().iter().eq(frames.iter().cloned()));
+  }
+
+  let copy       = std::env::temp_dir().join("wavefile-test-capacity.wav");
+  let spec       = WaveSpec { channels: 2, sample_rate: 48000, bits_per_sample: 24, format: Format::PCM };
+  let mut writer = WaveWriter::create_with_capacity(copy.to_str().unwrap(), spec, 5).unwrap();
+
+  for frame in frames.iter().take(1000) {
+    writer.write_frame(frame).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  assert!(WaveFile::open(copy.to_str().unwrap()).unwrap().iter().eq(frames.iter().take(1000).cloned()));
+  std::fs::remove_file(copy).unwrap();
+}
+
+#[test]
+fn test_batch_convert() {
+  let dir    = std::env::temp_dir();
+  let pcm    = dir.join("wavefile-test-batch-16.wav");
+  let widened  = dir.join("wavefile-test-batch-f32.wav");
+  let source = "./fixtures/test-s24le.wav";
+  let inputs = [
+    (source,                     pcm.to_str().unwrap()),
+    ("./fixtures/missing.wav",   "/dev/null"),
+    ("./fixtures/test-u8.wav",   widened.to_str().unwrap())
+  ];
+  let spec   = WaveSpec { channels: 2, sample_rate: 48000, bits_per_sample: 16, format: Format::PCM };
+
+  let results = batch::convert(&inputs, spec, 2);
+  let file    = WaveFile::open(source).unwrap();
+
+  assert_eq!(results.len(), 3);
+  assert_eq!(*results[0].as_ref().unwrap(), file.len() as u64);
+  assert!(results[1].is_err());
+  assert_eq!(*results[2].as_ref().unwrap(), file.len() as u64);
+
+  let copy = WaveFile::open(pcm.to_str().unwrap()).unwrap();
+
+  assert_eq!(copy.bits_per_sample(), 16);
+  assert!(copy.iter().eq(file.iter().map(|frame| frame.iter().map(|s| s >> 8).collect::<Vec<_>>())));
+
+  // the channel count must match.
+  let mono = WaveSpec { channels: 1, format: Format::IEEEFloat, bits_per_sample: 32, ..spec };
+
+  match batch::convert(&[(source, widened.to_str().unwrap())], mono, 1).pop() {
+    Some(Err(WaveError::Unsupported(_))) => { },
+    other => panic!("expected an unsupported conversion, got {:?}", other)
+  }
+
+  std::fs::remove_file(pcm).unwrap();
+  std::fs::remove_file(widened).unwrap();
+}
+
+#[test]
+fn test_borrowed_metadata() {
+  let file = WaveFile::open("./fixtures/test-ixml.wav").unwrap();
+  let body = file.chunk_data(*b"iXML").unwrap();
+
+  // the document is the chunk's body, not a copy of it.
+  assert_eq!(file.ixml().unwrap().as_ptr(), body.as_ptr());
+  assert_eq!(file.read_chunk(*b"iXML").unwrap(), body);
+  assert!(file.chunk_data(*b"zzzz").is_none());
+
+  let file = WaveFile::open("./fixtures/test-id3.wav").unwrap();
+
+  assert_eq!(file.id3().unwrap(), file.chunk_data(*b"id3 ").unwrap());
+
+  // text that isn't UTF-8 is still read.
+  let mut bytes = std::fs::read("./fixtures/test-ixml.wav").unwrap();
+  let ixml      = bytes.windows(4).position(|w| w == b"iXML").unwrap();
+
+  bytes[ixml + 8] = 0xff;
+
+  let file = WaveFile::from_bytes(&bytes, WaveFileOptions::new()).unwrap();
+
+  assert!(file.ixml().unwrap().starts_with('\u{fffd}'));
+}
+
+#[cfg(feature = "hound")]
+#[test]
+fn test_hound() {
+  let spec = WaveSpec { channels: 2, sample_rate: 44100, bits_per_sample: 32, format: Format::IEEEFloat };
+
+  assert_eq!(WaveSpec::from(hound::WavSpec::from(spec)), spec);
+
+  for path in &["./fixtures/test-s24le.wav", "./fixtures/test-f32le.wav"] {
+    let file       = WaveFile::open(*path).unwrap();
+    let copy       = std::env::temp_dir().join("wavefile-test-hound.wav");
+    let mut writer = hound::WavWriter::create(&copy, file.info().into()).unwrap();
+
+    file.write_to_hound(&mut writer).unwrap();
+    writer.finalize().unwrap();
+
+    let reread = WaveFile::open(copy.to_str().unwrap()).unwrap();
+
+    assert_eq!(reread.data_format(), file.data_format());
+    assert!(reread.iter().eq(file.iter()));
+    std::fs::remove_file(copy).unwrap();
+  }
+}
+
+#[cfg(feature = "symphonia")]
+#[test]
+fn test_symphonia() {
+  use symphonia_core::codecs::CodecParameters;
+  use symphonia_core::codecs::audio::AudioDecoder;
+  use symphonia_core::formats::{FormatReader,SeekMode,SeekTo};
+  use symphonia_core::io::MediaSourceStream;
+  use symphonia_core::units::Timestamp;
+
+  for path in &["./fixtures/test-s24le.wav", "./fixtures/test-f32le.wav", "./fixtures/test-info.wav"] {
+    let file       = WaveFile::open(*path).unwrap();
+    let source     = MediaSourceStream::new(Box::new(std::fs::File::open(path).unwrap()), Default::default());
+    let mut reader = SymphoniaReader::try_new(source, Default::default()).unwrap();
+    let params     = match reader.tracks()[0].codec_params {
+      Some(CodecParameters::Audio(ref params)) => params.clone(),
+      _                                        => panic!("missing audio parameters")
+    };
+
+    assert_eq!(params.sample_rate, Some(file.sample_rate() as u32));
+    assert_eq!(params.channels.as_ref().unwrap().count(), file.channels());
+    assert_eq!(reader.metadata().current().is_some(), !file.tags().is_empty());
+
+    let mut decoder = SymphoniaDecoder::try_new(&params, &Default::default()).unwrap();
+    let mut decoded = Vec::new();
+    let mut samples = Vec::<f32>::new();
+
+    while let Some(packet) = reader.next_packet().unwrap() {
+      decoder.decode(&packet).unwrap().copy_to_vec_interleaved(&mut samples);
+      decoded.extend_from_slice(&samples);
+    }
+
+    let mut expected = vec![0.0; file.len() * file.channels()];
+
+    file.read_frames_f32_into(0, &mut expected);
+    assert_eq!(decoded, expected);
+
+    let seeked = reader.seek(SeekMode::Accurate, SeekTo::Timestamp { ts: Timestamp::new(10), track_id: 0 }).unwrap();
+    let packet = reader.next_packet().unwrap().unwrap();
+
+    assert_eq!(seeked.actual_ts, Timestamp::new(10));
+    assert_eq!(packet.pts, Timestamp::new(10));
+  }
+}
+
+#[cfg(feature = "cpal")]
+#[test]
+fn test_cpal_playback() {
+  use cpal::Sample;
+
+  let file     = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let config   = file.info().stream_config();
+  let channels = file.channels();
+
+  assert_eq!(config.channels as usize, file.channels());
+  assert_eq!(config.sample_rate as usize, file.sample_rate());
+
+  let mut expected = vec![0.0; file.len() * file.channels()];
+
+  file.read_frames_f32_into(0, &mut expected);
+
+  let mut playback = file.playback();
+  let mut played   = Vec::new();
+  let mut out      = vec![0i16; 1000 * channels];
+
+  while !playback.is_finished() {
+    playback.fill(&mut out);
+    played.extend_from_slice(&out);
+  }
+
+  assert_eq!(playback.position() * channels, expected.len());
+  assert!(played[expected.len()..].iter().all(|&s| s == 0));
+  assert!(played.iter().zip(&expected).all(|(&s, &e)| s == i16::from_sample(e)));
+}
+
+#[cfg(feature = "rodio")]
+#[test]
+fn test_rodio_source() {
+  use rodio::Source;
+  use std::time::Duration;
+
+  let file     = WaveFile::open("./fixtures/test-f32le.wav").unwrap();
+  let channels = file.channels();
+  let rate     = file.sample_rate();
+  let frames   = file.len();
+
+  let mut expected = vec![0.0; frames * channels];
+
+  file.read_frames_f32_into(0, &mut expected);
+
+  let mut source = WaveFile::open("./fixtures/test-f32le.wav").unwrap().into_source().unwrap();
+
+  assert_eq!(source.channels().get() as usize, channels);
+  assert_eq!(source.sample_rate().get() as usize, rate);
+  assert_eq!(source.total_duration(), Some(Duration::from_secs_f64(frames as f64 / rate as f64)));
+  assert_eq!(source.size_hint(), (expected.len(), Some(expected.len())));
+
+  source.try_seek(Duration::from_secs(1)).unwrap();
+  assert!(source.by_ref().take(10).eq(expected[rate * channels..].iter().cloned().take(10)));
+
+  source.try_seek(Duration::from_secs(0)).unwrap();
+  assert!(source.by_ref().eq(expected.iter().cloned()));
+  assert!(source.is_exhausted());
+
+  let mut bytes = std::fs::read("./fixtures/test-f32le.wav").unwrap();
+
+  // the sample rate of the `fmt ` chunk, which can't be zero in an open file.
+  bytes[24..28].copy_from_slice(&[0; 4]);
+  assert!(WaveFile::from_bytes(&bytes, WaveFileOptions::default()).is_err());
+}
+
+#[cfg(feature = "ndarray")]
+#[test]
+fn test_to_ndarray() {
+  for path in &["./fixtures/test-s24le.wav", "./fixtures/test-u8.wav"] {
+    let file   = WaveFile::open(*path).unwrap();
+    let matrix = file.to_ndarray().unwrap();
+
+    assert_eq!(matrix.dim(), (file.len(), file.channels()));
+
+    let mut frame = vec![0.0; file.channels()];
+
+    for &index in &[0, file.len() / 2, file.len() - 1] {
+      file.read_frames_f32_into(index, &mut frame);
+      assert_eq!(matrix.row(index).to_vec(), frame);
+    }
+  }
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_record_batches() {
+  use arrow_array::{Array,Float32Array,Float64Array,UInt64Array};
+
+  let file     = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let channels = file.channels();
+  let batches  = file.record_batches(1000).collect::<Vec<_>>();
+
+  assert_eq!(batches.len(), file.len().div_ceil(1000));
+  assert_eq!(batches.iter().map(|batch| batch.num_rows()).sum::<usize>(), file.len());
+
+  let mut expected = vec![0.0; 1000 * channels];
+
+  file.read_frames_f32_into(3000, &mut expected);
+
+  let batch  = &batches[3];
+  let frames = batch.column(0).as_any().downcast_ref::<UInt64Array>().unwrap();
+  let times  = batch.column(1).as_any().downcast_ref::<Float64Array>().unwrap();
+
+  assert_eq!(batch.schema(), file.arrow_schema());
+  assert_eq!(frames.value(0), 3000);
+  assert_eq!(times.value(0), 3000.0 / file.sample_rate() as f64);
+
+  for channel in 0..channels {
+    let samples = batch.column(2 + channel).as_any().downcast_ref::<Float32Array>().unwrap();
+
+    assert_eq!(samples.len(), 1000);
+    assert_eq!(samples.value(7), expected[7 * channels + channel]);
+  }
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_write_parquet() {
+  use parquet::file::reader::{FileReader,SerializedFileReader};
+
+  let file = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let path = std::env::temp_dir().join("wavefile-test.parquet");
+
+  file.write_parquet(std::fs::File::create(&path).unwrap()).unwrap();
+
+  let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+
+  assert_eq!(reader.metadata().file_metadata().num_rows() as usize, file.len());
+  assert_eq!(reader.metadata().file_metadata().schema_descr().num_columns(), 2 + file.channels());
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_export_csv() {
+  let file     = WaveFile::open("./fixtures/test-f32le.wav").unwrap();
+  let channels = file.channels();
+  let mut csv  = Vec::new();
+
+  file.export_csv(&mut csv, 100..5100).unwrap();
+
+  let csv   = String::from_utf8(csv).unwrap();
+  let lines = csv.lines().collect::<Vec<_>>();
+
+  assert_eq!(lines.len(), 5001);
+  assert_eq!(lines[0].split(',').count(), channels + 1);
+
+  let mut frame = vec![0.0; channels];
+
+  file.read_frames_f32_into(4200, &mut frame);
+
+  let fields = lines[4101].split(',').collect::<Vec<_>>();
+
+  assert_eq!(fields[0], format!("{:.6}", 4200.0 / file.sample_rate() as f64));
+  assert_eq!(fields[1..].iter().map(|field| field.parse::<f32>().unwrap()).collect::<Vec<_>>(), frame);
+
+  let mut tail = Vec::new();
+
+  file.export_csv(&mut tail, file.len() - 2..file.len() + 10).unwrap();
+  assert_eq!(String::from_utf8(tail).unwrap().lines().count(), 3);
+}
+
+#[test]
+fn test_raw_readers() {
+  let file     = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let channels = file.channels();
+  let mut f32s = Vec::new();
+  let mut s16s = Vec::new();
+
+  file.as_f32le_reader().read_to_end(&mut f32s).unwrap();
+  file.as_s16le_reader().read_to_end(&mut s16s).unwrap();
+
+  let mut expected = vec![0.0; file.len() * channels];
+
+  file.read_frames_f32_into(0, &mut expected);
+  assert_eq!(f32s.len(), expected.len() * 4);
+  assert_eq!(s16s.len(), expected.len() * 2);
+
+  let mut f32s = Cursor::new(f32s);
+  let mut s16s = Cursor::new(s16s);
+
+  for &sample in &expected {
+    assert_eq!(f32s.read_f32::<LittleEndian>().unwrap(), sample);
+    assert_eq!(s16s.read_i16::<LittleEndian>().unwrap() as f32, (sample * 32768.0).round().clamp(-32768.0, 32767.0));
+  }
+
+  let wav     = WaveFile::open("./fixtures/test-u8.wav").unwrap();
+  let mut raw = wav.as_s16le_reader();
+  let mut buf = [0; 3];
+
+  // short reads still return every byte.
+  let mut total = 0;
+
+  loop {
+    let read = raw.read(&mut buf).unwrap();
+
+    if read == 0 {
+      break;
+    }
+    total += read;
+  }
+  assert_eq!(total, wav.len() * wav.channels() * 2);
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_tracing() {
+  use std::sync::{Arc,Mutex};
+  use tracing::{Event,Metadata,Subscriber};
+  use tracing::field::{Field,Visit};
+  use tracing::span::{Attributes,Id,Record};
+
+  /// Records the names of spans and the messages of events.
+  struct Recorder(Arc<Mutex<Vec<String>>>);
+
+  impl Visit for Recorder {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+      if field.name() == "message" {
+        self.0.lock().unwrap().push(format!("{:?}", value));
+      }
+    }
+  }
+
+  impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata) -> bool { true }
+    fn new_span(&self, span: &Attributes) -> Id {
+      self.0.lock().unwrap().push(span.metadata().name().to_string());
+      Id::from_u64(1)
+    }
+    fn record(&self, _: &Id, _: &Record) { }
+    fn record_follows_from(&self, _: &Id, _: &Id) { }
+    fn event(&self, event: &Event) {
+      event.record(&mut Recorder(self.0.clone()));
+    }
+    fn enter(&self, _: &Id) { }
+    fn exit(&self, _: &Id) { }
+  }
+
+  let log = Arc::new(Mutex::new(Vec::new()));
+
+  tracing::subscriber::with_default(Recorder(log.clone()), || {
+    let file = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+
+    file.read_frames(0, &mut [0; 16]);
+    assert!(WaveFile::open("./Cargo.toml").is_err());
+  });
+
+  let log = log.lock().unwrap();
+
+  for expected in &["open", "scan_chunks", "chunk", "opened file", "read block", "rejected file"] {
+    assert!(log.iter().any(|entry| entry == expected), "missing {}", expected);
+  }
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn test_wasm_wave_file() {
+  let bytes = std::fs::read("./fixtures/test-f32le.wav").unwrap();
+  let file  = WaveFile::open("./fixtures/test-f32le.wav").unwrap();
+  let wasm  = match WasmWaveFile::new(&bytes) {
+    Ok(wasm) => wasm,
+    Err(_)   => panic!("couldn't parse the fixture")
+  };
+
+  assert_eq!(wasm.channels(), file.channels());
+  assert_eq!(wasm.sample_rate(), file.sample_rate());
+  assert_eq!(wasm.frames(), file.len());
+  assert_eq!(wasm.format(), "IEEEFloat");
+
+  let mut expected = vec![0.0; 100 * file.channels()];
+
+  file.read_frames_f32_into(1000, &mut expected);
+  assert_eq!(wasm.read_frames(1000, 100), expected);
+  assert_eq!(wasm.read_frames(file.len() - 10, 100).len(), 10 * file.channels());
+  assert!(wasm.read_frames(file.len() + 10, 100).is_empty());
+}
+
+#[cfg(feature = "uniffi")]
+#[test]
+fn test_ffi() {
+  use ffi::{FfiError,FfiWaveFile};
+
+  let file    = WaveFile::open("./fixtures/test-markers.wav").unwrap();
+  let markers = FfiWaveFile::open("./fixtures/test-markers.wav".into()).unwrap();
+
+  assert_eq!(markers.markers().len(), file.markers().len());
+  assert_eq!(markers.regions().len(), file.regions().len());
+  assert_eq!(markers.regions()[0].label, file.regions()[0].label);
+
+  let file = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let ffi  = FfiWaveFile::open("./fixtures/test-s24le.wav".into()).unwrap();
+  let info = ffi.info();
+
+  assert_eq!(info.channels as usize, file.channels());
+  assert_eq!(info.frames as usize, file.len());
+
+  let clip = FfiWaveFile::from_bytes(ffi.extract(100, 500).unwrap()).unwrap();
+
+  assert_eq!(clip.info().frames, 500);
+  assert_eq!(clip.info().format, info.format);
+  assert_eq!(clip.read_frames(0, 500), ffi.read_frames(100, 500));
+  assert_eq!(ffi.read_frames(info.frames - 5, 100).len(), 5 * file.channels());
+
+  match FfiWaveFile::from_bytes(b"RIFF".to_vec()) {
+    Err(FfiError::Invalid(_)) => { },
+    _                         => panic!("parsed a truncated header")
+  }
+}
+
+#[test]
+fn test_sfz_region() {
+  let file = WaveFile::open("./fixtures/test-sampler.wav").unwrap();
+
+  assert_eq!(file.sfz_region("pad.wav"), "<region>\nsample=pad.wav\n\
+    pitch_keycenter=60\ntune=-50\n\
+    lokey=48\nhikey=72\nlovel=1\nhivel=127\nvolume=-3\n\
+    loop_mode=loop_continuous\nloop_type=forward\nloop_start=8\nloop_end=63\n");
+
+  let file = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+
+  assert_eq!(file.sfz_region("plain.wav"), "<region>\nsample=plain.wav\n");
+}
+
+#[cfg(feature = "embedded-io")]
+#[test]
+fn test_embedded_io() {
+  use embedded_io::{ErrorType,Read,Seek,SeekFrom};
+
+  struct Card { bytes: Vec<u8>, position: usize }
+
+  impl ErrorType for Card {
+    type Error = embedded_io::ErrorKind;
+  }
+
+  impl Read for Card {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+      let count = buf.len().min(self.bytes.len() - self.position);
+
+      buf[..count].copy_from_slice(&self.bytes[self.position..self.position + count]);
+      self.position += count;
+      Ok(count)
+    }
+  }
+
+  impl Seek for Card {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+      self.position = match pos {
+        SeekFrom::Start(offset)   => offset as usize,
+        SeekFrom::End(offset)     => (self.bytes.len() as i64 + offset) as usize,
+        SeekFrom::Current(offset) => (self.position as i64 + offset) as usize
+      }.min(self.bytes.len());
+      Ok(self.position as u64)
+    }
+  }
+
+  let open = |path: &str| {
+    EmbeddedWaveFile::open(Card { bytes: std::fs::read(path).unwrap(), position: 0 })
+  };
+
+  let file         = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let mut embedded = open("./fixtures/test-s24le.wav").unwrap();
+  let mut expected = vec![0.0; 300 * file.channels()];
+  let mut actual   = vec![0.0; 300 * file.channels()];
+
+  assert_eq!(embedded.len(), file.len());
+  assert_eq!(embedded.data_format(), file.data_format());
+  assert_eq!(file.read_frames_f32_into(1000, &mut expected),
+             embedded.read_frames_f32_into(1000, &mut actual).unwrap());
+  assert_eq!(actual, expected);
+  assert_eq!(embedded.read_frames_f32_into(file.len() - 10, &mut actual).unwrap(), 10);
+
+  let embedded = open("./fixtures/test-sampler.wav").unwrap();
+
+  assert_eq!(embedded.sampler().unwrap().midi_unity_note, 60);
+
+  let mut bytes = std::fs::read("./fixtures/test-s24le.wav").unwrap();
+
+  bytes.truncate(bytes.len() - 7);
+
+  let embedded = EmbeddedWaveFile::open(Card { bytes, position: 0 }).unwrap();
+
+  assert!(embedded.info().truncated);
+  assert_eq!(embedded.len(), file.len() - 2);
+
+  assert!(open("./fixtures/test-markers.wav").is_ok());
+  assert!(EmbeddedWaveFile::open(Card { bytes: b"RIFF\0\0\0\0WAVE".to_vec(), position: 0 }).is_err());
+}
+
+#[test]
+fn test_feed_fingerprint() {
+  let path = std::env::temp_dir().join("wavefile-test-fingerprint.wav");
+
+  {
+    let spec       = WaveSpec { channels: 2, sample_rate: 22050, bits_per_sample: 16, format: Format::PCM };
+    let mut writer = WaveWriter::create(path.to_str().unwrap(), spec).unwrap();
+
+    for frame in 0..22050 {
+      let sample = if frame % 2 == 0 { 8192 } else { 16384 };
+
+      writer.write_frame(&[sample, -4096]).unwrap();
+    }
+    writer.finalize().unwrap();
+  }
+
+  let file        = WaveFile::open(path.to_str().unwrap()).unwrap();
+  let mut samples = Vec::new();
+
+  file.feed_fingerprint(|block| samples.extend_from_slice(block)).unwrap();
+
+  // pairs of frames average to 12288, and then with the other channel.
+  assert_eq!(samples.len(), 11025);
+  assert!(samples.iter().all(|&sample| sample == 4096));
+  std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_wave_file() {
+  use std::future::poll_fn;
+  use std::pin::Pin;
+  use futures_core::Stream;
+
+  let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+  let file    = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let wav     = runtime.block_on(AsyncWaveFile::open("./fixtures/test-s24le.wav")).unwrap();
+
+  assert_eq!(wav.len(), file.len());
+  assert_eq!(wav.channels(), 2);
+
+  let mut frames = wav.frames();
+
+  for expected in file.iter().take(5000) {
+    let frame = runtime.block_on(poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)));
+
+    assert_eq!(frame.unwrap().unwrap(), expected);
+  }
+
+  // a file whose audio was cut short ends early.
+  let markers   = WaveFile::open("./fixtures/test-markers.wav").unwrap();
+  let data      = markers.chunks().find(|chunk| &chunk.0 == b"data").unwrap().1;
+  let mut bytes = std::fs::read("./fixtures/test-markers.wav").unwrap();
+
+  bytes.truncate(data + 10 * markers.info().block_align as usize);
+
+  let wav        = runtime.block_on(AsyncWaveFile::from_reader(std::io::Cursor::new(bytes), WaveFileOptions::new())).unwrap();
+  let mut frames = wav.frames();
+  let mut count  = 0;
+
+  while let Some(frame) = runtime.block_on(poll_fn(|cx| Pin::new(&mut frames).poll_next(cx))) {
+    frame.unwrap();
+    count += 1;
+  }
+
+  assert_eq!(count, 10);
+
+  let riff = std::io::Cursor::new(b"RIFF\0\0\0\0WAVE".to_vec());
+
+  assert!(runtime.block_on(AsyncWaveFile::from_reader(riff, WaveFileOptions::new())).is_err());
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_wave_writer() {
+  let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+  let spec    = WaveSpec { channels: 2, sample_rate: 8000, bits_per_sample: 32, format: Format::IEEEFloat };
+  let mut out = Vec::new();
+
+  {
+    let mut writer = AsyncWaveWriter::new(std::io::Cursor::new(&mut out), spec).unwrap();
+
+    writer.add_chunk(*b"note", b"before").unwrap();
+    for block in 0..10 {
+      let samples = (0..2000).map(|sample| (block * 2000 + sample) << 16).collect::<Vec<_>>();
+
+      runtime.block_on(writer.write_frames(&samples)).unwrap();
+    }
+    writer.add_chunk(*b"note", b"after").unwrap();
+
+    assert!(runtime.block_on(writer.write_frames(&[1, 2, 3])).is_err());
+    assert_eq!(writer.len(), 10000);
+    runtime.block_on(writer.finalize()).unwrap();
+  }
+
+  let file = WaveFile::from_bytes(&out, WaveFileOptions::new()).unwrap();
+
+  assert_eq!(file.len(), 10000);
+  assert_eq!(file.fact_frames, Some(10000));
+  assert_eq!(file.chunks().filter(|chunk| &chunk.0 == b"note").count(), 2);
+  assert_eq!(file.iter().nth(4321).unwrap(), [8642 << 16, 8643 << 16]);
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn test_futures_reader() {
+  use std::future::poll_fn;
+  use std::io::SeekFrom;
+  use std::pin::Pin;
+  use std::task::{Context,Poll};
+  use futures_core::Stream;
+
+  // yields to the executor before every read and seek.
+  struct Slow { bytes: Vec<u8>, position: usize, ready: bool }
+
+  impl Slow {
+    fn yield_once(&mut self, cx: &mut Context) -> bool {
+      self.ready = !self.ready;
+      if !self.ready {
+        cx.waker().wake_by_ref();
+      }
+      self.ready
+    }
+  }
+
+  impl futures_io::AsyncRead for Slow {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+      if !self.yield_once(cx) {
+        return Poll::Pending;
+      }
+
+      let count = buf.len().min(self.bytes.len() - self.position).min(100);
+
+      buf[..count].copy_from_slice(&self.bytes[self.position..self.position + count]);
+      self.position += count;
+      Poll::Ready(Ok(count))
+    }
+  }
+
+  impl futures_io::AsyncSeek for Slow {
+    fn poll_seek(mut self: Pin<&mut Self>, cx: &mut Context, pos: SeekFrom) -> Poll<std::io::Result<u64>> {
+      if !self.yield_once(cx) {
+        return Poll::Pending;
+      }
+
+      self.position = match pos {
+        SeekFrom::Start(offset)   => offset as usize,
+        SeekFrom::End(offset)     => (self.bytes.len() as i64 + offset) as usize,
+        SeekFrom::Current(offset) => (self.position as i64 + offset) as usize
+      };
+      Poll::Ready(Ok(self.position as u64))
+    }
+  }
+
+  let runtime    = tokio::runtime::Builder::new_current_thread().build().unwrap();
+  let file       = WaveFile::open("./fixtures/test-markers.wav").unwrap();
+  let slow       = Slow { bytes: std::fs::read("./fixtures/test-markers.wav").unwrap(), position: 0, ready: false };
+  let wav        = runtime.block_on(AsyncWaveFile::from_reader(FuturesReader(slow), WaveFileOptions::new())).unwrap();
+  let mut frames = wav.frames();
+
+  for expected in file.iter() {
+    let frame = runtime.block_on(poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)));
+
+    assert_eq!(frame.unwrap().unwrap(), expected);
+  }
+
+  assert!(runtime.block_on(poll_fn(|cx| Pin::new(&mut frames).poll_next(cx))).is_none());
+}
+
+#[test]
+fn test_progress() {
+  let file        = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let mut reports = Vec::new();
+
+  {
+    let mut progress = Progress::new(|done, total| reports.push((done, total)));
+
+    assert_eq!(file.read_all_with_progress(&mut progress).unwrap(), file.iter().flatten().collect::<Vec<_>>());
+  }
+
+  assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+  assert_eq!(reports.last(), Some(&(file.len(), file.len())));
+
+  // cancelling part way through stops at the next block.
+  let cancel       = CancelToken::new();
+  let mut blocks   = 0;
+  let token        = cancel.clone();
+  let mut progress = Progress::new(|_, _| {
+    blocks += 1;
+    if blocks == 3 {
+      token.cancel();
+    }
+  }).cancel_token(cancel.clone());
+
+  match file.bit_usage_with_progress(&mut progress) {
+    Err(WaveError::Cancelled) => { },
+    other                     => panic!("expected cancellation, got {:?}", other)
+  }
+  drop(progress);
+  assert_eq!(blocks, 3);
+
+  let mut progress = Progress::new(|_, _| ());
+
+  assert_eq!(file.bit_usage_with_progress(&mut progress).unwrap(), file.bit_usage());
+
+  let destination  = std::env::temp_dir().join("wavefile-test-progress.wav");
+  let inputs       = [("./fixtures/test-s24le.wav", destination.to_str().unwrap())];
+  let spec         = WaveSpec { channels: 2, sample_rate: 48000, bits_per_sample: 16, format: Format::PCM };
+  let mut files    = Vec::new();
+
+  {
+    let mut progress = Progress::new(|done, total| files.push((done, total)));
+
+    assert!(batch::convert_with_progress(&inputs, spec, 1, &mut progress)[0].is_ok());
+  }
+  assert_eq!(files, [(1, 1)]);
+
+  let mut progress = Progress::new(|_, _| ()).cancel_token(cancel);
+
+  match batch::convert_with_progress(&inputs, spec, 1, &mut progress).pop() {
+    Some(Err(WaveError::Cancelled)) => { },
+    other                           => panic!("expected cancellation, got {:?}", other)
+  }
+
+  std::fs::remove_file(destination).unwrap();
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_stream_backpressure() {
+  use std::cell::Cell;
+  use std::future::poll_fn;
+  use std::pin::Pin;
+  use std::rc::Rc;
+  use std::task::{Context,Poll};
+  use futures_core::Stream;
+  use tokio::io::ReadBuf;
+
+  // a connection that can't seek, delivering a little at a time.
+  struct Socket { bytes: Vec<u8>, sent: Rc<Cell<usize>>, ready: bool }
+
+  impl tokio::io::AsyncRead for Socket {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<std::io::Result<()>> {
+      self.ready = !self.ready;
+      if !self.ready {
+        cx.waker().wake_by_ref();
+        return Poll::Pending;
+      }
+
+      let sent  = self.sent.get();
+      let count = buf.remaining().min(self.bytes.len() - sent).min(500);
+
+      buf.put_slice(&self.bytes[sent..sent + count]);
+      self.sent.set(sent + count);
+      Poll::Ready(Ok(()))
+    }
+  }
+
+  let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+  let file    = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  let bytes   = std::fs::read("./fixtures/test-s24le.wav").unwrap();
+  let data    = file.chunks().find(|chunk| &chunk.0 == b"data").unwrap().1;
+  let stride  = file.info().block_align as usize;
+  let read    = |bytes: Vec<u8>, capacity| {
+    let sent   = Rc::new(Cell::new(0));
+    let socket = Socket { bytes, sent: sent.clone(), ready: false };
+    let wav    = runtime.block_on(AsyncWaveFile::from_stream(socket, WaveFileOptions::new())).unwrap();
+
+    (wav.frames_buffered(capacity), sent)
+  };
+
+  let (mut frames, sent) = read(bytes.clone(), 64);
+
+  assert_eq!(frames.capacity(), 64);
+  for (index, expected) in file.iter().enumerate() {
+    let frame = runtime.block_on(poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)));
+
+    assert_eq!(frame.unwrap().unwrap(), expected);
+    // the stream never reads more than its capacity ahead of the consumer.
+    assert!(frames.buffered() <= 64);
+    assert!(sent.get() <= data + (index + 1 + 64) * stride);
+  }
+  assert!(runtime.block_on(poll_fn(|cx| Pin::new(&mut frames).poll_next(cx))).is_none());
+
+  // a live stream leaves the size of its audio open, and runs until the
+  // connection closes.
+  let mut live = bytes.clone();
+
+  live[data - 4..data].copy_from_slice(&[0; 4]);
+  live.truncate(data + 100 * stride + 1);
+
+  let (mut frames, _) = read(live, 16);
+  let mut count       = 0;
+
+  while let Some(frame) = runtime.block_on(poll_fn(|cx| Pin::new(&mut frames).poll_next(cx))) {
+    frame.unwrap();
+    count += 1;
+  }
+  assert_eq!(count, 100);
+
+  // a connection dropped before the audio the header promised is an error.
+  let mut cut = bytes;
+
+  cut.truncate(data + 100 * stride);
+
+  let (mut frames, _) = read(cut, 16);
+  let mut results     = Vec::new();
+
+  while let Some(frame) = runtime.block_on(poll_fn(|cx| Pin::new(&mut frames).poll_next(cx))) {
+    results.push(frame.is_ok());
+  }
+  assert_eq!(results.len(), 101);
+  assert!(!results[100]);
+}
+
+#[test]
+fn test_thread_safety() {
+  use std::sync::Arc;
+  use std::thread;
+
+  fn shareable<T: Send + Sync>() {}
+  fn sendable<T: Send>() {}
+
+  shareable::<WaveFile>();
+  shareable::<WaveInfo>();
+  shareable::<WaveFileIterator>();
+  shareable::<CancelToken>();
+  sendable::<Prefetching>();
+  sendable::<WaveWriter<std::fs::File>>();
+  sendable::<Progress>();
+
+  let wav = Arc::new(WaveFile::open("./fixtures/test-s24le.wav").unwrap());
+
+  assert_eq!(wav.frame_ranges(0).len(), 1);
+  assert_eq!(wav.frame_ranges(wav.len() + 5).len(), wav.len());
+
+  let ranges = wav.frame_ranges(7);
+
+  assert_eq!(ranges.len(), 7);
+  assert_eq!(ranges[0].start, 0);
+  assert_eq!(ranges[6].end, wav.len());
+  assert!(ranges.windows(2).all(|pair| pair[0].end == pair[1].start));
+
+  let workers = ranges.into_iter().map(|range| {
+    let wav = wav.clone();
+
+    thread::spawn(move || {
+      let mut buffer = vec![0; range.len() * wav.channels()];
+
+      assert_eq!(wav.read_frames(range.start, &mut buffer), range.len());
+      buffer
+    })
+  }).collect::<Vec<_>>();
+  let samples = workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect::<Vec<_>>();
+
+  assert_eq!(samples, wav.iter().flatten().collect::<Vec<_>>());
+  assert_eq!(wav.info(), WaveFile::open("./fixtures/test-s24le.wav").unwrap().info());
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_blocking() {
+  use std::future::poll_fn;
+  use std::pin::Pin;
+  use std::sync::Arc;
+  use futures_core::Stream;
+
+  let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+  let options = WaveFileOptions::new().block_samples(1000);
+  let wav     = Arc::new(runtime.block_on(blocking::open_with("./fixtures/test-s24le.wav", options)).unwrap());
+  let samples = wav.iter().flatten().collect::<Vec<_>>();
+
+  assert!(runtime.block_on(blocking::open("./fixtures/missing.wav")).is_err());
+  assert_eq!(runtime.block_on(blocking::read_frames(wav.clone(), 10..20)).unwrap(), &samples[20..40]);
+  assert_eq!(runtime.block_on(blocking::read_frames(wav.clone(), wav.len() - 1..wav.len() + 5)).unwrap().len(), 2);
+
+  let mut blocks = blocking::blocks(wav.clone());
+  let expected   = wav.len().div_ceil(500);
+  let mut read   = Vec::new();
+
+  assert_eq!(blocks.size_hint(), (expected, Some(expected)));
+  while let Some(block) = runtime.block_on(poll_fn(|cx| Pin::new(&mut blocks).poll_next(cx))) {
+    let block = block.unwrap();
+
+    assert!(block.len() <= 1000);
+    read.extend(block);
+  }
+
+  assert_eq!(read, samples);
+}
+
+#[test]
+fn test_realtime_reader() {
+  let file       = WaveFile::open("./fixtures/test-markers.wav").unwrap();
+  let samples    = file.iter().flatten().collect::<Vec<_>>();
+  let channels   = file.channels();
+  let mut reader = RealtimeReader::open("./fixtures/test-markers.wav", 100).unwrap();
+  let mut buffer = vec![0; 250 * channels];
+  let mut read   = Vec::new();
+
+  assert_eq!(reader.len(), file.len());
+  assert_eq!(reader.max_frames(), 100);
+  assert_eq!(reader.read_frames(&mut buffer[..channels - 1]).unwrap(), 0);
+
+  loop {
+    let frames = reader.read_frames(&mut buffer).unwrap();
+
+    assert!(frames <= 100);
+    if frames == 0 {
+      break;
+    }
+    read.extend_from_slice(&buffer[..frames * channels]);
+  }
+
+  assert_eq!(read, samples);
+  assert_eq!(reader.position(), file.len());
+
+  reader.seek(10).unwrap();
+  assert_eq!(reader.read_frames(&mut buffer[..3 * channels]).unwrap(), 3);
+  assert_eq!(&buffer[..3 * channels], &samples[10 * channels..13 * channels]);
+
+  let mut floats   = vec![0.0; 3 * channels];
+  let mut expected = vec![0.0; 3 * channels];
+
+  reader.seek(file.len() - 2).unwrap();
+  file.read_frames_f32_into(file.len() - 2, &mut expected);
+  assert_eq!(reader.read_frames_f32_into(&mut floats).unwrap(), 2);
+  assert_eq!(&floats[..2 * channels], &expected[..2 * channels]);
+  assert_eq!(reader.read_frames_f32_into(&mut floats).unwrap(), 0);
+
+  reader.seek(file.len() + 10).unwrap();
+  assert_eq!(reader.position(), file.len());
+}
+
+#[test]
+fn test_gain() {
+  use transcode::{Clipping,Gain,Pipeline};
+
+  let dir      = std::env::temp_dir();
+  let src      = "./fixtures/test-s24le.wav";
+  let file     = WaveFile::open(src).unwrap();
+  let quieter  = dir.join("wavefile-gain-quieter.wav");
+  let louder   = dir.join("wavefile-gain-louder.wav");
+  let floats   = dir.join("wavefile-gain-floats.wav");
+
+  let mut pipeline = Pipeline::new().then(Gain::linear(0.5));
+
+  assert_eq!(pipeline.transcode(src, quieter.to_str().unwrap(), 24, Format::PCM).unwrap(), file.len() as u64);
+
+  let halved = WaveFile::open(quieter.to_str().unwrap()).unwrap();
+
+  for (original, frame) in file.iter().zip(halved.iter()) {
+    for (a, b) in original.iter().zip(frame.iter()) {
+      assert!((a - 2 * b).abs() <= 1);
+    }
+  }
+
+  // 3 dB more than the headroom above the file's peak.
+  let peak = file.iter().flatten().map(|sample| sample.abs()).max().unwrap() as f64 / 8388608.0;
+  let db   = 20.0 * (1.0 / peak).log10() + 3.0;
+
+  let mut clip = Pipeline::new().then(Gain::new(db));
+
+  clip.transcode(src, louder.to_str().unwrap(), 16, Format::PCM).unwrap();
+  assert_eq!(WaveFile::open(louder.to_str().unwrap()).unwrap().iter().flatten().map(|sample| sample.abs()).max(), Some(32768));
+
+  let mut strict = Pipeline::new().then(Gain::new(db).clipping(Clipping::Error));
+
+  match strict.transcode(src, louder.to_str().unwrap(), 16, Format::PCM) {
+    Err(WaveError::Clipped { frame }) => assert!(frame < file.len() as u64),
+    other                             => panic!("expected clipping, got {:?}", other)
+  }
+
+  let mut headroom = Pipeline::new().then(Gain::new(db).clipping(Clipping::Headroom));
+
+  headroom.transcode(src, floats.to_str().unwrap(), 32, Format::IEEEFloat).unwrap();
+
+  let floats      = WaveFile::open(floats.to_str().unwrap()).unwrap();
+  let mut samples = vec![0.0; floats.len() * floats.channels()];
+
+  floats.read_frames_f32_into(0, &mut samples);
+  assert!(samples.iter().any(|sample| sample.abs() > 1.0));
+
+  for path in &[quieter, louder, dir.join("wavefile-gain-floats.wav")] {
+    std::fs::remove_file(path).unwrap();
+  }
+}
+
+#[test]
+fn test_fades() {
+  use std::time::Duration;
+  use transcode::{Curve,FadeIn,FadeOut,Layout,Pipeline,Transform};
+
+  let layout   = Layout { channels: 2, sample_rate: 100, frames: 50 };
+  let mut fade = FadeIn::new(Duration::from_millis(100));
+  let mut ones = vec![1.0; 2 * 50];
+
+  fade.prepare(layout).unwrap();
+  fade.process(&mut ones).unwrap();
+  assert_eq!(&ones[..4], &[0.0, 0.0, 0.1, 0.1]);
+  assert!((ones[18] - 0.9).abs() < 1e-9);
+  assert!(ones[20..].iter().all(|&sample| sample == 1.0));
+
+  // the fade carries on from block to block.
+  let mut fade  = FadeOut::new(Duration::from_millis(100)).curve(Curve::EqualPower);
+  let mut ones  = vec![1.0; 2 * 45];
+  let mut block = vec![1.0; 2 * 5];
+
+  fade.prepare(layout).unwrap();
+  fade.process(&mut ones).unwrap();
+  fade.process(&mut block).unwrap();
+  ones.extend(block);
+  assert!(ones[..80].iter().all(|&sample| sample == 1.0));
+  assert_eq!(ones[98], 0.0);
+  assert!((ones[80] - Curve::EqualPower.gain(0.9)).abs() < 1e-9);
+  assert!((Curve::EqualPower.gain(0.5).powi(2) * 2.0 - 1.0).abs() < 1e-9);
+
+  let src      = "./fixtures/test-s24le.wav";
+  let file     = WaveFile::open(src).unwrap();
+  let dst      = std::env::temp_dir().join("wavefile-fades.wav");
+  let second   = Duration::from_secs(1);
+  let mut clip = Pipeline::new().then(FadeIn::new(second)).then(FadeOut::new(second).curve(Curve::EqualPower));
+
+  clip.transcode(src, dst.to_str().unwrap(), 24, Format::PCM).unwrap();
+
+  let faded  = WaveFile::open(dst.to_str().unwrap()).unwrap();
+  let frames = faded.iter().collect::<Vec<_>>();
+  let rate   = file.sample_rate();
+
+  assert_eq!(frames.len(), file.len());
+  assert_eq!(frames[0], vec![0, 0]);
+  assert_eq!(frames[frames.len() - 1], vec![0, 0]);
+  assert_eq!(frames[rate..file.len() - rate], file.iter().skip(rate).take(file.len() - 2 * rate).collect::<Vec<_>>()[..]);
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_reverse() {
+  let src      = "./fixtures/test-markers.wav";
+  let dst      = std::env::temp_dir().join("wavefile-reverse.wav");
+  let file     = WaveFile::open(src).unwrap();
+
+  reverse(src, dst.to_str().unwrap()).unwrap();
+
+  let reversed = WaveFile::open(dst.to_str().unwrap()).unwrap();
+  let mut forwards = file.iter().collect::<Vec<_>>();
+
+  forwards.reverse();
+  assert_eq!(reversed.info(), file.info());
+  assert_eq!(reversed.iter().collect::<Vec<_>>(), forwards);
+  assert!(reversed.cue_points().is_empty());
+
+  // float files keep their fact chunk.
+  let src = "./fixtures/test-f32le.wav";
+
+  reverse(src, dst.to_str().unwrap()).unwrap();
+
+  let file     = WaveFile::open(src).unwrap();
+  let reversed = WaveFile::open(dst.to_str().unwrap()).unwrap();
+
+  assert_eq!(reversed.fact_frames(), file.fact_frames());
+  assert_eq!(reversed.iter().next(), file.iter().last());
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_extract() {
+  use std::time::Duration;
+
+  let src    = "./fixtures/test-s24le.wav";
+  let dst    = std::env::temp_dir().join("wavefile-extract.wav");
+  let file   = WaveFile::open(src).unwrap();
+  let frames = file.iter().collect::<Vec<_>>();
+
+  assert_eq!(extract(src, dst.to_str().unwrap(), 100..350).unwrap(), 250);
+
+  let clip = WaveFile::open(dst.to_str().unwrap()).unwrap();
+
+  assert_eq!(clip.iter().collect::<Vec<_>>(), &frames[100..350]);
+  assert_eq!(clip.data_format(), file.data_format());
+
+  let rate = file.sample_rate() as u64;
+
+  assert_eq!(extract_time(src, dst.to_str().unwrap(), Duration::from_millis(500)..Duration::from_secs(60)).unwrap(),
+             file.len() - rate as usize / 2);
+  assert_eq!(WaveFile::open(dst.to_str().unwrap()).unwrap().iter().next().unwrap(), frames[rate as usize / 2]);
+
+  assert_eq!(extract(src, dst.to_str().unwrap(), file.len() + 1..file.len() + 5).unwrap(), 0);
+  assert!(WaveFile::open(dst.to_str().unwrap()).unwrap().is_empty());
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_concat() {
+  let dir     = std::env::temp_dir();
+  let dst     = dir.join("wavefile-concat.wav");
+  let dst     = dst.to_str().unwrap();
+  let markers = "./fixtures/test-markers.wav";
+  let file    = WaveFile::open(markers).unwrap();
+
+  assert_eq!(concat(&[markers, markers], dst).unwrap(), 2 * file.len());
+
+  let joined = WaveFile::open(dst).unwrap();
+
+  assert_eq!(joined.iter().collect::<Vec<_>>(), file.iter().chain(file.iter()).collect::<Vec<_>>());
+  assert!(joined.cue_points().is_empty());
+
+  concat_with(&[markers, markers], dst, ConcatOptions::new().merge_markers(true)).unwrap();
+
+  let labels   = WaveFile::open(dst).unwrap().labels();
+  let original = file.labels();
+  let seconds  = file.len() as f64 / file.sample_rate() as f64;
+
+  assert_eq!(labels.len(), 2 * original.len());
+  assert_eq!(labels[original.len()].text, original[0].text);
+  assert!((labels[original.len()].start - original[0].start - seconds).abs() < 1e-3);
+
+  // formats differing only in sample size are converted on request.
+  let s24 = "./fixtures/test-s24le.wav";
+  let s16 = "./fixtures/test-adm.wav";
+
+  assert!(concat(&[s24, s16], dst).is_err());
+  assert!(concat_with(&[s24, markers], dst, ConcatOptions::new().convert(true)).is_err());
+  assert!(concat::<&str, _>(&[], dst).is_err());
+
+  let first  = WaveFile::open(s24).unwrap();
+  let second = WaveFile::open(s16).unwrap();
+
+  concat_with(&[s24, s16], dst, ConcatOptions::new().convert(true)).unwrap();
+
+  let joined   = WaveFile::open(dst).unwrap();
+  let expected = first.iter().chain(second.iter().map(|frame| frame.iter().map(|s| s << 8).collect())).collect::<Vec<_>>();
+
+  assert_eq!(joined.bits_per_sample(), 24);
+  assert_eq!(joined.iter().collect::<Vec<_>>(), expected);
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_split_by_duration() {
+  use std::time::Duration;
+
+  let dir      = std::env::temp_dir();
+  let src      = "./fixtures/test-s24le.wav";
+  let file     = WaveFile::open(src).unwrap();
+  let mut seen = Vec::new();
+  let paths    = split_by_duration(src, Duration::from_millis(100), |segment| {
+    seen.push(segment.clone());
+    dir.join(format!("wavefile-split-{:03}.wav", segment.index)).to_str().unwrap().to_owned()
+  }).unwrap();
+  let length   = file.sample_rate() / 10;
+
+  assert_eq!(paths.len(), file.len().div_ceil(length));
+  assert_eq!(seen[1].frames, length..2 * length);
+  assert_eq!(seen.last().unwrap().frames.end, file.len());
+
+  let mut frames = Vec::new();
+
+  for path in &paths {
+    let segment = WaveFile::open(path.as_str()).unwrap();
+
+    assert!(segment.len() <= length);
+    frames.extend(segment.iter());
+  }
+
+  assert_eq!(frames, file.iter().collect::<Vec<_>>());
+  assert!(split_by_duration(src, Duration::ZERO, |_| String::new()).is_err());
+
+  for path in &paths {
+    std::fs::remove_file(path).unwrap();
+  }
+}
+
+#[test]
+fn test_split_at_cues() {
+  let dir     = std::env::temp_dir();
+  let src     = dir.join("wavefile-cues.wav");
+  let markers = WaveFile::open("./fixtures/test-markers.wav").unwrap();
+
+  std::fs::copy("./fixtures/test-markers.wav", &src).unwrap();
+
+  let paths       = split_at_cues(src.to_str().unwrap()).unwrap();
+  let mut cuts    = markers.markers().iter().map(|marker| marker.position as usize).filter(|&cut| cut > 0).collect::<Vec<_>>();
+  let mut lengths = Vec::new();
+
+  cuts.sort();
+  cuts.dedup();
+  assert_eq!(paths.len(), cuts.len() + 1);
+  assert!(paths[0].starts_with(dir.join("wavefile-cues-00").to_str().unwrap()));
+
+  for path in &paths {
+    lengths.push(WaveFile::open(path.as_str()).unwrap().len());
+  }
+
+  assert_eq!(lengths.iter().sum::<usize>(), markers.len());
+  assert_eq!(lengths[0], cuts[0]);
+
+  let mut labels = Vec::new();
+  let named      = split_at_cues_with(src.to_str().unwrap(), |segment| {
+    labels.push(segment.label.clone());
+    dir.join(format!("wavefile-cue-{}.wav", segment.index)).to_str().unwrap().to_owned()
+  }).unwrap();
+
+  let first = markers.markers().into_iter().find(|marker| marker.position as usize == cuts[0]).unwrap();
+
+  assert_eq!(labels[1], first.label.or(first.note));
+
+  for path in paths.iter().chain(&named) {
+    std::fs::remove_file(path).unwrap();
+  }
+  std::fs::remove_file(src).unwrap();
+}
+
+#[test]
+fn test_split_on_silence() {
+  use std::time::Duration;
+
+  let dir  = std::env::temp_dir();
+  let src  = dir.join("wavefile-silences.wav");
+  let spec = WaveSpec { channels: 2, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+  let mut writer = WaveWriter::create(src.to_str().unwrap(), spec).unwrap();
+
+  // a track, a second of hiss at -60 dBFS, a track with a short pause, hiss.
+  for frame in 0..28000 {
+    let sample = match frame {
+      0..=7999 | 16000..=19999 | 20400..=23999 => 8000,
+      _                                          => 32 * if frame % 2 == 0 { 1 } else { -1 }
+    };
+
+    writer.write_frame(&[sample, -sample]).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  let mut seen = Vec::new();
+  let paths    = split_on_silence_with(src.to_str().unwrap(), -50.0, Duration::from_millis(500), |segment| {
+    seen.push(segment.frames.clone());
+    dir.join(format!("wavefile-track-{}.wav", segment.index)).to_str().unwrap().to_owned()
+  }).unwrap();
+
+  assert_eq!(seen, vec![0..8000, 16000..24000]);
+  assert_eq!(WaveFile::open(paths[1].as_str()).unwrap().len(), 8000);
+
+  // a threshold below the hiss finds no silence.
+  assert_eq!(split_on_silence(src.to_str().unwrap(), -70.0, Duration::from_millis(500)).unwrap().len(), 1);
+  let unnamed = split_on_silence(src.to_str().unwrap(), -50.0, Duration::from_millis(500)).unwrap();
+
+  assert!(unnamed[0].ends_with("wavefile-silences-00.wav"));
+
+  for path in paths.iter().chain(&unnamed) {
+    std::fs::remove_file(path).unwrap();
+  }
+  std::fs::remove_file(src).unwrap();
+}
+
+#[test]
+fn test_extract_channel() {
+  let dst = std::env::temp_dir().join("wavefile-channel.wav");
+  let dst = dst.to_str().unwrap();
+
+  for src in &["./fixtures/test-s24le.wav", "./fixtures/test-f32le.wav", "./fixtures/test-u8.wav"] {
+    let file = WaveFile::open(*src).unwrap();
+
+    assert_eq!(extract_channel(*src, 1, dst).unwrap(), file.len());
+
+    let mono = WaveFile::open(dst).unwrap();
+
+    assert_eq!(mono.channels(), 1);
+    assert_eq!(mono.sample_rate(), file.sample_rate());
+    assert_eq!(mono.bits_per_sample(), file.bits_per_sample());
+    assert_eq!(mono.info().block_align as usize, file.info().block_align as usize / 2);
+    assert_eq!(mono.iter().map(|frame| frame[0]).collect::<Vec<_>>(),
+               file.iter().map(|frame| frame[1]).collect::<Vec<_>>());
+    assert!(extract_channel(*src, 2, dst).is_err());
+  }
+
+  // the right channel keeps its speaker.
+  assert_eq!(WaveFile::open("./fixtures/test-f32le.wav").unwrap().info().channel_mask, Some(0x3));
+  extract_channel("./fixtures/test-f32le.wav", 1, dst).unwrap();
+  assert_eq!(WaveFile::open(dst).unwrap().info().channel_mask, Some(0x2));
+
+  // stereo frames padded to six bytes are written packed.
+  let src  = std::env::temp_dir().join("wavefile-channel-padded.wav");
+  let src  = src.to_str().unwrap();
+  let spec = WaveSpec { channels: 3, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+  let mut writer = WaveWriter::create(src, spec).unwrap();
+
+  for frame in &[[100, -200, 7], [300, -400, 7], [500, -600, 7]] {
+    writer.write_frame(frame).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  let mut bytes = std::fs::read(src).unwrap();
+  LittleEndian::write_u16(&mut bytes[22..24], 2);
+  std::fs::write(src, &bytes).unwrap();
+
+  assert_eq!(extract_channel(src, 1, dst).unwrap(), 3);
+
+  let mono = WaveFile::open_strict(dst).unwrap();
+
+  assert_eq!(mono.info().block_align, 2);
+  assert_eq!(mono.info().byte_rate, 16000);
+  assert_eq!(mono.iter().collect::<Vec<_>>(), [[-200], [-400], [-600]]);
+  std::fs::remove_file(src).unwrap();
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_downmix() {
+  use transcode::{Clipping,Downmix,Layout,Pipeline,Transform};
+
+  let src  = "./fixtures/test-s24le.wav";
+  let file = WaveFile::open(src).unwrap();
+  let dst  = std::env::temp_dir().join("wavefile-downmix.wav");
+
+  let mut pipeline = Pipeline::new().then(Downmix::new().clipping(Clipping::Headroom));
+
+  assert_eq!(pipeline.transcode(src, dst.to_str().unwrap(), 32, Format::IEEEFloat).unwrap(), file.len() as u64);
+
+  let mono        = WaveFile::open(dst.to_str().unwrap()).unwrap();
+  let mut samples = vec![0.0; mono.len()];
+
+  mono.read_frames_f32_into(0, &mut samples);
+  assert_eq!(mono.channels(), 1);
+
+  for (frame, mixed) in file.iter().zip(samples) {
+    let expected = (frame[0] + frame[1]) as f64 / 8388608.0 * 10f64.powf(-3.0 / 20.0);
+
+    assert!((mixed as f64 - expected).abs() < 1e-6);
+  }
+
+  // a matrix can keep more than one channel, and must fit the input.
+  let layout    = Layout { channels: 3, sample_rate: 8000, frames: 1 };
+  let mut mix   = Downmix::matrix(vec![vec![1.0, 0.0, 0.5], vec![0.0, 1.0, 0.5]]);
+  let mut frame = vec![0.2, 0.4, 0.6];
+
+  assert_eq!(mix.prepare(layout).unwrap().channels, 2);
+  mix.process(&mut frame).unwrap();
+  assert!((frame[0] - 0.5).abs() < 1e-9 && (frame[1] - 0.7).abs() < 1e-9);
+  assert!(Downmix::matrix(vec![vec![0.5, 0.5]]).prepare(layout).is_err());
+
+  let mut strict = Downmix::matrix(vec![vec![1.0, 1.0]]).clipping(Clipping::Error);
+  let mut loud   = vec![0.25, 0.25, 0.75, 0.5];
+
+  strict.prepare(Layout { channels: 2, ..layout }).unwrap();
+  match strict.process(&mut loud) {
+    Err(WaveError::Clipped { frame }) => assert_eq!(frame, 1),
+    other                             => panic!("expected clipping, got {:?}", other)
+  }
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_upmix() {
+  use transcode::{Downmix,Layout,Pipeline,Transform,Upmix};
+
+  let src  = "./fixtures/test-markers.wav";
+  let file = WaveFile::open(src).unwrap();
+  let dst  = std::env::temp_dir().join("wavefile-upmix.wav");
+
+  let mut pipeline = Pipeline::new().then(Upmix::new(4).gain(1, -6.0).gain(3, -200.0));
+
+  assert_eq!(pipeline.transcode(src, dst.to_str().unwrap(), 16, Format::PCM).unwrap(), file.len() as u64);
+
+  let quad = WaveFile::open(dst.to_str().unwrap()).unwrap();
+
+  assert_eq!(quad.channels(), 4);
+
+  for (original, frame) in file.iter().zip(quad.iter()) {
+    assert_eq!(frame[0], original[0]);
+    assert_eq!(frame[2], original[0]);
+    assert!((frame[1] as f64 - original[0] as f64 * 10f64.powf(-6.0 / 20.0)).abs() <= 1.0);
+    assert_eq!(frame[3], 0);
+  }
+
+  let layout    = Layout { channels: 1, sample_rate: 8000, frames: 2 };
+  let mut route = Upmix::route(3, 1);
+  let mut block = vec![0.5, -0.25];
+
+  assert_eq!(route.prepare(layout).unwrap().channels, 3);
+  route.process(&mut block).unwrap();
+  assert_eq!(block, vec![0.0, 0.5, 0.0, 0.0, -0.25, 0.0]);
+
+  // stereo audio is mixed down first.
+  assert!(Upmix::new(2).prepare(Layout { channels: 2, ..layout }).is_err());
+  assert!(Pipeline::new().then(Downmix::new()).then(Upmix::new(2)).prepare(Layout { channels: 2, ..layout }).is_ok());
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_remap_channels() {
+  let dst = std::env::temp_dir().join("wavefile-remap.wav");
+  let dst = dst.to_str().unwrap();
+
+  for src in &["./fixtures/test-s24le.wav", "./fixtures/test-f32le.wav", "./fixtures/test-u8.wav"] {
+    let file = WaveFile::open(*src).unwrap();
+
+    assert_eq!(remap_channels(*src, dst, &[1, 0, 1]).unwrap(), file.len());
+
+    let remapped = WaveFile::open(dst).unwrap();
+
+    assert_eq!(remapped.channels(), 3);
+    assert_eq!(remapped.info().block_align as usize, file.info().block_align as usize / 2 * 3);
+    assert_eq!(remapped.iter().collect::<Vec<_>>(),
+               file.iter().map(|frame| vec![frame[1], frame[0], frame[1]]).collect::<Vec<_>>());
+    assert!(remap_channels(*src, dst, &[0, 2]).is_err());
+    assert!(remap_channels(*src, dst, &[]).is_err());
+  }
+
+  // speakers are kept only while in their defined order.
+  let float = "./fixtures/test-f32le.wav";
+
+  remap_channels(float, dst, &[1, 0]).unwrap();
+  assert_eq!(WaveFile::open(dst).unwrap().info().channel_mask, Some(0));
+  remap_channels(float, dst, &[0, 1]).unwrap();
+  assert_eq!(WaveFile::open(dst).unwrap().info().channel_mask, Some(0x3));
+
+  // stereo frames padded to six bytes are written packed.
+  let src  = std::env::temp_dir().join("wavefile-remap-padded.wav");
+  let src  = src.to_str().unwrap();
+  let spec = WaveSpec { channels: 3, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+  let mut writer = WaveWriter::create(src, spec).unwrap();
+
+  for frame in &[[100, -200, 7], [300, -400, 7], [500, -600, 7]] {
+    writer.write_frame(frame).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  let mut bytes = std::fs::read(src).unwrap();
+  LittleEndian::write_u16(&mut bytes[22..24], 2);
+  std::fs::write(src, &bytes).unwrap();
+
+  assert_eq!(remap_channels(src, dst, &[1, 0, 1, 1]).unwrap(), 3);
+
+  let remapped = WaveFile::open_strict(dst).unwrap();
+
+  assert_eq!(remapped.info().block_align, 8);
+  assert_eq!(remapped.info().byte_rate, 64000);
+  assert_eq!(remapped.iter().collect::<Vec<_>>(),
+             [[-200, 100, -200, -200], [-400, 300, -400, -400], [-600, 500, -600, -600]]);
+
+  remap_channels(src, dst, &[0]).unwrap();
+  assert_eq!(WaveFile::open_strict(dst).unwrap().iter().collect::<Vec<_>>(), [[100], [300], [500]]);
+  std::fs::remove_file(src).unwrap();
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_mid_side() {
+  use transcode::{Downmix,Layout,MidSide,Pipeline,Transform};
+
+  let layout      = Layout { channels: 2, sample_rate: 8000, frames: 2 };
+  let mut encode  = MidSide::encode();
+  let mut decode  = MidSide::decode();
+  let mut samples = vec![1.0, 1.0, 0.5, -0.25];
+
+  encode.prepare(layout).unwrap();
+  decode.prepare(layout).unwrap();
+  encode.process(&mut samples).unwrap();
+  assert_eq!(samples, vec![1.0, 0.0, 0.125, 0.375]);
+  decode.process(&mut samples).unwrap();
+  assert_eq!(samples, vec![1.0, 1.0, 0.5, -0.25]);
+  assert!(MidSide::encode().prepare(Layout { channels: 1, ..layout }).is_err());
+
+  // muting the side channel leaves the mid in both channels.
+  let src  = "./fixtures/test-s24le.wav";
+  let file = WaveFile::open(src).unwrap();
+  let dst  = std::env::temp_dir().join("wavefile-mid-side.wav");
+
+  let mut pipeline = Pipeline::new()
+    .then(MidSide::encode())
+    .then(Downmix::matrix(vec![vec![1.0, 0.0], vec![0.0, 0.0]]))
+    .then(MidSide::decode());
+
+  pipeline.transcode(src, dst.to_str().unwrap(), 24, Format::PCM).unwrap();
+
+  for (original, frame) in file.iter().zip(WaveFile::open(dst.to_str().unwrap()).unwrap().iter()) {
+    assert_eq!(frame[0], frame[1]);
+    assert!((2 * frame[0] - original[0] - original[1]).abs() <= 2);
+  }
+
+  let mut unchanged = Pipeline::new().then(MidSide::encode()).then(MidSide::decode());
+
+  unchanged.transcode(src, dst.to_str().unwrap(), 24, Format::PCM).unwrap();
+  assert_eq!(WaveFile::open(dst.to_str().unwrap()).unwrap().iter().collect::<Vec<_>>(), file.iter().collect::<Vec<_>>());
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_invert() {
+  use transcode::{Invert,Layout,Pipeline,Transform};
+
+  let layout      = Layout { channels: 3, sample_rate: 8000, frames: 2 };
+  let mut invert  = Invert::channels(&[2]);
+  let mut samples = vec![0.5, -0.25, 1.0, 0.0, 0.75, -1.0];
+
+  invert.prepare(layout).unwrap();
+  invert.process(&mut samples).unwrap();
+  assert_eq!(samples, vec![0.5, -0.25, -1.0, 0.0, 0.75, 1.0]);
+  assert!(Invert::channels(&[3]).prepare(layout).is_err());
+
+  let src  = "./fixtures/test-s24le.wav";
+  let file = WaveFile::open(src).unwrap();
+  let dst  = std::env::temp_dir().join("wavefile-invert.wav");
+
+  Pipeline::new().then(Invert::all()).transcode(src, dst.to_str().unwrap(), 24, Format::PCM).unwrap();
+
+  for (original, frame) in file.iter().zip(WaveFile::open(dst.to_str().unwrap()).unwrap().iter()) {
+    // full scale negative comes back as the largest positive sample.
+    assert_eq!(frame, original.iter().map(|&sample| (-sample).min(8388607)).collect::<Vec<_>>());
+  }
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_resample() {
+  use std::f64::consts::PI;
+  use transcode::{Layout,Pipeline,Resample,Transform};
+
+  fn tone(frequency: f64, rate: f64, frames: usize) -> Vec<f64> {
+    (0..frames).map(|frame| 0.5 * (2.0 * PI * frequency * frame as f64 / rate).sin()).collect()
+  }
+
+  fn resample(mut stage: Resample, rate: u32, samples: &[f64]) -> Vec<f64> {
+    let layout = stage.prepare(Layout { channels: 1, sample_rate: rate, frames: samples.len() as u64 }).unwrap();
+    let mut output = Vec::new();
+
+    for block in samples.chunks(1000) {
+      let mut block = block.to_vec();
+
+      stage.process(&mut block).unwrap();
+      output.extend(block);
+    }
+
+    stage.finish(&mut output).unwrap();
+    assert_eq!(output.len() as u64, layout.frames);
+    output
+  }
+
+  let input    = tone(1000.0, 48000.0, 48000);
+  let expected = tone(1000.0, 44100.0, 44100);
+
+  // a tone well within both bands comes out as sampled at the new rate.
+  for (stage, tolerance) in [(Resample::new(44100), 1e-4), (Resample::linear(44100), 2e-3)] {
+    let output = resample(stage, 48000, &input);
+
+    assert_eq!(output.len(), 44100);
+    assert!(output[200..44000].iter().zip(&expected[200..]).all(|(a, b)| (a - b).abs() < tolerance));
+  }
+
+  // a tone above the new Nyquist frequency is filtered out rather than
+  // aliased.
+  let rms    = |samples: &[f64]| (samples.iter().map(|sample| sample * sample).sum::<f64>() / samples.len() as f64).sqrt();
+  let sinc   = resample(Resample::new(44100), 48000, &tone(23000.0, 48000.0, 48000));
+  let linear = resample(Resample::linear(44100), 48000, &tone(23000.0, 48000.0, 48000));
+
+  assert!(rms(&sinc[200..44000]) < 1e-3);
+  assert!(rms(&linear[200..44000]) > 0.1);
+
+  let upsampled = resample(Resample::new(48000), 8000, &tone(1000.0, 8000.0, 8000));
+
+  assert_eq!(upsampled.len(), 48000);
+  assert!(upsampled[1000..47000].iter().zip(&input[1000..]).all(|(a, b)| (a - b).abs() < 1e-3));
+
+  let src  = "./fixtures/test-s24le.wav";
+  let file = WaveFile::open(src).unwrap();
+  let dst  = std::env::temp_dir().join("wavefile-resample.wav");
+
+  let mut pipeline = Pipeline::new().then(Resample::new(44100).zero_crossings(16));
+  let frames       = pipeline.transcode(src, dst.to_str().unwrap(), 16, Format::PCM).unwrap();
+  let resampled    = WaveFile::open(dst.to_str().unwrap()).unwrap();
+
+  assert_eq!(frames as usize, (file.len() * 441).div_ceil(480));
+  assert_eq!(resampled.sample_rate(), 44100);
+  assert_eq!(resampled.channels(), 2);
+  assert_eq!(resampled.len(), frames as usize);
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_dither() {
+  use transcode::{Dither,Layout,NoiseShaping,Pipeline,Transform};
+
+  let layout = Layout { channels: 1, sample_rate: 44100, frames: 100000 };
+  let lsb    = 1.0 / 32768.0;
+
+  // errors of each sample of a level of 0.3 LSB, which rounding alone
+  // would silence.
+  let errors = |shaping: NoiseShaping| {
+    let mut dither  = Dither::new(16).noise_shaping(shaping);
+    let mut samples = vec![0.3 * lsb; 100000];
+
+    dither.prepare(layout).unwrap();
+    dither.process(&mut samples).unwrap();
+    assert!(samples.iter().all(|sample| (sample / lsb).fract() == 0.0));
+    samples.iter().map(|sample| sample / lsb - 0.3).collect::<Vec<_>>()
+  };
+
+  let white = errors(NoiseShaping::Off);
+  let mean  = white.iter().sum::<f64>() / white.len() as f64;
+  let power = white.iter().map(|error| error * error).sum::<f64>() / white.len() as f64;
+
+  // the level survives on average, under noise of a quarter LSB squared.
+  assert!(mean.abs() < 0.01);
+  assert!((power - 0.25).abs() < 0.01);
+
+  // shaping moves noise out of the low frequencies.
+  let low = |errors: &[f64]| errors.chunks(16).map(|chunk| chunk.iter().sum::<f64>().powi(2)).sum::<f64>();
+
+  assert!(low(&errors(NoiseShaping::FirstOrder)) < low(&white) / 4.0);
+  assert!(low(&errors(NoiseShaping::Lipshitz)) < low(&white));
+  assert_eq!(errors(NoiseShaping::Off), white);
+
+  let src  = "./fixtures/test-s24le.wav";
+  let file = WaveFile::open(src).unwrap();
+  let dst  = std::env::temp_dir().join("wavefile-dither.wav");
+
+  Pipeline::new().then(Dither::new(16)).transcode(src, dst.to_str().unwrap(), 16, Format::PCM).unwrap();
+
+  for (original, frame) in file.iter().zip(WaveFile::open(dst.to_str().unwrap()).unwrap().iter()) {
+    for (a, b) in original.iter().zip(frame) {
+      assert!((*a as f64 / 256.0 - b as f64).abs() < 1.5);
+    }
+  }
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_normalize_peak() {
+  let dst = std::env::temp_dir().join("wavefile-normalized.wav");
+  let dst = dst.to_str().unwrap();
+
+  for src in &["./fixtures/test-s24le.wav", "./fixtures/test-f32le.wav"] {
+    let file  = WaveFile::open(*src).unwrap();
+    let peak  = |file: &WaveFile| {
+      let mut samples = vec![0.0; file.len() * file.channels()];
+
+      file.read_frames_f32_into(0, &mut samples);
+      samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs())) as f64
+    };
+    let gain  = normalize_peak(*src, dst, -1.0).unwrap();
+    let after = WaveFile::open(dst).unwrap();
+
+    assert_eq!(after.info().bits_per_sample, file.info().bits_per_sample);
+    assert_eq!(after.data_format(), file.data_format());
+    assert_eq!(after.len(), file.len());
+    assert!((20.0 * peak(&file).log10() + gain + 1.0).abs() < 1e-3);
+    assert!((20.0 * peak(&after).log10() + 1.0).abs() < 1e-3);
+    assert_eq!(after.chunk_data(*b"fmt "), file.chunk_data(*b"fmt "));
+    assert_eq!(after.info().channel_mask, file.info().channel_mask);
+  }
+
+  // metadata is kept, on the same side of the audio.
+  for src in &["./fixtures/test-markers.wav", "./fixtures/test-bext.wav"] {
+    let file = WaveFile::open(*src).unwrap();
+
+    normalize_peak(*src, dst, -1.0).unwrap();
+
+    let after = WaveFile::open(dst).unwrap();
+    let ids   = |file: &WaveFile| file.chunks().map(|chunk| chunk.0).collect::<Vec<_>>();
+
+    assert_eq!(ids(&after), ids(&file));
+    assert_eq!(after.markers(), file.markers());
+    assert_eq!(after.bext(), file.bext());
+    assert_eq!(after.tags(), file.tags());
+  }
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_loudness() {
+  use std::f64::consts::PI;
+  use loudness::Analyzer;
+
+  // seconds of a stereo tone at `dbfs`, phase shifted by `phase`.
+  fn tone(frequency: f64, dbfs: f64, phase: f64, seconds: usize) -> Vec<f64> {
+    let amplitude = 10f64.powf(dbfs / 20.0);
+
+    (0..48000 * seconds).flat_map(|frame| {
+      let sample = amplitude * (2.0 * PI * frequency * frame as f64 / 48000.0 + phase).sin();
+
+      vec![sample, sample]
+    }).collect()
+  }
+
+  // EBU Tech 3341's first case: a 1 kHz tone at -23 dBFS in both channels
+  // measures -23 LUFS.
+  let mut analyzer = Analyzer::new(2, 48000);
+
+  analyzer.add_frames(&tone(1000.0, -23.0, 0.0, 5));
+  assert!((analyzer.integrated().unwrap() + 23.0).abs() < 0.1);
+  assert!((analyzer.max_momentary().unwrap() + 23.0).abs() < 0.1);
+  assert!((analyzer.max_short_term().unwrap() + 23.0).abs() < 0.1);
+  assert!(analyzer.range().unwrap() < 0.1);
+  assert!((analyzer.true_peak().unwrap() + 23.0).abs() < 0.1);
+
+  // silence below the absolute gate doesn't count, only the blocks
+  // fading into it.
+  analyzer.add_frames(&tone(1000.0, -90.0, 0.0, 5));
+  assert!((analyzer.integrated().unwrap() + 23.0).abs() < 0.2);
+  assert_eq!(analyzer.loudness().integrated, analyzer.integrated().map(|lufs| lufs as f32));
+
+  // a quarter of the sample rate, peaking between samples 3 dB above them.
+  let mut peaks = Analyzer::new(2, 48000);
+
+  peaks.add_frames(&tone(12000.0, -6.0, PI / 4.0, 1));
+  assert!((peaks.true_peak().unwrap() + 6.0).abs() < 0.2);
+
+  let silent = Analyzer::new(2, 48000);
+
+  assert_eq!(silent.integrated(), None);
+  assert_eq!(silent.true_peak(), None);
+
+  let wav = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+
+  assert!(loudness::analyze(&wav).integrated().is_some());
+}
+
+#[test]
+fn test_normalize_loudness() {
+  let dir  = std::env::temp_dir();
+  let src  = dir.join("wavefile-quiet.wav");
+  let dst  = dir.join("wavefile-loud.wav");
+  let spec = WaveSpec { channels: 2, sample_rate: 48000, bits_per_sample: 24, format: Format::PCM };
+  let bext = WaveFile::open("./fixtures/test-bext.wav").unwrap().bext().unwrap().clone();
+  let mut writer = WaveWriter::create(src.to_str().unwrap(), spec).unwrap();
+
+  writer.set_bext(&bext).unwrap();
+  writer.set_labels(&[Label { start: 1.0, end: 2.0, text: "verse".into() }]).unwrap();
+
+  // a 1 kHz tone at -30 dBFS.
+  for frame in 0..48000 * 4 {
+    let sample = 10f64.powf(-30.0 / 20.0) * (2.0 * std::f64::consts::PI * frame as f64 / 48.0).sin();
+
+    writer.write_frame_f64(&[sample, sample]).unwrap();
+  }
+  writer.finalize().unwrap();
+
+  let (src, dst) = (src.to_str().unwrap(), dst.to_str().unwrap());
+  let gain       = normalize_loudness(src, dst, -23.0, -1.0).unwrap();
+  let measured   = loudness::analyze(&WaveFile::open(dst).unwrap());
+
+  assert!((gain - 7.0).abs() < 0.1);
+  assert!((measured.integrated().unwrap() + 23.0).abs() < 0.1);
+
+  // metadata is kept.
+  let (quiet, loud) = (WaveFile::open(src).unwrap(), WaveFile::open(dst).unwrap());
+
+  assert_eq!(loud.chunk_data(*b"fmt "), quiet.chunk_data(*b"fmt "));
+  assert_eq!(loud.chunks().map(|chunk| chunk.0).collect::<Vec<_>>(), quiet.chunks().map(|chunk| chunk.0).collect::<Vec<_>>());
+  assert_eq!(loud.bext(), Some(&bext));
+  assert_eq!(loud.labels(), quiet.labels());
+
+  // the ceiling holds the gain back.
+  let gain     = normalize_loudness(src, dst, -16.0, -25.0).unwrap();
+  let measured = loudness::analyze(&WaveFile::open(dst).unwrap());
+
+  assert!((gain - 5.0).abs() < 0.1);
+  assert!(measured.true_peak().unwrap() <= -25.0 + 0.01);
+
+  // a hundred frames are too short to measure.
+  assert!(normalize_loudness("./fixtures/test-markers.wav", dst, -23.0, -1.0).is_err());
+  std::fs::remove_file(src).unwrap();
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_dc_block() {
+  use std::f64::consts::PI;
+  use transcode::{DcBlock,DcOffset,Layout,Pipeline,Transform};
+
+  // a tone with a bias of 0.1 in the left channel and -0.05 in the right.
+  let mut samples = (0..48000 * 2).flat_map(|frame| {
+    let tone = 0.25 * (2.0 * PI * 1000.0 * frame as f64 / 48000.0).sin();
+
+    vec![tone + 0.1, tone - 0.05]
+  }).collect::<Vec<_>>();
+  let original  = samples.clone();
+  let mut block = DcBlock::new();
+
+  block.prepare(Layout { channels: 2, sample_rate: 48000, frames: 96000 }).unwrap();
+  block.process(&mut samples).unwrap();
+
+  let offsets = block.offsets();
+
+  assert!((offsets[0] - 0.1).abs() < 1e-6 && (offsets[1] + 0.05).abs() < 1e-6);
+
+  // once settled, the bias is gone and the tone is left.
+  for (channel, bias) in [0.1, -0.05].iter().enumerate() {
+    let settled = samples[48000..].iter().skip(channel).step_by(2);
+    let mean    = settled.clone().sum::<f64>() / 48000.0;
+
+    assert!(mean.abs() < 1e-3);
+    assert!(settled.zip(original[48000..].iter().skip(channel).step_by(2)).all(|(a, b)| (a - (b - bias)).abs() < 0.01));
+  }
+
+  let src    = "./fixtures/test-s24le.wav";
+  let dst    = std::env::temp_dir().join("wavefile-dc-block.wav");
+  let offset = DcOffset::new();
+
+  Pipeline::new().then(DcBlock::new().cutoff(10.0).report(offset.clone()))
+    .transcode(src, dst.to_str().unwrap(), 24, Format::PCM).unwrap();
+
+  let file = WaveFile::open(src).unwrap();
+  let mean = file.iter().map(|frame| frame[0] as f64).sum::<f64>() / file.len() as f64 / 8388608.0;
+
+  assert_eq!(offset.offsets().len(), 2);
+  assert!((offset.offsets()[0] - mean).abs() < 1e-9);
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_biquad() {
+  use std::f64::consts::PI;
+  use transcode::{Biquad,Layout,Pipeline,Transform};
+
+  // the gain of `filter` in decibels for a tone at `frequency`, once
+  // settled.
+  fn gain(mut filter: Biquad, frequency: f64) -> f64 {
+    let mut samples = (0..48000).map(|frame| (2.0 * PI * frequency * frame as f64 / 48000.0).sin() * 0.25).collect::<Vec<_>>();
+    let rms         = |samples: &[f64]| (samples.iter().map(|sample| sample * sample).sum::<f64>() / samples.len() as f64).sqrt();
+    let before      = rms(&samples[24000..]);
+
+    filter.prepare(Layout { channels: 1, sample_rate: 48000, frames: 48000 }).unwrap();
+    filter.process(&mut samples).unwrap();
+    20.0 * (rms(&samples[24000..]) / before).log10()
+  }
+
+  assert!(gain(Biquad::low_pass(1000.0), 100.0).abs() < 0.1);
+  assert!((gain(Biquad::low_pass(1000.0), 1000.0) + 3.01).abs() < 0.1);
+  assert!(gain(Biquad::low_pass(1000.0), 8000.0) < -35.0);
+  assert!(gain(Biquad::high_pass(80.0), 1000.0).abs() < 0.1);
+  assert!(gain(Biquad::high_pass(80.0), 20.0) < -20.0);
+  assert!((gain(Biquad::high_shelf(2000.0, 6.0), 15000.0) - 6.0).abs() < 0.2);
+  assert!(gain(Biquad::high_shelf(2000.0, 6.0), 100.0).abs() < 0.1);
+  assert!((gain(Biquad::low_shelf(200.0, -6.0), 30.0) + 6.0).abs() < 0.2);
+  assert!(gain(Biquad::low_shelf(200.0, -6.0), 5000.0).abs() < 0.1);
+  // a higher Q peaks at the frequency.
+  assert!(gain(Biquad::low_pass(1000.0).q(2.0), 1000.0) > 5.0);
+
+  let layout = Layout { channels: 2, sample_rate: 48000, frames: 0 };
+
+  assert!(Biquad::low_pass(24000.0).prepare(layout).is_err());
+  assert!(Biquad::low_pass(1000.0).q(0.0).prepare(layout).is_err());
+
+  let src = "./fixtures/test-s24le.wav";
+  let dst = std::env::temp_dir().join("wavefile-biquad.wav");
+
+  let mut pipeline = Pipeline::new().then(Biquad::high_pass(80.0)).then(Biquad::low_pass(16000.0));
+
+  assert_eq!(pipeline.transcode(src, dst.to_str().unwrap(), 24, Format::PCM).unwrap() as usize, WaveFile::open(src).unwrap().len());
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_concat_crossfade() {
+  use std::time::Duration;
+  use transcode::Curve;
+
+  let dir   = std::env::temp_dir();
+  let spec  = WaveSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, format: Format::PCM };
+  let paths = [dir.join("wavefile-crossfade-loud.wav"), dir.join("wavefile-crossfade-soft.wav")];
+
+  for (path, level) in paths.iter().zip(&[0.5, -0.25]) {
+    let mut writer = WaveWriter::create(path.to_str().unwrap(), spec).unwrap();
+
+    for _ in 0..800 {
+      writer.write_frame_f64(&[*level]).unwrap();
+    }
+    writer.finalize().unwrap();
+  }
+
+  let inputs = [paths[0].to_str().unwrap(), paths[1].to_str().unwrap()];
+  let dst    = dir.join("wavefile-crossfade.wav");
+  let dst    = dst.to_str().unwrap();
+  let fade   = Duration::from_millis(50);
+  let level  = |frame: usize| {
+    let mut sample = [0.0];
+
+    WaveFile::open(dst).unwrap().read_frames_f32_into(frame, &mut sample);
+    sample[0] as f64
+  };
+
+  assert_eq!(concat_with(&inputs, dst, ConcatOptions::new().crossfade(fade, Curve::Linear)).unwrap(), 1200);
+  assert_eq!(WaveFile::open(dst).unwrap().len(), 1200);
+  assert_eq!(level(399), 0.5);
+  assert!((level(600) - 0.125).abs() < 1e-4);
+  assert_eq!(level(800), -0.25);
+
+  concat_with(&inputs, dst, ConcatOptions::new().crossfade(fade, Curve::EqualPower)).unwrap();
+  assert!((level(600) - 0.25 * Curve::EqualPower.gain(0.5)).abs() < 1e-4);
+
+  // fades are cut short to the files, the second of three fading in over
+  // all of its 100 frames and so not out.
+  let markers = "./fixtures/test-markers.wav";
+  let options = ConcatOptions::new().crossfade(Duration::from_secs(1), Curve::Linear);
+
+  assert_eq!(concat_with(&[markers, markers, markers], dst, options).unwrap(), 200);
+
+  let options = ConcatOptions::new().crossfade(Duration::from_millis(5), Curve::Linear).merge_markers(true);
+
+  assert_eq!(concat_with(&[markers, markers], dst, options).unwrap(), 160);
+
+  let labels   = WaveFile::open(dst).unwrap().labels();
+  let original = WaveFile::open(markers).unwrap().labels();
+
+  assert!((labels[original.len()].start - original[0].start - 60.0 / 8000.0).abs() < 1e-3);
+
+  for path in &paths {
+    std::fs::remove_file(path).unwrap();
+  }
+  std::fs::remove_file(dst).unwrap();
+}
+
+#[test]
+fn test_strip_metadata() {
+  let path = std::env::temp_dir().join("wavefile-test-strip.wav");
+  let path = path.to_str().unwrap();
+
+  strip_metadata("./fixtures/test-info.wav", path, &[*b"DISP"]).unwrap();
+
+  let original = WaveFile::open("./fixtures/test-info.wav").unwrap();
+  let stripped = WaveFile::open(path).unwrap();
+  let ids      = stripped.chunks().map(|chunk| chunk.0).collect::<Vec<_>>();
+
+  assert_eq!(ids, [*b"fmt ", *b"DISP", *b"data"]);
+  assert!(stripped.tags().is_empty());
+  assert_eq!(stripped.title(), Some("Birds (DISP)".into()));
+  assert_eq!(stripped.iter().collect::<Vec<_>>(), original.iter().collect::<Vec<_>>());
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_write_bext_in_place() {
+  let path = std::env::temp_dir().join("wavefile-test-edit-bext.wav");
+  let path = path.to_str().unwrap();
+  std::fs::copy("./fixtures/test-bext.wav", path).unwrap();
+
+  let mut bext = WaveFile::open(path).unwrap().bext().unwrap().clone();
+  bext.description    = "Interview, take 4".into();
+  bext.coding_history = "A=PCM,F=48000,W=16\r\n".into();
+  write_bext(path, &bext).unwrap();
+
+  let file = WaveFile::open(path).unwrap();
+  assert_eq!(file.bext(), Some(&bext));
+  assert_eq!(std::fs::metadata(path).unwrap().len(), std::fs::metadata("./fixtures/test-bext.wav").unwrap().len());
+  assert_eq!(file.chunks().map(|chunk| chunk.0).collect::<Vec<_>>(), [*b"fmt ", *b"bext", *b"JUNK", *b"data"]);
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_write_info_tags_relocates() {
+  let path = std::env::temp_dir().join("wavefile-test-edit-info.wav");
+  let path = path.to_str().unwrap();
+  std::fs::copy("./fixtures/test-info.wav", path).unwrap();
+
+  let mut tags = WaveFile::open(path).unwrap().tags().clone();
+  tags.set(*b"INAM", "Morning Birds, recorded at the lake shortly after sunrise");
+  write_info_tags(path, &tags).unwrap();
+
+  let file  = WaveFile::open(path).unwrap();
+  let bytes = std::fs::read(path).unwrap();
+  let mut body  = Vec::new();
+  tags.write_to(&mut body).unwrap();
+
+  assert_eq!(file.chunks().next(), Some((*b"fmt ", 20, 16)));
+  assert_eq!(file.chunks().nth(1).unwrap().0, *b"JUNK");
+  assert!(bytes.ends_with(&body));
+  assert_eq!(&bytes[bytes.len() - body.len() - 8..][..4], b"LIST");
+  assert_eq!(LittleEndian::read_u32(&bytes[4..8]) as usize, bytes.len() - 8);
+  assert_eq!(file.tags(), &tags);
+  std::fs::remove_file(path).unwrap();
+}
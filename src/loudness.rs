@@ -0,0 +1,322 @@
+//! Loudness measurement following EBU R128 and ITU-R BS.1770, for checking
+//! and normalizing programme levels.
+//!
+//! The audio is K-weighted, and its power measured over blocks of 400 ms
+//! for momentary loudness and of 3 s for short term loudness, each starting
+//! 100 ms after the last.  Integrated loudness averages the momentary
+//! blocks, leaving out those quieter than -70 LUFS, and then those more
+//! than 10 LU quieter than the rest.  True peaks are found by oversampling.
+//!
+//! # Example
+//!
+//! ```
+//! use wavefile::WaveFile;
+//! use wavefile::loudness;
+//!
+//! let wav      = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+//! let analysis = loudness::analyze(&wav);
+//!
+//! if let Some(lufs) = analysis.integrated() {
+//!   println!("{:.1} LUFS, peaking at {:.1} dBTP", lufs, analysis.true_peak().unwrap());
+//! }
+//! ```
+
+use std::f64::consts::PI;
+
+use bext::Loudness;
+use speakers::SpeakerPosition;
+use transcode::{BlockReader,Section};
+use WaveFile;
+
+/// The quietest blocks counted, in LUFS.
+const ABSOLUTE_GATE : f64 = -70.0;
+
+/// How far below the loudness of the ungated blocks a block may be and
+/// still be counted, in LU, for integrated loudness and loudness range.
+const INTEGRATED_GATE : f64 = -10.0;
+const RANGE_GATE      : f64 = -20.0;
+
+/// The number of 100 ms segments in momentary and short term blocks.
+const MOMENTARY_SEGMENTS  : usize = 4;
+const SHORT_TERM_SEGMENTS : usize = 30;
+
+/// The number of input samples interpolated between to find true peaks.
+const TRUE_PEAK_TAPS : usize = 12;
+
+/// Measures the loudness of audio as it is added.
+///
+/// Samples are interleaved `f64`s with full scale at 1.0, as passed
+/// between the stages of a `transcode::Pipeline`.
+#[derive(Debug,Clone)]
+pub struct Analyzer {
+  channels: usize,
+  weights:  Vec<f64>,
+  /// The K-weighting filters of each channel.
+  filters:  Vec<[Section; 2]>,
+  /// The number of frames in a 100 ms segment, and in the current one.
+  segment:  usize,
+  filled:   usize,
+  /// The sum of the squared samples of each channel in the current
+  /// segment.
+  sums:     Vec<f64>,
+  /// The weighted mean square of each complete segment.
+  segments: Vec<f64>,
+  peaks:    TruePeak
+}
+
+impl Analyzer {
+  /// An analyzer for audio of `channels` channels at `sample_rate`, each
+  /// weighted equally.
+  pub fn new(channels: usize, sample_rate: u32) -> Analyzer {
+    let sample_rate = sample_rate.max(1) as f64;
+
+    Analyzer {
+      channels,
+      weights:  vec![1.0; channels],
+      filters:  vec![k_weighting(sample_rate); channels],
+      segment:  ((sample_rate / 10.0).round() as usize).max(1),
+      filled:   0,
+      sums:     vec![0.0; channels],
+      segments: Vec::new(),
+      peaks:    TruePeak::new(channels, sample_rate)
+    }
+  }
+
+  /// An analyzer for the audio of `file`, weighting its channels by their
+  /// speakers: surround channels count for 1.5 dB more and the LFE channel
+  /// not at all.  Six channels without speakers are taken to be 5.1.
+  pub fn for_file(file: &WaveFile) -> Analyzer {
+    let analyzer = Analyzer::new(file.channels(), file.sample_rate() as u32);
+    let speakers = match file.speakers() {
+      Some(speakers) => speakers,
+      None           => SpeakerPosition::decode(if file.channels() == 6 { 0x3f } else { 0 })
+    };
+
+    if speakers.len() != file.channels() {
+      return analyzer;
+    }
+
+    let weights = speakers.iter().map(|speaker| match *speaker {
+      SpeakerPosition::LowFrequency => 0.0,
+      SpeakerPosition::BackLeft | SpeakerPosition::BackRight | SpeakerPosition::BackCenter |
+      SpeakerPosition::SideLeft | SpeakerPosition::SideRight => 1.41,
+      _ => 1.0
+    }).collect::<Vec<_>>();
+
+    analyzer.channel_weights(&weights)
+  }
+
+  /// Sets the weight of the power of each channel.  Channels without a
+  /// weight are left out.
+  pub fn channel_weights(mut self, weights: &[f64]) -> Analyzer {
+    self.weights = (0..self.channels).map(|channel| weights.get(channel).cloned().unwrap_or(0.0)).collect();
+    self
+  }
+
+  /// Adds the whole frames in `samples` to the measurement.
+  pub fn add_frames(&mut self, samples: &[f64]) {
+    for frame in samples.chunks_exact(self.channels) {
+      for (channel, &sample) in frame.iter().enumerate() {
+        let [ref mut shelf, ref mut high_pass] = self.filters[channel];
+        let weighted = high_pass.process(shelf.process(sample));
+
+        self.sums[channel] += weighted * weighted;
+      }
+
+      self.peaks.add_frame(frame);
+      self.filled += 1;
+
+      if self.filled == self.segment {
+        let power = self.sums.iter().zip(&self.weights).map(|(sum, weight)| weight * sum).sum::<f64>();
+
+        self.segments.push(power / self.segment as f64);
+        self.sums.iter_mut().for_each(|sum| *sum = 0.0);
+        self.filled = 0;
+      }
+    }
+  }
+
+  /// The integrated loudness of the audio in LUFS, or `None` if no block
+  /// passes the gates, as when it is silent or shorter than 400 ms.
+  pub fn integrated(&self) -> Option<f64> {
+    let powers = self.powers(MOMENTARY_SEGMENTS);
+
+    gated(&powers, INTEGRATED_GATE).map(|gated| loudness(mean(&gated)))
+  }
+
+  /// The loudness range of the audio in LU, the spread of the loudness of
+  /// its short term blocks between the 10th and 95th percentiles, or `None`
+  /// if it has no such blocks loud enough.
+  pub fn range(&self) -> Option<f64> {
+    let powers     = self.powers(SHORT_TERM_SEGMENTS);
+    let mut levels = gated(&powers, RANGE_GATE)?.into_iter().map(loudness).collect::<Vec<_>>();
+
+    levels.sort_by(|a, b| a.total_cmp(b));
+
+    let percentile = |fraction: f64| levels[((levels.len() - 1) as f64 * fraction).round() as usize];
+
+    Some(percentile(0.95) - percentile(0.10))
+  }
+
+  /// The loudness of the loudest momentary block in LUFS.
+  pub fn max_momentary(&self) -> Option<f64> {
+    self.powers(MOMENTARY_SEGMENTS).into_iter().reduce(f64::max).map(loudness)
+  }
+
+  /// The loudness of the loudest short term block in LUFS.
+  pub fn max_short_term(&self) -> Option<f64> {
+    self.powers(SHORT_TERM_SEGMENTS).into_iter().reduce(f64::max).map(loudness)
+  }
+
+  /// The highest true peak of any channel in dBTP, or `None` for silence.
+  pub fn true_peak(&self) -> Option<f64> {
+    let peak = self.peaks.peak();
+
+    if peak > 0.0 { Some(20.0 * peak.log10()) } else { None }
+  }
+
+  /// The measurements, as stored in a `bext` chunk.
+  pub fn loudness(&self) -> Loudness {
+    Loudness {
+      integrated:     self.integrated().map(|lufs| lufs as f32),
+      range:          self.range().map(|lu| lu as f32),
+      max_true_peak:  self.true_peak().map(|dbtp| dbtp as f32),
+      max_momentary:  self.max_momentary().map(|lufs| lufs as f32),
+      max_short_term: self.max_short_term().map(|lufs| lufs as f32)
+    }
+  }
+
+  /// The mean power of each block of `segments` segments, starting a
+  /// segment apart.
+  fn powers(&self, segments: usize) -> Vec<f64> {
+    self.segments.windows(segments).map(mean).collect()
+  }
+}
+
+/// Measures the loudness of the audio of `file`, weighting its channels
+/// like `Analyzer::for_file`.
+pub fn analyze(file: &WaveFile) -> Analyzer {
+  let mut analyzer = Analyzer::for_file(file);
+  let mut reader   = BlockReader::new(file);
+  let mut samples  = Vec::new();
+
+  while reader.read(&mut samples) > 0 {
+    analyzer.add_frames(&samples);
+  }
+
+  analyzer
+}
+
+/// The powers above the absolute gate and within `relative` LU of their
+/// mean, if any.
+fn gated(powers: &[f64], relative: f64) -> Option<Vec<f64>> {
+  let loud = powers.iter().cloned().filter(|&power| loudness(power) > ABSOLUTE_GATE).collect::<Vec<_>>();
+
+  if loud.is_empty() {
+    return None;
+  }
+
+  let threshold = loudness(mean(&loud)) + relative;
+  let gated     = loud.into_iter().filter(|&power| loudness(power) > threshold).collect::<Vec<_>>();
+
+  if gated.is_empty() { None } else { Some(gated) }
+}
+
+fn mean(values: &[f64]) -> f64 {
+  values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// The loudness in LUFS of K-weighted audio of mean square `power`.
+fn loudness(power: f64) -> f64 {
+  -0.691 + 10.0 * power.log10()
+}
+
+/// The two filters of the K-weighting curve at `sample_rate`: a high shelf
+/// for the effect of the head, then a high pass.
+fn k_weighting(sample_rate: f64) -> [Section; 2] {
+  let k  = (PI * 1681.974450955533 / sample_rate).tan();
+  let q  = 0.7071752369554196;
+  let vh = 10f64.powf(3.999843853973347 / 20.0);
+  let vb = vh.powf(0.4996667741545416);
+  let a0 = 1.0 + k / q + k * k;
+
+  let shelf = Section::new([(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+                           [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0]);
+
+  let k  = (PI * 38.13547087602444 / sample_rate).tan();
+  let q  = 0.5003270373238773;
+  let a0 = 1.0 + k / q + k * k;
+
+  let high_pass = Section::new([1.0, -2.0, 1.0], [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0]);
+
+  [shelf, high_pass]
+}
+
+/// Finds the peaks between samples, by interpolating at a rate of at least
+/// 192 kHz.
+#[derive(Debug,Clone)]
+struct TruePeak {
+  /// The weights of the samples for each point between two of them.
+  phases:  Vec<[f64; TRUE_PEAK_TAPS]>,
+  /// The last samples of each channel, oldest first.
+  history: Vec<[f64; TRUE_PEAK_TAPS]>,
+  peak:    f64
+}
+
+impl TruePeak {
+  fn new(channels: usize, sample_rate: f64) -> TruePeak {
+    let factor = if sample_rate < 96000.0 { 4 } else if sample_rate < 192000.0 { 2 } else { 1 };
+    let centre = (TRUE_PEAK_TAPS / 2 - 1) as f64;
+    let radius = (TRUE_PEAK_TAPS / 2) as f64;
+
+    let phases = (1..factor).map(|phase| {
+      let mut taps = [0.0; TRUE_PEAK_TAPS];
+
+      for (tap, weight) in taps.iter_mut().enumerate() {
+        let x      = tap as f64 - centre - phase as f64 / factor as f64;
+        let window = 0.42 + 0.5 * (PI * x / radius).cos() + 0.08 * (2.0 * PI * x / radius).cos();
+
+        *weight = if x == 0.0 { 1.0 } else { (PI * x).sin() / (PI * x) } * window;
+      }
+
+      let total = taps.iter().sum::<f64>();
+
+      taps.iter_mut().for_each(|weight| *weight /= total);
+      taps
+    }).collect();
+
+    TruePeak { phases, history: vec![[0.0; TRUE_PEAK_TAPS]; channels], peak: 0.0 }
+  }
+
+  fn add_frame(&mut self, frame: &[f64]) {
+    for (history, &sample) in self.history.iter_mut().zip(frame) {
+      self.peak = self.peak.max(push(&self.phases, history, sample));
+    }
+  }
+
+  /// The highest peak, including those between the last samples.
+  fn peak(&self) -> f64 {
+    let mut peak = self.peak;
+
+    for history in &self.history {
+      let mut history = *history;
+
+      for _ in 0..TRUE_PEAK_TAPS / 2 {
+        peak = peak.max(push(&self.phases, &mut history, 0.0));
+      }
+    }
+
+    peak
+  }
+}
+
+/// Adds `sample` to `history`, returning the largest magnitude of it and
+/// the points interpolated at the middle of the history.
+fn push(phases: &[[f64; TRUE_PEAK_TAPS]], history: &mut [f64; TRUE_PEAK_TAPS], sample: f64) -> f64 {
+  history.copy_within(1.., 0);
+  history[TRUE_PEAK_TAPS - 1] = sample;
+
+  phases.iter().fold(sample.abs(), |peak, taps| {
+    peak.max(taps.iter().zip(history.iter()).map(|(weight, sample)| weight * sample).sum::<f64>().abs())
+  })
+}
@@ -0,0 +1,819 @@
+//! Operations producing new files from the audio of existing ones, such as
+//! reversing it.  Where the output keeps the format of the source, frames
+//! are copied as they are encoded rather than decoded and written again.
+
+use std::fs::File;
+use std::io::{BufWriter,Write};
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use cue::CuePoint;
+use error::WaveError;
+use labels::{self,Label};
+use loudness;
+use formats::Format;
+use transcode::{self,duration_frames,BlockReader,Clipping,Curve,Gain,Pipeline};
+use writer::{WaveSpec,WaveWriter};
+use {WaveFile,RIFF,WAVE,FMT_,FACT,DATA,CUE_,LIST};
+
+/// Number of frames copied at a time.
+const BLOCK_FRAMES : usize = 4096;
+
+/// Writes the audio of the wavefile at `src` backwards to a new file at
+/// `dst`, in the same format.  The frames are read a block at a time from
+/// the end, so the file is never held in memory.  Only the chunks
+/// describing the audio are kept, as markers and the like would no longer
+/// line up with it.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::reverse;
+///
+/// reverse("./cymbal.wav", "./reverse-cymbal.wav").unwrap();
+/// ```
+pub fn reverse<S: Into<String>, T: Into<String>>(src: S, dst: T) -> Result<(), WaveError> {
+  let file      = WaveFile::open(src)?;
+  let mut out   = BufWriter::new(File::create(dst.into())?);
+  let mut block = Vec::with_capacity(BLOCK_FRAMES * file.stride);
+  let mut end   = file.len();
+
+  write_header(&mut out, &file, file.len(), &[])?;
+
+  while end > 0 {
+    let start = end.saturating_sub(BLOCK_FRAMES);
+
+    block.clear();
+    for frame in frame_bytes(&file, start..end).chunks_exact(file.stride).rev() {
+      block.extend_from_slice(frame);
+    }
+    out.write_all(&block)?;
+    end = start;
+  }
+
+  write_trailer(&mut out, &file, file.len(), &[])
+}
+
+/// Writes the frames of the wavefile at `src` in `frames` to a new file at
+/// `dst`, in the same format, for cutting a clip out of a long recording.
+/// The range is clamped to the frames present.  The encoded frames are
+/// copied as they are, and only the chunks describing the audio are kept.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::extract;
+///
+/// // the second second of a 48 kHz recording.
+/// extract("./interview.wav", "./clip.wav", 48000..96000).unwrap();
+/// ```
+pub fn extract<S: Into<String>, T: Into<String>>(src: S, dst: T, frames: Range<usize>) -> Result<usize, WaveError> {
+  let file = WaveFile::open(src)?;
+
+  copy_frames(&file, dst.into(), frames)
+}
+
+/// Writes the audio of the wavefile at `src` between two times to a new
+/// file at `dst`, like `extract`, returning the number of frames written.
+pub fn extract_time<S: Into<String>, T: Into<String>>(src: S, dst: T, span: Range<Duration>) -> Result<usize, WaveError> {
+  let file  = WaveFile::open(src)?;
+  let rate  = file.sample_rate() as u32;
+  let start = duration_frames(span.start, rate) as usize;
+
+  copy_frames(&file, dst.into(), start..duration_frames(span.end, rate) as usize)
+}
+
+/// Writes channel `channel` of the wavefile at `src`, counting from zero,
+/// to a new mono file at `dst` in the same format, returning the number of
+/// frames written, for pulling one microphone's track out of a
+/// multitrack recording.  The channel's samples are copied as they are
+/// from each block of frames, and only the chunks describing the audio are
+/// kept.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::extract_channel;
+///
+/// // the lavalier on the third track of a location recorder.
+/// extract_channel("./scene-12.wav", 2, "./scene-12-lav.wav").unwrap();
+/// ```
+pub fn extract_channel<S: Into<String>, T: Into<String>>(src: S, channel: usize, dst: T) -> Result<usize, WaveError> {
+  remap_channels(src, dst, &[channel])
+}
+
+/// Writes the channels of the wavefile at `src` to a new file at `dst` in
+/// the same format, reordered, dropped or duplicated by `map`, returning
+/// the number of frames written.  Channel `i` of the new file is channel
+/// `map[i]` of the source, counting from zero.  Samples are copied as they
+/// are, and only the chunks describing the audio are kept.
+///
+/// An extensible file keeps the speakers of its channels if they remain in
+/// the order the format defines, and otherwise leaves them unassigned, as
+/// the format has no way to state any other order.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::remap_channels;
+///
+/// // 5.1 from the SMPTE order, L R C LFE Ls Rs, to the film order,
+/// // L C R Ls Rs LFE.
+/// remap_channels("./mix-smpte.wav", "./mix-film.wav", &[0, 2, 1, 4, 5, 3]).unwrap();
+/// ```
+pub fn remap_channels<S: Into<String>, T: Into<String>>(src: S, dst: T, map: &[usize]) -> Result<usize, WaveError> {
+  let file     = WaveFile::open(src)?;
+  let channels = file.channels();
+
+  if map.is_empty() || map.len() > u16::MAX as usize {
+    return Err(WaveError::WriteError(format!("Files hold from 1 to {} channels, not {}", u16::MAX, map.len())));
+  }
+
+  if let Some(&channel) = map.iter().find(|&&channel| channel >= channels) {
+    return Err(WaveError::WriteError(format!("Channel {} is out of range for {} channels", channel, channels)));
+  }
+
+  let width = file.info.bits_per_sample as usize / 8;
+
+  if width * map.len() > u16::MAX as usize {
+    return Err(WaveError::WriteError(format!("Frames of {} channels of {} bytes exceed the block align", map.len(), width)));
+  }
+
+  let format    = remapped_format(&file, map);
+  let data_size = (file.len() * map.len() * width) as u64;
+  let mut out   = BufWriter::new(File::create(dst.into())?);
+  let mut block = Vec::with_capacity(BLOCK_FRAMES * map.len() * width);
+  let mut start = 0;
+
+  start_file(&mut out, &format, file.fact_frames.is_some(), file.len(), data_size, &[])?;
+
+  while start < file.len() {
+    let end = (start + BLOCK_FRAMES).min(file.len());
+
+    block.clear();
+    for frame in frame_bytes(&file, start..end).chunks_exact(file.stride) {
+      for &channel in map {
+        block.extend_from_slice(&frame[channel * width..(channel + 1) * width]);
+      }
+    }
+    out.write_all(&block)?;
+    start = end;
+  }
+
+  end_file(&mut out, data_size, &[])?;
+  Ok(file.len())
+}
+
+/// The `fmt ` chunk body of `file` for a file holding its channels as
+/// chosen by `map`, its frames packed without any padding of the source.
+fn remapped_format(file: &WaveFile, map: &[usize]) -> Vec<u8> {
+  let mut format = file.chunk_data(*b"fmt ").unwrap_or_default().to_vec();
+  let width      = file.info.bits_per_sample as usize / 8;
+  let align      = width * map.len();
+  let byte_rate  = (file.info.sample_rate as u64 * align as u64).min(u32::MAX as u64) as u32;
+
+  format[2..4].copy_from_slice(&(map.len() as u16).to_le_bytes());
+  format[8..12].copy_from_slice(&byte_rate.to_le_bytes());
+  format[12..14].copy_from_slice(&(align as u16).to_le_bytes());
+
+  if let Some(mask) = file.info.channel_mask {
+    let speakers = (0..32).map(|bit| 1u32 << bit).filter(|&bit| mask & bit != 0).collect::<Vec<_>>();
+    let assigned = map.iter().map(|&channel| speakers.get(channel).cloned()).collect::<Option<Vec<_>>>();
+    let mask     = match assigned {
+      Some(ref assigned) if assigned.windows(2).all(|pair| pair[0] < pair[1]) => assigned.iter().fold(0, |mask, bit| mask | bit),
+      _                                                                        => 0
+    };
+
+    format[20..24].copy_from_slice(&mask.to_le_bytes());
+  }
+
+  format
+}
+
+/// Writes the audio of the wavefile at `src` to a new file at `dst` in the
+/// same format, scaled so its highest sample peaks at `target_dbfs`
+/// decibels relative to full scale, and returns the gain applied in
+/// decibels.  The file is read twice, once to find the peak and once to
+/// copy it.  Silence is copied unchanged.
+///
+/// The `fmt ` chunk is kept, with any speakers and valid bits it states,
+/// and so are metadata chunks like `bext`, `cue ` and `LIST`, as the audio
+/// keeps its length.  A `levl` peak envelope is dropped, and loudness
+/// values in a `bext` chunk still describe the source.
+///
+/// A target above full scale clips PCM files, while float files keep the
+/// samples beyond it.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::normalize_peak;
+///
+/// let gain = normalize_peak("./kick.wav", "./kick-normalized.wav", -1.0).unwrap();
+///
+/// println!("raised by {:.1} dB", gain);
+/// ```
+pub fn normalize_peak<S: Into<String>, T: Into<String>>(src: S, dst: T, target_dbfs: f64) -> Result<f64, WaveError> {
+  let file = WaveFile::open(src)?;
+  let peak = sample_peak(&file);
+  let gain = if peak > 0.0 { 10f64.powf(target_dbfs / 20.0) / peak } else { 1.0 };
+
+  apply_gain(&file, dst.into(), gain)?;
+  Ok(20.0 * gain.log10())
+}
+
+/// Writes the audio of the wavefile at `src` to a new file at `dst` in the
+/// same format, scaled to an integrated loudness of `target_lufs`, such as
+/// -23 LUFS for broadcast or -16 LUFS for streaming, and returns the gain
+/// applied in decibels.  The file is read twice, once to measure it with a
+/// `loudness::Analyzer` and once to copy it.
+///
+/// The gain is held back as needed to keep the true peak at or below
+/// `true_peak_ceiling` dBTP, in which case the file comes out quieter than
+/// the target.  Audio too quiet or short to measure fails with
+/// `WaveError::Unsupported`.  The format and metadata are kept as by
+/// `normalize_peak`.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::normalize_loudness;
+///
+/// normalize_loudness("./episode.wav", "./episode-normalized.wav", -16.0, -1.0).unwrap();
+/// ```
+pub fn normalize_loudness<S: Into<String>, T: Into<String>>(src: S, dst: T, target_lufs: f64, true_peak_ceiling: f64)
+                                                            -> Result<f64, WaveError> {
+  let file     = WaveFile::open(src)?;
+  let analysis = loudness::analyze(&file);
+  let measured = match analysis.integrated() {
+    Some(lufs) => lufs,
+    None       => return Err(WaveError::Unsupported("The audio is too quiet or short to measure its loudness".into()))
+  };
+  let gain     = match analysis.true_peak() {
+    Some(peak) => (target_lufs - measured).min(true_peak_ceiling - peak),
+    None       => target_lufs - measured
+  };
+
+  apply_gain(&file, dst.into(), 10f64.powf(gain / 20.0))?;
+  Ok(gain)
+}
+
+/// The largest magnitude of any sample of `file`, on the scale of
+/// `BlockReader`.
+fn sample_peak(file: &WaveFile) -> f64 {
+  let mut reader  = BlockReader::new(file);
+  let mut samples = Vec::new();
+  let mut peak    = 0.0f64;
+
+  while reader.read(&mut samples) > 0 {
+    peak = samples.iter().fold(peak, |peak, sample| peak.max(sample.abs()));
+  }
+
+  peak
+}
+
+/// Copies the audio of `file` to a new file at `dst` in the same format,
+/// multiplied by `gain`.  The `fmt ` chunk and the metadata chunks of
+/// `file` are copied as they are, on the same side of the audio, except for
+/// a `levl` chunk, whose peak envelope no longer matches the audio.
+fn apply_gain(file: &WaveFile, dst: String, gain: f64) -> Result<(), WaveError> {
+  let clipping     = if file.data_format() == Format::IEEEFloat { Clipping::Headroom } else { Clipping::Clip };
+  let mut pipeline = Pipeline::new().then(Gain::linear(gain).clipping(clipping));
+  let spec         = spec_of(file);
+  let out          = BufWriter::new(File::create(dst)?);
+  let mut writer   = match file.chunk_data(*b"fmt ") {
+    // the writer packs its frames, so padded ones need a new format.
+    Some(format) if file.stride == spec.channels as usize * (spec.bits_per_sample as usize / 8) => {
+      WaveWriter::with_format(out, spec, format)?
+    },
+    _ => WaveWriter::new(out, spec)?
+  };
+  let bytes        = file.bytes();
+  let metadata     = file.chunks().filter(|chunk| ![*b"fmt ", *b"fact", *b"data", *b"levl"].contains(&chunk.0));
+  let (ahead, after): (Vec<_>, Vec<_>) = metadata.partition(|&(_, offset, _)| offset < file.data_offset);
+  let body         = |offset: usize, size: usize| &bytes[offset.min(bytes.len())..(offset + size).min(bytes.len())];
+
+  for (id, offset, size) in ahead {
+    writer.add_chunk(id, body(offset, size))?;
+  }
+
+  pipeline.run(file, &mut writer)?;
+
+  for (id, offset, size) in after {
+    writer.add_chunk(id, body(offset, size))?;
+  }
+  writer.finalize()
+}
+
+/// A part of a file cut out by one of the `split` functions, as passed to
+/// the function naming its file.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Segment {
+  /// The position of the segment among the others, counting from zero.
+  pub index:  usize,
+  /// The frames of the source the segment holds.
+  pub frames: Range<usize>,
+  /// The label of the marker or region the segment starts at, if any.
+  pub label:  Option<String>
+}
+
+/// Cuts the wavefile at `src` into segments of `length`, the last of which
+/// may be shorter, writing each to the path `naming` returns for it, and
+/// returns those paths in order.  Segments are copied like `extract`.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use wavefile::split_by_duration;
+///
+/// // lecture-000.wav, lecture-001.wav and so on, ten minutes each.
+/// split_by_duration("./lecture.wav", Duration::from_secs(600), |segment| {
+///   format!("./lecture-{:03}.wav", segment.index)
+/// }).unwrap();
+/// ```
+pub fn split_by_duration<S, F>(src: S, length: Duration, naming: F) -> Result<Vec<String>, WaveError>
+  where S: Into<String>, F: FnMut(&Segment) -> String {
+  let file   = WaveFile::open(src)?;
+  let frames = duration_frames(length, file.sample_rate() as u32) as usize;
+
+  if frames == 0 {
+    return Err(WaveError::WriteError("Segments must last at least one frame".into()));
+  }
+
+  let segments = (0..file.len().div_ceil(frames)).map(|index| Segment {
+    index,
+    frames: index * frames..((index + 1) * frames).min(file.len()),
+    label:  None
+  });
+
+  split(&file, segments, naming)
+}
+
+/// Cuts the wavefile at `src` at each of its cue points, writing the
+/// segments next to it, named after the file and the label of the marker
+/// each starts at, such as `take-01-Chorus.wav`, and returns their paths in
+/// order.  This exports the parts of a recording marked out in an editor.
+pub fn split_at_cues<S: Into<String>>(src: S) -> Result<Vec<String>, WaveError> {
+  let src  = src.into();
+  let stem = Path::new(&src).with_extension("").to_string_lossy().into_owned();
+
+  split_at_cues_with(src.as_str(), |segment| match segment.label {
+    Some(ref label) => format!("{}-{:02}-{}.wav", stem, segment.index, file_name(label)),
+    None            => format!("{}-{:02}.wav", stem, segment.index)
+  })
+}
+
+/// Cuts the wavefile at `src` at each of its cue points like
+/// `split_at_cues`, writing each segment to the path `naming` returns for
+/// it.  A segment's label is that of the marker it starts at, or else its
+/// note.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::split_at_cues_with;
+///
+/// split_at_cues_with("./album-side-a.wav", |segment| {
+///   format!("./{} {}.wav", segment.index + 1, segment.label.as_deref().unwrap_or("Untitled"))
+/// }).unwrap();
+/// ```
+pub fn split_at_cues_with<S, F>(src: S, naming: F) -> Result<Vec<String>, WaveError>
+  where S: Into<String>, F: FnMut(&Segment) -> String {
+  let file        = WaveFile::open(src)?;
+  let mut markers = file.markers();
+
+  markers.sort_by_key(|marker| marker.position);
+
+  let mut cuts = markers.iter().map(|marker| marker.position as usize).filter(|&cut| cut > 0 && cut < file.len()).collect::<Vec<_>>();
+
+  cuts.dedup();
+  cuts.insert(0, 0);
+
+  let segments = cuts.iter().enumerate().map(|(index, &start)| Segment {
+    index,
+    frames: start..cuts.get(index + 1).cloned().unwrap_or(file.len()),
+    label:  markers.iter()
+      .find(|marker| marker.position as usize == start)
+      .and_then(|marker| marker.label.clone().or_else(|| marker.note.clone()))
+  }).collect::<Vec<_>>();
+
+  split(&file, segments, naming)
+}
+
+/// Cuts the wavefile at `src` into the sounds between its silences, as
+/// when breaking a digitized record or tape into tracks, writing them next
+/// to it, named `take-00.wav`, `take-01.wav` and so on, and returns their
+/// paths in order.
+///
+/// A silence is a run of at least `min_gap` of frames in which no sample
+/// reaches `threshold_db` decibels relative to full scale.  Silences are
+/// left out of the segments; shorter pauses, such as those within a track,
+/// are kept.
+pub fn split_on_silence<S: Into<String>>(src: S, threshold_db: f64, min_gap: Duration) -> Result<Vec<String>, WaveError> {
+  let src  = src.into();
+  let stem = Path::new(&src).with_extension("").to_string_lossy().into_owned();
+
+  split_on_silence_with(src.as_str(), threshold_db, min_gap, |segment| format!("{}-{:02}.wav", stem, segment.index))
+}
+
+/// Cuts the wavefile at `src` into the sounds between its silences like
+/// `split_on_silence`, writing each segment to the path `naming` returns
+/// for it.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use wavefile::split_on_silence_with;
+///
+/// // gaps between tracks on a record are quieter than -50 dBFS for over two
+/// // seconds.
+/// split_on_silence_with("./side-a.wav", -50.0, Duration::from_secs(2), |segment| {
+///   format!("./track-{:02}.wav", segment.index + 1)
+/// }).unwrap();
+/// ```
+pub fn split_on_silence_with<S, F>(src: S, threshold_db: f64, min_gap: Duration, naming: F) -> Result<Vec<String>, WaveError>
+  where S: Into<String>, F: FnMut(&Segment) -> String {
+  let file  = WaveFile::open(src)?;
+  let gap   = (duration_frames(min_gap, file.sample_rate() as u32) as usize).max(1);
+  let mut start    = 0;
+  let mut segments = Vec::new();
+
+  for silence in silences(&file, 10f64.powf(threshold_db / 20.0), gap) {
+    if silence.start > start {
+      segments.push(Segment { index: segments.len(), frames: start..silence.start, label: None });
+    }
+    start = silence.end;
+  }
+
+  if file.len() > start {
+    segments.push(Segment { index: segments.len(), frames: start..file.len(), label: None });
+  }
+
+  split(&file, segments, naming)
+}
+
+/// The runs of at least `min_frames` frames of `file` in which no sample
+/// reaches `threshold`, in order.
+fn silences(file: &WaveFile, threshold: f64, min_frames: usize) -> Vec<Range<usize>> {
+  let channels    = file.channels();
+  let mut reader  = BlockReader::new(file);
+  let mut samples = Vec::new();
+  let mut runs    = Vec::new();
+  let mut quiet   = 0;
+  let mut next    = 0;
+
+  while reader.read(&mut samples) > 0 {
+    for frame in samples.chunks_exact(channels) {
+      next += 1;
+      if frame.iter().all(|sample| sample.abs() < threshold) {
+        quiet += 1;
+        continue;
+      }
+
+      if quiet >= min_frames {
+        runs.push(next - 1 - quiet..next - 1);
+      }
+      quiet = 0;
+    }
+  }
+
+  if quiet >= min_frames {
+    runs.push(next - quiet..next);
+  }
+
+  runs
+}
+
+/// `label` with any characters that don't belong in a file name replaced.
+fn file_name(label: &str) -> String {
+  label.chars().map(|c| if c.is_alphanumeric() || " -_".contains(c) { c } else { '_' }).collect()
+}
+
+/// Writes each of `segments` of `file` to the path `naming` returns for
+/// it, returning the paths.
+fn split<I, F>(file: &WaveFile, segments: I, mut naming: F) -> Result<Vec<String>, WaveError>
+  where I: IntoIterator<Item = Segment>, F: FnMut(&Segment) -> String {
+  segments.into_iter().map(|segment| {
+    let path = naming(&segment);
+
+    copy_frames(file, path.clone(), segment.frames)?;
+    Ok(path)
+  }).collect()
+}
+
+/// How `concat_with` joins files.
+#[derive(Debug,Clone,Default)]
+pub struct ConcatOptions {
+  convert:   bool,
+  markers:   bool,
+  crossfade: Option<(Duration, Curve)>
+}
+
+impl ConcatOptions {
+  /// Joins files of the same format, dropping their markers.
+  pub fn new() -> ConcatOptions {
+    ConcatOptions::default()
+  }
+
+  /// Converts files whose sample format differs from the first's to it,
+  /// rather than failing.  Their channel counts and sample rates must still
+  /// match.
+  pub fn convert(mut self, convert: bool) -> ConcatOptions {
+    self.convert = convert;
+    self
+  }
+
+  /// Keeps the markers and regions of every file, moved to where its audio
+  /// falls in the output.
+  pub fn merge_markers(mut self, merge: bool) -> ConcatOptions {
+    self.markers = merge;
+    self
+  }
+
+  /// Overlaps each file with the next by `length`, fading one out as the
+  /// other fades in along `curve`, for seamless loops and mixes.  An
+  /// overlap is cut short where a file is shorter than it, and the files
+  /// are decoded to mix them.
+  pub fn crossfade(mut self, length: Duration, curve: Curve) -> ConcatOptions {
+    self.crossfade = Some((length, curve));
+    self
+  }
+}
+
+/// Joins the audio of the wavefiles in `inputs`, in order, into a new file
+/// at `dst` in the format of the first, returning the number of frames
+/// written.  The files must share a format.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::concat;
+///
+/// concat(&["./intro.wav", "./interview.wav", "./outro.wav"], "./episode.wav").unwrap();
+/// ```
+pub fn concat<S: AsRef<str>, T: Into<String>>(inputs: &[S], dst: T) -> Result<usize, WaveError> {
+  concat_with(inputs, dst, ConcatOptions::default())
+}
+
+/// Joins the audio of the wavefiles in `inputs` like `concat`, as set by
+/// `options`.
+///
+/// When every file shares a format and none are crossfaded, the encoded
+/// frames are copied as they are, so the join is sample accurate.
+/// Otherwise they are decoded and written in the sample format of the
+/// first file.  Only the chunks
+/// describing the audio are kept, along with any markers merged.
+pub fn concat_with<S: AsRef<str>, T: Into<String>>(inputs: &[S], dst: T, options: ConcatOptions) -> Result<usize, WaveError> {
+  let files = inputs.iter().map(|input| WaveFile::open(input.as_ref())).collect::<Result<Vec<_>, _>>()?;
+  let first = match files.first() {
+    Some(first) => first,
+    None        => return Err(WaveError::WriteError("No files to join".into()))
+  };
+
+  for (input, file) in inputs.iter().zip(&files) {
+    let convertible = options.convert && file.channels() == first.channels() && file.sample_rate() == first.sample_rate();
+
+    if !convertible && !same_format(file, first) {
+      return Err(WaveError::Unsupported(format!("{} holds {}, but {} holds {}",
+                                                input.as_ref(), describe(file), inputs[0].as_ref(), describe(first))));
+    }
+  }
+
+  let (length, curve) = options.crossfade.unwrap_or((Duration::ZERO, Curve::default()));
+  let overlaps        = overlaps(&files, duration_frames(length, first.sample_rate() as u32) as usize);
+  let starts          = files.iter().zip(&overlaps).scan(0, |start, (file, overlap)| {
+    let begin = *start;
+
+    *start += file.len() - overlap;
+    Some(begin)
+  }).collect::<Vec<_>>();
+  let frames          = files.iter().map(|file| file.len()).sum::<usize>() - overlaps.iter().sum::<usize>();
+  let labels          = if options.markers { merge_labels(&files, &starts) } else { Vec::new() };
+
+  if files.iter().all(|file| same_format(file, first)) && overlaps.iter().all(|&overlap| overlap == 0) {
+    let trailing = label_chunks(&labels, first.sample_rate() as u32)?;
+    let mut out  = BufWriter::new(File::create(dst.into())?);
+
+    write_header(&mut out, first, frames, &trailing)?;
+    for file in &files {
+      out.write_all(frame_bytes(file, 0..file.len()))?;
+    }
+    write_trailer(&mut out, first, frames, &trailing)?;
+  } else {
+    let channels    = first.channels();
+    let mut writer  = WaveWriter::create(dst, spec_of(first))?;
+    let mut samples = Vec::new();
+    let mut mixed   = Vec::new();
+    // the end of the last file, waiting to be mixed with the next.
+    let mut tail    = Vec::new();
+    let mut fading  = 0;
+
+    if !labels.is_empty() {
+      writer.set_labels(&labels)?;
+    }
+
+    for (file, &overlap) in files.iter().zip(&overlaps) {
+      let mut reader = BlockReader::new(file);
+      let mut frame  = 0;
+      let held       = file.len() - overlap;
+      let mut next   = Vec::new();
+
+      while reader.read(&mut samples) > 0 {
+        mixed.clear();
+        for sample in samples.chunks_exact(channels) {
+          if frame < fading {
+            let position = frame as f64 / fading as f64;
+            let (out, into) = (curve.gain(1.0 - position), curve.gain(position));
+
+            mixed.extend(sample.iter().zip(&tail[frame * channels..]).map(|(sample, last)| last * out + sample * into));
+          } else if frame >= held {
+            next.extend_from_slice(sample);
+          } else {
+            mixed.extend_from_slice(sample);
+          }
+          frame += 1;
+        }
+
+        transcode::write(&mut writer, &mixed, channels)?;
+      }
+
+      tail   = next;
+      fading = overlap;
+    }
+
+    writer.finalize()?;
+  }
+
+  Ok(frames)
+}
+
+/// The number of frames each of `files` overlaps the next by, at most
+/// `length`, with no file overlapped for longer than it lasts.
+fn overlaps(files: &[WaveFile], length: usize) -> Vec<usize> {
+  let mut faded_in = 0;
+
+  files.iter().enumerate().map(|(index, file)| {
+    let overlap = match files.get(index + 1) {
+      Some(next) => length.min(file.len() - faded_in).min(next.len()),
+      None       => 0
+    };
+
+    faded_in = overlap;
+    overlap
+  }).collect()
+}
+
+/// The format of the frames of `file`, for writing more like them.
+fn spec_of(file: &WaveFile) -> WaveSpec {
+  WaveSpec {
+    channels:        file.channels() as u16,
+    sample_rate:     file.sample_rate() as u32,
+    bits_per_sample: file.bits_per_sample() as u16,
+    format:          file.data_format()
+  }
+}
+
+/// Whether the frames of `a` are encoded the same way as those of `b`.
+fn same_format(a: &WaveFile, b: &WaveFile) -> bool {
+  let (a, b) = (a.info(), b.info());
+
+  a.channels == b.channels && a.sample_rate == b.sample_rate && a.bits_per_sample == b.bits_per_sample &&
+    a.block_align == b.block_align && a.valid_bps == b.valid_bps && a.channel_mask == b.channel_mask &&
+    a.subformat.unwrap_or(a.audio_format) == b.subformat.unwrap_or(b.audio_format)
+}
+
+fn describe(file: &WaveFile) -> String {
+  format!("{} channel(s) of {} bit {:?} audio at {} Hz",
+          file.channels(), file.bits_per_sample(), file.data_format(), file.sample_rate())
+}
+
+/// The labels of every file in `files`, moved to where its audio falls
+/// once they are joined, starting at the frame in `starts`.
+fn merge_labels(files: &[WaveFile], starts: &[usize]) -> Vec<Label> {
+  let mut merged = Vec::new();
+
+  for (file, &start) in files.iter().zip(starts) {
+    let seconds = start as f64 / file.sample_rate().max(1) as f64;
+
+    merged.extend(file.labels().into_iter().map(|label| Label {
+      start: label.start + seconds,
+      end:   label.end + seconds,
+      text:  label.text
+    }));
+  }
+
+  merged
+}
+
+/// The `cue ` and associated data chunks marking `labels`, if there are
+/// any.
+fn label_chunks(labels: &[Label], sample_rate: u32) -> Result<Vec<(u32, Vec<u8>)>, WaveError> {
+  if labels.is_empty() {
+    return Ok(Vec::new());
+  }
+
+  let (cues, adtl) = labels::to_cues(labels, sample_rate);
+  let mut cue_body  = Vec::new();
+  let mut list_body = Vec::new();
+
+  CuePoint::write_chunk(&cues, &mut cue_body)?;
+  adtl.write_to(&mut list_body)?;
+  Ok(vec![(CUE_, cue_body), (LIST, list_body)])
+}
+
+/// Copies the frames of `file` in `frames`, clamped to those present, to a
+/// new file at `dst`, returning their number.
+fn copy_frames(file: &WaveFile, dst: String, frames: Range<usize>) -> Result<usize, WaveError> {
+  let end     = frames.end.min(file.len());
+  let start   = frames.start.min(end);
+  let mut out = BufWriter::new(File::create(dst)?);
+
+  write_header(&mut out, file, end - start, &[])?;
+  out.write_all(frame_bytes(file, start..end))?;
+  write_trailer(&mut out, file, end - start, &[])?;
+  Ok(end - start)
+}
+
+/// The encoded bytes of the frames of `file` in `frames`.
+fn frame_bytes(file: &WaveFile, frames: Range<usize>) -> &[u8] {
+  &file.bytes()[file.data_offset + frames.start * file.stride..file.data_offset + frames.end * file.stride]
+}
+
+/// Writes the start of a file holding `frames` frames in the format of
+/// `file`, followed by the chunks in `trailing`, up to and including the
+/// header of its data chunk.
+fn write_header<W: Write>(out: &mut W, file: &WaveFile, frames: usize, trailing: &[(u32, Vec<u8>)]) -> Result<(), WaveError> {
+  let format = file.chunk_data(*b"fmt ").unwrap_or_default();
+
+  start_file(out, format, file.fact_frames.is_some(), frames, frames as u64 * file.stride as u64, trailing)
+}
+
+/// Ends a file started by `write_header` once its frames are written.
+fn write_trailer<W: Write>(out: &mut W, file: &WaveFile, frames: usize, trailing: &[(u32, Vec<u8>)]) -> Result<(), WaveError> {
+  end_file(out, frames as u64 * file.stride as u64, trailing)
+}
+
+/// Writes the start of a file with the `fmt ` chunk body `format` and
+/// `data_size` bytes of `frames` frames, followed by the chunks in
+/// `trailing`, up to and including the header of its data chunk.
+fn start_file<W: Write>(out: &mut W, format: &[u8], fact: bool, frames: usize, data_size: u64, trailing: &[(u32, Vec<u8>)]) -> Result<(), WaveError> {
+  let chunks    = trailing.iter().fold(0, |total, chunk| total + 8 + padded(chunk.1.len() as u64));
+  let riff_size = 4 + 8 + padded(format.len() as u64) + if fact { 12 } else { 0 } + 8 + padded(data_size) + chunks;
+
+  if riff_size > u32::MAX as u64 {
+    return Err(WaveError::WriteError("File exceeds 4 GiB".into()));
+  }
+
+  out.write_u32::<LittleEndian>(RIFF)?;
+  out.write_u32::<LittleEndian>(riff_size as u32)?;
+  out.write_u32::<LittleEndian>(WAVE)?;
+  write_chunk(out, FMT_, format)?;
+
+  // non-PCM files state their length in frames.
+  if fact {
+    out.write_u32::<LittleEndian>(FACT)?;
+    out.write_u32::<LittleEndian>(4)?;
+    out.write_u32::<LittleEndian>(frames as u32)?;
+  }
+
+  out.write_u32::<LittleEndian>(DATA)?;
+  out.write_u32::<LittleEndian>(data_size as u32)?;
+  Ok(())
+}
+
+/// Ends a file started by `start_file` once its `data_size` bytes of
+/// frames are written.
+fn end_file<W: Write>(out: &mut W, data_size: u64, trailing: &[(u32, Vec<u8>)]) -> Result<(), WaveError> {
+  if data_size % 2 == 1 {
+    out.write_u8(0)?;
+  }
+
+  for &(id, ref body) in trailing {
+    write_chunk(out, id, body)?;
+  }
+
+  out.flush()?;
+  Ok(())
+}
+
+fn write_chunk<W: Write>(out: &mut W, id: u32, body: &[u8]) -> Result<(), WaveError> {
+  out.write_u32::<LittleEndian>(id)?;
+  out.write_u32::<LittleEndian>(body.len() as u32)?;
+  out.write_all(body)?;
+
+  if body.len() % 2 == 1 {
+    out.write_u8(0)?;
+  }
+
+  Ok(())
+}
+
+/// The size of a chunk body of `size` bytes along with its pad byte.
+fn padded(size: u64) -> u64 {
+  size + (size & 1)
+}
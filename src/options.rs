@@ -0,0 +1,205 @@
+use formats::Format;
+use text::TextEncoding;
+
+/// Number of samples decoded at a time when iterating over frames.
+const BLOCK_SAMPLES   : usize = 4096;
+/// Size of each read made by `WaveFile::open_io_uring`.
+const READ_BLOCK_SIZE : usize = 1 << 20;
+
+/// What to do with chunks this crate doesn't recognize.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum UnknownChunks {
+  /// Skip them by their declared size.  They are still listed by
+  /// `WaveFile::chunks()`.
+  Skip,
+  /// Fail to open the file.
+  Reject
+}
+
+/// What to do when a chunk this crate reads appears more than once, e.g. a
+/// second `fmt ` or `bext` chunk.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum DuplicateChunks {
+  /// Use the first one and skip the rest.
+  First,
+  /// Let each one replace the last.  The audio is still taken from the
+  /// first `data` chunk.
+  Last,
+  /// Fail to open the file.
+  Reject
+}
+
+/// Configures how `WaveFile::open_with` parses a file, e.g. to be more
+/// forgiving with trusted recordings or more defensive with untrusted
+/// uploads.
+///
+/// # Example
+///
+/// ```
+/// use wavefile::{WaveFile,WaveFileOptions,Format};
+///
+/// let options = WaveFileOptions::new()
+///   .strict(true)
+///   .max_scan_bytes(1 << 20)
+///   .allowed_formats(&[Format::PCM])
+///   .load_metadata(false);
+///
+/// let wav = WaveFile::open_with("./fixtures/test-s24le.wav", options).unwrap();
+///
+/// assert!(wav.bext().is_none());
+/// ```
+#[derive(Debug,Clone,PartialEq)]
+pub struct WaveFileOptions {
+  pub(crate) strict:          bool,
+  pub(crate) unknown_chunks:  UnknownChunks,
+  pub(crate) duplicates:      DuplicateChunks,
+  pub(crate) max_scan_bytes:  Option<usize>,
+  pub(crate) allowed_formats: Option<Vec<Format>>,
+  pub(crate) load_metadata:   bool,
+  pub(crate) lazy_metadata:   bool,
+  pub(crate) encoding:        TextEncoding,
+  pub(crate) max_channels:    Option<usize>,
+  pub(crate) max_chunks:      Option<usize>,
+  pub(crate) max_allocation:  Option<usize>,
+  pub(crate) trust_fact:      bool,
+  pub(crate) block_samples:   usize,
+  pub(crate) read_block_size: usize
+}
+
+impl WaveFileOptions {
+  /// The default options: lenient parsing that skips unknown chunks and
+  /// uses the first of duplicated ones, scans
+  /// the whole file, accepts every supported format and loads all metadata
+  /// as UTF-8, without any limits, taking the length of audio that isn't
+  /// PCM from its `fact` chunk, and decodes 4096 samples at a time.
+  pub fn new() -> WaveFileOptions {
+    WaveFileOptions {
+      strict:          false,
+      unknown_chunks:  UnknownChunks::Skip,
+      duplicates:      DuplicateChunks::First,
+      max_scan_bytes:  None,
+      allowed_formats: None,
+      load_metadata:   true,
+      lazy_metadata:   false,
+      encoding:        TextEncoding::default(),
+      max_channels:    None,
+      max_chunks:      None,
+      max_allocation:  None,
+      trust_fact:      true,
+      block_samples:   BLOCK_SAMPLES,
+      read_block_size: READ_BLOCK_SIZE
+    }
+  }
+
+  /// Rejects files whose format chunk is internally inconsistent, rather
+  /// than working around it.
+  pub fn strict(mut self, strict: bool) -> WaveFileOptions {
+    self.strict = strict;
+    self
+  }
+
+  pub fn unknown_chunks(mut self, policy: UnknownChunks) -> WaveFileOptions {
+    self.unknown_chunks = policy;
+    self
+  }
+
+  pub fn duplicate_chunks(mut self, policy: DuplicateChunks) -> WaveFileOptions {
+    self.duplicates = policy;
+    self
+  }
+
+  /// Stops looking for chunks `bytes` bytes into the file.  Files whose
+  /// `fmt ` or `data` chunk starts later fail to open.
+  pub fn max_scan_bytes(mut self, bytes: usize) -> WaveFileOptions {
+    self.max_scan_bytes = Some(bytes);
+    self
+  }
+
+  /// Rejects files whose audio isn't in one of the given formats.  For
+  /// extensible files, the subformat is checked.
+  pub fn allowed_formats(mut self, formats: &[Format]) -> WaveFileOptions {
+    self.allowed_formats = Some(formats.to_vec());
+    self
+  }
+
+  /// Whether to parse metadata chunks such as `bext` or `LIST`.  When
+  /// disabled only the format and audio data are read and all other chunks
+  /// are skipped, though they are still listed by `WaveFile::chunks()`.
+  pub fn load_metadata(mut self, load: bool) -> WaveFileOptions {
+    self.load_metadata = load;
+    self
+  }
+
+  /// Whether to put off parsing the `LIST`, `bext` and `cue ` chunks until
+  /// their contents are first asked for, so opening files for their audio
+  /// alone stays fast.  Such chunks that turn out to be malformed then read
+  /// as missing, rather than failing to open, and are reported by
+  /// `WaveFile::validate()`.
+  pub fn lazy_metadata(mut self, lazy: bool) -> WaveFileOptions {
+    self.lazy_metadata = lazy;
+    self
+  }
+
+  /// The encoding used to decode metadata text.
+  pub fn text_encoding(mut self, encoding: TextEncoding) -> WaveFileOptions {
+    self.encoding = encoding;
+    self
+  }
+
+  /// Whether the frame count of the `fact` chunk takes precedence over the
+  /// size of the data chunk for audio that isn't PCM.  It is only used if
+  /// it's smaller, as the data chunk can be padded past the last frame.
+  pub fn trust_fact(mut self, trust: bool) -> WaveFileOptions {
+    self.trust_fact = trust;
+    self
+  }
+
+  /// Decodes `samples` samples at a time, rounded down to whole frames,
+  /// when iterating over frames with `WaveFile::iter()` or
+  /// `WaveFile::prefetching()`.  The latter queues blocks of this size, so
+  /// larger ones ride out longer stalls of slow storage at the cost of
+  /// memory.
+  pub fn block_samples(mut self, samples: usize) -> WaveFileOptions {
+    self.block_samples = samples;
+    self
+  }
+
+  /// The size of each read made by `WaveFile::open_io_uring()`, which
+  /// suits different storage best: larger for networked filesystems,
+  /// smaller for embedded flash.  Files opened otherwise are mapped rather
+  /// than read.
+  pub fn read_block_size(mut self, bytes: usize) -> WaveFileOptions {
+    self.read_block_size = bytes.clamp(1, u32::MAX as usize);
+    self
+  }
+
+  /// Rejects files with more than `channels` channels.  Every frame read
+  /// allocates a sample per channel.
+  pub fn max_channels(mut self, channels: usize) -> WaveFileOptions {
+    self.max_channels = Some(channels);
+    self
+  }
+
+  /// Rejects files with more than `chunks` top-level chunks, bounding the
+  /// memory used to list them.
+  pub fn max_chunks(mut self, chunks: usize) -> WaveFileOptions {
+    self.max_chunks = Some(chunks);
+    self
+  }
+
+  /// Rejects files with metadata chunks larger than `bytes`.  Memory used
+  /// to parse a chunk is proportional to its size, so this bounds every
+  /// allocation made while opening a file, and together with `max_chunks`
+  /// their total.  Methods decoding the whole file into memory, like
+  /// `WaveFile::read_all_with_progress()`, fail rather than allocate more.
+  pub fn max_allocation(mut self, bytes: usize) -> WaveFileOptions {
+    self.max_allocation = Some(bytes);
+    self
+  }
+}
+
+impl Default for WaveFileOptions {
+  fn default() -> WaveFileOptions {
+    WaveFileOptions::new()
+  }
+}
@@ -0,0 +1,68 @@
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+
+use {WaveFile,Frame};
+
+/// An iterator over the frames of a file, decoded ahead on a worker thread.
+///
+/// Returned by `WaveFile::prefetching`.  The worker decodes blocks of frames
+/// into a bounded queue while the frames before them are consumed, so that
+/// waiting on the disk rarely holds up the consumer.  Dropping the iterator
+/// stops the worker.
+pub struct Prefetching {
+  blocks:   Receiver<Vec<i32>>,
+  block:    Vec<i32>,
+  offset:   usize,
+  channels: usize
+}
+
+impl Prefetching {
+  pub(crate) fn new(file: WaveFile, depth: usize) -> Prefetching {
+    let channels         = file.channels();
+    let (sender, blocks) = sync_channel(depth);
+
+    thread::spawn(move || {
+      let frames   = (file.options.block_samples / channels).max(1);
+      let mut next = 0;
+
+      loop {
+        let mut block = vec![0; frames * channels];
+        let read      = file.read_frames(next, &mut block);
+
+        if read == 0 {
+          break;
+        }
+        block.truncate(read * channels);
+        next += read;
+
+        // the iterator was dropped.
+        if sender.send(block).is_err() {
+          break;
+        }
+      }
+    });
+
+    Prefetching {
+      blocks,
+      block:  Vec::new(),
+      offset: 0,
+      channels
+    }
+  }
+}
+
+impl Iterator for Prefetching {
+  type Item = Frame;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.offset == self.block.len() {
+      self.block  = self.blocks.recv().ok()?;
+      self.offset = 0;
+    }
+
+    let frame = self.block[self.offset..self.offset + self.channels].to_vec();
+
+    self.offset += self.channels;
+    Some(frame)
+  }
+}
@@ -0,0 +1,94 @@
+//! Progress reporting and cancellation for operations over whole files,
+//! so applications can show a progress bar and let users abort.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use error::WaveError;
+
+/// A flag cancelling the operations it is passed to, shared between the
+/// clones of a token so it can be set from another thread.
+#[derive(Debug,Clone,Default)]
+pub struct CancelToken {
+  cancelled: Arc<AtomicBool>
+}
+
+impl CancelToken {
+  pub fn new() -> CancelToken {
+    CancelToken::default()
+  }
+
+  /// Asks the operations using the token to stop.  They return
+  /// `WaveError::Cancelled` once they next check it.
+  pub fn cancel(&self) {
+    self.cancelled.store(true, Ordering::Relaxed);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::Relaxed)
+  }
+
+  /// Fails with `WaveError::Cancelled` once the token is cancelled.
+  pub(crate) fn check(&self) -> Result<(), WaveError> {
+    if self.is_cancelled() {
+      return Err(WaveError::Cancelled);
+    }
+
+    Ok(())
+  }
+}
+
+/// Reports how far an operation has got, as the amount of work done and
+/// the total, after each block, and checks a `CancelToken` as it goes.
+///
+/// # Example
+///
+/// ```
+/// use wavefile::{CancelToken,Progress,WaveFile};
+///
+/// let wav          = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+/// let mut reported = 0;
+///
+/// {
+///   let mut progress = Progress::new(|done, total| reported = done * 100 / total);
+///
+///   wav.read_all_with_progress(&mut progress).unwrap();
+/// }
+/// assert_eq!(reported, 100);
+///
+/// let cancel       = CancelToken::new();
+/// let mut progress = Progress::new(|_, _| ()).cancel_token(cancel.clone());
+///
+/// cancel.cancel();
+/// assert!(wav.read_all_with_progress(&mut progress).is_err());
+/// ```
+pub struct Progress<'a> {
+  report: Box<dyn FnMut(usize, usize) + Send + 'a>,
+  cancel: CancelToken
+}
+
+impl<'a> Progress<'a> {
+  /// Calls `report` with the work done and the total work as an operation
+  /// goes.
+  pub fn new<F: FnMut(usize, usize) + Send + 'a>(report: F) -> Progress<'a> {
+    Progress { report: Box::new(report), cancel: CancelToken::new() }
+  }
+
+  /// Stops the operation with `WaveError::Cancelled` once `cancel` is
+  /// cancelled.
+  pub fn cancel_token(mut self, cancel: CancelToken) -> Progress<'a> {
+    self.cancel = cancel;
+    self
+  }
+
+  /// Reports `done` out of `total`, then checks for cancellation.
+  pub(crate) fn update(&mut self, done: usize, total: usize) -> Result<(), WaveError> {
+    (self.report)(done, total);
+    self.cancel.check()
+  }
+
+  pub(crate) fn token(&self) -> &CancelToken {
+    &self.cancel
+  }
+}
+
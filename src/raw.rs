@@ -0,0 +1,92 @@
+use std::io::{self,Read};
+
+use WaveFile;
+
+/// Raw interleaved samples converted from the frames of a file, decoded a
+/// block at a time as they are read.
+struct RawReader<'a> {
+  file:    &'a WaveFile,
+  /// Appends the encoding of a sample to a block.
+  encode:  fn(f32, &mut Vec<u8>),
+  /// The index of the first frame not yet encoded into `block`.
+  next:    usize,
+  samples: Vec<f32>,
+  block:   Vec<u8>,
+  /// Position of the next byte to read within `block`.
+  offset:  usize
+}
+
+impl<'a> RawReader<'a> {
+  fn new(file: &'a WaveFile, encode: fn(f32, &mut Vec<u8>)) -> RawReader<'a> {
+    let channels = file.channels();
+
+    RawReader {
+      file,
+      encode,
+      next:    0,
+      samples: vec![0.0; (file.options.block_samples / channels).max(1) * channels],
+      block:   Vec::new(),
+      offset:  0
+    }
+  }
+}
+
+impl<'a> Read for RawReader<'a> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if self.offset == self.block.len() {
+      let frames = self.file.read_frames_f32_into(self.next, &mut self.samples);
+
+      self.block.clear();
+      for &sample in &self.samples[..frames * self.file.channels()] {
+        (self.encode)(sample, &mut self.block);
+      }
+      self.next  += frames;
+      self.offset = 0;
+    }
+
+    let count = buf.len().min(self.block.len() - self.offset);
+
+    buf[..count].copy_from_slice(&self.block[self.offset..self.offset + count]);
+    self.offset += count;
+    Ok(count)
+  }
+}
+
+fn f32le(sample: f32, out: &mut Vec<u8>) {
+  out.extend_from_slice(&sample.to_le_bytes());
+}
+
+fn s16le(sample: f32, out: &mut Vec<u8>) {
+  let sample = (sample * 32768.0).round().clamp(-32768.0, 32767.0) as i16;
+
+  out.extend_from_slice(&sample.to_le_bytes());
+}
+
+impl WaveFile {
+  /// Every frame of the file as raw interleaved little endian 32 bit
+  /// floats, converted like `read_frames_f32_into()`, for piping to tools
+  /// such as `ffmpeg -f f32le` or `sox -t f32`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use std::io::Read;
+  /// use wavefile::WaveFile;
+  ///
+  /// let wav     = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+  /// let mut raw = Vec::new();
+  ///
+  /// wav.as_f32le_reader().read_to_end(&mut raw).unwrap();
+  /// assert_eq!(raw.len(), wav.len() * wav.channels() * 4);
+  /// ```
+  pub fn as_f32le_reader(&self) -> impl Read + '_ {
+    RawReader::new(self, f32le)
+  }
+
+  /// Every frame of the file as raw interleaved little endian 16 bit
+  /// integers, rounded from the floats of `as_f32le_reader()` and clipped,
+  /// for piping to tools such as `ffmpeg -f s16le`.
+  pub fn as_s16le_reader(&self) -> impl Read + '_ {
+    RawReader::new(self, s16le)
+  }
+}
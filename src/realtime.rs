@@ -0,0 +1,178 @@
+use std::fs::File;
+use std::io::{Read,Seek,SeekFrom};
+
+use decode;
+use error::WaveError;
+use options::WaveFileOptions;
+use {WaveFile,WaveInfo};
+
+/// Reads the frames of a file in order for code running on or beside an
+/// audio thread, where a call must never stall for longer than it can be
+/// budgeted for.
+///
+/// `WaveFile` decodes straight from a memory map, so any frame may fault in
+/// a page and wait on the disk without warning.  This reader instead holds
+/// the file open and reads it through a buffer of `max_frames` frames,
+/// allocated when the file is opened along with everything else it needs.
+///
+/// # Latency
+///
+/// A call to `read_frames()` or `read_frames_f32_into()`:
+///
+/// * never allocates, locks or frees memory,
+/// * makes at most one `read` system call, of at most `max_frames` frames,
+///   and none once the end of the file is reached,
+/// * decodes no more frames than it reads, in time linear in their number.
+///
+/// So its worst case is that of one read of `max_frames * block_align`
+/// bytes from the file, plus decoding them.  A read may return fewer
+/// frames than asked for, which is not the end of the file; bytes of a
+/// partial frame are kept for the next call.  Only `seek()` makes any other
+/// system call.
+///
+/// # Example
+///
+/// ```
+/// use wavefile::RealtimeReader;
+///
+/// let mut reader = RealtimeReader::open("./fixtures/test-s24le.wav", 512).unwrap();
+/// // allocated before the audio thread starts.
+/// let mut buffer = vec![0.0; 512 * reader.channels()];
+/// let mut frames = 0;
+///
+/// loop {
+///   match reader.read_frames_f32_into(&mut buffer).unwrap() {
+///     0     => break,
+///     count => frames += count
+///   }
+/// }
+///
+/// assert_eq!(frames, reader.len());
+/// ```
+pub struct RealtimeReader {
+  file:     File,
+  /// The file's header, for its format and metadata.
+  header:   WaveFile,
+  /// The encoded frames read, beginning with `carry` bytes of a partial
+  /// frame left by the last read.
+  bytes:    Vec<u8>,
+  carry:    usize,
+  /// The index of the next frame to decode.
+  position: usize
+}
+
+impl RealtimeReader {
+  /// Opens the file at `path` to be read at most `max_frames` frames at a
+  /// time.
+  pub fn open<S: Into<String>>(path: S, max_frames: usize) -> Result<RealtimeReader, WaveError> {
+    RealtimeReader::open_with(path, WaveFileOptions::default(), max_frames)
+  }
+
+  /// Opens the file at `path` like `WaveFile::open_with`, to be read at
+  /// most `max_frames` frames at a time.
+  pub fn open_with<S: Into<String>>(path: S, options: WaveFileOptions, max_frames: usize) -> Result<RealtimeReader, WaveError> {
+    let path     = path.into();
+    let header   = WaveFile::open_with(path.as_str(), options)?;
+    let mut file = File::open(path)?;
+
+    file.seek(SeekFrom::Start(header.data_offset as u64))?;
+
+    Ok(RealtimeReader {
+      file,
+      bytes:    vec![0; max_frames.max(1) * header.stride],
+      header,
+      carry:    0,
+      position: 0
+    })
+  }
+
+  /// Decodes the next frames into `buffer`, interleaved and on the same
+  /// scale as `WaveFile::read_frames()`, returning their number.  Returns
+  /// zero only once every frame is read, or if `buffer` can't hold a
+  /// frame.
+  pub fn read_frames(&mut self, buffer: &mut [i32]) -> Result<usize, WaveError> {
+    let decoder = self.header.decoder;
+
+    self.pull(decoder, buffer)
+  }
+
+  /// Decodes the next frames into `buffer` like `read_frames()`, as floats
+  /// like `WaveFile::read_frames_f32_into()`.
+  pub fn read_frames_f32_into(&mut self, buffer: &mut [f32]) -> Result<usize, WaveError> {
+    let decoder = self.header.decoder_f32;
+
+    self.pull(decoder, buffer)
+  }
+
+  fn pull<T>(&mut self, decoder: decode::Decoder<T>, buffer: &mut [T]) -> Result<usize, WaveError> {
+    let channels = self.header.channels();
+    let stride   = self.header.stride;
+    let wanted   = (buffer.len() / channels).min(self.max_frames()).min(self.header.len() - self.position);
+
+    if wanted == 0 {
+      return Ok(0);
+    }
+
+    let read       = self.file.read(&mut self.bytes[self.carry..wanted * stride])?;
+    let available  = self.carry + read;
+    let frames     = available / stride;
+    let frame_size = channels * (self.header.info.bits_per_sample as usize / 8);
+
+    decode::decode_frames(decoder, channels, frame_size, stride, &self.bytes[..frames * stride],
+                          &mut buffer[..frames * channels]);
+    self.bytes.copy_within(frames * stride..available, 0);
+    self.carry     = available - frames * stride;
+    self.position += frames;
+    Ok(frames)
+  }
+
+  /// Moves to frame `frame`, or the end of the file if it lies beyond.
+  /// This makes a system call, so is best left to a thread other than the
+  /// audio thread.
+  pub fn seek(&mut self, frame: usize) -> Result<(), WaveError> {
+    let frame = frame.min(self.header.len());
+
+    self.file.seek(SeekFrom::Start((self.header.data_offset + frame * self.header.stride) as u64))?;
+    self.carry    = 0;
+    self.position = frame;
+    Ok(())
+  }
+
+  /// The index of the next frame to be read.
+  pub fn position(&self) -> usize {
+    self.position
+  }
+
+  /// The most frames decoded by one read.
+  pub fn max_frames(&self) -> usize {
+    self.bytes.len() / self.header.stride
+  }
+
+  /// The file's header, for its metadata.  Reading frames through it
+  /// touches the memory map, so isn't real-time safe.
+  pub fn file(&self) -> &WaveFile {
+    &self.header
+  }
+
+  /// See `WaveFile::info()`.
+  pub fn info(&self) -> WaveInfo {
+    self.header.info()
+  }
+
+  pub fn channels(&self) -> usize {
+    self.header.channels()
+  }
+
+  pub fn sample_rate(&self) -> usize {
+    self.header.sample_rate()
+  }
+
+  /// The total number of frames present in the file.
+  pub fn len(&self) -> usize {
+    self.header.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.header.is_empty()
+  }
+}
@@ -0,0 +1,198 @@
+use std::fs::{self,File};
+use std::io::{BufWriter,Write};
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+
+use error::WaveError;
+use {FourCC,check_riff,fourcc,RIFF,WAVE,FMT_,DATA};
+
+/// A single problem fixed by `repair`.
+#[derive(Debug,Clone,PartialEq)]
+pub enum Repair {
+  /// The RIFF header's size didn't match the chunks that follow it.
+  RiffSize { declared: u32, actual: u32 },
+  /// The data chunk's size was unset or ran past the end of the file.  The
+  /// audio now runs to the last complete frame.
+  DataSize { declared: u32, actual: u32 },
+  /// The block align didn't match the channel count and sample size.
+  BlockAlign { declared: u16, actual: u16 },
+  /// The byte rate didn't match the sample rate and block align.
+  ByteRate { declared: u32, actual: u32 },
+  /// A chunk after the audio ran past the end of the file and was dropped.
+  DroppedChunk { id: FourCC, offset: usize },
+  /// Bytes too few to hold a chunk were dropped from the end of the file.
+  TrailingBytes { count: usize }
+}
+
+/// What `repair` changed, in file order.
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct RepairReport {
+  pub repairs: Vec<Repair>
+}
+
+impl RepairReport {
+  /// Returns `true` if nothing needed fixing, so the copy is identical to
+  /// the original.
+  pub fn is_clean(&self) -> bool {
+    self.repairs.is_empty()
+  }
+}
+
+/// Writes a copy of the wavefile at `src` to `dst` with its RIFF and data
+/// chunk sizes recomputed from the file's real length, as needed for files
+/// left behind by recorders that crashed or lost power before finalizing
+/// them.  Any incomplete frame at the end of the audio is dropped.  If
+/// `fix_format` is set, the block align and byte rate are recomputed from
+/// the channel count, sample size and sample rate as well.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::repair;
+///
+/// let report = repair("./crashed.wav", "./repaired.wav", true).unwrap();
+///
+/// for fix in &report.repairs {
+///   println!("{:?}", fix);
+/// }
+/// ```
+pub fn repair<S: Into<String>, T: Into<String>>(src: S, dst: T, fix_format: bool) -> Result<RepairReport, WaveError> {
+  let bytes      = fs::read(src.into())?;
+  let mut report = RepairReport::default();
+
+  if bytes.len() < 12 {
+    return Err(WaveError::ParseError("Unexpected EOF".into()));
+  }
+  check_riff(&bytes)?;
+
+  let mut chunks = Vec::new();
+  let mut fmt    = None;
+  let mut data   = None;
+  let mut pos    = 12;
+
+  while pos + 8 <= bytes.len() {
+    let id        = LittleEndian::read_u32(&bytes[pos..pos + 4]);
+    let declared  = LittleEndian::read_u32(&bytes[pos + 4..pos + 8]);
+    let start     = pos + 8;
+    let available = bytes.len() - start;
+
+    if id == DATA && data.is_none() {
+      let unset = declared == 0 || declared == u32::MAX;
+      let size  = if unset || declared as usize > available { available } else { declared as usize };
+
+      data = Some((chunks.len(), declared));
+      chunks.push((id, start, size));
+
+      // an unset size means the recorder never got to write what follows.
+      if unset {
+        pos = bytes.len();
+        break;
+      }
+    } else if declared as usize > available {
+      report.repairs.push(Repair::DroppedChunk { id: fourcc(id), offset: start });
+      pos = bytes.len();
+      break;
+    } else {
+      if id == FMT_ && fmt.is_none() {
+        fmt = Some(chunks.len());
+      }
+      chunks.push((id, start, declared as usize));
+    }
+
+    pos = start + declared as usize + (declared as usize & 1);
+  }
+
+  if pos < bytes.len() {
+    report.repairs.push(Repair::TrailingBytes { count: bytes.len() - pos });
+  }
+
+  let fmt_index = match fmt {
+    Some(index) => index,
+    None        => return Err(WaveError::MissingChunk { id: *b"fmt ", offset: pos.min(bytes.len()) })
+  };
+  let (data_index, data_declared) = match data {
+    Some(data) => data,
+    None       => return Err(WaveError::MissingChunk { id: *b"data", offset: pos.min(bytes.len()) })
+  };
+
+  let (_, fmt_start, fmt_size) = chunks[fmt_index];
+
+  if fmt_size < 16 {
+    return Err(WaveError::ParseError(format!("Format chunk is only {} bytes long", fmt_size)));
+  }
+
+  let mut format  = bytes[fmt_start..fmt_start + fmt_size].to_vec();
+  let channels    = LittleEndian::read_u16(&format[2..4]);
+  let sample_rate = LittleEndian::read_u32(&format[4..8]);
+  let byte_rate   = LittleEndian::read_u32(&format[8..12]);
+  let block_align = LittleEndian::read_u16(&format[12..14]);
+  let bits        = LittleEndian::read_u16(&format[14..16]);
+  let frame_size  = channels as u32 * (bits as u32 / 8);
+
+  if frame_size == 0 || frame_size > u16::MAX as u32 {
+    let msg = format!("Invalid channel count {} or bits per sample {} value", channels, bits);
+    return Err(WaveError::ParseError(msg));
+  }
+
+  let mut stride = (block_align as u32).max(frame_size);
+
+  if fix_format {
+    let rate = sample_rate as u64 * frame_size as u64;
+
+    if block_align as u32 != frame_size {
+      report.repairs.push(Repair::BlockAlign { declared: block_align, actual: frame_size as u16 });
+      LittleEndian::write_u16(&mut format[12..14], frame_size as u16);
+    }
+
+    if byte_rate as u64 != rate && rate <= u32::MAX as u64 {
+      report.repairs.push(Repair::ByteRate { declared: byte_rate, actual: rate as u32 });
+      LittleEndian::write_u32(&mut format[8..12], rate as u32);
+    }
+
+    stride = frame_size;
+  }
+
+  let data_size = chunks[data_index].2 / stride as usize * stride as usize;
+
+  if data_size > u32::MAX as usize {
+    return Err(WaveError::WriteError("Data chunk exceeds 4 GiB".into()));
+  }
+
+  chunks[data_index].2 = data_size;
+
+  if data_size != data_declared as usize {
+    report.repairs.push(Repair::DataSize { declared: data_declared, actual: data_size as u32 });
+  }
+
+  let riff_size = chunks.iter().fold(4, |total, &(_, _, size)| total + 8 + size as u64 + (size & 1) as u64);
+  let declared  = LittleEndian::read_u32(&bytes[4..8]);
+
+  if riff_size > u32::MAX as u64 {
+    return Err(WaveError::WriteError("File exceeds 4 GiB".into()));
+  }
+
+  if riff_size != declared as u64 {
+    report.repairs.insert(0, Repair::RiffSize { declared, actual: riff_size as u32 });
+  }
+
+  let mut out = BufWriter::new(File::create(dst.into())?);
+
+  out.write_u32::<LittleEndian>(RIFF)?;
+  out.write_u32::<LittleEndian>(riff_size as u32)?;
+  out.write_u32::<LittleEndian>(WAVE)?;
+
+  for (index, &(id, start, size)) in chunks.iter().enumerate() {
+    let body = if index == fmt_index { &format[..] } else { &bytes[start..start + size] };
+
+    out.write_u32::<LittleEndian>(id)?;
+    out.write_u32::<LittleEndian>(size as u32)?;
+    out.write_all(body)?;
+
+    if size % 2 == 1 {
+      out.write_u8(0)?;
+    }
+  }
+
+  out.flush()?;
+  Ok(report)
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use rodio::{ChannelCount,Sample,SampleRate,Source};
+use rodio::source::SeekError;
+
+use error::WaveError;
+use WaveFile;
+
+/// The frames of a file as a rodio `Source`, decoded to floats a block at
+/// a time.
+///
+/// Returned by `WaveFile::into_source`.
+///
+/// # Example
+///
+/// ```
+/// extern crate rodio;
+/// extern crate wavefile;
+///
+/// use rodio::Source;
+/// use wavefile::WaveFile;
+///
+/// let wav    = WaveFile::open("./fixtures/test-s24le.wav").unwrap();
+/// let frames = wav.len();
+/// let source = wav.into_source().unwrap();
+///
+/// assert_eq!(source.channels().get(), 2);
+/// assert_eq!(source.count(), frames * 2);
+/// ```
+pub struct RodioSource {
+  file:        WaveFile,
+  channels:    ChannelCount,
+  sample_rate: SampleRate,
+  /// The index of the first frame not yet decoded into `block`.
+  next:        usize,
+  /// Samples of frames decoded ahead, reused from block to block.
+  block:       Vec<f32>,
+  /// Position of the next sample to yield within `block`.
+  offset:      usize
+}
+
+impl RodioSource {
+  fn new(file: WaveFile) -> Result<RodioSource, WaveError> {
+    let sample_rate = match SampleRate::new(file.info.sample_rate) {
+      Some(rate) => rate,
+      None       => return Err(WaveError::Unsupported("a sample rate of 0".into()))
+    };
+    let channels    = ChannelCount::new(file.info.channels).expect("WaveFile::open rejects files without channels");
+    let frames      = (file.options.block_samples / file.channels()).max(1);
+
+    Ok(RodioSource {
+      channels,
+      sample_rate,
+      next:   0,
+      block:  Vec::with_capacity(frames * file.channels()),
+      offset: 0,
+      file
+    })
+  }
+
+  /// The number of samples not yet yielded.
+  fn remaining(&self) -> usize {
+    (self.file.len() - self.next) * self.file.channels() + self.block.len() - self.offset
+  }
+}
+
+impl Iterator for RodioSource {
+  type Item = Sample;
+
+  fn next(&mut self) -> Option<Sample> {
+    if self.offset == self.block.len() {
+      let channels = self.file.channels();
+
+      self.block.resize(self.block.capacity(), 0.0);
+
+      let frames = self.file.read_frames_f32_into(self.next, &mut self.block);
+
+      self.block.truncate(frames * channels);
+      self.next  += frames;
+      self.offset = 0;
+      if frames == 0 {
+        return None;
+      }
+    }
+
+    self.offset += 1;
+    Some(self.block[self.offset - 1] as Sample)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let remaining = self.remaining();
+
+    (remaining, Some(remaining))
+  }
+}
+
+impl Source for RodioSource {
+  /// The file is a single span, lasting until its end.
+  fn current_span_len(&self) -> Option<usize> {
+    None
+  }
+
+  fn is_exhausted(&self) -> bool {
+    self.remaining() == 0
+  }
+
+  fn channels(&self) -> ChannelCount {
+    self.channels
+  }
+
+  fn sample_rate(&self) -> SampleRate {
+    self.sample_rate
+  }
+
+  fn total_duration(&self) -> Option<Duration> {
+    Some(Duration::from_secs_f64(self.file.len() as f64 / self.sample_rate.get() as f64))
+  }
+
+  /// Seeks to the frame at `pos`, or to the end of the file if it is
+  /// shorter.
+  fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+    let frame = (pos.as_secs_f64() * self.sample_rate.get() as f64) as usize;
+
+    self.next   = frame.min(self.file.len());
+    trace_event!(DEBUG, frame = self.next, "seek");
+    self.block.clear();
+    self.offset = 0;
+    Ok(())
+  }
+}
+
+impl WaveFile {
+  /// Plays the file through rodio.  See `RodioSource`.
+  ///
+  /// Fails if the file's sample rate is 0.
+  pub fn into_source(self) -> Result<RodioSource, WaveError> {
+    RodioSource::new(self)
+  }
+}
@@ -0,0 +1,202 @@
+use std::fmt::Write;
+use std::io::Cursor;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use error::WaveError;
+
+const LOOP_FORWARD   : u32 = 0;
+const LOOP_PING_PONG : u32 = 1;
+const LOOP_BACKWARD  : u32 = 2;
+
+/// How a sampler should play back a loop.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum LoopType {
+  /// Play from start to end, then jump back to the start.
+  Forward,
+  /// Alternate between playing forwards and backwards.
+  PingPong,
+  /// Play from end to start, then jump back to the end.
+  Backward,
+  /// A manufacturer specific loop type.
+  Other(u32)
+}
+
+impl LoopType {
+  pub fn decode(val: u32) -> LoopType {
+    match val {
+      LOOP_FORWARD   => LoopType::Forward,
+      LOOP_PING_PONG => LoopType::PingPong,
+      LOOP_BACKWARD  => LoopType::Backward,
+      other          => LoopType::Other(other)
+    }
+  }
+}
+
+/// A loop described by the `smpl` chunk.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct SampleLoop {
+  /// ID of the cue point associated with this loop.
+  pub cue_id:     u32,
+  pub loop_type:  LoopType,
+  /// First frame of the loop.
+  pub start:      u32,
+  /// Last frame of the loop, inclusive.
+  pub end:        u32,
+  /// Fraction of a frame at which to loop, where `0x80000000` is half a frame.
+  pub fraction:   u32,
+  /// Number of times to play the loop, or `0` to loop forever.
+  pub play_count: u32
+}
+
+/// Contents of the `smpl` chunk, describing how a sampler should play the
+/// file.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Sampler {
+  /// MIDI manufacturer association code of the sampler this was written for.
+  pub manufacturer:        u32,
+  pub product:             u32,
+  /// Duration of one sample in nanoseconds.
+  pub sample_period:       u32,
+  /// The MIDI note at which the sample plays back at its original pitch.
+  pub midi_unity_note:     u32,
+  /// Fraction of a semitone above the unity note, where `0x80000000` is
+  /// 50 cents.
+  pub midi_pitch_fraction: u32,
+  pub smpte_format:        u32,
+  pub smpte_offset:        u32,
+  pub loops:               Vec<SampleLoop>,
+  /// Manufacturer specific data following the loops.
+  pub sampler_data:        Vec<u8>
+}
+
+/// Contents of the `inst` chunk, describing how the file maps onto the keys
+/// and velocities of an instrument.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct Instrument {
+  /// The MIDI note at which the sample plays back at its original pitch.
+  pub base_note:     u8,
+  /// Pitch adjustment in cents, from -50 to +50.
+  pub detune:        i8,
+  /// Gain adjustment in decibels.
+  pub gain:          i8,
+  pub low_note:      u8,
+  pub high_note:     u8,
+  pub low_velocity:  u8,
+  pub high_velocity: u8
+}
+
+impl Instrument {
+  pub(crate) fn parse_chunk(data: &[u8]) -> Result<Instrument, WaveError> {
+    let mut cursor = Cursor::new(data);
+
+    Ok(Instrument {
+      base_note:     cursor.read_u8()?,
+      detune:        cursor.read_i8()?,
+      gain:          cursor.read_i8()?,
+      low_note:      cursor.read_u8()?,
+      high_note:     cursor.read_u8()?,
+      low_velocity:  cursor.read_u8()?,
+      high_velocity: cursor.read_u8()?
+    })
+  }
+}
+
+impl Sampler {
+  /// The fine tuning of the unity note, in cents.
+  pub fn fine_tune(&self) -> f64 {
+    self.midi_pitch_fraction as f64 * 100.0 / 4294967296.0
+  }
+
+  pub(crate) fn parse_chunk(data: &[u8]) -> Result<Sampler, WaveError> {
+    let mut cursor = Cursor::new(data);
+    let manufacturer        = cursor.read_u32::<LittleEndian>()?;
+    let product             = cursor.read_u32::<LittleEndian>()?;
+    let sample_period       = cursor.read_u32::<LittleEndian>()?;
+    let midi_unity_note     = cursor.read_u32::<LittleEndian>()?;
+    let midi_pitch_fraction = cursor.read_u32::<LittleEndian>()?;
+    let smpte_format        = cursor.read_u32::<LittleEndian>()?;
+    let smpte_offset        = cursor.read_u32::<LittleEndian>()?;
+    let loop_count          = cursor.read_u32::<LittleEndian>()? as usize;
+    let data_size           = cursor.read_u32::<LittleEndian>()? as usize;
+
+    if loop_count > (data.len() - 36) / 24 {
+      let msg = format!("Sampler chunk declares {} loops but only holds {} bytes", loop_count, data.len());
+      return Err(WaveError::ParseError(msg));
+    }
+
+    let mut loops = Vec::with_capacity(loop_count);
+
+    for _ in 0..loop_count {
+      let cue_id     = cursor.read_u32::<LittleEndian>()?;
+      let loop_type  = LoopType::decode(cursor.read_u32::<LittleEndian>()?);
+      let start      = cursor.read_u32::<LittleEndian>()?;
+      let end        = cursor.read_u32::<LittleEndian>()?;
+      let fraction   = cursor.read_u32::<LittleEndian>()?;
+      let play_count = cursor.read_u32::<LittleEndian>()?;
+
+      loops.push(SampleLoop { cue_id, loop_type, start, end, fraction, play_count });
+    }
+
+    let rest         = &data[cursor.position() as usize..];
+    let sampler_data = rest[..data_size.min(rest.len())].to_vec();
+
+    Ok(Sampler {
+      manufacturer,
+      product,
+      sample_period,
+      midi_unity_note,
+      midi_pitch_fraction,
+      smpte_format,
+      smpte_offset,
+      loops,
+      sampler_data
+    })
+  }
+}
+
+/// An SFZ `<region>` playing `sample` as described by a file's `smpl` and
+/// `inst` chunks.  See `WaveFile::sfz_region`.
+pub(crate) fn sfz_region(sample: &str, sampler: Option<&Sampler>, instrument: Option<Instrument>) -> String {
+  let mut sfz = format!("<region>\nsample={}\n", sample);
+
+  match (sampler, instrument) {
+    (Some(sampler), _) => {
+      // The sample sounds `fine_tune()` cents above its unity note.
+      let _ = writeln!(sfz, "pitch_keycenter={}", sampler.midi_unity_note);
+      let _ = writeln!(sfz, "tune={}", -sampler.fine_tune().round() as i32);
+    },
+    (None, Some(instrument)) => {
+      let _ = writeln!(sfz, "pitch_keycenter={}", instrument.base_note);
+      let _ = writeln!(sfz, "tune={}", instrument.detune);
+    },
+    (None, None) => {}
+  }
+
+  if let Some(instrument) = instrument {
+    let _ = writeln!(sfz, "lokey={}\nhikey={}", instrument.low_note, instrument.high_note);
+    let _ = writeln!(sfz, "lovel={}\nhivel={}", instrument.low_velocity, instrument.high_velocity);
+    let _ = writeln!(sfz, "volume={}", instrument.gain);
+  }
+
+  // SFZ plays a single loop per region.
+  match sampler.and_then(|sampler| sampler.loops.first()) {
+    Some(sample_loop) => {
+      let loop_type = match sample_loop.loop_type {
+        LoopType::PingPong => "alternate",
+        LoopType::Backward => "backward",
+        _                  => "forward"
+      };
+
+      let _ = writeln!(sfz, "loop_mode=loop_continuous\nloop_type={}", loop_type);
+      let _ = writeln!(sfz, "loop_start={}\nloop_end={}", sample_loop.start, sample_loop.end);
+      if sample_loop.play_count != 0 {
+        let _ = writeln!(sfz, "loop_count={}", sample_loop.play_count);
+      }
+    },
+    None if sampler.is_some() => sfz.push_str("loop_mode=no_loop\n"),
+    None => {}
+  }
+
+  sfz
+}
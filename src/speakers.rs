@@ -1,25 +1,26 @@
 
-const SPEAKER_FRONT_LEFT            : isize = (1 << 0);
-const SPEAKER_FRONT_RIGHT           : isize = (1 << 1);
-const SPEAKER_FRONT_CENTER          : isize = (1 << 2);
-const SPEAKER_LOW_FREQUENCY         : isize = (1 << 3);
-const SPEAKER_BACK_LEFT             : isize = (1 << 4);
-const SPEAKER_BACK_RIGHT            : isize = (1 << 5);
-const SPEAKER_FRONT_LEFT_OF_CENTER  : isize = (1 << 6);
-const SPEAKER_FRONT_RIGHT_OF_CENTER : isize = (1 << 7);
-const SPEAKER_BACK_CENTER           : isize = (1 << 8);
-const SPEAKER_SIDE_LEFT             : isize = (1 << 9);
-const SPEAKER_SIDE_RIGHT            : isize = (1 << 10);
-const SPEAKER_TOP_CENTER            : isize = (1 << 11);
-const SPEAKER_TOP_FRONT_LEFT        : isize = (1 << 12);
-const SPEAKER_TOP_FRONT_CENTER      : isize = (1 << 13);
-const SPEAKER_TOP_FRONT_RIGHT       : isize = (1 << 14);
-const SPEAKER_TOP_BACK_LEFT         : isize = (1 << 15);
-const SPEAKER_TOP_BACK_CENTER       : isize = (1 << 16);
-const SPEAKER_TOP_BACK_RIGHT        : isize = (1 << 17);
-const SPEAKER_RESERVED              : isize = (1 << 31);
+const SPEAKER_FRONT_LEFT            : isize = 1 << 0;
+const SPEAKER_FRONT_RIGHT           : isize = 1 << 1;
+const SPEAKER_FRONT_CENTER          : isize = 1 << 2;
+const SPEAKER_LOW_FREQUENCY         : isize = 1 << 3;
+const SPEAKER_BACK_LEFT             : isize = 1 << 4;
+const SPEAKER_BACK_RIGHT            : isize = 1 << 5;
+const SPEAKER_FRONT_LEFT_OF_CENTER  : isize = 1 << 6;
+const SPEAKER_FRONT_RIGHT_OF_CENTER : isize = 1 << 7;
+const SPEAKER_BACK_CENTER           : isize = 1 << 8;
+const SPEAKER_SIDE_LEFT             : isize = 1 << 9;
+const SPEAKER_SIDE_RIGHT            : isize = 1 << 10;
+const SPEAKER_TOP_CENTER            : isize = 1 << 11;
+const SPEAKER_TOP_FRONT_LEFT        : isize = 1 << 12;
+const SPEAKER_TOP_FRONT_CENTER      : isize = 1 << 13;
+const SPEAKER_TOP_FRONT_RIGHT       : isize = 1 << 14;
+const SPEAKER_TOP_BACK_LEFT         : isize = 1 << 15;
+const SPEAKER_TOP_BACK_CENTER       : isize = 1 << 16;
+const SPEAKER_TOP_BACK_RIGHT        : isize = 1 << 17;
+const SPEAKER_RESERVED              : isize = 1 << 31;
 
 #[derive(Debug,Copy,Clone,PartialEq)]
+#[allow(clippy::enum_clike_unportable_variant)]
 pub enum SpeakerPosition {
   FrontLeft          = SPEAKER_FRONT_LEFT,
   FrontRight         = SPEAKER_FRONT_RIGHT,
@@ -47,7 +48,8 @@ impl SpeakerPosition {
     let mut speakers = Vec::with_capacity(8);
     let mut i = SPEAKER_FRONT_LEFT;
 
-    while i < SPEAKER_RESERVED {
+    // bits past the last defined position are reserved.
+    while i <= SPEAKER_TOP_BACK_RIGHT {
       if bits & i != 0 {
         speakers.push(match i {
           SPEAKER_FRONT_LEFT            => SpeakerPosition::FrontLeft,
@@ -0,0 +1,73 @@
+//! Opening files read from a stream rather than mapped into memory.  Only
+//! the chunks before the audio are loaded, and parsed by the same scanner
+//! as `WaveFile::open`, while frames are read from the stream on demand.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use decode;
+use error::WaveError;
+use options::WaveFileOptions;
+use WaveFile;
+use DATA;
+
+/// The length of the RIFF header starting every file.
+pub(crate) const RIFF_HEADER : usize = 12;
+
+/// Given `bytes`, the start of a file ending in the header of a chunk,
+/// returns the number of bytes of the chunk's body to load, or `None` once
+/// the header is that of the data chunk.  At most `scan_end` bytes are
+/// loaded in all.
+pub(crate) fn chunk_body(bytes: &[u8], scan_end: usize, options: &WaveFileOptions) -> Result<Option<usize>, WaveError> {
+  let start      = bytes.len() - 8;
+  let chunk_id   = LittleEndian::read_u32(&bytes[start..start + 4]);
+  let chunk_size = LittleEndian::read_u32(&bytes[start + 4..]);
+
+  if chunk_id == DATA {
+    return Ok(None);
+  }
+
+  if let Some(max) = options.max_allocation {
+    if chunk_size as usize > max {
+      return Err(WaveError::LimitExceeded { limit: "max_allocation", max, found: chunk_size as usize });
+    }
+  }
+
+  // odd sized chunks are followed by a pad byte.
+  Ok(Some((chunk_size as usize + (chunk_size & 1) as usize).min(scan_end - bytes.len())))
+}
+
+/// Parses `bytes`, the chunks of a stream of `len` bytes up to and
+/// including the header of its data chunk, reading the size of the audio
+/// from the header of the data chunk rather than the bytes present.
+pub(crate) fn open_header(bytes: &[u8], len: usize, options: WaveFileOptions) -> Result<WaveFile, WaveError> {
+  let mut file  = WaveFile::from_bytes(bytes, options)?;
+  let size      = LittleEndian::read_u32(&bytes[file.data_offset - 4..file.data_offset]);
+  let available = len - file.data_offset;
+
+  // the scanner only saw the header of the data chunk.
+  file.info.data_size_untrusted = size == 0 || size == u32::MAX;
+  file.info.truncated           = !file.info.data_size_untrusted && size as usize > available;
+  file.data_size                = if file.info.data_size_untrusted { available } else { available.min(size as usize) };
+  file.count_frames();
+
+  Ok(file)
+}
+
+/// The byte range of the stream holding up to `frames` frames starting at
+/// frame `first`, and the number of frames within it.
+#[cfg(feature = "embedded-io")]
+pub(crate) fn frame_range(file: &WaveFile, first: usize, frames: usize) -> (u64, usize, usize) {
+  let frames = frames.min(file.len().saturating_sub(first));
+  let start  = file.data_offset + first.min(file.len()) * file.stride;
+
+  (start as u64, frames * file.stride, frames)
+}
+
+/// Decodes the encoded frames in `block` into `buffer`.
+pub(crate) fn decode_block<T>(file: &WaveFile, decoder: decode::Decoder<T>, block: &[u8], buffer: &mut [T]) {
+  let channels   = file.channels();
+  let frame_size = channels * (file.info.bits_per_sample as usize / 8);
+  let frames     = block.len() / file.stride;
+
+  decode::decode_frames(decoder, channels, frame_size, file.stride, block, &mut buffer[..frames * channels]);
+}
@@ -0,0 +1,382 @@
+use std::io::Read;
+
+use symphonia_core::audio::{AsGenericAudioBufferRef,Audio,AudioBuffer,AudioSpec,Channels,GenericAudioBufferRef,Position};
+use symphonia_core::codecs::{CodecInfo,CodecParameters};
+use symphonia_core::codecs::audio::{AudioCodecId,AudioCodecParameters,AudioDecoder,AudioDecoderOptions,FinalizeResult};
+use symphonia_core::codecs::audio::well_known::{CODEC_ID_PCM_U8,CODEC_ID_PCM_S16LE,CODEC_ID_PCM_S24LE,CODEC_ID_PCM_S32LE,
+                                                CODEC_ID_PCM_F32LE,CODEC_ID_PCM_F64LE};
+use symphonia_core::codecs::registry::{RegisterableAudioDecoder,SupportedAudioCodec};
+use symphonia_core::errors::{Error,Result,SeekErrorKind,unsupported_error};
+use symphonia_core::formats::prelude::*;
+use symphonia_core::formats::probe::{ProbeFormatData,ProbeableFormat,Score,Scoreable};
+use symphonia_core::formats::well_known::FORMAT_ID_WAVE;
+use symphonia_core::io::{MediaSourceStream,ReadBytes,ScopedStream};
+use symphonia_core::meta::{Metadata,MetadataBuilder,MetadataInfo,MetadataLog,RawTag,Tag};
+use symphonia_core::meta::well_known::METADATA_ID_WAVE;
+use symphonia_core::packet::PacketRef;
+use symphonia_core::{support_audio_codec,support_format};
+
+use decode;
+use error::WaveError;
+use formats::Format;
+use options::WaveFileOptions;
+use WaveFile;
+
+const FORMAT_INFO : FormatInfo = FormatInfo {
+  format:     FORMAT_ID_WAVE,
+  short_name: "wavefile",
+  long_name:  "Waveform Audio File Format (RIFF, RF64, BW64)"
+};
+
+const METADATA_INFO : MetadataInfo = MetadataInfo {
+  metadata:   METADATA_ID_WAVE,
+  short_name: "wavefile",
+  long_name:  "Waveform Audio File Format INFO tags"
+};
+
+/// A Symphonia `FormatReader` backed by this crate's parser, so that RF64
+/// and BW64 files and files with unusual chunk layouts play in
+/// Symphonia-based players.
+///
+/// The whole stream is read when the reader is created.  Packets hold
+/// whole frames of the file's samples, with any padding between frames
+/// removed, and can be decoded by `SymphoniaDecoder` or Symphonia's own
+/// PCM decoder.  The file's `INFO` tags are reported as metadata.
+///
+/// # Example
+///
+/// ```
+/// extern crate symphonia_core;
+/// extern crate wavefile;
+///
+/// use std::fs::File;
+/// use symphonia_core::formats::FormatReader;
+/// use symphonia_core::io::MediaSourceStream;
+/// use wavefile::SymphoniaReader;
+///
+/// let file       = File::open("./fixtures/test-s24le.wav").unwrap();
+/// let source     = MediaSourceStream::new(Box::new(file), Default::default());
+/// let mut reader = SymphoniaReader::try_new(source, Default::default()).unwrap();
+/// let mut frames = 0;
+///
+/// while let Some(packet) = reader.next_packet().unwrap() {
+///   frames += packet.dur.get();
+/// }
+/// assert_eq!(frames, reader.tracks()[0].num_frames.unwrap());
+/// ```
+pub struct SymphoniaReader<'s> {
+  source:     MediaSourceStream<'s>,
+  file:       WaveFile,
+  media_info: MediaInfo,
+  tracks:     Vec<Track>,
+  metadata:   MetadataLog,
+  /// Number of frames in each packet.
+  frames:     usize,
+  /// The index of the first frame of the next packet.
+  next:       usize
+}
+
+impl<'s> SymphoniaReader<'s> {
+  /// Reads the file from `source` with the default `WaveFileOptions`.
+  pub fn try_new(source: MediaSourceStream<'s>, opts: FormatOptions) -> Result<SymphoniaReader<'s>> {
+    SymphoniaReader::try_new_with(source, opts, WaveFileOptions::default())
+  }
+
+  /// Reads the file from `source`, parsing it with `options`.
+  pub fn try_new_with(mut source: MediaSourceStream<'s>, opts: FormatOptions, options: WaveFileOptions)
+                      -> Result<SymphoniaReader<'s>> {
+    let mut bytes = Vec::new();
+
+    source.read_to_end(&mut bytes)?;
+
+    let file   = WaveFile::from_bytes(&bytes, options).map_err(symphonia_error)?;
+    let frames = (file.options.block_samples / file.channels()).max(1);
+    let codec  = match codec(file.data_format(), file.bits_per_sample()) {
+      Some(codec) => codec,
+      None        => return unsupported_error("wavefile: unsupported sample format")
+    };
+
+    let mut params = AudioCodecParameters::new();
+
+    params.for_codec(codec)
+          .with_sample_rate(file.info.sample_rate)
+          .with_channels(channels(&file))
+          .with_bits_per_coded_sample(file.info.bits_per_sample as u32)
+          .with_bits_per_sample(file.info.valid_bps.unwrap_or(file.info.bits_per_sample) as u32)
+          .with_max_frames_per_packet(frames as u64);
+
+    let mut track = Track::new(0);
+
+    track.with_codec_params(CodecParameters::Audio(params))
+         .with_num_frames(file.len() as u64)
+         .with_duration(Duration::from(file.len() as u64));
+    if let Some(time_base) = TimeBase::try_from_recip(file.info.sample_rate) {
+      track.with_time_base(time_base);
+    }
+
+    let mut metadata = opts.external_data.metadata.unwrap_or_default();
+
+    if !file.tags().is_empty() {
+      let mut builder = MetadataBuilder::new(METADATA_INFO);
+
+      for (id, value) in &file.tags().tags {
+        builder.add_tag(Tag::new(RawTag::new(String::from_utf8_lossy(id), value.as_str())));
+      }
+      metadata.push(builder.build());
+    }
+
+    Ok(SymphoniaReader {
+      source,
+      media_info: MediaInfo::from_track(&track),
+      tracks:     vec![track],
+      file,
+      metadata,
+      frames,
+      next:       0
+    })
+  }
+
+  /// The parsed file, for metadata Symphonia has no place for.
+  pub fn file(&self) -> &WaveFile {
+    &self.file
+  }
+}
+
+impl Scoreable for SymphoniaReader<'_> {
+  fn score(mut source: ScopedStream<&mut MediaSourceStream<'_>>) -> Result<Score> {
+    let riff = source.read_quad_bytes()?;
+
+    source.ignore_bytes(4)?;
+
+    let wave = source.read_quad_bytes()?;
+
+    match (&riff, &wave) {
+      (b"RIFF", b"WAVE") => Ok(Score::Supported(255)),
+      (b"RF64", b"WAVE") => Ok(Score::Supported(255)),
+      (b"BW64", b"WAVE") => Ok(Score::Supported(255)),
+      _                  => Ok(Score::Unsupported)
+    }
+  }
+}
+
+impl<'s> ProbeableFormat<'s> for SymphoniaReader<'s> {
+  fn try_probe_new(source: MediaSourceStream<'s>, opts: FormatOptions) -> Result<Box<dyn FormatReader + 's>> {
+    Ok(Box::new(SymphoniaReader::try_new(source, opts)?))
+  }
+
+  fn probe_data() -> &'static [ProbeFormatData] {
+    &[
+      support_format!(
+        FORMAT_INFO,
+        &["wav", "wave", "rf64", "bw64"],
+        &["audio/vnd.wave", "audio/x-wav", "audio/wav", "audio/wave"],
+        &[b"RIFF", b"RF64", b"BW64"]
+      )
+    ]
+  }
+}
+
+impl FormatReader for SymphoniaReader<'_> {
+  fn format_info(&self) -> &FormatInfo {
+    &FORMAT_INFO
+  }
+
+  fn media_info(&self) -> &MediaInfo {
+    &self.media_info
+  }
+
+  fn metadata(&mut self) -> Metadata<'_> {
+    self.metadata.metadata()
+  }
+
+  fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+    let required_ts = match to {
+      SeekTo::Timestamp { ts, .. } => ts,
+      SeekTo::Time { time, .. }    => {
+        let time_base = self.tracks[0].time_base.ok_or(Error::SeekError(SeekErrorKind::Unseekable))?;
+
+        time_base.calc_timestamp(time).ok_or(Error::SeekError(SeekErrorKind::OutOfRange))?
+      }
+    };
+
+    // every frame can be reached directly, so seeks are always exact.
+    if required_ts.is_negative() || required_ts.get() as u64 > self.file.len() as u64 {
+      return Err(Error::SeekError(SeekErrorKind::OutOfRange));
+    }
+    self.next = required_ts.get() as usize;
+    trace_event!(DEBUG, frame = self.next, "seek");
+
+    Ok(SeekedTo { track_id: 0, required_ts, actual_ts: required_ts })
+  }
+
+  fn tracks(&self) -> &[Track] {
+    &self.tracks
+  }
+
+  fn next_packet(&mut self) -> Result<Option<Packet>> {
+    let file   = &self.file;
+    let frames = self.frames.min(file.len().saturating_sub(self.next));
+
+    if frames == 0 {
+      return Ok(None);
+    }
+
+    let frame_size = file.channels() * (file.bits_per_sample() / 8);
+    let start      = file.data_offset + self.next * file.stride;
+    let data       = &file.bytes()[start..start + frames * file.stride];
+    let body       = if file.stride == frame_size {
+      data.to_vec()
+    } else {
+      data.chunks(file.stride).flat_map(|frame| &frame[..frame_size]).cloned().collect()
+    };
+    let packet     = Packet::new(0, Timestamp::new(self.next as i64), Duration::from(frames as u64), body);
+
+    self.next += frames;
+    Ok(Some(packet))
+  }
+
+  fn into_inner<'a>(self: Box<Self>) -> MediaSourceStream<'a> where Self: 'a {
+    self.source
+  }
+}
+
+/// A Symphonia `AudioDecoder` for the PCM and float codecs `SymphoniaReader`
+/// produces, decoding packets to `f32` with the same decoders as
+/// `WaveFile::read_frames_f32_into()`.
+///
+/// Unsigned 8 bit audio is left to Symphonia's own PCM decoder.
+pub struct SymphoniaDecoder {
+  params:     AudioCodecParameters,
+  decoder:    decode::Decoder<f32>,
+  frame_size: usize,
+  samples:    Vec<f32>,
+  buffer:     AudioBuffer<f32>
+}
+
+impl SymphoniaDecoder {
+  pub fn try_new(params: &AudioCodecParameters, _opts: &AudioDecoderOptions) -> Result<SymphoniaDecoder> {
+    let (format, bytes_per_sample) = match params.codec {
+      CODEC_ID_PCM_S16LE => (Format::PCM, 2),
+      CODEC_ID_PCM_S24LE => (Format::PCM, 3),
+      CODEC_ID_PCM_S32LE => (Format::PCM, 4),
+      CODEC_ID_PCM_F32LE => (Format::IEEEFloat, 4),
+      CODEC_ID_PCM_F64LE => (Format::IEEEFloat, 8),
+      _                  => return unsupported_error("wavefile: unsupported codec")
+    };
+    let rate     = match params.sample_rate {
+      Some(rate) => rate,
+      None       => return unsupported_error("wavefile: sample rate is required")
+    };
+    let channels = match params.channels {
+      Some(ref channels) if channels.count() > 0 => channels.clone(),
+      _                                          => return unsupported_error("wavefile: channels are required")
+    };
+    let capacity = params.max_frames_per_packet.unwrap_or(0) as usize;
+
+    Ok(SymphoniaDecoder {
+      params:     params.clone(),
+      decoder:    decode::float_decoder(format, bytes_per_sample),
+      frame_size: channels.count() * bytes_per_sample,
+      samples:    Vec::new(),
+      buffer:     AudioBuffer::new(AudioSpec::new(rate, channels), capacity)
+    })
+  }
+}
+
+impl AudioDecoder for SymphoniaDecoder {
+  fn reset(&mut self) {
+    // packets are decoded independently of each other.
+  }
+
+  fn codec_info(&self) -> &CodecInfo {
+    &SymphoniaDecoder::supported_codecs().iter()
+      .find(|codec| codec.id == self.params.codec)
+      .expect("the codec was checked when the decoder was created")
+      .info
+  }
+
+  fn codec_params(&self) -> &AudioCodecParameters {
+    &self.params
+  }
+
+  fn decode_ref(&mut self, packet: &PacketRef<'_>) -> Result<GenericAudioBufferRef<'_>> {
+    let channels = self.buffer.spec().channels().count();
+    let frames   = packet.data.len() / self.frame_size;
+    let samples  = &mut self.samples;
+
+    samples.resize(frames * channels, 0.0);
+    (self.decoder)(&packet.data[..frames * self.frame_size], samples);
+
+    self.buffer.clear();
+    self.buffer.grow_capacity(frames);
+    self.buffer.render_with(Some(frames), |frame, planes| {
+      for (channel, plane) in planes.iter_mut().enumerate() {
+        plane[frame] = samples[frame * channels + channel];
+      }
+      Ok(())
+    })?;
+    Ok(self.buffer.as_generic_audio_buffer_ref())
+  }
+
+  fn finalize(&mut self) -> FinalizeResult {
+    Default::default()
+  }
+
+  fn last_decoded(&self) -> GenericAudioBufferRef<'_> {
+    self.buffer.as_generic_audio_buffer_ref()
+  }
+}
+
+impl RegisterableAudioDecoder for SymphoniaDecoder {
+  fn try_registry_new(params: &AudioCodecParameters, opts: &AudioDecoderOptions) -> Result<Box<dyn AudioDecoder>> {
+    Ok(Box::new(SymphoniaDecoder::try_new(params, opts)?))
+  }
+
+  fn supported_codecs() -> &'static [SupportedAudioCodec] {
+    &[
+      support_audio_codec!(CODEC_ID_PCM_S16LE, "pcm_s16le", "PCM Signed 16-bit Little-Endian Interleaved"),
+      support_audio_codec!(CODEC_ID_PCM_S24LE, "pcm_s24le", "PCM Signed 24-bit Little-Endian Interleaved"),
+      support_audio_codec!(CODEC_ID_PCM_S32LE, "pcm_s32le", "PCM Signed 32-bit Little-Endian Interleaved"),
+      support_audio_codec!(CODEC_ID_PCM_F32LE, "pcm_f32le", "PCM 32-bit Little-Endian Floating Point Interleaved"),
+      support_audio_codec!(CODEC_ID_PCM_F64LE, "pcm_f64le", "PCM 64-bit Little-Endian Floating Point Interleaved")
+    ]
+  }
+}
+
+/// The codec of samples of the format and size given.  Wave files store
+/// 8 bit samples unsigned.
+fn codec(format: Format, bits_per_sample: usize) -> Option<AudioCodecId> {
+  match (format, bits_per_sample) {
+    (Format::PCM, 8)        => Some(CODEC_ID_PCM_U8),
+    (Format::PCM, 16)       => Some(CODEC_ID_PCM_S16LE),
+    (Format::PCM, 24)       => Some(CODEC_ID_PCM_S24LE),
+    (Format::PCM, 32)       => Some(CODEC_ID_PCM_S32LE),
+    (Format::IEEEFloat, 32) => Some(CODEC_ID_PCM_F32LE),
+    (Format::IEEEFloat, 64) => Some(CODEC_ID_PCM_F64LE),
+    _                       => None
+  }
+}
+
+/// The speaker positions of the file's channels, from its channel mask
+/// when that names one speaker per channel.
+fn channels(file: &WaveFile) -> Channels {
+  let count = file.channels() as u32;
+  let mask  = file.info.channel_mask
+    .and_then(Position::from_wave_channel_mask)
+    .filter(|mask| mask.bits().count_ones() == count)
+    .or_else(|| Position::from_wave_channel_count(count));
+
+  match mask {
+    Some(mask) => Channels::Positioned(mask),
+    None       => Channels::Discrete(count as u16)
+  }
+}
+
+fn symphonia_error(e: WaveError) -> Error {
+  match e {
+    WaveError::IoError(e)            => Error::IoError(e),
+    WaveError::Unsupported(_)        => Error::Unsupported("wavefile: unsupported wave file"),
+    WaveError::LimitExceeded { .. }  => Error::LimitError("wavefile: file exceeds a configured limit"),
+    _                                => Error::DecodeError("wavefile: malformed wave file")
+  }
+}
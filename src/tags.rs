@@ -0,0 +1,159 @@
+use std::io::{Cursor,Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use error::WaveError;
+use text::TextEncoding;
+use FourCC;
+
+/// Clipboard format of a `DISP` chunk holding plain text.
+const CF_TEXT : u32 = 1;
+
+/// The text tags stored in a `LIST` chunk of type `INFO`, such as `INAM`
+/// (title) or `IART` (artist), in file order.
+#[derive(Debug,Clone,PartialEq,Default)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct InfoTags {
+  pub tags: Vec<(FourCC, String)>
+}
+
+impl InfoTags {
+  /// Returns the value of the first tag with the given ID.
+  pub fn get(&self, id: &FourCC) -> Option<&str> {
+    self.tags.iter().find(|tag| &tag.0 == id).map(|tag| tag.1.as_str())
+  }
+
+  /// Sets the value of the tag with the given ID, replacing the first
+  /// existing value or adding the tag at the end.
+  pub fn set<S: Into<String>>(&mut self, id: FourCC, value: S) {
+    let value = value.into();
+
+    match self.tags.iter_mut().find(|tag| tag.0 == id) {
+      Some(tag) => tag.1 = value,
+      None      => self.tags.push((id, value))
+    }
+  }
+
+  /// The `INAM` tag.
+  pub fn title(&self) -> Option<&str> {
+    self.get(b"INAM")
+  }
+
+  /// The `IART` tag.
+  pub fn artist(&self) -> Option<&str> {
+    self.get(b"IART")
+  }
+
+  /// The `IPRD` tag, naming the album or product the file belongs to.
+  pub fn product(&self) -> Option<&str> {
+    self.get(b"IPRD")
+  }
+
+  /// The `ICMT` tag.
+  pub fn comment(&self) -> Option<&str> {
+    self.get(b"ICMT")
+  }
+
+  /// The `ICRD` tag, holding the creation date.
+  pub fn creation_date(&self) -> Option<&str> {
+    self.get(b"ICRD")
+  }
+
+  /// The `IGNR` tag.
+  pub fn genre(&self) -> Option<&str> {
+    self.get(b"IGNR")
+  }
+
+  /// The `ICOP` tag.
+  pub fn copyright(&self) -> Option<&str> {
+    self.get(b"ICOP")
+  }
+
+  /// The `ISFT` tag, naming the software which created the file.
+  pub fn software(&self) -> Option<&str> {
+    self.get(b"ISFT")
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.tags.is_empty()
+  }
+
+  /// Writes the body of an `INFO` list, including the list type.
+  pub fn write_to<W: Write>(&self, out: &mut W) -> Result<(), WaveError> {
+    out.write_all(b"INFO")?;
+
+    for (id, value) in &self.tags {
+      // values are stored NUL terminated.
+      let size = value.len() + 1;
+
+      out.write_all(id)?;
+      out.write_u32::<LittleEndian>(size as u32)?;
+      out.write_all(value.as_bytes())?;
+      out.write_all(if size % 2 == 1 { b"\0\0" } else { b"\0" })?;
+    }
+
+    Ok(())
+  }
+
+  /// Parses the body of an `INFO` list, not including the list type.
+  pub(crate) fn parse_list(data: &[u8], encoding: TextEncoding) -> Result<InfoTags, WaveError> {
+    let mut tags   = Vec::new();
+    let mut cursor = Cursor::new(data);
+
+    while (cursor.position() as usize) + 8 <= data.len() {
+      let start = cursor.position() as usize;
+      let mut id = [0u8; 4];
+
+      id.copy_from_slice(&data[start..start + 4]);
+      cursor.set_position(start as u64 + 4);
+
+      let size = cursor.read_u32::<LittleEndian>()? as usize;
+      let body = start + 8;
+
+      if size > data.len() - body {
+        let msg = format!("INFO tag {} overruns its list", String::from_utf8_lossy(&id));
+        return Err(WaveError::ParseError(msg));
+      }
+
+      tags.push((id, encoding.decode_zstring(&data[body..body + size])));
+
+      // entries are word aligned.
+      cursor.set_position((body + size + (size & 1)) as u64);
+    }
+
+    Ok(InfoTags { tags })
+  }
+}
+
+/// Contents of a `DISP` chunk, an object meant to represent the file when it
+/// is displayed, stored in a Windows clipboard format.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Disp {
+  /// The clipboard format of `data`, e.g. `1` for `CF_TEXT`.
+  pub format: u32,
+  pub data:   Vec<u8>
+}
+
+impl Disp {
+  /// The text of the chunk, if it is stored as `CF_TEXT`.
+  pub fn text(&self) -> Option<String> {
+    self.decode_text(TextEncoding::default())
+  }
+
+  /// The text of the chunk decoded with the given encoding, if it is stored
+  /// as `CF_TEXT`, which uses the code page of the system that wrote it.
+  pub fn decode_text(&self, encoding: TextEncoding) -> Option<String> {
+    if self.format == CF_TEXT {
+      Some(encoding.decode_zstring(&self.data))
+    } else {
+      None
+    }
+  }
+
+  pub(crate) fn parse_chunk(data: &[u8]) -> Result<Disp, WaveError> {
+    let mut cursor = Cursor::new(data);
+    let format     = cursor.read_u32::<LittleEndian>()?;
+
+    Ok(Disp { format, data: data[4..].to_vec() })
+  }
+}
@@ -0,0 +1,46 @@
+/// Characters of Windows-1252 in the range `0x80` to `0x9f`, where it differs
+/// from Latin-1.  Unassigned bytes map to the C1 control at the same position.
+const WINDOWS_1252_HIGH : [char; 32] = [
+  '\u{20ac}', '\u{0081}', '\u{201a}', '\u{0192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+  '\u{02c6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008d}', '\u{017d}', '\u{008f}',
+  '\u{0090}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+  '\u{02dc}', '\u{2122}', '\u{0161}', '\u{203a}', '\u{0153}', '\u{009d}', '\u{017e}', '\u{0178}'
+];
+
+/// The character encoding used to decode text stored in metadata chunks such
+/// as `INFO`, `bext`, `cart` and the associated data list.
+///
+/// The specifications leave the encoding of these strings open, and files
+/// written on Windows frequently use the system code page rather than UTF-8.
+#[derive(Debug,Copy,Clone,PartialEq,Default)]
+pub enum TextEncoding {
+  /// UTF-8, replacing invalid sequences with `U+FFFD`.
+  #[default]
+  Utf8,
+  /// ISO 8859-1, where every byte is the code point of the same value.
+  Latin1,
+  /// The Windows Western European code page.
+  Windows1252
+}
+
+impl TextEncoding {
+  /// Decodes `bytes` into a string.
+  pub fn decode(&self, bytes: &[u8]) -> String {
+    match *self {
+      TextEncoding::Utf8        => String::from_utf8_lossy(bytes).into_owned(),
+      TextEncoding::Latin1      => bytes.iter().map(|&b| b as char).collect(),
+      TextEncoding::Windows1252 => bytes.iter().map(|&b| {
+        match b {
+          0x80..=0x9f => WINDOWS_1252_HIGH[b as usize - 0x80],
+          _           => b as char
+        }
+      }).collect()
+    }
+  }
+
+  /// Decodes a string terminated by a NUL byte or the end of the data.
+  pub fn decode_zstring(&self, bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    self.decode(&bytes[..end])
+  }
+}
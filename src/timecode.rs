@@ -0,0 +1,191 @@
+use std::fmt;
+use std::str::FromStr;
+
+use error::WaveError;
+
+/// Frames in ten minutes of 29.97 fps drop frame timecode.
+const DROP_FRAMES_PER_10_MINUTES : u64 = 17982;
+/// Frames in a minute of 29.97 fps drop frame timecode not divisible by ten.
+const DROP_FRAMES_PER_MINUTE     : u64 = 1798;
+
+/// A SMPTE timecode frame rate.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum FrameRate {
+  /// 24000/1001 frames per second, counted as 24.
+  Fps23976,
+  Fps24,
+  Fps25,
+  /// 30000/1001 frames per second, counted as 30.
+  Fps2997,
+  /// 30000/1001 frames per second, skipping frame numbers 0 and 1 at the
+  /// start of every minute not divisible by ten to stay in step with the
+  /// clock.
+  Fps2997Drop,
+  Fps30
+}
+
+impl FrameRate {
+  /// The number of frames counted per timecode second.
+  pub fn nominal(&self) -> u32 {
+    match *self {
+      FrameRate::Fps23976 | FrameRate::Fps24 => 24,
+      FrameRate::Fps25                       => 25,
+      _                                      => 30
+    }
+  }
+
+  /// Returns `true` for drop frame timecode.
+  pub fn is_drop_frame(&self) -> bool {
+    *self == FrameRate::Fps2997Drop
+  }
+
+  /// The actual frame rate as a fraction.
+  fn ratio(&self) -> (u64, u64) {
+    match *self {
+      FrameRate::Fps23976                         => (24000, 1001),
+      FrameRate::Fps2997 | FrameRate::Fps2997Drop => (30000, 1001),
+      other                                       => (other.nominal() as u64, 1)
+    }
+  }
+}
+
+/// A SMPTE timecode of the form `hh:mm:ss:ff`, or `hh:mm:ss;ff` for drop
+/// frame timecode.  Hours wrap around at midnight.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct Timecode {
+  pub hours:      u8,
+  pub minutes:    u8,
+  pub seconds:    u8,
+  pub frames:     u8,
+  pub drop_frame: bool
+}
+
+impl Timecode {
+  /// The timecode `hh:mm:ss:ff` at `rate`, failing unless it names a frame
+  /// the rate counts: hours to 23, frames below the rate's `nominal()`, and
+  /// for drop frame timecode not a frame number it skips.
+  pub fn new(hours: u8, minutes: u8, seconds: u8, frames: u8, rate: FrameRate) -> Result<Timecode, WaveError> {
+    let timecode = Timecode { hours, minutes, seconds, frames, drop_frame: rate.is_drop_frame() };
+
+    if !timecode.is_valid(rate.nominal()) {
+      return Err(WaveError::ParseError(format!("Invalid timecode {} at {} frames per second", timecode, rate.nominal())));
+    }
+
+    Ok(timecode)
+  }
+
+  /// Parses `hh:mm:ss:ff` like `from_str`, but checks the frames against
+  /// `rate`, which also decides whether the timecode is drop frame.
+  pub fn parse(s: &str, rate: FrameRate) -> Result<Timecode, WaveError> {
+    let timecode = s.parse::<Timecode>()?;
+
+    Timecode::new(timecode.hours, timecode.minutes, timecode.seconds, timecode.frames, rate)
+  }
+
+  /// The timecode of the frame `count` frames after midnight.
+  pub fn from_frames(count: u64, rate: FrameRate) -> Timecode {
+    let nominal = rate.nominal() as u64;
+    let mut count = count;
+
+    if rate.is_drop_frame() {
+      let tens      = count / DROP_FRAMES_PER_10_MINUTES;
+      let remainder = count % DROP_FRAMES_PER_10_MINUTES;
+
+      count += 18 * tens;
+      if remainder > 1 {
+        count += 2 * ((remainder - 2) / DROP_FRAMES_PER_MINUTE);
+      }
+    }
+
+    let seconds = count / nominal;
+
+    Timecode {
+      hours:      (seconds / 3600 % 24) as u8,
+      minutes:    (seconds / 60 % 60) as u8,
+      seconds:    (seconds % 60) as u8,
+      frames:     (count % nominal) as u8,
+      drop_frame: rate.is_drop_frame()
+    }
+  }
+
+  /// The number of frames from midnight to this timecode, which should be
+  /// valid at `rate`, as checked by `new`.
+  pub fn to_frames(&self, rate: FrameRate) -> u64 {
+    let minutes = self.hours as u64 * 60 + self.minutes as u64;
+    let count   = (minutes * 60 + self.seconds as u64) * rate.nominal() as u64 + self.frames as u64;
+
+    if rate.is_drop_frame() {
+      count - 2 * (minutes - minutes / 10)
+    } else {
+      count
+    }
+  }
+
+  /// The timecode of the frame containing the sample `samples` samples
+  /// after midnight, e.g. a `bext` time reference.
+  pub fn from_samples(samples: u64, sample_rate: u32, rate: FrameRate) -> Timecode {
+    let (num, den) = rate.ratio();
+    let frames     = samples as u128 * num as u128 / (den as u128 * sample_rate.max(1) as u128);
+
+    Timecode::from_frames(frames as u64, rate)
+  }
+
+  /// The first sample, counted from midnight, within the frame of this
+  /// timecode.
+  pub fn to_samples(&self, sample_rate: u32, rate: FrameRate) -> u64 {
+    let (num, den) = rate.ratio();
+    let scaled     = self.to_frames(rate) as u128 * den as u128 * sample_rate as u128;
+
+    scaled.div_ceil(num as u128) as u64
+  }
+
+  /// Returns `true` if the fields name a frame of a rate counting `nominal`
+  /// frames per second.
+  fn is_valid(&self, nominal: u32) -> bool {
+    let skipped = self.drop_frame && self.seconds == 0 && self.frames < 2 && !self.minutes.is_multiple_of(10);
+
+    self.hours < 24 && self.minutes < 60 && self.seconds < 60 && (self.frames as u32) < nominal && !skipped
+  }
+}
+
+impl fmt::Display for Timecode {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let separator = if self.drop_frame { ';' } else { ':' };
+
+    write!(f, "{:02}:{:02}:{:02}{}{:02}", self.hours, self.minutes, self.seconds, separator, self.frames)
+  }
+}
+
+impl FromStr for Timecode {
+  type Err = WaveError;
+
+  /// Parses `hh:mm:ss:ff`, where a `;` or `.` before the frames denotes drop
+  /// frame timecode.  Without a frame rate the frames are only checked to
+  /// be below 30, the most any rate counts, so `Timecode::parse` should be
+  /// preferred where the rate is known.
+  fn from_str(s: &str) -> Result<Timecode, WaveError> {
+    let invalid    = || WaveError::ParseError(format!("Invalid timecode {:?}", s));
+    let drop_frame = s.contains(';') || s.contains('.');
+    let fields     = s.split(&[':', ';', '.'][..])
+      .map(|field| field.parse::<u8>().map_err(|_| invalid()))
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if fields.len() != 4 {
+      return Err(invalid());
+    }
+
+    let timecode = Timecode {
+      hours:      fields[0],
+      minutes:    fields[1],
+      seconds:    fields[2],
+      frames:     fields[3],
+      drop_frame
+    };
+
+    if !timecode.is_valid(FrameRate::Fps30.nominal()) {
+      return Err(invalid());
+    }
+
+    Ok(timecode)
+  }
+}
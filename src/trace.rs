@@ -0,0 +1,26 @@
+//! Instrumentation through `tracing`, which expands to nothing unless the
+//! `tracing` feature is enabled.
+
+/// Enters a span at the given level for the rest of the enclosing block,
+/// as `let _span = trace_span!(DEBUG, "name", fields...);`.
+#[cfg(feature = "tracing")]
+macro_rules! trace_span {
+  ($level:ident, $($arg:tt)*) => { ::tracing::span!(::tracing::Level::$level, $($arg)*).entered() }
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_span {
+  ($level:ident, $($arg:tt)*) => { () }
+}
+
+/// Emits an event at the given level, as
+/// `trace_event!(WARN, field = value, "message");`.
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+  ($level:ident, $($arg:tt)*) => { ::tracing::event!(::tracing::Level::$level, $($arg)*) }
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_event {
+  ($level:ident, $($arg:tt)*) => { () }
+}
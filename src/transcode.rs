@@ -0,0 +1,1361 @@
+//! A pipeline of transforms applied to audio as it is copied from one file
+//! to another, such as level changes, so that simple processing doesn't
+//! need a round trip through an audio editor.
+//!
+//! Stages work on interleaved samples as `f64`s between -1.0 and 1.0,
+//! whatever the formats of the files, a block of frames at a time.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use wavefile::Format;
+//! use wavefile::transcode::{Clipping,Gain,Pipeline};
+//!
+//! let mut pipeline = Pipeline::new().then(Gain::new(-6.0).clipping(Clipping::Error));
+//!
+//! pipeline.transcode("./in.wav", "./quieter.wav", 24, Format::PCM).unwrap();
+//! ```
+
+use std::f64::consts::{FRAC_1_SQRT_2,FRAC_PI_2,PI};
+use std::io::{Seek,Write};
+use std::mem;
+use std::sync::{Arc,Mutex};
+use std::time::Duration;
+
+use error::WaveError;
+use formats::Format;
+use writer::{WaveSpec,WaveWriter};
+use WaveFile;
+
+/// Number of frames read from the source at a time.
+const BLOCK_FRAMES : usize = 4096;
+
+/// The shape of the audio passing between two stages of a `Pipeline`.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct Layout {
+  pub channels:    usize,
+  pub sample_rate: u32,
+  /// The number of frames, known before any are processed.
+  pub frames:      u64
+}
+
+/// A stage of a `Pipeline`, processing interleaved frames.
+///
+/// A stage can also be used on its own, by calling `prepare` with the
+/// layout of the audio and then `process` on each block in turn.
+pub trait Transform {
+  /// Called once before any audio with the layout of the audio the stage
+  /// receives, returning the layout of the audio it produces.  The layout
+  /// is unchanged by default.
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    Ok(layout)
+  }
+
+  /// Replaces the whole frames in `samples` with the stage's output for
+  /// them.
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError>;
+
+  /// Called once the input has ended, to append any frames the stage still
+  /// holds to `samples`.
+  fn finish(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    let _ = samples;
+    Ok(())
+  }
+}
+
+/// How a stage treats samples it drives past full scale.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Default)]
+pub enum Clipping {
+  /// Clamps samples to full scale.
+  #[default]
+  Clip,
+  /// Fails with `WaveError::Clipped`.
+  Error,
+  /// Leaves samples beyond full scale, for a later stage to bring down or
+  /// for float output to keep.  PCM output clips them as they are written.
+  Headroom
+}
+
+impl Clipping {
+  /// Applies the policy to `samples`, interleaved frames of `channels`
+  /// channels, with `first` frames before them.
+  fn apply(self, samples: &mut [f64], channels: usize, first: u64) -> Result<(), WaveError> {
+    match self {
+      Clipping::Clip     => samples.iter_mut().for_each(|sample| *sample = sample.clamp(-1.0, 1.0)),
+      Clipping::Error    => {
+        if let Some(index) = samples.iter().position(|sample| sample.abs() > 1.0) {
+          return Err(WaveError::Clipped { frame: first + (index / channels) as u64 });
+        }
+      },
+      Clipping::Headroom => ()
+    }
+
+    Ok(())
+  }
+}
+
+/// Changes the level of the audio by a number of decibels.
+#[derive(Debug,Clone)]
+pub struct Gain {
+  factor:   f64,
+  clipping: Clipping,
+  channels: usize,
+  /// The number of frames processed.
+  frames:   u64
+}
+
+impl Gain {
+  /// Changes the level by `db` decibels, clipping at full scale.
+  pub fn new(db: f64) -> Gain {
+    Gain::linear(10f64.powf(db / 20.0))
+  }
+
+  /// Multiplies every sample by `factor`, clipping at full scale.
+  pub fn linear(factor: f64) -> Gain {
+    Gain { factor, clipping: Clipping::default(), channels: 1, frames: 0 }
+  }
+
+  /// Sets how samples driven past full scale are treated.
+  pub fn clipping(mut self, clipping: Clipping) -> Gain {
+    self.clipping = clipping;
+    self
+  }
+}
+
+impl Transform for Gain {
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    self.channels = layout.channels;
+    self.frames   = 0;
+    Ok(layout)
+  }
+
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    samples.iter_mut().for_each(|sample| *sample *= self.factor);
+    self.clipping.apply(samples, self.channels, self.frames)?;
+    self.frames += (samples.len() / self.channels) as u64;
+    Ok(())
+  }
+}
+
+/// The shape of a change in level over time.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Default)]
+pub enum Curve {
+  /// Changes the gain at a constant rate.
+  #[default]
+  Linear,
+  /// Follows a quarter sine, keeping the power of two uncorrelated signals
+  /// faded against each other constant.
+  EqualPower
+}
+
+impl Curve {
+  /// The gain `position` of the way through a fade in, from 0.0 to 1.0.
+  pub fn gain(self, position: f64) -> f64 {
+    let position = position.clamp(0.0, 1.0);
+
+    match self {
+      Curve::Linear     => position,
+      Curve::EqualPower => (position * FRAC_PI_2).sin()
+    }
+  }
+}
+
+/// Raises the level of the start of the audio from silence.
+#[derive(Debug,Clone)]
+pub struct FadeIn {
+  length:   Duration,
+  curve:    Curve,
+  channels: usize,
+  /// The length of the fade, and the number of frames processed.
+  frames:   u64,
+  position: u64
+}
+
+impl FadeIn {
+  /// Fades in over `length`, linearly.
+  pub fn new(length: Duration) -> FadeIn {
+    FadeIn { length, curve: Curve::default(), channels: 1, frames: 0, position: 0 }
+  }
+
+  pub fn curve(mut self, curve: Curve) -> FadeIn {
+    self.curve = curve;
+    self
+  }
+}
+
+impl Transform for FadeIn {
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    self.channels = layout.channels;
+    self.frames   = duration_frames(self.length, layout.sample_rate);
+    self.position = 0;
+    Ok(layout)
+  }
+
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    for frame in samples.chunks_exact_mut(self.channels) {
+      if self.position >= self.frames {
+        break;
+      }
+
+      let gain = self.curve.gain(self.position as f64 / self.frames as f64);
+
+      frame.iter_mut().for_each(|sample| *sample *= gain);
+      self.position += 1;
+    }
+
+    Ok(())
+  }
+}
+
+/// Lowers the level of the end of the audio to silence.
+#[derive(Debug,Clone)]
+pub struct FadeOut {
+  length:   Duration,
+  curve:    Curve,
+  channels: usize,
+  /// The frame the fade starts at, and the number of frames processed.
+  start:    u64,
+  end:      u64,
+  position: u64
+}
+
+impl FadeOut {
+  /// Fades out over `length`, linearly, ending with the audio.
+  pub fn new(length: Duration) -> FadeOut {
+    FadeOut { length, curve: Curve::default(), channels: 1, start: 0, end: 0, position: 0 }
+  }
+
+  pub fn curve(mut self, curve: Curve) -> FadeOut {
+    self.curve = curve;
+    self
+  }
+}
+
+impl Transform for FadeOut {
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    self.channels = layout.channels;
+    self.end      = layout.frames;
+    self.start    = layout.frames.saturating_sub(duration_frames(self.length, layout.sample_rate));
+    self.position = 0;
+    Ok(layout)
+  }
+
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    let length = (self.end - self.start) as f64;
+
+    for frame in samples.chunks_exact_mut(self.channels) {
+      if self.position >= self.start {
+        // the last frame is the first of the silence, as is anything a
+        // stage before adds after it.
+        let gain = if self.position < self.end {
+          self.curve.gain((self.end - self.position - 1) as f64 / length)
+        } else {
+          0.0
+        };
+
+        frame.iter_mut().for_each(|sample| *sample *= gain);
+      }
+      self.position += 1;
+    }
+
+    Ok(())
+  }
+}
+
+/// Mixes the channels of the audio down to fewer, such as stereo to mono
+/// for telephony or a podcast feed.
+///
+/// Each output channel is a weighted sum of the input channels, given by a
+/// matrix with a row of weights for each output channel and a column for
+/// each input channel.
+///
+/// # Example
+///
+/// A downmix can be used on its own, outside a pipeline.
+///
+/// ```
+/// use wavefile::transcode::{Downmix,Layout,Transform};
+///
+/// let mut mix     = Downmix::matrix(vec![vec![0.5, 0.5]]);
+/// let mut samples = vec![1.0, 0.0, 0.5, 0.5];
+///
+/// let layout = mix.prepare(Layout { channels: 2, sample_rate: 8000, frames: 2 }).unwrap();
+///
+/// mix.process(&mut samples).unwrap();
+/// assert_eq!(layout.channels, 1);
+/// assert_eq!(samples, vec![0.5, 0.5]);
+/// ```
+#[derive(Debug,Clone)]
+pub struct Downmix {
+  /// The weights of the input channels in each output channel, or `None`
+  /// for every channel at -3 dB into one.
+  matrix:   Option<Vec<Vec<f64>>>,
+  clipping: Clipping,
+  weights:  Vec<Vec<f64>>,
+  frames:   u64,
+  mixed:    Vec<f64>
+}
+
+/// The weight of each channel mixed into mono by `Downmix::new`, -3 dB.
+const DOWNMIX_WEIGHT : f64 = 0.707_945_784_384_137_9;
+
+impl Downmix {
+  /// Mixes every channel into one, each lowered by 3 dB, clipping at full
+  /// scale.  This keeps the level of uncorrelated channels, while material
+  /// common to two channels comes out 3 dB louder.
+  pub fn new() -> Downmix {
+    Downmix { matrix: None, clipping: Clipping::default(), weights: Vec::new(), frames: 0, mixed: Vec::new() }
+  }
+
+  /// Mixes the channels by `matrix`, whose rows hold the weights of each
+  /// input channel in an output channel, clipping at full scale.
+  pub fn matrix(matrix: Vec<Vec<f64>>) -> Downmix {
+    Downmix { matrix: Some(matrix), ..Downmix::new() }
+  }
+
+  /// Sets how samples driven past full scale are treated.
+  pub fn clipping(mut self, clipping: Clipping) -> Downmix {
+    self.clipping = clipping;
+    self
+  }
+}
+
+impl Default for Downmix {
+  fn default() -> Downmix {
+    Downmix::new()
+  }
+}
+
+impl Transform for Downmix {
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    self.weights = match self.matrix {
+      Some(ref matrix) => matrix.clone(),
+      None             => vec![vec![DOWNMIX_WEIGHT; layout.channels]]
+    };
+
+    if self.weights.is_empty() || self.weights.iter().any(|row| row.len() != layout.channels) {
+      return Err(WaveError::Unsupported(format!("The downmix needs a row of {} weights for each output channel", layout.channels)));
+    }
+
+    self.frames = 0;
+    Ok(Layout { channels: self.weights.len(), ..layout })
+  }
+
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    let inputs = self.weights[0].len();
+
+    self.mixed.clear();
+    for frame in samples.chunks_exact(inputs) {
+      for row in &self.weights {
+        self.mixed.push(row.iter().zip(frame).map(|(weight, sample)| weight * sample).sum());
+      }
+    }
+
+    self.clipping.apply(&mut self.mixed, self.weights.len(), self.frames)?;
+    self.frames += (samples.len() / inputs) as u64;
+    mem::swap(samples, &mut self.mixed);
+    Ok(())
+  }
+}
+
+/// Copies mono audio into several channels, each at its own gain, for
+/// building test material or a speaker check that plays in one channel.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::Format;
+/// use wavefile::transcode::{Pipeline,Upmix};
+///
+/// // a tone in the centre channel of a 5.1 file only.
+/// let mut pipeline = Pipeline::new().then(Upmix::route(6, 2));
+///
+/// pipeline.transcode("./tone.wav", "./centre-check.wav", 24, Format::PCM).unwrap();
+/// ```
+#[derive(Debug,Clone)]
+pub struct Upmix {
+  /// The factor the source is multiplied by in each channel.
+  gains:    Vec<f64>,
+  clipping: Clipping,
+  frames:   u64,
+  mixed:    Vec<f64>
+}
+
+impl Upmix {
+  /// Copies the audio into `channels` channels unchanged.
+  pub fn new(channels: usize) -> Upmix {
+    Upmix::linear(vec![1.0; channels])
+  }
+
+  /// Copies the audio into `channel` of `channels` channels, the others
+  /// silent.
+  pub fn route(channels: usize, channel: usize) -> Upmix {
+    Upmix::linear((0..channels).map(|index| if index == channel { 1.0 } else { 0.0 }).collect())
+  }
+
+  /// Copies the audio into a channel for each of `factors`, multiplied by
+  /// it.
+  pub fn linear(factors: Vec<f64>) -> Upmix {
+    Upmix { gains: factors, clipping: Clipping::default(), frames: 0, mixed: Vec::new() }
+  }
+
+  /// Changes the level of `channel` by `db` decibels.
+  pub fn gain(mut self, channel: usize, db: f64) -> Upmix {
+    if let Some(gain) = self.gains.get_mut(channel) {
+      *gain *= 10f64.powf(db / 20.0);
+    }
+
+    self
+  }
+
+  /// Sets how samples driven past full scale are treated.
+  pub fn clipping(mut self, clipping: Clipping) -> Upmix {
+    self.clipping = clipping;
+    self
+  }
+}
+
+impl Transform for Upmix {
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    if layout.channels != 1 {
+      return Err(WaveError::Unsupported(format!("The upmix needs mono audio, not {} channels", layout.channels)));
+    }
+
+    if self.gains.is_empty() {
+      return Err(WaveError::Unsupported("The upmix needs at least one channel".into()));
+    }
+
+    self.frames = 0;
+    Ok(Layout { channels: self.gains.len(), ..layout })
+  }
+
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    self.mixed.clear();
+    for &sample in samples.iter() {
+      self.mixed.extend(self.gains.iter().map(|gain| gain * sample));
+    }
+
+    self.clipping.apply(&mut self.mixed, self.gains.len(), self.frames)?;
+    self.frames += samples.len() as u64;
+    mem::swap(samples, &mut self.mixed);
+    Ok(())
+  }
+}
+
+/// Converts stereo audio between left and right channels and mid and side
+/// channels, so stages between an encode and a decode process the sum and
+/// the difference of the channels separately.
+///
+/// Encoding halves the sum and difference, so mid and side stay within
+/// full scale, and decoding restores the left and right channels exactly.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::Format;
+/// use wavefile::transcode::{Downmix,MidSide,Pipeline};
+///
+/// // widens the stereo image by raising the side channel by half.
+/// let mut pipeline = Pipeline::new()
+///   .then(MidSide::encode())
+///   .then(Downmix::matrix(vec![vec![1.0, 0.0], vec![0.0, 1.5]]))
+///   .then(MidSide::decode());
+///
+/// pipeline.transcode("./mix.wav", "./wider.wav", 24, Format::PCM).unwrap();
+/// ```
+#[derive(Debug,Clone)]
+pub struct MidSide {
+  decode:   bool,
+  clipping: Clipping,
+  frames:   u64
+}
+
+impl MidSide {
+  /// Converts left and right channels to mid and side channels.
+  pub fn encode() -> MidSide {
+    MidSide { decode: false, clipping: Clipping::default(), frames: 0 }
+  }
+
+  /// Converts mid and side channels to left and right channels, clipping
+  /// at full scale.
+  pub fn decode() -> MidSide {
+    MidSide { decode: true, ..MidSide::encode() }
+  }
+
+  /// Sets how samples driven past full scale are treated.
+  pub fn clipping(mut self, clipping: Clipping) -> MidSide {
+    self.clipping = clipping;
+    self
+  }
+}
+
+impl Transform for MidSide {
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    if layout.channels != 2 {
+      return Err(WaveError::Unsupported(format!("Mid and side need stereo audio, not {} channel(s)", layout.channels)));
+    }
+
+    self.frames = 0;
+    Ok(layout)
+  }
+
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    let scale = if self.decode { 1.0 } else { 0.5 };
+
+    for frame in samples.chunks_exact_mut(2) {
+      let (a, b) = (frame[0], frame[1]);
+
+      frame[0] = (a + b) * scale;
+      frame[1] = (a - b) * scale;
+    }
+
+    self.clipping.apply(samples, 2, self.frames)?;
+    self.frames += (samples.len() / 2) as u64;
+    Ok(())
+  }
+}
+
+/// Inverts the polarity of some or all channels, such as to fix a
+/// microphone wired with its pins swapped.
+#[derive(Debug,Clone)]
+pub struct Invert {
+  /// The channels to invert, or `None` for all of them.
+  channels: Option<Vec<usize>>,
+  /// Whether each channel is inverted.
+  inverted: Vec<bool>
+}
+
+impl Invert {
+  /// Inverts every channel.
+  pub fn all() -> Invert {
+    Invert { channels: None, inverted: Vec::new() }
+  }
+
+  /// Inverts the given channels, counting from zero.
+  pub fn channels(channels: &[usize]) -> Invert {
+    Invert { channels: Some(channels.to_vec()), inverted: Vec::new() }
+  }
+}
+
+impl Transform for Invert {
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    self.inverted = vec![self.channels.is_none(); layout.channels];
+
+    for &channel in self.channels.iter().flatten() {
+      match self.inverted.get_mut(channel) {
+        Some(inverted) => *inverted = true,
+        None           => return Err(WaveError::Unsupported(format!("Channel {} is out of range for {} channels", channel, layout.channels)))
+      }
+    }
+
+    Ok(layout)
+  }
+
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    for frame in samples.chunks_exact_mut(self.inverted.len()) {
+      for (sample, &inverted) in frame.iter_mut().zip(&self.inverted) {
+        if inverted {
+          *sample = -*sample;
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// How `Resample` finds the audio between the input frames.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Default)]
+pub enum Interpolation {
+  /// Draws a straight line between neighbouring frames.  This is quick,
+  /// but dulls high frequencies and lets some alias, so suits previews.
+  Linear,
+  /// Filters the audio with a windowed sinc function, keeping it within
+  /// the lower of the two Nyquist frequencies, for mastering.
+  #[default]
+  Sinc
+}
+
+/// Changes the sample rate of the audio, such as from 48 kHz to 44.1 kHz.
+///
+/// The sinc filter reaches `zero_crossings` zero crossings either side of
+/// each frame, so must see that far ahead of the frame it produces.  More
+/// zero crossings sharpen its cutoff, at the cost of latency and time.
+/// Either way, the output is aligned with the input and lasts as long.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::Format;
+/// use wavefile::transcode::{Pipeline,Resample};
+///
+/// let mut pipeline = Pipeline::new().then(Resample::new(44100).zero_crossings(64));
+///
+/// pipeline.transcode("./master-48k.wav", "./master-cd.wav", 16, Format::PCM).unwrap();
+/// ```
+#[derive(Debug,Clone)]
+pub struct Resample {
+  rate:           u32,
+  interpolation:  Interpolation,
+  zero_crossings: usize,
+  cutoff:         f64,
+  channels:       usize,
+  /// The input and output sample rates.
+  from:           u64,
+  to:             u64,
+  /// The number of input frames each side of an output frame it draws on.
+  radius:         usize,
+  /// The frequency of the filter's cutoff, as a fraction of the input
+  /// sample rate's Nyquist frequency.
+  band:           f64,
+  /// The weights of the input frames for each fraction of a frame an
+  /// output frame may fall at, if there are few enough such fractions.
+  table:          Vec<f64>,
+  weights:        Vec<f64>,
+  /// Input frames still needed, interleaved, the first of which is input
+  /// frame `base`, which is negative for the silence before the audio.
+  buffer:         Vec<f64>,
+  base:           i64,
+  received:       u64,
+  /// The index of the next output frame.
+  next:           u64,
+  resampled:      Vec<f64>
+}
+
+/// The most weights `Resample` keeps in its table.
+const RESAMPLE_TABLE : usize = 1 << 16;
+
+impl Resample {
+  /// Resamples the audio to `rate` through a sinc filter with 32 zero
+  /// crossings, cutting off at 95% of the Nyquist frequency.
+  pub fn new(rate: u32) -> Resample {
+    Resample {
+      rate,
+      interpolation:  Interpolation::default(),
+      zero_crossings: 32,
+      cutoff:         0.95,
+      channels:       1,
+      from:           1,
+      to:             1,
+      radius:         1,
+      band:           1.0,
+      table:          Vec::new(),
+      weights:        Vec::new(),
+      buffer:         Vec::new(),
+      base:           0,
+      received:       0,
+      next:           0,
+      resampled:      Vec::new()
+    }
+  }
+
+  /// Resamples the audio to `rate` by linear interpolation.
+  pub fn linear(rate: u32) -> Resample {
+    Resample::new(rate).interpolation(Interpolation::Linear)
+  }
+
+  pub fn interpolation(mut self, interpolation: Interpolation) -> Resample {
+    self.interpolation = interpolation;
+    self
+  }
+
+  /// Sets the zero crossings of the sinc filter either side of a frame, at
+  /// least one.
+  pub fn zero_crossings(mut self, zero_crossings: usize) -> Resample {
+    self.zero_crossings = zero_crossings.max(1);
+    self
+  }
+
+  /// Sets the sinc filter's cutoff as a fraction of the lower Nyquist
+  /// frequency, between 0.5 and 1.0.  Higher cutoffs keep more of the
+  /// highest frequencies, and let more alias unless there are more zero
+  /// crossings.
+  pub fn cutoff(mut self, cutoff: f64) -> Resample {
+    self.cutoff = cutoff.clamp(0.5, 1.0);
+    self
+  }
+
+  /// The number of input frames past a frame needed to produce it, once
+  /// prepared.
+  pub fn latency(&self) -> usize {
+    if self.from == self.to { 0 } else { self.radius }
+  }
+
+  /// Fills `weights` with the weight of each input frame drawn on by an
+  /// output frame falling `fraction` of the way between two input frames.
+  fn kernel(&self, fraction: f64, weights: &mut Vec<f64>) {
+    let radius = self.radius as f64;
+
+    weights.clear();
+    weights.extend((0..2 * self.radius).map(|tap| {
+      let x = tap as f64 - (radius - 1.0) - fraction;
+
+      match self.interpolation {
+        Interpolation::Linear => 1.0 - x.abs(),
+        Interpolation::Sinc   => {
+          let window = 0.42 + 0.5 * (PI * x / radius).cos() + 0.08 * (2.0 * PI * x / radius).cos();
+
+          sinc(self.band * x) * window.max(0.0)
+        }
+      }
+    }));
+
+    // so constant input comes out unchanged.
+    let total = weights.iter().sum::<f64>();
+
+    weights.iter_mut().for_each(|weight| *weight /= total);
+  }
+
+  /// Produces output frames until they need input frames after `available`,
+  /// or `limit` are produced, then forgets input frames no longer needed.
+  fn produce(&mut self, available: i64, limit: Option<u64>) {
+    let channels = self.channels;
+    let radius   = self.radius as i64;
+    let phases   = self.table.len() / (2 * self.radius);
+    let mut weights = mem::take(&mut self.weights);
+
+    self.resampled.clear();
+    while limit.is_none_or(|limit| self.next < limit) {
+      let position = self.next * self.from;
+      let frame    = (position / self.to) as i64;
+      let fraction = position % self.to;
+
+      if frame + radius >= available {
+        break;
+      }
+
+      let weights = if phases > 0 {
+        let phase = (fraction * phases as u64 / self.to) as usize;
+
+        &self.table[phase * 2 * self.radius..(phase + 1) * 2 * self.radius]
+      } else {
+        self.kernel(fraction as f64 / self.to as f64, &mut weights);
+        &weights[..]
+      };
+      let first = (frame - radius + 1 - self.base) as usize * channels;
+
+      for channel in 0..channels {
+        let taps = self.buffer[first + channel..].iter().step_by(channels);
+
+        self.resampled.push(weights.iter().zip(taps).map(|(weight, sample)| weight * sample).sum());
+      }
+      self.next += 1;
+    }
+
+    self.weights = weights;
+
+    // the last frame is kept for linear interpolation to hold.
+    let first  = (self.next * self.from / self.to) as i64 - radius + 1;
+    let unused = ((first - self.base).max(0) as usize).min((self.buffer.len() / channels).saturating_sub(1));
+
+    self.buffer.drain(..unused * channels);
+    self.base += unused as i64;
+  }
+}
+
+/// The normalized sinc function.
+fn sinc(x: f64) -> f64 {
+  if x == 0.0 { 1.0 } else { (PI * x).sin() / (PI * x) }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+  if b == 0 { a } else { gcd(b, a % b) }
+}
+
+impl Transform for Resample {
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    if self.rate == 0 || layout.sample_rate == 0 {
+      return Err(WaveError::Unsupported("Sample rates must be above zero".into()));
+    }
+
+    self.channels = layout.channels;
+    self.from     = layout.sample_rate as u64;
+    self.to       = self.rate as u64;
+    self.band     = self.cutoff * (self.to as f64 / self.from as f64).min(1.0);
+    self.radius   = match self.interpolation {
+      Interpolation::Linear => 1,
+      // the filter widens as its band narrows, to reach as many zero
+      // crossings.
+      Interpolation::Sinc   => (self.zero_crossings as f64 / self.band).ceil() as usize
+    };
+
+    let phases = self.to / gcd(self.from, self.to);
+
+    self.table.clear();
+    if phases as usize * 2 * self.radius <= RESAMPLE_TABLE {
+      let mut weights = Vec::new();
+
+      for phase in 0..phases {
+        self.kernel(phase as f64 / phases as f64, &mut weights);
+        self.table.extend_from_slice(&weights);
+      }
+    }
+
+    self.buffer   = vec![0.0; (self.radius - 1) * self.channels];
+    self.base     = 1 - self.radius as i64;
+    self.received = 0;
+    self.next     = 0;
+
+    let frames = (layout.frames as u128 * self.to as u128).div_ceil(self.from as u128) as u64;
+
+    Ok(Layout { sample_rate: self.rate, frames, ..layout })
+  }
+
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    if self.from == self.to {
+      return Ok(());
+    }
+
+    self.buffer.extend_from_slice(samples);
+    self.received += (samples.len() / self.channels) as u64;
+
+    let available = self.base + (self.buffer.len() / self.channels) as i64;
+
+    self.produce(available, None);
+    mem::swap(samples, &mut self.resampled);
+    Ok(())
+  }
+
+  fn finish(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    if self.from == self.to || self.received == 0 {
+      return Ok(());
+    }
+
+    // the sinc filter sees silence after the audio, while linear
+    // interpolation holds the last frame.
+    match self.interpolation {
+      Interpolation::Linear => {
+        let last = self.buffer[self.buffer.len() - self.channels..].to_vec();
+
+        self.buffer.extend(last);
+      },
+      Interpolation::Sinc   => self.buffer.resize(self.buffer.len() + self.radius * self.channels, 0.0)
+    }
+
+    let available = self.base + (self.buffer.len() / self.channels) as i64;
+    let frames    = (self.received as u128 * self.to as u128).div_ceil(self.from as u128) as u64;
+
+    self.produce(available, Some(frames));
+    samples.append(&mut self.resampled);
+    Ok(())
+  }
+}
+
+/// How `Dither` shapes the spectrum of the noise it adds.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Default)]
+pub enum NoiseShaping {
+  /// Leaves the noise white.
+  #[default]
+  Off,
+  /// Feeds back the last error, moving noise towards high frequencies.
+  FirstOrder,
+  /// Lipshitz's five tap filter, which moves noise to where the ear is
+  /// least sensitive at 44.1 kHz, raising its total power.
+  Lipshitz
+}
+
+impl NoiseShaping {
+  /// The weights of the last errors fed back.
+  fn coefficients(self) -> &'static [f64] {
+    match self {
+      NoiseShaping::Off        => &[],
+      NoiseShaping::FirstOrder => &[1.0],
+      NoiseShaping::Lipshitz   => &[2.033, -2.165, 1.959, -1.590, 0.6149]
+    }
+  }
+}
+
+/// Reduces the audio to a lower bit depth with triangular dither, such as
+/// 24-bit masters to 16 bits for CD, so the error is noise rather than
+/// distortion following the signal.
+///
+/// The stage rounds samples to `bits` itself, so is the last in the
+/// pipeline, writing PCM samples of that size.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::Format;
+/// use wavefile::transcode::{Dither,NoiseShaping,Pipeline};
+///
+/// let mut pipeline = Pipeline::new().then(Dither::new(16).noise_shaping(NoiseShaping::Lipshitz));
+///
+/// pipeline.transcode("./master-24.wav", "./cd.wav", 16, Format::PCM).unwrap();
+/// ```
+#[derive(Debug,Clone)]
+pub struct Dither {
+  bits:     u16,
+  shaping:  NoiseShaping,
+  seed:     u64,
+  state:    u64,
+  channels: usize,
+  /// The last errors of each channel, most recent first.
+  errors:   Vec<[f64; 5]>
+}
+
+impl Dither {
+  /// Dithers to `bits` bits, from 2 to 32, without noise shaping.
+  pub fn new(bits: u16) -> Dither {
+    Dither { bits: bits.clamp(2, 32), shaping: NoiseShaping::default(), seed: 0x2545_f491_4f6c_dd1d, state: 0, channels: 1, errors: Vec::new() }
+  }
+
+  pub fn noise_shaping(mut self, shaping: NoiseShaping) -> Dither {
+    self.shaping = shaping;
+    self
+  }
+
+  /// Seeds the noise, which is the same for every run with the same seed.
+  pub fn seed(mut self, seed: u64) -> Dither {
+    self.seed = seed;
+    self
+  }
+
+  /// Uniform noise between -0.5 and 0.5, by xorshift64*.
+  fn noise(&mut self) -> f64 {
+    self.state ^= self.state >> 12;
+    self.state ^= self.state << 25;
+    self.state ^= self.state >> 27;
+
+    (self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+  }
+}
+
+impl Transform for Dither {
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    self.channels = layout.channels;
+    self.errors   = vec![[0.0; 5]; layout.channels];
+    // xorshift never leaves zero.
+    self.state    = self.seed.max(1);
+    Ok(layout)
+  }
+
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    let scale        = (1u64 << (self.bits - 1)) as f64;
+    let coefficients = self.shaping.coefficients();
+
+    for frame in samples.chunks_exact_mut(self.channels) {
+      for (channel, sample) in frame.iter_mut().enumerate() {
+        let errors    = self.errors[channel];
+        let shaped    = coefficients.iter().zip(&errors).map(|(weight, error)| weight * error).sum::<f64>();
+        let wanted    = *sample * scale - shaped;
+        let dither    = self.noise() + self.noise();
+        let quantized = (wanted + dither).round().clamp(-scale, scale - 1.0);
+
+        self.errors[channel] = [quantized - wanted, errors[0], errors[1], errors[2], errors[3]];
+        *sample = quantized / scale;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// The mean level of each channel of the audio passing a `DcBlock`, shared
+/// between clones so it can be read once the stage is in a pipeline.
+#[derive(Debug,Clone,Default)]
+pub struct DcOffset {
+  sums: Arc<Mutex<(Vec<f64>, u64)>>
+}
+
+impl DcOffset {
+  pub fn new() -> DcOffset {
+    DcOffset::default()
+  }
+
+  /// The mean of each channel's samples so far, on the scale of full
+  /// scale at 1.0, which is the DC offset removed.
+  pub fn offsets(&self) -> Vec<f64> {
+    let sums = self.sums.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    sums.0.iter().map(|sum| if sums.1 > 0 { sum / sums.1 as f64 } else { 0.0 }).collect()
+  }
+}
+
+/// Removes any DC offset from the audio with a gentle high pass filter,
+/// as recorded by interfaces with a bias on their inputs, and measures it.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::Format;
+/// use wavefile::transcode::{DcBlock,DcOffset,Pipeline};
+///
+/// let offset       = DcOffset::new();
+/// let mut pipeline = Pipeline::new().then(DcBlock::new().report(offset.clone()));
+///
+/// pipeline.transcode("./field.wav", "./field-fixed.wav", 24, Format::PCM).unwrap();
+/// println!("removed offsets of {:?}", offset.offsets());
+/// ```
+#[derive(Debug,Clone)]
+pub struct DcBlock {
+  cutoff:   f64,
+  report:   DcOffset,
+  channels: usize,
+  /// The feedback of the filter.
+  pole:     f64,
+  /// The last input and output of each channel.
+  inputs:   Vec<f64>,
+  outputs:  Vec<f64>
+}
+
+impl DcBlock {
+  /// Filters below 5 Hz.
+  pub fn new() -> DcBlock {
+    DcBlock { cutoff: 5.0, report: DcOffset::new(), channels: 1, pole: 0.0, inputs: Vec::new(), outputs: Vec::new() }
+  }
+
+  /// Sets the frequency in Hz below which the filter cuts, as the audio
+  /// would take longer to settle at lower frequencies.
+  pub fn cutoff(mut self, hertz: f64) -> DcBlock {
+    self.cutoff = hertz.max(0.0);
+    self
+  }
+
+  /// Reports the offsets measured to `offset`.
+  pub fn report(mut self, offset: DcOffset) -> DcBlock {
+    self.report = offset;
+    self
+  }
+
+  /// The mean of each channel's samples so far, like `DcOffset::offsets`.
+  pub fn offsets(&self) -> Vec<f64> {
+    self.report.offsets()
+  }
+}
+
+impl Default for DcBlock {
+  fn default() -> DcBlock {
+    DcBlock::new()
+  }
+}
+
+impl Transform for DcBlock {
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    self.channels = layout.channels;
+    self.pole     = (-2.0 * PI * self.cutoff / layout.sample_rate.max(1) as f64).exp();
+    self.inputs   = vec![0.0; layout.channels];
+    self.outputs  = vec![0.0; layout.channels];
+    *self.report.sums.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = (vec![0.0; layout.channels], 0);
+    Ok(layout)
+  }
+
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    let mut sums = self.report.sums.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    for frame in samples.chunks_exact_mut(self.channels) {
+      for (channel, sample) in frame.iter_mut().enumerate() {
+        let output = *sample - self.inputs[channel] + self.pole * self.outputs[channel];
+
+        sums.0[channel]      += *sample;
+        self.inputs[channel]  = *sample;
+        self.outputs[channel] = output;
+        *sample               = output;
+      }
+      sums.1 += 1;
+    }
+
+    Ok(())
+  }
+}
+
+/// The frequency response of a `Biquad`.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum Response {
+  /// Cuts above the frequency, by 12 dB an octave.
+  LowPass,
+  /// Cuts below the frequency, by 12 dB an octave.
+  HighPass,
+  /// Changes the level below the frequency by a number of decibels.
+  LowShelf(f64),
+  /// Changes the level above the frequency by a number of decibels.
+  HighShelf(f64)
+}
+
+/// A second order filter, for quick cleanup such as cutting rumble below
+/// 80 Hz or rolling off hiss, from the Audio EQ Cookbook.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::Format;
+/// use wavefile::transcode::{Biquad,Pipeline};
+///
+/// let mut pipeline = Pipeline::new()
+///   .then(Biquad::high_pass(80.0))
+///   .then(Biquad::high_shelf(8000.0, -3.0));
+///
+/// pipeline.transcode("./interview.wav", "./interview-clean.wav", 24, Format::PCM).unwrap();
+/// ```
+#[derive(Debug,Clone)]
+pub struct Biquad {
+  response:  Response,
+  frequency: f64,
+  q:         f64,
+  clipping:  Clipping,
+  /// The filter of each channel.
+  sections:  Vec<Section>,
+  frames:    u64
+}
+
+impl Biquad {
+  pub fn new(response: Response, frequency: f64) -> Biquad {
+    Biquad { response, frequency, q: FRAC_1_SQRT_2, clipping: Clipping::default(), sections: Vec::new(), frames: 0 }
+  }
+
+  /// Cuts above `frequency` in Hz.
+  pub fn low_pass(frequency: f64) -> Biquad {
+    Biquad::new(Response::LowPass, frequency)
+  }
+
+  /// Cuts below `frequency` in Hz.
+  pub fn high_pass(frequency: f64) -> Biquad {
+    Biquad::new(Response::HighPass, frequency)
+  }
+
+  /// Changes the level below `frequency` in Hz by `db` decibels.
+  pub fn low_shelf(frequency: f64, db: f64) -> Biquad {
+    Biquad::new(Response::LowShelf(db), frequency)
+  }
+
+  /// Changes the level above `frequency` in Hz by `db` decibels.
+  pub fn high_shelf(frequency: f64, db: f64) -> Biquad {
+    Biquad::new(Response::HighShelf(db), frequency)
+  }
+
+  /// Sets the filter's Q, by default 0.707, which is as flat as possible
+  /// without a peak at the frequency.  Higher values peak there, and lower
+  /// ones roll off more gently.
+  pub fn q(mut self, q: f64) -> Biquad {
+    self.q = q;
+    self
+  }
+
+  /// Sets how samples driven past full scale are treated.
+  pub fn clipping(mut self, clipping: Clipping) -> Biquad {
+    self.clipping = clipping;
+    self
+  }
+
+  /// The section filtering a channel at `sample_rate`.
+  fn section(&self, sample_rate: f64) -> Section {
+    let w0    = 2.0 * PI * self.frequency / sample_rate;
+    let cos   = w0.cos();
+    let alpha = w0.sin() / (2.0 * self.q);
+
+    let (b, a) = match self.response {
+      Response::LowPass  => ([(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0], [1.0 + alpha, -2.0 * cos, 1.0 - alpha]),
+      Response::HighPass => ([(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0], [1.0 + alpha, -2.0 * cos, 1.0 - alpha]),
+      Response::LowShelf(db) => {
+        let a    = 10f64.powf(db / 40.0);
+        let root = 2.0 * a.sqrt() * alpha;
+
+        ([a * ((a + 1.0) - (a - 1.0) * cos + root), 2.0 * a * ((a - 1.0) - (a + 1.0) * cos), a * ((a + 1.0) - (a - 1.0) * cos - root)],
+         [(a + 1.0) + (a - 1.0) * cos + root, -2.0 * ((a - 1.0) + (a + 1.0) * cos), (a + 1.0) + (a - 1.0) * cos - root])
+      },
+      Response::HighShelf(db) => {
+        let a    = 10f64.powf(db / 40.0);
+        let root = 2.0 * a.sqrt() * alpha;
+
+        ([a * ((a + 1.0) + (a - 1.0) * cos + root), -2.0 * a * ((a - 1.0) + (a + 1.0) * cos), a * ((a + 1.0) + (a - 1.0) * cos - root)],
+         [(a + 1.0) - (a - 1.0) * cos + root, 2.0 * ((a - 1.0) - (a + 1.0) * cos), (a + 1.0) - (a - 1.0) * cos - root])
+      }
+    };
+
+    Section::new([b[0] / a[0], b[1] / a[0], b[2] / a[0]], [a[1] / a[0], a[2] / a[0]])
+  }
+}
+
+impl Transform for Biquad {
+  fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    let nyquist = layout.sample_rate as f64 / 2.0;
+    let fits    = self.frequency > 0.0 && self.frequency < nyquist && self.q > 0.0;
+
+    if !fits {
+      return Err(WaveError::Unsupported(format!("A filter at {} Hz with a Q of {} doesn't fit audio at {} Hz",
+                                                self.frequency, self.q, layout.sample_rate)));
+    }
+
+    self.sections = vec![self.section(layout.sample_rate as f64); layout.channels];
+    self.frames   = 0;
+    Ok(layout)
+  }
+
+  fn process(&mut self, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    let channels = self.sections.len();
+
+    for frame in samples.chunks_exact_mut(channels) {
+      for (sample, section) in frame.iter_mut().zip(&mut self.sections) {
+        *sample = section.process(*sample);
+      }
+    }
+
+    self.clipping.apply(samples, channels, self.frames)?;
+    self.frames += (samples.len() / channels) as u64;
+    Ok(())
+  }
+}
+
+/// A second order filter of one channel, in transposed direct form II.
+#[derive(Debug,Copy,Clone)]
+pub(crate) struct Section {
+  b:     [f64; 3],
+  a:     [f64; 2],
+  state: [f64; 2]
+}
+
+impl Section {
+  /// A filter with the feedforward weights `b` and feedback weights `a`,
+  /// scaled so the weight of the output is one.
+  pub(crate) fn new(b: [f64; 3], a: [f64; 2]) -> Section {
+    Section { b, a, state: [0.0; 2] }
+  }
+
+  pub(crate) fn process(&mut self, input: f64) -> f64 {
+    let output = self.b[0] * input + self.state[0];
+
+    self.state[0] = self.b[1] * input - self.a[0] * output + self.state[1];
+    self.state[1] = self.b[2] * input - self.a[1] * output;
+    output
+  }
+}
+
+/// A series of transforms run over the audio of a file in order.
+#[derive(Default)]
+pub struct Pipeline {
+  stages: Vec<Box<dyn Transform + Send>>
+}
+
+impl Pipeline {
+  /// A pipeline without stages, which copies audio unchanged.
+  pub fn new() -> Pipeline {
+    Pipeline::default()
+  }
+
+  /// Adds `stage` after those already added.
+  pub fn then<T: Transform + Send + 'static>(mut self, stage: T) -> Pipeline {
+    self.stages.push(Box::new(stage));
+    self
+  }
+
+  /// Prepares every stage for audio of `layout`, returning the layout the
+  /// last one produces.
+  pub fn prepare(&mut self, layout: Layout) -> Result<Layout, WaveError> {
+    self.stages.iter_mut().try_fold(layout, |layout, stage| stage.prepare(layout))
+  }
+
+  /// Runs the audio of `file` through the pipeline into `writer`, whose
+  /// channel count and sample rate must be those the pipeline produces,
+  /// returning the number of frames written.  The writer is left to be
+  /// finalized by the caller.
+  pub fn run<W: Write + Seek>(&mut self, file: &WaveFile, writer: &mut WaveWriter<W>) -> Result<u64, WaveError> {
+    let layout = self.prepare(layout_of(file))?;
+    let spec   = writer.spec();
+
+    if layout.channels != spec.channels as usize || layout.sample_rate != spec.sample_rate {
+      return Err(WaveError::Unsupported(format!("The pipeline produces {} channel(s) at {} Hz, not {} channel(s) at {} Hz",
+                                                layout.channels, layout.sample_rate, spec.channels, spec.sample_rate)));
+    }
+
+    self.pump(file, writer, layout.channels)
+  }
+
+  /// Runs the audio of the file at `src` through the pipeline into a new
+  /// file at `dst` with samples of the given size and format, returning the
+  /// number of frames written.
+  pub fn transcode<S: Into<String>, T: Into<String>>(&mut self, src: S, dst: T, bits_per_sample: u16, format: Format)
+                                                     -> Result<u64, WaveError> {
+    let file   = WaveFile::open(src)?;
+    let layout = self.prepare(layout_of(&file))?;
+    let spec   = WaveSpec { channels: layout.channels as u16, sample_rate: layout.sample_rate, bits_per_sample, format };
+
+    let mut writer = WaveWriter::create(dst, spec)?;
+    let written    = self.pump(&file, &mut writer, layout.channels)?;
+
+    writer.finalize()?;
+    Ok(written)
+  }
+
+  /// Runs the audio of `file` through the prepared stages into `writer`.
+  fn pump<W: Write + Seek>(&mut self, file: &WaveFile, writer: &mut WaveWriter<W>, channels: usize) -> Result<u64, WaveError> {
+    let mut reader  = BlockReader::new(file);
+    let mut samples = Vec::new();
+    let mut written = 0;
+
+    while reader.read(&mut samples) > 0 {
+      self.process(0, &mut samples)?;
+      written += write(writer, &samples, channels)?;
+    }
+
+    // each stage's tail is run through the stages after it.
+    for stage in 0..self.stages.len() {
+      samples.clear();
+      self.stages[stage].finish(&mut samples)?;
+      self.process(stage + 1, &mut samples)?;
+      written += write(writer, &samples, channels)?;
+    }
+
+    Ok(written)
+  }
+
+  fn process(&mut self, first: usize, samples: &mut Vec<f64>) -> Result<(), WaveError> {
+    self.stages[first..].iter_mut().try_for_each(|stage| stage.process(samples))
+  }
+}
+
+/// The number of frames `duration` lasts at `sample_rate`, rounded.
+pub(crate) fn duration_frames(duration: Duration, sample_rate: u32) -> u64 {
+  (duration.as_secs_f64() * sample_rate as f64).round() as u64
+}
+
+/// The layout of the audio of `file`.
+pub(crate) fn layout_of(file: &WaveFile) -> Layout {
+  Layout { channels: file.channels(), sample_rate: file.sample_rate() as u32, frames: file.len() as u64 }
+}
+
+/// Reads the frames of a file in blocks, as floats between -1.0 and 1.0.
+pub(crate) struct BlockReader<'a> {
+  file:  &'a WaveFile,
+  next:  usize,
+  ints:  Vec<i32>,
+  float: Vec<f32>
+}
+
+impl<'a> BlockReader<'a> {
+  pub(crate) fn new(file: &'a WaveFile) -> BlockReader<'a> {
+    BlockReader { file, next: 0, ints: Vec::new(), float: Vec::new() }
+  }
+
+  /// Replaces `samples` with the next block of frames, returning their
+  /// number, which is zero at the end of the file.
+  pub(crate) fn read(&mut self, samples: &mut Vec<f64>) -> usize {
+    let channels = self.file.channels();
+    let frames   = BLOCK_FRAMES.min(self.file.len() - self.next);
+
+    samples.clear();
+    if self.file.data_format() == Format::IEEEFloat {
+      // floats are read as stored, keeping any beyond full scale.
+      self.float.resize(frames * channels, 0.0);
+      self.file.read_frames_f32_into(self.next, &mut self.float);
+      samples.extend(self.float.iter().map(|&sample| sample as f64));
+    } else {
+      // integers are read exactly, at their bit depth.
+      let scale = (1u64 << (self.file.bits_per_sample() - 1)) as f64;
+
+      self.ints.resize(frames * channels, 0);
+      self.file.read_frames(self.next, &mut self.ints);
+      samples.extend(self.ints.iter().map(|&sample| sample as f64 / scale));
+    }
+
+    self.next += frames;
+    frames
+  }
+}
+
+/// Writes the frames in `samples` to `writer`, returning their number.
+pub(crate) fn write<W: Write + Seek>(writer: &mut WaveWriter<W>, samples: &[f64], channels: usize) -> Result<u64, WaveError> {
+  for frame in samples.chunks_exact(channels) {
+    writer.write_frame_f64(frame)?;
+  }
+
+  Ok((samples.len() / channels) as u64)
+}
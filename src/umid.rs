@@ -0,0 +1,154 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher,Hasher};
+use std::sync::atomic::{AtomicUsize,Ordering};
+use std::time::{SystemTime,UNIX_EPOCH};
+
+/// SMPTE 330M universal label of a UMID for audio material, whose material
+/// number is a random UUID and whose instance number is locally assigned.
+const UMID_LABEL : [u8; 12] = [
+  0x06, 0x0a, 0x2b, 0x34, 0x01, 0x01, 0x01, 0x05, 0x01, 0x01, 0x02, 0x10
+];
+
+const UMID_BASIC    : u8 = 0x13;
+const UMID_EXTENDED : u8 = 0x33;
+
+/// Distinguishes UMIDs generated within the same instant.
+static GENERATED : AtomicUsize = AtomicUsize::new(0);
+
+/// A SMPTE 330M unique material identifier, as stored in the `bext` chunk.
+/// A basic UMID uses the first 32 bytes; an extended UMID adds a 32 byte
+/// source pack describing when and where the material was created.
+#[derive(Copy,Clone,PartialEq,Eq,Hash)]
+pub struct Umid {
+  bytes: [u8; 64]
+}
+
+impl Umid {
+  pub fn from_bytes(bytes: [u8; 64]) -> Umid {
+    Umid { bytes }
+  }
+
+  /// Generates a new basic UMID with a random material number.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use wavefile::Umid;
+  ///
+  /// let umid = Umid::generate();
+  ///
+  /// assert!(!umid.is_nil() && !umid.is_extended());
+  /// assert_ne!(umid, Umid::generate());
+  /// ```
+  pub fn generate() -> Umid {
+    let mut bytes = [0u8; 64];
+
+    bytes[..12].copy_from_slice(&UMID_LABEL);
+    bytes[12] = UMID_BASIC;
+
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let count = GENERATED.fetch_add(1, Ordering::Relaxed);
+
+    for half in 0..2 {
+      let mut hasher = RandomState::new().build_hasher();
+
+      hasher.write_u128(nanos);
+      hasher.write_usize(count);
+      hasher.write_usize(half);
+      bytes[16 + half * 8..24 + half * 8].copy_from_slice(&hasher.finish().to_be_bytes());
+    }
+
+    // mark the material number as a version 4 UUID.
+    bytes[22] = (bytes[22] & 0x0f) | 0x40;
+    bytes[24] = (bytes[24] & 0x3f) | 0x80;
+
+    Umid { bytes }
+  }
+
+  pub fn as_bytes(&self) -> &[u8; 64] {
+    &self.bytes
+  }
+
+  /// Returns `true` if the UMID is unset, i.e. all zeroes.
+  pub fn is_nil(&self) -> bool {
+    self.bytes.iter().all(|&b| b == 0)
+  }
+
+  /// Returns `true` if the UMID includes a source pack.
+  pub fn is_extended(&self) -> bool {
+    self.bytes[12] == UMID_EXTENDED
+  }
+
+  /// The SMPTE universal label identifying the UMID and its material type.
+  pub fn universal_label(&self) -> &[u8] {
+    &self.bytes[..12]
+  }
+
+  /// Distinguishes copies and other instances of the same material.
+  pub fn instance_number(&self) -> u32 {
+    (self.bytes[13] as u32) << 16 | (self.bytes[14] as u32) << 8 | self.bytes[15] as u32
+  }
+
+  /// The globally unique number identifying the material.
+  pub fn material_number(&self) -> &[u8] {
+    &self.bytes[16..32]
+  }
+
+  /// The source pack of an extended UMID, holding the time, place and
+  /// organisation of the material's creation.
+  pub fn source_pack(&self) -> Option<&[u8]> {
+    if self.is_extended() { Some(&self.bytes[32..]) } else { None }
+  }
+}
+
+impl Default for Umid {
+  fn default() -> Umid {
+    Umid { bytes: [0u8; 64] }
+  }
+}
+
+impl fmt::Display for Umid {
+  /// Formats the UMID as hex digits, 64 for a basic and 128 for an extended
+  /// UMID.
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let len = if self.is_extended() { 64 } else { 32 };
+
+    for byte in &self.bytes[..len] {
+      write!(f, "{:02X}", byte)?;
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Debug for Umid {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Umid({})", self)
+  }
+}
+
+/// Serializes the UMID as a byte sequence, since serde only implements its
+/// traits for arrays of up to 32 elements.
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Umid {
+  fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(self.bytes.iter())
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Umid {
+  fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Umid, D::Error> {
+    use serde::de::Error;
+
+    let bytes     = Vec::<u8>::deserialize(deserializer)?;
+    let mut umid  = [0u8; 64];
+
+    if bytes.len() != umid.len() {
+      return Err(D::Error::invalid_length(bytes.len(), &"64 bytes"));
+    }
+
+    umid.copy_from_slice(&bytes);
+    Ok(Umid { bytes: umid })
+  }
+}
@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
+
+use io_uring::{opcode, types, IoUring};
+use memmap::{Mmap, Protection};
+
+/// Number of block reads kept in flight at once.
+const QUEUE_DEPTH : usize = 32;
+
+/// Reads the whole file at `path` into anonymous memory in blocks of
+/// `block_size` bytes, with up to `QUEUE_DEPTH` reads in flight at a time.
+pub(crate) fn read(path: &str, block_size: usize) -> io::Result<Mmap> {
+  let file     = File::open(path)?;
+  let size     = file.metadata()?.len() as usize;
+  let mut mmap = Mmap::anonymous(size, Protection::ReadWrite)?;
+  let _span    = trace_span!(DEBUG, "read_io_uring", size, block_size);
+
+  {
+    let buffer = unsafe { mmap.as_mut_slice() };
+
+    match IoUring::new(QUEUE_DEPTH as u32) {
+      Ok(mut ring) => read_blocks(&mut ring, &file, buffer, block_size)?,
+      // kernels without io_uring, or sandboxes forbidding it.
+      Err(_e)      => {
+        trace_event!(DEBUG, error = %_e, "io_uring unavailable, reading normally");
+        file.read_exact_at(buffer, 0)?
+      }
+    }
+  }
+
+  Ok(mmap)
+}
+
+fn read_blocks(ring: &mut IoUring, file: &File, buffer: &mut [u8], block_size: usize) -> io::Result<()> {
+  let fd = types::Fd(file.as_raw_fd());
+
+  for (batch, blocks) in buffer.chunks_mut(block_size * QUEUE_DEPTH).enumerate() {
+    let start      = batch * block_size * QUEUE_DEPTH;
+    let mut blocks = blocks.chunks_mut(block_size).collect::<Vec<_>>();
+
+    for (i, block) in blocks.iter_mut().enumerate() {
+      let read = opcode::Read::new(fd, block.as_mut_ptr(), block.len() as u32)
+        .offset((start + i * block_size) as u64)
+        .build()
+        .user_data(i as u64);
+
+      // every block outlives its read, as the whole batch is waited for
+      // below, and the queue holds a batch.
+      unsafe { ring.submission().push(&read) }
+        .map_err(|_| io::Error::other("io_uring submission queue full"))?;
+    }
+
+    loop {
+      match ring.submit_and_wait(blocks.len()) {
+        Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+        result => { result?; break; }
+      }
+    }
+
+    let results = ring.completion().map(|entry| (entry.user_data() as usize, entry.result())).collect::<Vec<_>>();
+
+    for (i, result) in results {
+      if result < 0 {
+        return Err(io::Error::from_raw_os_error(-result));
+      }
+
+      // a read may stop short, which is finished the ordinary way.
+      let read = result as usize;
+
+      if read < blocks[i].len() {
+        file.read_exact_at(&mut blocks[i][read..], (start + i * block_size + read) as u64)?;
+      }
+    }
+  }
+
+  Ok(())
+}
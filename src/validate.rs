@@ -0,0 +1,247 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use error::WaveError;
+use formats::Format;
+use progress::Progress;
+use {WaveFile,FourCC,single_chunk_key};
+
+/// Recordings longer than this are most likely mislabeled or corrupt.
+const MAX_PLAUSIBLE_SECONDS : f64 = 24.0 * 60.0 * 60.0;
+
+/// Number of frames `bit_usage` checks between progress reports.
+const BIT_USAGE_BLOCK_FRAMES : usize = 1 << 16;
+
+/// How serious an `Issue` is.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,PartialOrd,Ord)]
+pub enum Severity {
+  /// Allowed by the specification, but likely to trip up other software or
+  /// to indicate a problem with the recording.
+  Warning,
+  /// Breaks the specification.
+  Violation
+}
+
+/// A problem found by `WaveFile::validate`.
+#[derive(Debug,Clone,PartialEq)]
+pub enum Issue {
+  /// The RIFF header's size doesn't match the length of the file.
+  RiffSize { declared: u32, actual: u64 },
+  /// An odd-sized chunk isn't followed by the pad byte needed to keep the
+  /// next chunk word aligned.
+  MissingPadByte { id: FourCC, offset: usize },
+  /// The data chunk's size was left unset, as streaming encoders do.
+  DataSizeUnset,
+  /// The data chunk runs past the end of the file.
+  Truncated { declared: u32, available: usize },
+  /// The data chunk ends in the middle of a frame.
+  PartialFrame { bytes: usize },
+  /// The block align doesn't match the channel count and sample size.
+  BlockAlign { declared: u16, expected: u32 },
+  /// The byte rate doesn't match the sample rate and block align.
+  ByteRate { declared: u32, expected: u64 },
+  /// A file whose audio isn't PCM lacks the `fact` chunk holding its length.
+  MissingFact,
+  /// The `fmt ` chunk follows the audio, which many readers can't cope with.
+  FormatAfterData,
+  /// A chunk that should appear only once is repeated.  Only the copy
+  /// chosen by `WaveFileOptions::duplicate_chunks` is used, so a copy that
+  /// differs from the first one is a conflict.
+  DuplicateChunk { id: FourCC, offset: usize, conflicting: bool },
+  /// The audio is empty or implausibly long.
+  SuspiciousDuration { seconds: f64 },
+  /// A metadata chunk whose parsing `WaveFileOptions::lazy_metadata` put
+  /// off is malformed, so its accessor treats it as missing.
+  MalformedMetadata { error: String }
+}
+
+impl Issue {
+  pub fn severity(&self) -> Severity {
+    match *self {
+      Issue::DataSizeUnset | Issue::PartialFrame { .. } | Issue::MissingFact |
+      Issue::FormatAfterData | Issue::SuspiciousDuration { .. } |
+      Issue::DuplicateChunk { conflicting: false, .. } => Severity::Warning,
+      _                                                => Severity::Violation
+    }
+  }
+}
+
+/// Checks a file that was opened successfully for departures from the
+/// specification.
+pub(crate) fn check(file: &WaveFile) -> Vec<Issue> {
+  let bytes      = file.bytes();
+  let info       = file.info();
+  let mut issues = Vec::new();
+
+  let declared = LittleEndian::read_u32(&bytes[4..8]);
+  let actual   = bytes.len() as u64 - 8;
+
+  if declared as u64 != actual {
+    issues.push(Issue::RiffSize { declared, actual });
+  }
+
+  for (id, offset, size) in file.chunks() {
+    let end = offset + size;
+
+    if size % 2 == 1 && end <= bytes.len() && !pad_byte_follows(bytes, end) {
+      issues.push(Issue::MissingPadByte { id, offset });
+    }
+  }
+
+  let mut firsts = Vec::new();
+
+  for (id, offset, size) in file.chunks() {
+    let body = &bytes[offset.min(bytes.len())..(offset + size).min(bytes.len())];
+
+    if let Some(key) = single_chunk_key(bytes, LittleEndian::read_u32(&id), offset, size) {
+      match firsts.iter().find(|first: &&((u32, u32), &[u8])| first.0 == key) {
+        Some(first) => issues.push(Issue::DuplicateChunk { id, offset, conflicting: first.1 != body }),
+        None        => firsts.push((key, body))
+      }
+    }
+  }
+
+  if let Some((_, offset, size)) = file.chunks().find(|chunk| &chunk.0 == b"data") {
+    if info.data_size_untrusted {
+      issues.push(Issue::DataSizeUnset);
+    } else if info.truncated {
+      issues.push(Issue::Truncated { declared: size as u32, available: bytes.len() - offset });
+    } else if size % file.stride != 0 {
+      issues.push(Issue::PartialFrame { bytes: size % file.stride });
+    }
+  }
+
+  let frame_size = info.channels as u32 * (info.bits_per_sample as u32 / 8);
+  let byte_rate  = info.sample_rate as u64 * info.block_align as u64;
+
+  if info.block_align as u32 != frame_size {
+    issues.push(Issue::BlockAlign { declared: info.block_align, expected: frame_size });
+  }
+
+  if info.byte_rate as u64 != byte_rate {
+    issues.push(Issue::ByteRate { declared: info.byte_rate, expected: byte_rate });
+  }
+
+  if info.subformat.unwrap_or(info.audio_format) != Format::PCM && !file.chunks().any(|chunk| &chunk.0 == b"fact") {
+    issues.push(Issue::MissingFact);
+  }
+
+  let ids = file.chunks().map(|chunk| chunk.0).collect::<Vec<_>>();
+
+  if ids.iter().position(|id| id == b"fmt ") > ids.iter().position(|id| id == b"data") {
+    issues.push(Issue::FormatAfterData);
+  }
+
+  let seconds = info.total_frames as f64 / info.sample_rate.max(1) as f64;
+
+  if info.total_frames == 0 || seconds > MAX_PLAUSIBLE_SECONDS {
+    issues.push(Issue::SuspiciousDuration { seconds });
+  }
+
+  for error in file.deferred_errors() {
+    issues.push(Issue::MalformedMetadata { error: error.to_string() });
+  }
+
+  issues
+}
+
+/// Returns `true` unless the chunk ending at `end` is followed directly by
+/// another chunk, or by the end of the file, rather than by a pad byte.
+fn pad_byte_follows(bytes: &[u8], end: usize) -> bool {
+  if end == bytes.len() {
+    return false;
+  }
+
+  let is_id = |at: usize| {
+    at + 4 <= bytes.len() && bytes[at..at + 4].iter().all(|&b| (0x20..0x7f).contains(&b))
+  };
+
+  // a missing pad byte shows as a chunk ID one byte early.
+  !is_id(end) || is_id(end + 1)
+}
+
+/// How many bits of each sample the audio actually uses, as found by
+/// `WaveFile::bit_usage`.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct BitUsage {
+  /// The sample size the file declares, or its valid bits per sample if
+  /// given.
+  pub declared: u16,
+  /// The bits the audio uses.  For PCM, the bits left once the trailing
+  /// zeros shared by every sample are removed; for float audio, the depth
+  /// of the coarsest PCM grid every sample lies on, or `declared` if there
+  /// is none finer than the float's precision.  Zero for silence.
+  pub used:     u16
+}
+
+impl BitUsage {
+  /// Returns `true` if the audio uses fewer bits than declared, e.g. 16 bit
+  /// audio padded to 24 bits or stored as floats.
+  pub fn is_padded(&self) -> bool {
+    self.used < self.declared
+  }
+}
+
+pub(crate) fn bit_usage(file: &WaveFile, mut progress: Option<&mut Progress>) -> Result<BitUsage, WaveError> {
+  let info      = file.info();
+  let bps       = info.bits_per_sample as usize / 8;
+  let data      = &file.bytes()[file.data_offset..file.data_offset + file.data_size];
+  let is_float  = file.data_format() == Format::IEEEFloat;
+  let mut used  = 0;
+  let mut bits  = 0u64;
+
+  for (index, block) in data.chunks(BIT_USAGE_BLOCK_FRAMES * file.stride).enumerate() {
+    let samples = block.chunks_exact(file.stride)
+      .flat_map(|frame| frame[..info.channels as usize * bps].chunks(bps));
+
+    if is_float {
+      used = samples
+        .map(|sample| {
+          let value = if bps == 4 { LittleEndian::read_f32(sample) as f64 } else { LittleEndian::read_f64(sample) };
+          grid_bits(value)
+        })
+        .fold(used, u16::max);
+    } else {
+      bits = samples.fold(bits, |bits, sample| bits | LittleEndian::read_uint(sample, bps));
+    }
+
+    if let Some(ref mut progress) = progress {
+      progress.update(((index + 1) * BIT_USAGE_BLOCK_FRAMES).min(file.len()), file.len())?;
+    }
+  }
+
+  if is_float {
+    // the largest grid bits not finer than the float's own precision.
+    let precision = if bps == 4 { 24 } else { 53 };
+    let declared  = info.bits_per_sample;
+
+    return Ok(BitUsage { declared, used: if used > precision { declared } else { used } });
+  }
+
+  let declared = info.valid_bps.unwrap_or(info.bits_per_sample).min(info.bits_per_sample);
+
+  // samples are left aligned, so unused bits are the trailing ones.
+  let unused = if bits == 0 { info.bits_per_sample } else { bits.trailing_zeros() as u16 };
+
+  Ok(BitUsage { declared, used: info.bits_per_sample.saturating_sub(unused).min(declared) })
+}
+
+/// The bit depth of the coarsest PCM grid on which `value` lies, counting
+/// the sign, i.e. the smallest `bits` for which `value * 2^(bits - 1)` is a
+/// whole number.
+fn grid_bits(value: f64) -> u16 {
+  if value == 0.0 {
+    return 0;
+  }
+
+  if !value.is_finite() {
+    return u16::MAX;
+  }
+
+  let raw      = value.to_bits();
+  let exponent = ((raw >> 52) & 0x7ff) as i32;
+  let mantissa = if exponent == 0 { raw & 0xf_ffff_ffff_ffff } else { raw & 0xf_ffff_ffff_ffff | 1 << 52 };
+  // the exponent of the lowest set bit of the value.
+  let lowest   = exponent.max(1) - 1075 + mantissa.trailing_zeros() as i32;
+
+  (1 - lowest).max(1) as u16
+}
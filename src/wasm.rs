@@ -0,0 +1,74 @@
+use wasm_bindgen::prelude::*;
+
+use options::WaveFileOptions;
+use WaveFile;
+
+/// A wavefile parsed from bytes in the browser, for JavaScript.
+///
+/// ```js
+/// const wav     = new WasmWaveFile(new Uint8Array(await file.arrayBuffer()));
+/// const samples = wav.readFrames(0, wav.frames);   // a Float32Array
+/// ```
+#[wasm_bindgen]
+pub struct WasmWaveFile {
+  file: WaveFile
+}
+
+#[wasm_bindgen]
+impl WasmWaveFile {
+  /// Parses a copy of `bytes`, throwing an `Error` if they aren't a
+  /// wavefile that can be read.
+  #[wasm_bindgen(constructor)]
+  pub fn new(bytes: &[u8]) -> Result<WasmWaveFile, JsError> {
+    match WaveFile::from_bytes(bytes, WaveFileOptions::default()) {
+      Ok(file) => Ok(WasmWaveFile { file }),
+      Err(e)   => Err(JsError::new(&e.to_string()))
+    }
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn channels(&self) -> usize {
+    self.file.channels()
+  }
+
+  #[wasm_bindgen(getter, js_name = sampleRate)]
+  pub fn sample_rate(&self) -> usize {
+    self.file.sample_rate()
+  }
+
+  #[wasm_bindgen(getter, js_name = bitsPerSample)]
+  pub fn bits_per_sample(&self) -> usize {
+    self.file.bits_per_sample()
+  }
+
+  /// `"PCM"` or `"IEEEFloat"`.
+  #[wasm_bindgen(getter)]
+  pub fn format(&self) -> String {
+    format!("{:?}", self.file.data_format())
+  }
+
+  /// The number of frames in the file.
+  #[wasm_bindgen(getter)]
+  pub fn frames(&self) -> usize {
+    self.file.len()
+  }
+
+  /// The length of the file in seconds.
+  #[wasm_bindgen(getter)]
+  pub fn seconds(&self) -> f64 {
+    self.file.len() as f64 / self.file.sample_rate().max(1) as f64
+  }
+
+  /// Decodes up to `count` frames starting at frame `first`, interleaved,
+  /// as floats like `read_frames_f32_into()`.  Fewer frames are returned
+  /// at the end of the file.
+  #[wasm_bindgen(js_name = readFrames)]
+  pub fn read_frames(&self, first: usize, count: usize) -> Vec<f32> {
+    let channels    = self.file.channels();
+    let count       = count.min(self.file.len().saturating_sub(first));
+    let mut samples = vec![0.0; count * channels];
+
+    self.file.read_frames_f32_into(first, &mut samples);
+    samples
+  }
+}
@@ -0,0 +1,445 @@
+use std::fs::File;
+use std::io::{BufWriter,Seek,SeekFrom,Write};
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+
+use error::WaveError;
+use formats::Format;
+use cart::Cart;
+use bext::Bext;
+use levl::PeakBuilder;
+use labels::{self,Label};
+use cue::CuePoint;
+use {RIFF,WAVE,FMT_,DATA,FACT,CART,BEXT,LEVL,CUE_,LIST,FourCC};
+
+/// Describes the sample format of the audio written by a `WaveWriter`.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct WaveSpec {
+  /// Number of distinct audio channels.
+  pub channels:        u16,
+  /// Number of audio samples per second.
+  pub sample_rate:     u32,
+  /// Number of bits used to represent each sample.
+  pub bits_per_sample: u16,
+  /// Either `Format::PCM` or `Format::IEEEFloat`.
+  pub format:          Format
+}
+
+impl WaveSpec {
+  fn block_align(&self) -> u16 {
+    self.channels * (self.bits_per_sample / 8)
+  }
+
+  fn validate(&self) -> Result<(), WaveError> {
+    let supported = match self.format {
+      Format::PCM       => [8, 16, 24, 32].contains(&self.bits_per_sample),
+      Format::IEEEFloat => [32, 64].contains(&self.bits_per_sample),
+      Format::Extended  => false
+    };
+
+    if !supported || self.channels == 0 {
+      let msg = format!("Can't write {} channel(s) of {} bit {:?} audio",
+                        self.channels, self.bits_per_sample, self.format);
+      return Err(WaveError::Unsupported(msg));
+    }
+
+    Ok(())
+  }
+}
+
+/// Writes frames of audio to a new wavefile.
+///
+/// Samples are written on the same scale `WaveFileIterator` reads them: PCM
+/// samples use the file's bit depth, while float samples are scaled to the
+/// full range of an `i32`.
+///
+/// Chunks added before the first frame is written are placed ahead of the
+/// audio data, later ones after it.  The file's sizes are filled in by
+/// `finalize()`, or on a best-effort basis when the writer is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use wavefile::{WaveWriter,WaveSpec,Format};
+///
+/// let spec = WaveSpec { channels: 1, sample_rate: 44100, bits_per_sample: 16, format: Format::PCM };
+/// let mut writer = WaveWriter::create("./sine.wav", spec).unwrap();
+///
+/// for i in 0..44100 {
+///   let t = i as f64 / 44100.0;
+///   writer.write_frame(&[((t * 440.0 * 6.283).sin() * 32767.0) as i32]).unwrap();
+/// }
+///
+/// writer.finalize().unwrap();
+/// ```
+pub struct WaveWriter<W: Write + Seek> {
+  inner:       W,
+  spec:        WaveSpec,
+  pending:     Vec<(u32, Vec<u8>)>,
+  fact_offset: Option<u64>,
+  data_offset: Option<u64>,
+  data_size:   u64,
+  frames:      u64,
+  peaks:       Option<PeakBuilder>,
+  finalized:   bool
+}
+
+impl WaveWriter<BufWriter<File>> {
+  /// Creates a new file at `path`, replacing any existing file.
+  pub fn create<S: Into<String>>(path: S, spec: WaveSpec) -> Result<WaveWriter<BufWriter<File>>, WaveError> {
+    let file = File::create(path.into())?;
+
+    WaveWriter::new(BufWriter::new(file), spec)
+  }
+
+  /// Creates a new file at `path` like `create`, collecting `capacity`
+  /// bytes before each write to the file.
+  pub fn create_with_capacity<S: Into<String>>(path: S, spec: WaveSpec, capacity: usize)
+                                               -> Result<WaveWriter<BufWriter<File>>, WaveError> {
+    let file = File::create(path.into())?;
+
+    WaveWriter::new(BufWriter::with_capacity(capacity, file), spec)
+  }
+}
+
+impl<W: Write + Seek> WaveWriter<W> {
+  /// Creates a writer producing a wavefile in `inner`, which is expected to
+  /// be empty.
+  pub fn new(inner: W, spec: WaveSpec) -> Result<WaveWriter<W>, WaveError> {
+    WaveWriter::start(inner, spec, None)
+  }
+
+  /// Creates a writer like `new` whose `fmt ` chunk has the body `format`
+  /// rather than one derived from `spec`, keeping details of an existing
+  /// file's format such as its speakers.  `format` must describe frames
+  /// laid out as `spec` describes them.
+  pub(crate) fn with_format(inner: W, spec: WaveSpec, format: &[u8]) -> Result<WaveWriter<W>, WaveError> {
+    WaveWriter::start(inner, spec, Some(format))
+  }
+
+  fn start(inner: W, spec: WaveSpec, format: Option<&[u8]>) -> Result<WaveWriter<W>, WaveError> {
+    spec.validate()?;
+
+    let mut writer = WaveWriter {
+      inner,
+      spec,
+      pending:     Vec::new(),
+      fact_offset: None,
+      data_offset: None,
+      data_size:   0,
+      frames:      0,
+      peaks:       None,
+      finalized:   false
+    };
+
+    writer.write_header(format)?;
+    Ok(writer)
+  }
+
+  /// The format of the audio being written.
+  pub fn spec(&self) -> WaveSpec {
+    self.spec
+  }
+
+  /// The number of frames written so far.
+  pub fn len(&self) -> usize {
+    self.frames as usize
+  }
+
+  /// Returns `true` if no frames have been written yet.
+  pub fn is_empty(&self) -> bool {
+    self.frames == 0
+  }
+
+  /// Adds a Broadcast Wave `bext` chunk to the file.  Use `Umid::generate()`
+  /// to give the file a unique material identifier.
+  pub fn set_bext(&mut self, bext: &Bext) -> Result<(), WaveError> {
+    let mut body = Vec::new();
+
+    bext.write_to(&mut body)?;
+    self.add_chunk_body(BEXT, body)
+  }
+
+  /// Adds an AES46 `cart` chunk to the file.
+  pub fn set_cart(&mut self, cart: &Cart) -> Result<(), WaveError> {
+    let mut body = Vec::new();
+
+    cart.write_to(&mut body)?;
+    self.add_chunk_body(CART, body)
+  }
+
+  /// Adds `cue ` and associated data chunks marking the given labels, such
+  /// as those read from an Audacity label track.
+  pub fn set_labels(&mut self, labels: &[Label]) -> Result<(), WaveError> {
+    let (cues, adtl) = labels::to_cues(labels, self.spec.sample_rate);
+    let mut cue_body  = Vec::new();
+    let mut list_body = Vec::new();
+
+    CuePoint::write_chunk(&cues, &mut cue_body)?;
+    adtl.write_to(&mut list_body)?;
+    self.add_chunk_body(CUE_, cue_body)?;
+    self.add_chunk_body(LIST, list_body)
+  }
+
+  /// Computes a peak envelope of the audio as it is written, summarizing
+  /// every `block_size` frames, and stores it in a `levl` chunk after the
+  /// audio data.  Must be called before any frames are written.
+  pub fn generate_peak_envelope(&mut self, block_size: u32) -> Result<(), WaveError> {
+    if self.frames > 0 || block_size == 0 {
+      let msg = "Peak envelopes need a block size and must be enabled before writing audio";
+      return Err(WaveError::WriteError(msg.into()));
+    }
+
+    let scale = match self.spec.format {
+      Format::PCM => (1u64 << (self.spec.bits_per_sample - 1)) as f64,
+      _           => 2147483648.0
+    };
+
+    self.peaks = Some(PeakBuilder::new(self.spec.channels, block_size, scale));
+    Ok(())
+  }
+
+  /// Adds a chunk with the given ID and body to the file, followed by a pad
+  /// byte if the body has an odd length.  The chunks making up the file's
+  /// structure (`RIFF`, `fmt `, `fact` and `data`) can't be added.
+  pub fn add_chunk(&mut self, id: FourCC, body: &[u8]) -> Result<(), WaveError> {
+    let id = LittleEndian::read_u32(&id);
+
+    if [RIFF, FMT_, FACT, DATA].contains(&id) {
+      let msg = format!("Can't add a {:?} chunk", String::from_utf8_lossy(&::fourcc(id)));
+      return Err(WaveError::WriteError(msg));
+    }
+
+    self.add_chunk_body(id, body.to_vec())
+  }
+
+  /// Writes a single frame, which must contain one sample per channel.
+  pub fn write_frame(&mut self, frame: &[i32]) -> Result<(), WaveError> {
+    self.start_frame(frame.len())?;
+
+    let bytes_per_sample = self.spec.bits_per_sample as usize / 8;
+
+    for &sample in frame {
+      match self.spec.format {
+        Format::IEEEFloat if bytes_per_sample == 4 => {
+          self.inner.write_f32::<LittleEndian>((sample as f64 / 2147483647.0) as f32)?;
+        },
+        Format::IEEEFloat => {
+          self.inner.write_f64::<LittleEndian>(sample as f64 / 2147483647.0)?;
+        },
+        _ => {
+          let max     = (1i64 << (bytes_per_sample * 8 - 1)) - 1;
+          let clamped = (sample as i64).max(-max - 1).min(max);
+
+          self.write_pcm(clamped, bytes_per_sample)?;
+        }
+      }
+    }
+
+    if let Some(ref mut peaks) = self.peaks {
+      peaks.add_frame(frame);
+    }
+
+    self.end_frame()
+  }
+
+  /// Writes a single frame of samples between -1.0 and 1.0, on the scale
+  /// `WaveFile::read_frames_f32_into` reads them.  Float files store the
+  /// samples as they are, so keep any beyond full scale, while PCM samples
+  /// are rounded to the file's bit depth and clipped.
+  pub fn write_frame_f64(&mut self, frame: &[f64]) -> Result<(), WaveError> {
+    self.start_frame(frame.len())?;
+
+    let bytes_per_sample = self.spec.bits_per_sample as usize / 8;
+    let max              = (1i64 << (bytes_per_sample * 8 - 1)) - 1;
+
+    for &sample in frame {
+      match self.spec.format {
+        Format::IEEEFloat if bytes_per_sample == 4 => self.inner.write_f32::<LittleEndian>(sample as f32)?,
+        Format::IEEEFloat                          => self.inner.write_f64::<LittleEndian>(sample)?,
+        _ => {
+          let clamped = (sample * (max + 1) as f64).round().max((-max - 1) as f64).min(max as f64);
+
+          self.write_pcm(clamped as i64, bytes_per_sample)?;
+        }
+      }
+    }
+
+    if let Some(ref mut peaks) = self.peaks {
+      // on the scale `write_frame` takes.
+      let scale = if self.spec.format == Format::IEEEFloat { i32::MAX as f64 } else { (max + 1) as f64 };
+      let frame = frame.iter().map(|&sample| (sample * scale).round().max(-scale).min(scale - 1.0) as i32).collect::<Vec<_>>();
+
+      peaks.add_frame(&frame);
+    }
+
+    self.end_frame()
+  }
+
+  /// Writes a PCM sample, offsetting 8 bit samples as they are stored
+  /// unsigned.
+  fn write_pcm(&mut self, sample: i64, bytes_per_sample: usize) -> Result<(), WaveError> {
+    if bytes_per_sample == 1 {
+      self.inner.write_u8((sample + 128) as u8)?;
+    } else {
+      self.inner.write_int::<LittleEndian>(sample, bytes_per_sample)?;
+    }
+
+    Ok(())
+  }
+
+  fn start_frame(&mut self, samples: usize) -> Result<(), WaveError> {
+    if samples != self.spec.channels as usize {
+      let msg = format!("Frame has {} samples but the file has {} channel(s)",
+                        samples, self.spec.channels);
+      return Err(WaveError::WriteError(msg));
+    }
+
+    if self.data_offset.is_none() {
+      self.start_data()?;
+    }
+
+    Ok(())
+  }
+
+  fn end_frame(&mut self) -> Result<(), WaveError> {
+    self.data_size += self.spec.block_align() as u64;
+    self.frames    += 1;
+
+    if self.data_size > u32::MAX as u64 {
+      return Err(WaveError::WriteError("Audio data exceeds 4 GiB".into()));
+    }
+
+    Ok(())
+  }
+
+  /// Writes any remaining chunks and fills in the sizes in the file header.
+  pub fn finalize(mut self) -> Result<(), WaveError> {
+    self.finish()
+  }
+
+  fn add_chunk_body(&mut self, id: u32, body: Vec<u8>) -> Result<(), WaveError> {
+    if self.data_offset.is_some() {
+      self.pending.push((id, body));
+      Ok(())
+    } else {
+      self.write_chunk(id, &body)
+    }
+  }
+
+  fn write_header(&mut self, format: Option<&[u8]>) -> Result<(), WaveError> {
+    self.inner.write_u32::<LittleEndian>(RIFF)?;
+    self.inner.write_u32::<LittleEndian>(0)?;
+    self.inner.write_u32::<LittleEndian>(WAVE)?;
+
+    match format {
+      Some(body) => self.write_chunk(FMT_, body)?,
+      None       => {
+        self.inner.write_u32::<LittleEndian>(FMT_)?;
+        self.inner.write_u32::<LittleEndian>(16)?;
+        self.inner.write_u16::<LittleEndian>(self.spec.format as u16)?;
+        self.inner.write_u16::<LittleEndian>(self.spec.channels)?;
+        self.inner.write_u32::<LittleEndian>(self.spec.sample_rate)?;
+        self.inner.write_u32::<LittleEndian>(self.spec.sample_rate * self.spec.block_align() as u32)?;
+        self.inner.write_u16::<LittleEndian>(self.spec.block_align())?;
+        self.inner.write_u16::<LittleEndian>(self.spec.bits_per_sample)?;
+      }
+    }
+
+    // non-PCM files must state their length in a fact chunk.
+    if self.spec.format != Format::PCM {
+      self.inner.write_u32::<LittleEndian>(FACT)?;
+      self.inner.write_u32::<LittleEndian>(4)?;
+      self.fact_offset = Some(self.inner.stream_position()?);
+      self.inner.write_u32::<LittleEndian>(0)?;
+    }
+
+    Ok(())
+  }
+
+  fn write_chunk(&mut self, id: u32, body: &[u8]) -> Result<(), WaveError> {
+    if body.len() > u32::MAX as usize - 1 {
+      return Err(WaveError::WriteError("Chunk exceeds 4 GiB".into()));
+    }
+
+    self.inner.write_u32::<LittleEndian>(id)?;
+    self.inner.write_u32::<LittleEndian>(body.len() as u32)?;
+    self.inner.write_all(body)?;
+
+    if body.len() % 2 == 1 {
+      self.inner.write_u8(0)?;
+    }
+
+    Ok(())
+  }
+
+  fn start_data(&mut self) -> Result<(), WaveError> {
+    self.inner.write_u32::<LittleEndian>(DATA)?;
+    self.inner.write_u32::<LittleEndian>(0)?;
+    self.data_offset = Some(self.inner.stream_position()?);
+    Ok(())
+  }
+
+  /// The stream the file is written to.
+  #[cfg(feature = "async")]
+  pub(crate) fn get_mut(&mut self) -> &mut W {
+    &mut self.inner
+  }
+
+  pub(crate) fn finish(&mut self) -> Result<(), WaveError> {
+    if self.finalized {
+      return Ok(());
+    }
+    self.finalized = true;
+
+    let data_offset = match self.data_offset {
+      Some(offset) => offset,
+      None         => {
+        self.start_data()?;
+        self.inner.stream_position()?
+      }
+    };
+
+    if self.data_size % 2 == 1 {
+      self.inner.write_u8(0)?;
+    }
+
+    if let Some(peaks) = self.peaks.take() {
+      let mut body = Vec::new();
+
+      peaks.finish().write_to(&mut body)?;
+      self.write_chunk(LEVL, &body)?;
+    }
+
+    for (id, body) in ::std::mem::take(&mut self.pending) {
+      self.write_chunk(id, &body)?;
+    }
+
+    let end = self.inner.stream_position()?;
+
+    if end - 8 > u32::MAX as u64 {
+      return Err(WaveError::WriteError("File exceeds 4 GiB".into()));
+    }
+
+    self.inner.seek(SeekFrom::Start(4))?;
+    self.inner.write_u32::<LittleEndian>((end - 8) as u32)?;
+    self.inner.seek(SeekFrom::Start(data_offset - 4))?;
+    self.inner.write_u32::<LittleEndian>(self.data_size as u32)?;
+
+    if let Some(offset) = self.fact_offset {
+      self.inner.seek(SeekFrom::Start(offset))?;
+      // the data chunk's size limit keeps the count within 32 bits.
+      self.inner.write_u32::<LittleEndian>(self.frames as u32)?;
+    }
+
+    self.inner.seek(SeekFrom::Start(end))?;
+    self.inner.flush()?;
+    Ok(())
+  }
+}
+
+impl<W: Write + Seek> Drop for WaveWriter<W> {
+  fn drop(&mut self) {
+    let _ = self.finish();
+  }
+}